@@ -20,12 +20,21 @@ pub enum FetcherConfigError {
 	#[error("Email imap field is missing and it's not clear what it should be")]
 	EmailImapFieldMissing,
 
+	#[error("Email smtp field is missing and it's not clear what it should be")]
+	EmailSmtpFieldMissing,
+
 	#[error("Telegram bot token isn't set up")]
 	TelegramBotTokenMissing,
 
 	#[error("Discord bot token isn't set up")]
 	DiscordBotTokenMissing,
 
+	#[error("Matrix access token isn't set up")]
+	MatrixAccessTokenMissing,
+
+	#[error("HTTP secret {0:?} isn't set up")]
+	HttpSecretMissing(String),
+
 	#[error("Importing is unavailable")]
 	ImportingUnavailable,
 
@@ -37,18 +46,66 @@ pub enum FetcherConfigError {
 	//BadDurationFormat(#[from] duration_str::DError),
 	BadDurationFormat(String),
 
+	#[error("http_options - timeout {0:?} is not a valid duration format, e.g. 30s, 2m")]
+	BadHttpTimeout(String),
+
+	#[error(
+		"paginated - next_page - link query is for a different format than the one the pages are parsed as"
+	)]
+	PaginatedLinkMismatch,
+
+	#[error("file - rotate - size {0:?} is not a valid size, e.g. 1000, 512K, 10M")]
+	BadFileSize(String),
+
+	#[error("email - filters - {0:?} is neither a date, e.g. 2024-01-31, nor a duration, e.g. 7d")]
+	BadEmailDate(String),
+
+	#[error("exec - timeout {0:?} is not a valid duration format, e.g. 30s, 2m")]
+	BadExecTimeout(String),
+
+	#[error("exec - cmd can't be an empty list, it should contain at least the program to run")]
+	EmptyExecCommand,
+
 	#[error("refresh - at is not a valid time format, e.g. 14:30")]
 	BadTimeFormat(#[from] chrono::ParseError),
 
+	#[error("refresh - cron is not a valid cron expression, e.g. \"30 9,17 * * Mon-Fri\"")]
+	BadCronFormat(#[from] fetcher_core::job::timepoint::BadCronError),
+
+	#[error(
+		"refresh - cron - timezone {0:?} is not a valid IANA time zone name, e.g. Europe/Berlin"
+	)]
+	BadTimezone(String),
+
 	#[error("Error setting up HTTP client")]
 	FetcherCoreHttp(#[from] fetcher_core::source::http::HttpError),
 
+	#[error("Error setting up an email sink")]
+	FetcherCoreSmtp(#[from] fetcher_core::sink::email::SmtpError),
+
+	#[error("Error setting up a feed")]
+	FetcherCoreFeed(#[from] fetcher_core::sink::feed::FeedError),
+
+	#[error("Error setting up a webhook")]
+	FetcherCoreWebhook(#[from] fetcher_core::sink::webhook::WebhookError),
+
+	#[error("Error setting up a Matrix sink")]
+	FetcherCoreMatrix(#[from] fetcher_core::sink::matrix::MatrixError),
+
+	#[error("Error setting up a Discord webhook")]
+	FetcherCoreDiscordWebhook(#[from] fetcher_core::sink::discord::InvalidWebhookUrlError),
+
 	#[error("Error setting up HTML parser")]
 	FetcherCoreHtml(#[from] fetcher_core::action::transform::entry::html::HtmlError),
 
 	#[error("Error setting up regex")]
 	FetcherCoreBadRegex(#[from] fetcher_core::error::BadRegexError),
 
+	#[error(
+		"Invalid comparison {0:?}. It should be a comparison operator (<, <=, >, >=, ==, !=) followed by a number, e.g. \"< 100\""
+	)]
+	BadComparison(String),
+
 	#[error("Error setting up extract action")]
 	FetcherCoreExtract(#[from] fetcher_core::action::transform::field::extract::ExtractError),
 
@@ -4,6 +4,9 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+pub mod branch;
+pub mod compare;
+pub mod condition;
 pub mod contains;
 pub mod decode_html;
 pub mod extract;
@@ -19,21 +22,24 @@ pub mod trim;
 pub mod use_as;
 
 use self::{
-	contains::Contains, decode_html::DecodeHtml, extract::Extract, html::Html, import::Import,
-	json::Json, remove_html::RemoveHtml, replace::Replace, set::Set, shorten::Shorten, take::Take,
-	trim::Trim, use_as::Use,
+	branch::If, compare::Compare, condition::Condition, contains::Contains,
+	decode_html::DecodeHtml, extract::Extract, html::Html, import::Import, json::Json,
+	remove_html::RemoveHtml, replace::Replace, set::Set, shorten::Shorten, take::Take, trim::Trim,
+	use_as::Use,
 };
 use super::{external_data::ProvideExternalData, sink::Sink};
 use crate::FetcherConfigError;
 use fetcher_core::{
 	action::{
 		Action as CAction,
+		filter::Exists as CExists,
 		transform::{
 			Caps as CCaps, DebugPrint as CDebugPrint, Feed as CFeed, Http as CHttp,
 			field::{Field as CField, TransformFieldWrapper as CTransformFieldWrapper},
 		},
 	},
 	read_filter::ReadFilter as CReadFilter,
+	source::http::HttpClient as CHttpClient,
 };
 
 use serde::{Deserialize, Serialize};
@@ -47,6 +53,11 @@ pub enum Action {
 	ReadFilter,
 	Take(Take),
 	Contains(Contains),
+	Exists(Field),
+	Compare(Compare),
+	Not(Box<Condition>),
+	Any(Vec<Condition>),
+	All(Vec<Condition>),
 
 	// entry transforms
 	DebugPrint,
@@ -69,6 +80,7 @@ pub enum Action {
 	// other
 	Sink(Sink),
 	Import(Import),
+	If(If),
 }
 
 // TODO: add media
@@ -87,6 +99,7 @@ impl Action {
 	pub fn decode_from_conf<RF, D>(
 		self,
 		rf: Option<Arc<RwLock<RF>>>,
+		http_client: Option<&CHttpClient>,
 		external: &D,
 	) -> Result<Option<Vec<CAction>>, FetcherConfigError>
 	where
@@ -119,11 +132,23 @@ impl Action {
 			}
 			Action::Take(x) => filter!(x.decode_from_conf()),
 			Action::Contains(x) => x.decode_from_conf()?,
+			Action::Exists(x) => filter!(CExists(x.decode_from_conf())),
+			Action::Compare(x) => x
+				.decode_from_conf()?
+				.into_iter()
+				.map(|c| CAction::Filter(Box::new(c)))
+				.collect(),
+			Action::Not(x) => vec![CAction::Filter(Condition::Not(x).decode_from_conf()?)],
+			Action::Any(x) => vec![CAction::Filter(Condition::Any(x).decode_from_conf()?)],
+			Action::All(x) => vec![CAction::Filter(Condition::All(x).decode_from_conf()?)],
 
 			// entry transforms
 			Action::Feed => transform!(CFeed),
 			Action::Html(x) => transform!(x.decode_from_conf()?),
-			Action::Http => transform!(CHttp::new(CField::Link)?),
+			Action::Http => match http_client {
+				Some(client) => transform!(CHttp::with_client(CField::Link, client.clone())),
+				None => transform!(CHttp::new(CField::Link)?),
+			},
 			Action::Json(x) => transform!(x.decode_from_conf()?),
 			Action::Use(x) => x.decode_from_conf(),
 
@@ -143,10 +168,11 @@ impl Action {
 
 			// other
 			Action::Sink(x) => vec![CAction::Sink(x.decode_from_conf(external)?)],
-			Action::Import(x) => match x.decode_from_conf(rf, external) {
+			Action::Import(x) => match x.decode_from_conf(rf, http_client, external) {
 				Ok(Some(v)) => v,
 				not_ok => return not_ok,
 			},
+			Action::If(x) => vec![x.decode_from_conf(rf.as_ref(), http_client, external)?],
 		};
 
 		Ok(Some(act))
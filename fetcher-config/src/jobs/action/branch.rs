@@ -0,0 +1,102 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{Action, condition::Condition};
+use crate::{error::FetcherConfigError as ConfigError, jobs::external_data::ProvideExternalData};
+use fetcher_core::{
+	action::{Action as CAction, branch::Branch as CBranch},
+	read_filter::ReadFilter as CReadFilter,
+	source::http::HttpClient as CHttpClient,
+};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct If {
+	#[serde(flatten)]
+	pub condition: Condition,
+	pub then: Option<Vec<Action>>,
+	#[serde(rename = "else")]
+	pub otherwise: Option<Vec<Action>>,
+}
+
+// serde ignores unknown fields next to a flattened enum, so all keys except `then` and `else` are checked to be a single condition manually
+impl<'de> Deserialize<'de> for If {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let mut fields = serde_json::Map::deserialize(deserializer)?;
+
+		let mut take = |key| {
+			fields
+				.remove(key)
+				.map(serde_json::from_value)
+				.transpose()
+				.map_err(D::Error::custom)
+		};
+
+		let then = take("then")?;
+		let otherwise = take("else")?;
+
+		if fields.len() != 1 {
+			return Err(D::Error::custom(format!(
+				"expected a single condition, got {}: {:?}",
+				fields.len(),
+				fields.keys().collect::<Vec<_>>()
+			)));
+		}
+
+		let condition =
+			serde_json::from_value(serde_json::Value::Object(fields)).map_err(D::Error::custom)?;
+
+		Ok(Self {
+			condition,
+			then,
+			otherwise,
+		})
+	}
+}
+
+impl If {
+	pub fn decode_from_conf<RF, D>(
+		self,
+		rf: Option<&Arc<RwLock<RF>>>,
+		http_client: Option<&CHttpClient>,
+		external: &D,
+	) -> Result<CAction, ConfigError>
+	where
+		RF: CReadFilter + 'static,
+		D: ProvideExternalData + ?Sized,
+	{
+		Ok(CAction::Branch(CBranch {
+			condition: self.condition.decode_from_conf()?,
+			then: decode_actions(self.then, rf, http_client, external)?,
+			otherwise: decode_actions(self.otherwise, rf, http_client, external)?,
+		}))
+	}
+}
+
+fn decode_actions<RF, D>(
+	actions: Option<Vec<Action>>,
+	rf: Option<&Arc<RwLock<RF>>>,
+	http_client: Option<&CHttpClient>,
+	external: &D,
+) -> Result<Vec<CAction>, ConfigError>
+where
+	RF: CReadFilter + 'static,
+	D: ProvideExternalData + ?Sized,
+{
+	itertools::process_results(
+		actions.into_iter().flatten().filter_map(|act| {
+			act.decode_from_conf(rf.cloned(), http_client, external)
+				.transpose()
+		}),
+		|i| i.flatten().collect(),
+	)
+}
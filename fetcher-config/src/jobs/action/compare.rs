@@ -0,0 +1,56 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::Field;
+use crate::error::FetcherConfigError as ConfigError;
+use fetcher_core::action::filter::compare::{Compare as CCompare, CompareOp as CCompareOp};
+
+/// A comparison operator followed by a number, e.g. `< 100`
+type Comparison = String;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Compare(pub HashMap<Field, Comparison>);
+
+impl Compare {
+	pub fn decode_from_conf(self) -> Result<Vec<CCompare>, ConfigError> {
+		self.0
+			.into_iter()
+			.map(|(field, comparison)| {
+				let (op, value) =
+					parse_comparison(&comparison).ok_or(ConfigError::BadComparison(comparison))?;
+
+				Ok(CCompare {
+					field: field.decode_from_conf(),
+					op,
+					value,
+				})
+			})
+			.collect()
+	}
+}
+
+fn parse_comparison(s: &str) -> Option<(CCompareOp, f64)> {
+	// longer operators go first to avoid matching "<" in "<="
+	const OPS: &[(&str, CCompareOp)] = &[
+		("<=", CCompareOp::LessOrEqual),
+		(">=", CCompareOp::GreaterOrEqual),
+		("==", CCompareOp::Equal),
+		("!=", CCompareOp::NotEqual),
+		("<", CCompareOp::Less),
+		(">", CCompareOp::Greater),
+		("=", CCompareOp::Equal),
+	];
+
+	let s = s.trim();
+	OPS.iter().find_map(|&(op_str, op)| {
+		let value = s.strip_prefix(op_str)?.trim().parse().ok()?;
+		Some((op, value))
+	})
+}
@@ -0,0 +1,62 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use super::{Field, compare::Compare, contains::Contains};
+use crate::error::FetcherConfigError as ConfigError;
+use fetcher_core::action::filter::{
+	All as CAll, Any as CAny, Condition as CCondition, Exists as CExists, Not as CNot,
+};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Condition {
+	Contains(Contains),
+	Exists(Field),
+	Compare(Compare),
+	Not(Box<Condition>),
+	Any(Vec<Condition>),
+	All(Vec<Condition>),
+}
+
+impl Condition {
+	pub fn decode_from_conf(self) -> Result<Box<dyn CCondition>, ConfigError> {
+		Ok(match self {
+			Condition::Contains(x) => all(x
+				.decode_into_conditions()?
+				.into_iter()
+				.map(|c| Box::new(c) as Box<dyn CCondition>)
+				.collect()),
+			Condition::Exists(x) => Box::new(CExists(x.decode_from_conf())),
+			Condition::Compare(x) => all(x
+				.decode_from_conf()?
+				.into_iter()
+				.map(|c| Box::new(c) as Box<dyn CCondition>)
+				.collect()),
+			Condition::Not(x) => Box::new(CNot(x.decode_from_conf()?)),
+			Condition::Any(x) => Box::new(CAny(
+				x.into_iter()
+					.map(Condition::decode_from_conf)
+					.collect::<Result<_, _>>()?,
+			)),
+			Condition::All(x) => Box::new(CAll(
+				x.into_iter()
+					.map(Condition::decode_from_conf)
+					.collect::<Result<_, _>>()?,
+			)),
+		})
+	}
+}
+
+/// Combine several conditions into one that matches only if all of them do
+fn all(mut conditions: Vec<Box<dyn CCondition>>) -> Box<dyn CCondition> {
+	if conditions.len() == 1 {
+		conditions.remove(0)
+	} else {
+		Box::new(CAll(conditions))
+	}
+}
@@ -19,14 +19,17 @@ pub struct Contains(pub HashMap<Field, RegEx>);
 
 impl Contains {
 	pub fn decode_from_conf(self) -> Result<Vec<CAction>, ConfigError> {
+		Ok(self
+			.decode_into_conditions()?
+			.into_iter()
+			.map(|c| CAction::Filter(Box::new(c)))
+			.collect())
+	}
+
+	pub fn decode_into_conditions(self) -> Result<Vec<CContains>, ConfigError> {
 		self.0
 			.into_iter()
-			.map(|(field, re)| {
-				Ok(CAction::Filter(Box::new(CContains::new(
-					&re,
-					field.decode_from_conf(),
-				)?)))
-			})
+			.map(|(field, re)| Ok(CContains::new(&re, field.decode_from_conf())?))
 			.collect()
 	}
 }
@@ -8,7 +8,10 @@ use crate::{
 	error::{FetcherConfigError, Result},
 	jobs::external_data::{ExternalDataResult, ProvideExternalData},
 };
-use fetcher_core::{action::Action as CAction, read_filter::ReadFilter as CReadFilter};
+use fetcher_core::{
+	action::Action as CAction, read_filter::ReadFilter as CReadFilter,
+	source::http::HttpClient as CHttpClient,
+};
 
 use itertools::process_results;
 use serde::{Deserialize, Serialize};
@@ -23,6 +26,7 @@ impl Import {
 	pub fn decode_from_conf<RF, D>(
 		self,
 		rf: Option<Arc<RwLock<RF>>>,
+		http_client: Option<&CHttpClient>,
 		external: &D,
 	) -> Result<Option<Vec<CAction>>>
 	where
@@ -33,7 +37,7 @@ impl Import {
 			ExternalDataResult::Ok(x) => {
 				let v = process_results(
 					x.into_iter()
-						.map(|x| x.decode_from_conf(rf.clone(), external)),
+						.map(|x| x.decode_from_conf(rf.clone(), http_client, external)),
 					|i| {
 						i.flatten(/* option */).flatten(/* inner vec */).collect::<Vec<_>>()
 					},
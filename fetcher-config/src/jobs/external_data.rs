@@ -10,8 +10,8 @@ use super::{
 	read_filter::Kind as ReadFilterKind,
 };
 use fetcher_core::{
-	auth as c_auth, read_filter::ReadFilter as CReadFilter, task::entry_to_msg_map::EntryToMsgMap,
-	utils::DisplayDebug,
+	auth as c_auth, read_filter::ReadFilter as CReadFilter, source::http::cache::HttpCache,
+	task::entry_to_msg_map::EntryToMsgMap, utils::DisplayDebug,
 };
 
 use std::{
@@ -42,6 +42,12 @@ pub trait ProvideExternalData {
 	fn discord_bot_token(&self) -> ExternalDataResult<String> {
 		ExternalDataResult::Unavailable
 	}
+	fn matrix_access_token(&self) -> ExternalDataResult<String> {
+		ExternalDataResult::Unavailable
+	}
+	fn http_secret(&self, _name: &str) -> ExternalDataResult<String> {
+		ExternalDataResult::Unavailable
+	}
 
 	fn read_filter(
 		&self,
@@ -60,6 +66,14 @@ pub trait ProvideExternalData {
 		ExternalDataResult::Unavailable
 	}
 
+	fn http_cache(
+		&self,
+		_job: &JobName,
+		_task: Option<&TaskName>,
+	) -> ExternalDataResult<HttpCache> {
+		ExternalDataResult::Unavailable
+	}
+
 	/// import action `name`
 	fn import(&self, _name: &str) -> ExternalDataResult<Vec<Action>> {
 		ExternalDataResult::Unavailable
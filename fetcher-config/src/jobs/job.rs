@@ -16,7 +16,7 @@ use super::{
 	read_filter,
 	sink::Sink,
 	source::Source,
-	task::Task,
+	task::{Task, digest::Digest, http_options::HttpOptions},
 };
 use crate::FetcherConfigError;
 use fetcher_core::{job::Job as CJob, utils::OptionExt};
@@ -36,7 +36,11 @@ pub struct Job {
 	#[serde(rename = "process")]
 	pub actions: Option<Vec<Action>>,
 	pub entry_to_msg_map_enabled: Option<bool>,
+	pub edit_on_update: Option<bool>,
+	pub delete_removed: Option<bool>,
 	pub sink: Option<Sink>,
+	pub digest: Option<Digest>,
+	pub http_options: Option<HttpOptions>,
 
 	pub tasks: Option<HashMap<TaskName, Task>>,
 	pub refresh: Option<TimePoint>,
@@ -68,7 +72,11 @@ impl Job {
 					source: self.source,
 					actions: self.actions,
 					entry_to_msg_map_enabled: self.entry_to_msg_map_enabled,
+					edit_on_update: self.edit_on_update,
+					delete_removed: self.delete_removed,
 					sink: self.sink,
+					digest: self.digest,
+					http_options: self.http_options,
 				};
 
 				let job = CJob {
@@ -116,9 +124,25 @@ impl Job {
 				task.entry_to_msg_map_enabled = self.entry_to_msg_map_enabled;
 			}
 
+			if task.edit_on_update.is_none() {
+				task.edit_on_update = self.edit_on_update;
+			}
+
+			if task.delete_removed.is_none() {
+				task.delete_removed = self.delete_removed;
+			}
+
 			if task.sink.is_none() {
 				task.sink.clone_from(&self.sink);
 			}
+
+			if task.digest.is_none() {
+				task.digest.clone_from(&self.digest);
+			}
+
+			if task.http_options.is_none() {
+				task.http_options.clone_from(&self.http_options);
+			}
 		}
 
 		// FIXME: broken. Filtering can remove tasks from the tasks map. Then, when checking if we should pass the task name as a tag, we ignore the fact that we could've had more tasks in the job and skip the tag which we shouldn't do
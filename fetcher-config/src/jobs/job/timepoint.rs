@@ -5,7 +5,7 @@
  */
 
 use crate::FetcherConfigError;
-use fetcher_core::job::timepoint::TimePoint as CTimePoint;
+use fetcher_core::job::timepoint::{Cron as CCron, TimePoint as CTimePoint};
 
 use serde::{Deserialize, Serialize};
 
@@ -14,6 +14,14 @@ use serde::{Deserialize, Serialize};
 pub enum TimePoint {
 	Every(String),
 	At(String),
+	Cron(Cron),
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+pub enum Cron {
+	Schedule(String),
+	WithTimezone { schedule: String, timezone: String },
 }
 
 impl TimePoint {
@@ -26,6 +34,24 @@ impl TimePoint {
 				let time = chrono::NaiveTime::parse_from_str(&at, "%H:%M")?;
 				CTimePoint::Time(time)
 			}
+			TimePoint::Cron(cron) => CTimePoint::Cron(Box::new(cron.decode_from_conf()?)),
 		})
 	}
 }
+
+impl Cron {
+	pub fn decode_from_conf(self) -> Result<CCron, FetcherConfigError> {
+		let (schedule, timezone) = match self {
+			Cron::Schedule(schedule) => (schedule, None),
+			Cron::WithTimezone { schedule, timezone } => {
+				let tz = timezone
+					.parse::<chrono_tz::Tz>()
+					.map_err(|_| FetcherConfigError::BadTimezone(timezone))?;
+
+				(schedule, Some(tz))
+			}
+		};
+
+		Ok(CCron::new(&schedule, timezone)?)
+	}
+}
@@ -5,10 +5,18 @@
  */
 
 mod discord;
+mod email;
 mod exec;
+mod feed;
+mod file;
+mod matrix;
 mod telegram;
+mod webhook;
 
-use self::{discord::Discord, exec::Exec, telegram::Telegram};
+use self::{
+	discord::Discord, email::Email, exec::Exec, feed::Feed, file::File, matrix::Matrix,
+	telegram::Telegram, webhook::Webhook,
+};
 use crate::{FetcherConfigError, jobs::external_data::ProvideExternalData};
 use fetcher_core::sink::{Sink as CSink, Stdout as CStdout};
 
@@ -19,6 +27,11 @@ use serde::{Deserialize, Serialize};
 pub enum Sink {
 	Telegram(Telegram),
 	Discord(Discord),
+	Matrix(Matrix),
+	Email(Email),
+	Webhook(Webhook),
+	Feed(Feed),
+	File(File),
 	Exec(Exec),
 	Stdout,
 }
@@ -31,7 +44,12 @@ impl Sink {
 		Ok(match self {
 			Self::Telegram(x) => Box::new(x.decode_from_conf(external)?),
 			Self::Discord(x) => Box::new(x.decode_from_conf(external)?),
-			Self::Exec(x) => Box::new(x.decode_from_conf()),
+			Self::Matrix(x) => Box::new(x.decode_from_conf(external)?),
+			Self::Email(x) => Box::new(x.decode_from_conf(external)?),
+			Self::Webhook(x) => Box::new(x.decode_from_conf()?),
+			Self::Feed(x) => Box::new(x.decode_from_conf()?),
+			Self::File(x) => Box::new(x.decode_from_conf()?),
+			Self::Exec(x) => Box::new(x.decode_from_conf()?),
 			Self::Stdout => Box::new(CStdout {}),
 		})
 	}
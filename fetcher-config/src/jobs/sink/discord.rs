@@ -8,14 +8,18 @@ use crate::{
 	FetcherConfigError as ConfigError,
 	jobs::external_data::{ExternalDataResult, ProvideExternalData},
 };
-use fetcher_core::sink::discord::{Discord as CDiscord, Target as CTarget};
+use fetcher_core::sink::discord::{
+	Discord as CDiscord, Target as CTarget, Threads as CThreads, Webhook as CWebhook,
+};
 
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
-#[serde(transparent)]
 pub struct Discord {
+	#[serde(flatten)]
 	pub target: Target,
+	pub threads: Option<Threads>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -23,6 +27,22 @@ pub struct Discord {
 pub enum Target {
 	User(u64),
 	Channel(u64),
+	Webhook(Webhook),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+	pub url: Url,
+	pub username: Option<String>,
+	pub avatar_url: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Threads {
+	PerEntry,
+	PerTag,
 }
 
 impl Discord {
@@ -30,21 +50,46 @@ impl Discord {
 	where
 		D: ProvideExternalData + ?Sized,
 	{
+		let with_threads = |discord: CDiscord| match self.threads {
+			Some(threads) => discord.with_threads(threads.decode_from_conf()),
+			None => discord,
+		};
+
+		let target = match self.target {
+			Target::User(i) => CTarget::User(i),
+			Target::Channel(i) => CTarget::Channel(i),
+			// webhooks don't need a bot
+			Target::Webhook(webhook) => {
+				let discord = CDiscord::new_webhook(webhook.decode_from_conf())?;
+				return Ok(with_threads(discord));
+			}
+		};
+
 		let token = match external.discord_bot_token() {
 			ExternalDataResult::Ok(v) => v,
 			ExternalDataResult::Unavailable => return Err(ConfigError::DiscordBotTokenMissing),
 			ExternalDataResult::Err(e) => return Err(e.into()),
 		};
 
-		Ok(CDiscord::new(&token, self.target.decode_from_conf()))
+		Ok(with_threads(CDiscord::new(&token, target)))
 	}
 }
 
-impl Target {
-	pub fn decode_from_conf(self) -> CTarget {
+impl Webhook {
+	pub fn decode_from_conf(self) -> CWebhook {
+		CWebhook {
+			url: self.url,
+			username: self.username,
+			avatar_url: self.avatar_url,
+		}
+	}
+}
+
+impl Threads {
+	pub fn decode_from_conf(self) -> CThreads {
 		match self {
-			Target::User(i) => CTarget::User(i),
-			Target::Channel(i) => CTarget::Channel(i),
+			Threads::PerEntry => CThreads::PerEntry,
+			Threads::PerTag => CThreads::PerTag,
 		}
 	}
 }
@@ -0,0 +1,96 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{
+	FetcherConfigError as ConfigError,
+	jobs::external_data::{ExternalDataResult, ProvideExternalData},
+};
+use fetcher_core::sink::{Email as CEmail, email::Security as CSecurity};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Email {
+	smtp: Option<String>,
+	port: Option<u16>,
+	security: Option<Security>,
+	#[serde(rename = "email")]
+	address: String,
+	auth: Auth,
+	to: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Auth {
+	#[serde(rename = "gmail_oauth2")]
+	GmailOAuth2,
+	Password,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Security {
+	Tls,
+	#[serde(rename = "starttls")]
+	StartTls,
+	None,
+}
+
+impl Email {
+	pub fn decode_from_conf<D>(self, external: &D) -> Result<CEmail, ConfigError>
+	where
+		D: ProvideExternalData + ?Sized,
+	{
+		Ok(match self.auth {
+			Auth::GmailOAuth2 => {
+				if self.smtp.is_some() || self.port.is_some() || self.security.is_some() {
+					tracing::warn!("The smtp, port, and security fields are ignored in Gmail mode");
+				}
+
+				let oauth = match external.google_oauth2() {
+					ExternalDataResult::Ok(v) => v,
+					ExternalDataResult::Unavailable => {
+						return Err(ConfigError::GoogleOAuth2TokenMissing);
+					}
+					ExternalDataResult::Err(e) => return Err(e.into()),
+				};
+
+				CEmail::new_gmail(self.address, oauth, self.to)?
+			}
+			Auth::Password => {
+				let passwd = match external.email_password() {
+					ExternalDataResult::Ok(v) => v,
+					ExternalDataResult::Unavailable => {
+						return Err(ConfigError::EmailPasswordMissing);
+					}
+					ExternalDataResult::Err(e) => return Err(e.into()),
+				};
+
+				CEmail::new_generic(
+					self.smtp.ok_or(ConfigError::EmailSmtpFieldMissing)?,
+					self.port,
+					self.security
+						.map_or(CSecurity::Tls, Security::decode_from_conf),
+					self.address,
+					passwd,
+					self.to,
+				)?
+			}
+		})
+	}
+}
+
+impl Security {
+	pub fn decode_from_conf(self) -> CSecurity {
+		match self {
+			Security::Tls => CSecurity::Tls,
+			Security::StartTls => CSecurity::StartTls,
+			Security::None => CSecurity::None,
+		}
+	}
+}
@@ -4,17 +4,19 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use crate::{FetcherConfigError, jobs::source::exec::Command};
+
 use fetcher_core::sink::Exec as CExec;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(transparent)]
 pub struct Exec {
-	pub cmd: String,
+	pub cmd: Command,
 }
 
 impl Exec {
-	pub fn decode_from_conf(self) -> CExec {
-		CExec { cmd: self.cmd }
+	pub fn decode_from_conf(self) -> Result<CExec, FetcherConfigError> {
+		self.cmd.decode_from_conf()
 	}
 }
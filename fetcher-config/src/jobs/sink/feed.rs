@@ -0,0 +1,81 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::FetcherConfigError as ConfigError;
+use fetcher_core::sink::{
+	Feed as CFeed,
+	feed::{DEFAULT_MAX_ITEMS, Format as CFormat},
+};
+
+use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, path::PathBuf};
+use url::Url;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Feed {
+	pub path: PathBuf,
+	pub format: Option<Format>,
+	pub title: Option<String>,
+	pub link: Option<Url>,
+	pub max_items: Option<usize>,
+	pub serve: Option<Serve>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Format {
+	Atom,
+	Rss,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Serve {
+	pub address: SocketAddr,
+	pub path: Option<String>,
+}
+
+impl Feed {
+	pub fn decode_from_conf(self) -> Result<CFeed, ConfigError> {
+		let file_name = self
+			.path
+			.file_name()
+			.map(|name| name.to_string_lossy().into_owned())
+			.unwrap_or_default();
+
+		let title = self.title.unwrap_or_else(|| {
+			self.path.file_stem().map_or_else(
+				|| file_name.clone(),
+				|stem| stem.to_string_lossy().into_owned(),
+			)
+		});
+
+		let mut feed = CFeed::new(
+			self.path,
+			self.format.map_or(CFormat::Atom, Format::decode_from_conf),
+			title,
+			self.link,
+			self.max_items.unwrap_or(DEFAULT_MAX_ITEMS),
+		);
+
+		if let Some(serve) = self.serve {
+			let path = serve.path.unwrap_or_else(|| format!("/{file_name}"));
+			feed.serve(serve.address, path)?;
+		}
+
+		Ok(feed)
+	}
+}
+
+impl Format {
+	pub fn decode_from_conf(self) -> CFormat {
+		match self {
+			Format::Atom => CFormat::Atom,
+			Format::Rss => CFormat::Rss,
+		}
+	}
+}
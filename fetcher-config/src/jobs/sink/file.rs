@@ -0,0 +1,96 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::FetcherConfigError as ConfigError;
+use fetcher_core::sink::{
+	File as CFile,
+	file::{Format as CFormat, Rotation as CRotation},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct File {
+	pub path: String,
+	pub format: Option<Format>,
+	pub rotate: Option<Rotation>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Format {
+	Text,
+	JsonLines,
+	Markdown,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Rotation {
+	Size(Size),
+	Daily,
+}
+
+// either a number of bytes or a string with a K/M/G suffix, e.g. 10M
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Size {
+	Bytes(u64),
+	WithSuffix(String),
+}
+
+impl File {
+	pub fn decode_from_conf(self) -> Result<CFile, ConfigError> {
+		Ok(CFile::new(
+			self.path,
+			self.format.map_or(CFormat::Text, Format::decode_from_conf),
+			self.rotate.map(Rotation::decode_from_conf).transpose()?,
+		))
+	}
+}
+
+impl Format {
+	pub fn decode_from_conf(self) -> CFormat {
+		match self {
+			Format::Text => CFormat::Text,
+			Format::JsonLines => CFormat::JsonLines,
+			Format::Markdown => CFormat::Markdown,
+		}
+	}
+}
+
+impl Rotation {
+	pub fn decode_from_conf(self) -> Result<CRotation, ConfigError> {
+		Ok(match self {
+			Rotation::Size(size) => CRotation::Size(size.decode_from_conf()?),
+			Rotation::Daily => CRotation::Daily,
+		})
+	}
+}
+
+impl Size {
+	pub fn decode_from_conf(self) -> Result<u64, ConfigError> {
+		let s = match self {
+			Size::Bytes(bytes) => return Ok(bytes),
+			Size::WithSuffix(s) => s,
+		};
+
+		let trimmed = s.trim();
+		let (num, multiplier) = match trimmed.char_indices().last() {
+			Some((i, 'K' | 'k')) => (&trimmed[..i], 1024),
+			Some((i, 'M' | 'm')) => (&trimmed[..i], 1024 * 1024),
+			Some((i, 'G' | 'g')) => (&trimmed[..i], 1024 * 1024 * 1024),
+			_ => (trimmed, 1),
+		};
+
+		num.trim()
+			.parse::<u64>()
+			.ok()
+			.and_then(|num| num.checked_mul(multiplier))
+			.ok_or(ConfigError::BadFileSize(s))
+	}
+}
@@ -0,0 +1,36 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{
+	FetcherConfigError as ConfigError,
+	jobs::external_data::{ExternalDataResult, ProvideExternalData},
+};
+use fetcher_core::sink::Matrix as CMatrix;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Matrix {
+	pub homeserver: Url,
+	pub room_id: String,
+}
+
+impl Matrix {
+	pub fn decode_from_conf<D>(self, external: &D) -> Result<CMatrix, ConfigError>
+	where
+		D: ProvideExternalData + ?Sized,
+	{
+		let access_token = match external.matrix_access_token() {
+			ExternalDataResult::Ok(v) => v,
+			ExternalDataResult::Unavailable => return Err(ConfigError::MatrixAccessTokenMissing),
+			ExternalDataResult::Err(e) => return Err(e.into()),
+		};
+
+		Ok(CMatrix::new(self.homeserver, access_token, self.room_id)?)
+	}
+}
@@ -10,13 +10,30 @@ use crate::{
 	FetcherConfigError as ConfigError,
 	jobs::external_data::{ExternalDataResult, ProvideExternalData},
 };
-use fetcher_core::sink::{Telegram as CTelegram, telegram::LinkLocation as CLinkLocation};
+use fetcher_core::sink::{
+	Telegram as CTelegram,
+	telegram::{
+		Button as CButton, Chat as CChat, LinkLocation as CLinkLocation, Options as COptions,
+	},
+};
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct Telegram {
-	pub chat_id: i64,
+	pub chat_id: Chat,
 	pub link_location: Option<LinkLocation>,
+	pub topic_id: Option<i32>,
+	pub disable_notification: Option<bool>,
+	pub protect_content: Option<bool>,
+	pub link_preview: Option<bool>,
+	pub buttons: Option<Vec<Button>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Chat {
+	Id(i64),
+	Username(String),
 }
 
 /// Refer to [`crate::sink::message::LinkLocation`]
@@ -27,6 +44,13 @@ pub enum LinkLocation {
 	Bottom,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Button {
+	pub text: String,
+	pub url: String,
+}
+
 impl Telegram {
 	pub fn decode_from_conf<D>(self, external: &D) -> Result<CTelegram, ConfigError>
 	where
@@ -38,15 +62,38 @@ impl Telegram {
 			ExternalDataResult::Err(e) => return Err(e.into()),
 		};
 
+		let options = COptions {
+			message_thread_id: self.topic_id,
+			disable_notification: self.disable_notification.unwrap_or(false),
+			protect_content: self.protect_content.unwrap_or(false),
+			link_preview: self.link_preview.unwrap_or(false),
+			buttons: self
+				.buttons
+				.into_iter()
+				.flatten()
+				.map(Button::decode_from_conf)
+				.collect(),
+		};
+
 		Ok(CTelegram::new(
 			token,
-			self.chat_id,
+			self.chat_id.decode_from_conf(),
 			self.link_location
 				.map_or(CLinkLocation::PreferTitle, LinkLocation::decode_from_conf),
+			options,
 		))
 	}
 }
 
+impl Chat {
+	pub fn decode_from_conf(self) -> CChat {
+		match self {
+			Chat::Id(id) => CChat::Id(id),
+			Chat::Username(username) => CChat::Username(username),
+		}
+	}
+}
+
 impl LinkLocation {
 	pub fn decode_from_conf(self) -> CLinkLocation {
 		match self {
@@ -55,3 +102,12 @@ impl LinkLocation {
 		}
 	}
 }
+
+impl Button {
+	pub fn decode_from_conf(self) -> CButton {
+		CButton {
+			text: self.text,
+			url: self.url,
+		}
+	}
+}
@@ -0,0 +1,52 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::FetcherConfigError as ConfigError;
+use fetcher_core::sink::{Webhook as CWebhook, webhook::Method as CMethod};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use url::Url;
+
+const DEFAULT_RETRIES: u32 = 3;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+	pub url: Url,
+	pub method: Option<Method>,
+	pub headers: Option<HashMap<String, String>>,
+	pub body: Option<serde_json::Value>,
+	pub retries: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Method {
+	Post,
+	Put,
+}
+
+impl Webhook {
+	pub fn decode_from_conf(self) -> Result<CWebhook, ConfigError> {
+		Ok(CWebhook::new(
+			self.url,
+			self.method.map_or(CMethod::Post, Method::decode_from_conf),
+			self.headers.unwrap_or_default(),
+			self.body,
+			self.retries.unwrap_or(DEFAULT_RETRIES),
+		)?)
+	}
+}
+
+impl Method {
+	pub fn decode_from_conf(self) -> CMethod {
+		match self {
+			Method::Post => CMethod::Post,
+			Method::Put => CMethod::Put,
+		}
+	}
+}
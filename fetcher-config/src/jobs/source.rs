@@ -8,18 +8,27 @@ pub mod email;
 pub mod exec;
 pub mod file;
 pub mod http;
+pub mod paginated;
 pub mod reddit;
 pub mod string;
 
 use self::{
-	email::Email, exec::Exec, file::File, http::Http, reddit::Reddit, string::StringSource,
+	email::Email, exec::Exec, file::File, http::Http, paginated::Paginated, reddit::Reddit,
+	string::StringSource,
+};
+use crate::{
+	FetcherConfigError,
+	jobs::{
+		external_data::ProvideExternalData,
+		named::{JobName, TaskName},
+	},
 };
-use crate::{FetcherConfigError, jobs::external_data::ProvideExternalData};
 use fetcher_core::{
+	action::filter::Filter as CFilter,
 	read_filter::ReadFilter as CReadFilter,
 	source::{
 		Source as CSource, SourceWithSharedRF as CSourceWithSharedRF,
-		always_errors::AlwaysErrors as CAlwaysErrors,
+		always_errors::AlwaysErrors as CAlwaysErrors, http::HttpClient as CHttpClient,
 	},
 };
 
@@ -32,6 +41,7 @@ pub enum Source {
 	// with shared read filter
 	String(StringSource),
 	Http(Http),
+	Paginated(Paginated),
 	File(File),
 	Reddit(Reddit),
 	Exec(Exec),
@@ -45,10 +55,13 @@ impl Source {
 	pub fn decode_from_conf<RF, D>(
 		self,
 		rf: Option<RF>,
+		job: &JobName,
+		task_name: Option<&TaskName>,
+		http_client: Option<&CHttpClient>,
 		external: &D,
 	) -> Result<Box<dyn CSource>, FetcherConfigError>
 	where
-		RF: CReadFilter + 'static,
+		RF: CReadFilter + Clone + 'static,
 		D: ProvideExternalData + ?Sized,
 	{
 		// make a dyn CSourceWithSharedRF out of a CFetch and the read filter parameter
@@ -64,10 +77,24 @@ impl Source {
 		Ok(match self {
 			// with shared read filter
 			Self::String(x) => with_read_filter!(x.decode_from_conf()),
-			Self::Http(x) => with_read_filter!(x.decode_from_conf()?),
+			Self::Http(x) => {
+				with_read_filter!(x.decode_from_conf(job, task_name, http_client, external)?)
+			}
+			Self::Paginated(x) => {
+				// stop fetching pages once an already read entry has been reached
+				let paginated_rf = rf.clone().map(|rf| Box::new(rf) as Box<dyn CFilter>);
+
+				with_read_filter!(x.decode_from_conf(
+					paginated_rf,
+					job,
+					task_name,
+					http_client,
+					external
+				)?)
+			}
 			Self::File(x) => with_read_filter!(x.decode_from_conf()),
 			Self::Reddit(x) => with_read_filter!(x.decode_from_conf()),
-			Self::Exec(x) => with_read_filter!(x.decode_from_conf()),
+			Self::Exec(x) => with_read_filter!(x.decode_from_conf()?),
 
 			// with custom read filter
 			Self::Email(x) => Box::new(x.decode_from_conf(external)?),
@@ -6,25 +6,31 @@
 
 mod auth;
 mod filters;
+mod security;
 mod view_mode;
 
 use serde::{Deserialize, Serialize};
 
-use self::{auth::Auth, filters::Filters, view_mode::ViewMode};
+use self::{auth::Auth, filters::Filters, security::Security, view_mode::ViewMode};
 use crate::{
 	FetcherConfigError as ConfigError,
 	jobs::external_data::{ExternalDataResult, ProvideExternalData},
 };
-use fetcher_core::source::Email as CEmail;
+use fetcher_core::source::{Email as CEmail, email::Security as CSecurity};
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct Email {
 	imap: Option<String>,
+	port: Option<u16>,
+	security: Option<Security>,
 	email: String,
 	auth: Auth,
+	mailbox: Option<String>,
+	#[serde(default)]
 	filters: Filters,
 	view_mode: ViewMode,
+	idle: Option<bool>,
 }
 
 impl Email {
@@ -32,10 +38,10 @@ impl Email {
 	where
 		D: ProvideExternalData + ?Sized,
 	{
-		Ok(match self.auth {
+		let mut email = match self.auth {
 			Auth::GmailOAuth2 => {
-				if self.imap.is_some() {
-					tracing::warn!("The imap address field is ignored in Gmail mode");
+				if self.imap.is_some() || self.port.is_some() || self.security.is_some() {
+					tracing::warn!("The imap, port, and security fields are ignored in Gmail mode");
 				}
 
 				let oauth = match external.google_oauth2() {
@@ -49,7 +55,7 @@ impl Email {
 				CEmail::new_gmail(
 					self.email,
 					oauth,
-					self.filters.decode_from_conf(),
+					self.filters.decode_from_conf()?,
 					self.view_mode.decode_from_conf(),
 				)
 			}
@@ -64,12 +70,22 @@ impl Email {
 
 				CEmail::new_generic(
 					self.imap.ok_or(ConfigError::EmailImapFieldMissing)?,
+					self.port,
+					self.security
+						.map_or(CSecurity::Tls, Security::decode_from_conf),
 					self.email,
 					passwd,
-					self.filters.decode_from_conf(),
+					self.filters.decode_from_conf()?,
 					self.view_mode.decode_from_conf(),
 				)
 			}
-		})
+		};
+
+		if let Some(mailbox) = self.mailbox {
+			email.mailbox = mailbox;
+		}
+		email.idle = self.idle.unwrap_or(false);
+
+		Ok(email)
 	}
 }
@@ -4,24 +4,59 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use fetcher_core::source::email::Filters as CFilters;
+use crate::FetcherConfigError;
+use fetcher_core::source::email::{DateBound as CDateBound, Filters as CFilters};
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use serde_with::{OneOrMany, serde_as};
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde_as]
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct Filters {
-	sender: Option<String>,
+	#[serde_as(deserialize_as = "Option<OneOrMany<_>>")]
+	#[serde(default)]
+	sender: Option<Vec<String>>,
+	#[serde_as(deserialize_as = "Option<OneOrMany<_>>")]
+	#[serde(default)]
+	recipient: Option<Vec<String>>,
 	subjects: Option<Vec<String>>,
 	exclude_subjects: Option<Vec<String>>,
+	#[serde_as(deserialize_as = "Option<OneOrMany<_>>")]
+	#[serde(default)]
+	body: Option<Vec<String>>,
+	since: Option<String>,
+	before: Option<String>,
+	unseen: Option<bool>,
+	flagged: Option<bool>,
+	raw: Option<String>,
 }
 
 impl Filters {
-	pub fn decode_from_conf(self) -> CFilters {
-		CFilters {
-			sender: self.sender,
-			subjects: self.subjects,
-			exclude_subjects: self.exclude_subjects,
-		}
+	pub fn decode_from_conf(self) -> Result<CFilters, FetcherConfigError> {
+		Ok(CFilters {
+			senders: self.sender.unwrap_or_default(),
+			recipients: self.recipient.unwrap_or_default(),
+			subjects: self.subjects.unwrap_or_default(),
+			exclude_subjects: self.exclude_subjects.unwrap_or_default(),
+			body: self.body.unwrap_or_default(),
+			since: self.since.map(decode_date).transpose()?,
+			before: self.before.map(decode_date).transpose()?,
+			unseen: self.unseen.unwrap_or(true),
+			flagged: self.flagged,
+			raw: self.raw,
+		})
 	}
 }
+
+/// Either an exact date, e.g. 2024-01-31, or a duration that means that long ago, e.g. 7d
+fn decode_date(date: String) -> Result<CDateBound, FetcherConfigError> {
+	if let Ok(date) = NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+		return Ok(CDateBound::Date(date));
+	}
+
+	duration_str::parse_std(&date)
+		.map(CDateBound::Ago)
+		.map_err(|_| FetcherConfigError::BadEmailDate(date))
+}
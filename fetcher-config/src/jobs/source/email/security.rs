@@ -0,0 +1,28 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use fetcher_core::source::email::Security as CSecurity;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Security {
+	Tls,
+	#[serde(rename = "starttls")]
+	StartTls,
+	None,
+}
+
+impl Security {
+	pub fn decode_from_conf(self) -> CSecurity {
+		match self {
+			Security::Tls => CSecurity::Tls,
+			Security::StartTls => CSecurity::StartTls,
+			Security::None => CSecurity::None,
+		}
+	}
+}
@@ -4,22 +4,118 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use fetcher_core::source::Exec as CExec;
+use crate::FetcherConfigError;
+use fetcher_core::{
+	exec::{Cmd as CCmd, Format as CFormat},
+	source::Exec as CExec,
+};
 
 use serde::{Deserialize, Serialize};
 use serde_with::{OneOrMany, serde_as};
+use std::{collections::HashMap, path::PathBuf};
 
 #[serde_as]
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(transparent)]
 pub struct Exec {
 	#[serde_as(deserialize_as = "OneOrMany<_>")]
-	pub cmd: Vec<String>,
+	pub cmd: Vec<Command>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Command {
+	Simple(String),
+	Detailed(DetailedCommand),
+}
+
+// a separate struct since fields of struct variants of untagged enums can't be denied
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DetailedCommand {
+	pub cmd: Cmd,
+	pub format: Option<Format>,
+	pub env: Option<HashMap<String, String>>,
+	pub cwd: Option<PathBuf>,
+	pub timeout: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Cmd {
+	Shell(String),
+	Program(Vec<String>),
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+	#[default]
+	Text,
+	Json,
 }
 
 impl Exec {
+	pub fn decode_from_conf(self) -> Result<Vec<CExec>, FetcherConfigError> {
+		self.cmd
+			.into_iter()
+			.map(Command::decode_from_conf)
+			.collect()
+	}
+}
+
+impl Command {
+	pub fn decode_from_conf(self) -> Result<CExec, FetcherConfigError> {
+		let (cmd, format, env, cwd, timeout) = match self {
+			Self::Simple(cmd) => (Cmd::Shell(cmd), None, None, None, None),
+			Self::Detailed(DetailedCommand {
+				cmd,
+				format,
+				env,
+				cwd,
+				timeout,
+			}) => (cmd, format, env, cwd, timeout),
+		};
+
+		let timeout = timeout
+			.map(|timeout| {
+				duration_str::parse_std(&timeout)
+					.map_err(|_| FetcherConfigError::BadExecTimeout(timeout))
+			})
+			.transpose()?;
+
+		Ok(CExec {
+			cmd: cmd.decode_from_conf()?,
+			format: format.unwrap_or_default().decode_from_conf(),
+			env: env.unwrap_or_default(),
+			cwd,
+			timeout,
+		})
+	}
+}
+
+impl Cmd {
+	pub fn decode_from_conf(self) -> Result<CCmd, FetcherConfigError> {
+		match self {
+			Self::Shell(cmd) => Ok(CCmd::Shell(cmd)),
+			Self::Program(mut args) => {
+				if args.is_empty() {
+					return Err(FetcherConfigError::EmptyExecCommand);
+				}
+
+				let program = args.remove(0);
+				Ok(CCmd::Program { program, args })
+			}
+		}
+	}
+}
+
+impl Format {
 	#[must_use]
-	pub fn decode_from_conf(self) -> Vec<CExec> {
-		self.cmd.into_iter().map(|cmd| CExec { cmd }).collect()
+	pub fn decode_from_conf(self) -> CFormat {
+		match self {
+			Self::Text => CFormat::Text,
+			Self::Json => CFormat::Json,
+		}
 	}
 }
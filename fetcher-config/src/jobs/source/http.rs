@@ -4,10 +4,27 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use fetcher_core::source::{Http as CHttp, http::HttpError as CHttpError};
+pub mod cache;
+
+use crate::{
+	FetcherConfigError,
+	jobs::{
+		external_data::{ExternalDataResult, ProvideExternalData},
+		named::{JobName, TaskName},
+	},
+};
+use fetcher_core::source::{
+	Http as CHttp,
+	http::{
+		Body as CBody, HttpClient as CHttpClient, HttpError as CHttpError, Method as CMethod,
+		Request as CRequest, cache::HttpCache as CHttpCache,
+	},
+};
 
 use serde::{Deserialize, Serialize};
 use serde_with::{OneOrMany, serde_as};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
 use url::Url;
 
 #[serde_as]
@@ -21,6 +38,7 @@ pub struct Http(#[serde_as(deserialize_as = "OneOrMany<_>")] pub Vec<Request>);
 pub enum Request {
 	Untagged(Url),
 	Tagged(TaggedRequest),
+	Detailed(DetailedRequest),
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -30,20 +48,137 @@ pub enum TaggedRequest {
 	Post { url: Url, body: String },
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DetailedRequest {
+	pub url: Url,
+	pub method: Option<Method>,
+	pub body: Option<Body>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Method {
+	Get,
+	Post,
+	Put,
+	Patch,
+	Delete,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Body {
+	Json(serde_json::Value),
+	Form(HashMap<String, String>),
+	Raw(String),
+}
+
 impl Http {
-	pub fn decode_from_conf(self) -> Result<Vec<CHttp>, CHttpError> {
+	pub fn decode_from_conf<D>(
+		self,
+		job: &JobName,
+		task: Option<&TaskName>,
+		http_client: Option<&CHttpClient>,
+		external: &D,
+	) -> Result<Vec<CHttp>, FetcherConfigError>
+	where
+		D: ProvideExternalData + ?Sized,
+	{
+		// shared between all requests of the task
+		let cache = decode_cache(job, task, external)?;
+
+		let client = match http_client {
+			Some(client) => client.clone(),
+			None => CHttpClient::shared()?,
+		};
+
 		self.0
 			.into_iter()
-			.map(Request::decode_from_conf)
-			.collect::<Result<_, CHttpError>>()
+			.map(|req| {
+				let mut http = req.decode_from_conf(client.clone())?;
+				http.cache.clone_from(&cache);
+
+				Ok::<_, FetcherConfigError>(http)
+			})
+			.collect()
 	}
 }
 
+pub fn decode_cache<D>(
+	job: &JobName,
+	task: Option<&TaskName>,
+	external: &D,
+) -> Result<Option<Arc<Mutex<CHttpCache>>>, FetcherConfigError>
+where
+	D: ProvideExternalData + ?Sized,
+{
+	Ok(match external.http_cache(job, task) {
+		ExternalDataResult::Ok(cache) => Some(Arc::new(Mutex::new(cache))),
+		ExternalDataResult::Unavailable => {
+			tracing::debug!("HTTP cache is unavailable, skipping");
+			None
+		}
+		ExternalDataResult::Err(e) => return Err(e.into()),
+	})
+}
+
 impl Request {
-	pub fn decode_from_conf(self) -> Result<CHttp, CHttpError> {
+	pub fn decode_from_conf(self, client: CHttpClient) -> Result<CHttp, CHttpError> {
+		let (url, request) = match self {
+			Self::Untagged(url) | Self::Tagged(TaggedRequest::Get(url)) => {
+				(url, CRequest::default())
+			}
+			Self::Tagged(TaggedRequest::Post { url, body }) => (
+				url,
+				CRequest {
+					method: CMethod::Post,
+					body: Some(CBody::Json(serde_json::from_str(&body)?)),
+				},
+			),
+			Self::Detailed(DetailedRequest { url, method, body }) => (
+				url,
+				CRequest {
+					// send a POST request by default if there's a body to send
+					method: method.map_or_else(
+						|| {
+							if body.is_some() {
+								CMethod::Post
+							} else {
+								CMethod::Get
+							}
+						},
+						Method::decode_from_conf,
+					),
+					body: body.map(Body::decode_from_conf),
+				},
+			),
+		};
+
+		Ok(CHttp::new(url, request, client))
+	}
+}
+
+impl Method {
+	#[must_use]
+	pub fn decode_from_conf(self) -> CMethod {
+		match self {
+			Self::Get => CMethod::Get,
+			Self::Post => CMethod::Post,
+			Self::Put => CMethod::Put,
+			Self::Patch => CMethod::Patch,
+			Self::Delete => CMethod::Delete,
+		}
+	}
+}
+
+impl Body {
+	#[must_use]
+	pub fn decode_from_conf(self) -> CBody {
 		match self {
-			Self::Untagged(url) | Self::Tagged(TaggedRequest::Get(url)) => CHttp::new_get(url),
-			Self::Tagged(TaggedRequest::Post { url, body }) => CHttp::new_post(url, &body),
+			Self::Json(json) => CBody::Json(json),
+			Self::Form(form) => CBody::Form(form.into_iter().collect()),
+			Self::Raw(raw) => CBody::Raw(raw),
 		}
 	}
 }
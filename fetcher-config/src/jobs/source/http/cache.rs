@@ -0,0 +1,59 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use fetcher_core::source::http::cache::Validators as CValidators;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Validators {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub etag: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub last_modified: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(transparent)]
+pub struct HttpCache(pub HashMap<String, Validators>);
+
+impl Validators {
+	#[must_use]
+	pub fn decode_from_conf(self) -> CValidators {
+		CValidators {
+			etag: self.etag,
+			last_modified: self.last_modified,
+		}
+	}
+
+	#[must_use]
+	pub fn encode_into_conf(validators: CValidators) -> Self {
+		Self {
+			etag: validators.etag,
+			last_modified: validators.last_modified,
+		}
+	}
+}
+
+impl HttpCache {
+	#[must_use]
+	pub fn decode_from_conf(self) -> HashMap<String, CValidators> {
+		self.0
+			.into_iter()
+			.map(|(url, validators)| (url, validators.decode_from_conf()))
+			.collect()
+	}
+
+	#[must_use]
+	pub fn encode_into_conf(map: HashMap<String, CValidators>) -> Self {
+		Self(
+			map.into_iter()
+				.map(|(url, validators)| (url, Validators::encode_into_conf(validators)))
+				.collect(),
+		)
+	}
+}
@@ -0,0 +1,107 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::http::{Request, decode_cache};
+use crate::{
+	FetcherConfigError,
+	jobs::{
+		action::{
+			html::{Html, query::ElementDataQuery},
+			json::{Json, StringQuery},
+		},
+		external_data::ProvideExternalData,
+		named::{JobName, TaskName},
+	},
+};
+use fetcher_core::{
+	action::{filter::Filter as CFilter, transform::Transform as CTransform},
+	source::http::{
+		HttpClient as CHttpClient,
+		paginated::{
+			DEFAULT_MAX_PAGES, LinkQuery as CLinkQuery, NextPage as CNextPage,
+			Paginated as CPaginated,
+		},
+	},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Debug)] // deny_unknown_fields not allowed since it uses flatten
+pub struct Paginated {
+	pub http: Request,
+	#[serde(flatten)]
+	pub parser: Parser,
+	pub next_page: NextPage,
+	pub max_pages: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Parser {
+	Json(Json),
+	Html(Html),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum NextPage {
+	Link(LinkQuery),
+	Page(String),
+	Offset(String),
+}
+
+// HTML queries always contain data_location, so try them first
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum LinkQuery {
+	Html(ElementDataQuery),
+	Json(StringQuery),
+}
+
+impl Paginated {
+	pub fn decode_from_conf<D>(
+		self,
+		read_filter: Option<Box<dyn CFilter>>,
+		job: &JobName,
+		task: Option<&TaskName>,
+		http_client: Option<&CHttpClient>,
+		external: &D,
+	) -> Result<CPaginated, FetcherConfigError>
+	where
+		D: ProvideExternalData + ?Sized,
+	{
+		let client = match http_client {
+			Some(client) => client.clone(),
+			None => CHttpClient::shared()?,
+		};
+
+		let mut http = self.http.decode_from_conf(client)?;
+		http.cache = decode_cache(job, task, external)?;
+
+		let next_page = match (self.next_page, &self.parser) {
+			(NextPage::Link(LinkQuery::Json(query)), Parser::Json(_)) => {
+				CNextPage::Link(CLinkQuery::Json(query.decode_from_conf()?))
+			}
+			(NextPage::Link(LinkQuery::Html(query)), Parser::Html(_)) => {
+				CNextPage::Link(CLinkQuery::Html(query.decode_from_conf()?))
+			}
+			(NextPage::Link(_), _) => return Err(FetcherConfigError::PaginatedLinkMismatch),
+			(NextPage::Page(param), _) => CNextPage::Page(param),
+			(NextPage::Offset(param), _) => CNextPage::Offset(param),
+		};
+
+		let parser: Box<dyn CTransform> = match self.parser {
+			Parser::Json(x) => Box::new(x.decode_from_conf()?),
+			Parser::Html(x) => Box::new(x.decode_from_conf()?),
+		};
+
+		let mut paginated = CPaginated::new(http, parser, next_page);
+		paginated.max_pages = self.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+		paginated.read_filter = read_filter;
+
+		Ok(paginated)
+	}
+}
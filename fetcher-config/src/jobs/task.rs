@@ -4,13 +4,16 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+pub mod digest;
 pub mod entry_to_msg_map;
+pub mod http_options;
 
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tap::TapOptional;
 use tokio::sync::RwLock;
 
+use self::{digest::Digest, http_options::HttpOptions};
 use super::{
 	action::Action,
 	external_data::{ExternalDataResult, ProvideExternalData},
@@ -32,7 +35,11 @@ pub struct Task {
 	#[serde(rename = "process")]
 	pub actions: Option<Vec<Action>>,
 	pub entry_to_msg_map_enabled: Option<bool>,
+	pub edit_on_update: Option<bool>,
+	pub delete_removed: Option<bool>,
 	pub sink: Option<Sink>,
+	pub digest: Option<Digest>,
+	pub http_options: Option<HttpOptions>,
 }
 
 impl Task {
@@ -62,10 +69,18 @@ impl Task {
 			None => None,
 		};
 
+		// shared between the source and all actions of the task
+		let http_client = self
+			.http_options
+			.map(|x| x.decode_from_conf(external))
+			.transpose()?;
+
 		let actions = self.actions.try_map(|acts| {
 			let mut acts = itertools::process_results(
-				acts.into_iter()
-					.filter_map(|act| act.decode_from_conf(rf.clone(), external).transpose()),
+				acts.into_iter().filter_map(|act| {
+					act.decode_from_conf(rf.clone(), http_client.as_ref(), external)
+						.transpose()
+				}),
 				|i| i.flatten().collect::<Vec<_>>(),
 			)?;
 
@@ -76,17 +91,36 @@ impl Task {
 			Ok::<_, FetcherConfigError>(acts)
 		})?;
 
-		let entry_to_msg_map_enabled = self
-			.entry_to_msg_map_enabled
-			.tap_some(|b| {
-				// TODO: include task name
-				tracing::info!(
-					"Overriding entry_to_msg_map_enabled for {} from the default to {}",
-					job,
-					b
-				);
-			})
-			.unwrap_or_else(|| self.source.as_ref().is_some_and(Source::supports_replies));
+		let edit_on_update = self.edit_on_update.unwrap_or(false);
+		let delete_removed = self.delete_removed.unwrap_or(false);
+
+		// editing and deleting require knowing which message each entry was sent as
+		let entry_to_msg_map_enabled =
+			self.entry_to_msg_map_enabled
+				.tap_some(|b| {
+					// TODO: include task name
+					tracing::info!(
+						"Overriding entry_to_msg_map_enabled for {} from the default to {}",
+						job,
+						b
+					);
+				})
+				.unwrap_or_else(|| {
+					edit_on_update
+						|| delete_removed || self.source.as_ref().is_some_and(Source::supports_replies)
+				});
+
+		if edit_on_update && !entry_to_msg_map_enabled {
+			tracing::warn!(
+				"edit_on_update is enabled for {job} but entry_to_msg_map_enabled is disabled, sent messages won't be edited"
+			);
+		}
+
+		if delete_removed && !entry_to_msg_map_enabled {
+			tracing::warn!(
+				"delete_removed is enabled for {job} but entry_to_msg_map_enabled is disabled, sent messages won't be deleted"
+			);
+		}
 
 		let entry_to_msg_map = if entry_to_msg_map_enabled {
 			match external.entry_to_msg_map(job, task_name) {
@@ -119,14 +153,23 @@ impl Task {
 			(None, None) => None,
 		};
 
+		let mut metrics_labels = vec![("job", job.as_str().to_owned())];
+		if let Some(task_name) = task_name {
+			metrics_labels.push(("task", task_name.as_str().to_owned()));
+		}
+
 		Ok(CTask {
 			tag,
 			source: self
 				.source
-				.map(|x| x.decode_from_conf(rf, external))
+				.map(|x| x.decode_from_conf(rf, job, task_name, http_client.as_ref(), external))
 				.transpose()?,
 			actions,
 			entry_to_msg_map,
+			edit_on_update,
+			delete_removed,
+			digest: self.digest.map(Digest::decode_from_conf),
+			metrics_labels,
 		})
 	}
 }
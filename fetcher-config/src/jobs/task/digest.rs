@@ -0,0 +1,26 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use fetcher_core::task::digest::Digest as CDigest;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Digest {
+	pub header: Option<String>,
+	pub max_items: Option<usize>,
+}
+
+impl Digest {
+	#[must_use]
+	pub fn decode_from_conf(self) -> CDigest {
+		CDigest {
+			header: self.header,
+			max_items: self.max_items,
+		}
+	}
+}
@@ -14,12 +14,33 @@ use fetcher_core::{entry::EntryId as CEntryId, sink::message::MessageId as CMess
 pub struct EntryId(pub String);
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(transparent)]
-pub struct MessageId(pub i64);
+#[serde(untagged)]
+pub enum MessageId {
+	Int(i64),
+	Str(String),
+}
 
+/// The entry to message map save file.
+///
+/// Saved without the content hashes and the other parts of the messages if none are known, which is also the format used before content hashes were introduced
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(transparent)]
-pub struct EntryToMsgMap(pub HashMap<EntryId, MessageId>);
+#[serde(untagged)]
+pub enum EntryToMsgMap {
+	WithContentHashes {
+		messages: HashMap<EntryId, MessageId>,
+		content_hashes: HashMap<EntryId, u64>,
+		#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+		parts: HashMap<EntryId, Vec<MessageId>>,
+	},
+	Messages(HashMap<EntryId, MessageId>),
+}
+
+/// The messages, their content hashes, and the other parts of the messages
+pub type DecodedEntryToMsgMap = (
+	HashMap<CEntryId, CMessageId>,
+	HashMap<CEntryId, u64>,
+	HashMap<CEntryId, Vec<CMessageId>>,
+);
 
 impl EntryId {
 	#[must_use]
@@ -36,35 +57,91 @@ impl EntryId {
 impl MessageId {
 	#[must_use]
 	pub fn decode_from_conf(self) -> CMessageId {
-		self.0.into()
+		match self {
+			Self::Int(id) => CMessageId::Int(id),
+			Self::Str(id) => CMessageId::Str(id),
+		}
 	}
 
 	#[must_use]
 	pub fn encode_into_conf(msgid: CMessageId) -> Self {
-		Self(msgid.0)
+		match msgid {
+			CMessageId::Int(id) => Self::Int(id),
+			CMessageId::Str(id) => Self::Str(id),
+		}
 	}
 }
 
 impl EntryToMsgMap {
 	#[must_use]
-	pub fn decode_from_conf(self) -> HashMap<CEntryId, CMessageId> {
-		self.0
+	pub fn decode_from_conf(self) -> DecodedEntryToMsgMap {
+		let (messages, content_hashes, parts) = match self {
+			Self::WithContentHashes {
+				messages,
+				content_hashes,
+				parts,
+			} => (messages, content_hashes, parts),
+			Self::Messages(messages) => (messages, HashMap::new(), HashMap::new()),
+		};
+
+		let messages = messages
 			.into_iter()
 			.map(|(eid, msgid)| (eid.decode_from_conf(), msgid.decode_from_conf()))
-			.collect()
+			.collect();
+
+		let content_hashes = content_hashes
+			.into_iter()
+			.map(|(eid, hash)| (eid.decode_from_conf(), hash))
+			.collect();
+
+		let parts = parts
+			.into_iter()
+			.map(|(eid, parts)| {
+				(
+					eid.decode_from_conf(),
+					parts.into_iter().map(MessageId::decode_from_conf).collect(),
+				)
+			})
+			.collect();
+
+		(messages, content_hashes, parts)
 	}
 
 	#[must_use]
-	pub fn encode_into_conf(map: HashMap<CEntryId, CMessageId>) -> Self {
-		Self(
-			map.into_iter()
-				.map(|(eid, msgid)| {
+	pub fn encode_into_conf(
+		map: HashMap<CEntryId, CMessageId>,
+		content_hashes: HashMap<CEntryId, u64>,
+		parts: HashMap<CEntryId, Vec<CMessageId>>,
+	) -> Self {
+		let messages = map
+			.into_iter()
+			.map(|(eid, msgid)| {
+				(
+					EntryId::encode_into_conf(eid),
+					MessageId::encode_into_conf(msgid),
+				)
+			})
+			.collect();
+
+		if content_hashes.is_empty() && parts.is_empty() {
+			return Self::Messages(messages);
+		}
+
+		Self::WithContentHashes {
+			messages,
+			content_hashes: content_hashes
+				.into_iter()
+				.map(|(eid, hash)| (EntryId::encode_into_conf(eid), hash))
+				.collect(),
+			parts: parts
+				.into_iter()
+				.map(|(eid, parts)| {
 					(
 						EntryId::encode_into_conf(eid),
-						MessageId::encode_into_conf(msgid),
+						parts.into_iter().map(MessageId::encode_into_conf).collect(),
 					)
 				})
 				.collect(),
-		)
+		}
 	}
 }
@@ -0,0 +1,129 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::{
+	FetcherConfigError,
+	jobs::external_data::{ExternalDataResult, ProvideExternalData},
+};
+use fetcher_core::source::http::{
+	Auth as CAuth, ClientOptions as CClientOptions, HttpClient as CHttpClient,
+};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use url::Url;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HttpOptions {
+	pub headers: Option<HashMap<String, Value>>,
+	pub cookies: Option<HashMap<String, Value>>,
+	pub auth: Option<Auth>,
+	pub timeout: Option<String>,
+	pub proxy: Option<Url>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Auth {
+	Basic {
+		username: String,
+		password: Option<Value>,
+	},
+	Bearer(Value),
+}
+
+/// A value that is either written inline or loaded from a secret saved with `fetcher save http_secret <name>`
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Value {
+	Inline(String),
+	Secret(Secret),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Secret {
+	pub secret: String,
+}
+
+impl HttpOptions {
+	pub fn decode_from_conf<D>(self, external: &D) -> Result<CHttpClient, FetcherConfigError>
+	where
+		D: ProvideExternalData + ?Sized,
+	{
+		let mut headers = self
+			.headers
+			.unwrap_or_default()
+			.into_iter()
+			.map(|(name, value)| Ok((name, value.decode_from_conf(external)?)))
+			.collect::<Result<Vec<_>, FetcherConfigError>>()?;
+
+		if let Some(cookies) = self.cookies {
+			let cookies = cookies
+				.into_iter()
+				.map(|(name, value)| Ok(format!("{name}={}", value.decode_from_conf(external)?)))
+				.collect::<Result<Vec<_>, FetcherConfigError>>()?;
+
+			headers.push(("Cookie".to_owned(), cookies.join("; ")));
+		}
+
+		let timeout = self
+			.timeout
+			.map(|timeout| {
+				duration_str::parse_std(&timeout)
+					.map_err(|_| FetcherConfigError::BadHttpTimeout(timeout))
+			})
+			.transpose()?;
+
+		let options = CClientOptions {
+			headers,
+			auth: self
+				.auth
+				.map(|auth| auth.decode_from_conf(external))
+				.transpose()?,
+			timeout,
+			proxy: self.proxy,
+		};
+
+		Ok(CHttpClient::new(options)?)
+	}
+}
+
+impl Auth {
+	pub fn decode_from_conf<D>(self, external: &D) -> Result<CAuth, FetcherConfigError>
+	where
+		D: ProvideExternalData + ?Sized,
+	{
+		Ok(match self {
+			Self::Basic { username, password } => CAuth::Basic {
+				username,
+				password: password
+					.map(|password| password.decode_from_conf(external))
+					.transpose()?,
+			},
+			Self::Bearer(token) => CAuth::Bearer(token.decode_from_conf(external)?),
+		})
+	}
+}
+
+impl Value {
+	pub fn decode_from_conf<D>(self, external: &D) -> Result<String, FetcherConfigError>
+	where
+		D: ProvideExternalData + ?Sized,
+	{
+		match self {
+			Self::Inline(value) => Ok(value),
+			Self::Secret(Secret { secret }) => match external.http_secret(&secret) {
+				ExternalDataResult::Ok(value) => Ok(value),
+				ExternalDataResult::Unavailable => {
+					Err(FetcherConfigError::HttpSecretMissing(secret))
+				}
+				ExternalDataResult::Err(e) => Err(e.into()),
+			},
+		}
+	}
+}
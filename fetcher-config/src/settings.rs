@@ -7,9 +7,13 @@
 pub mod discord;
 pub mod email_password;
 pub mod google;
+pub mod http_secrets;
+pub mod matrix;
 pub mod telegram;
 
 pub use self::discord::Discord;
 pub use self::email_password::EmailPassword;
 pub use self::google::Google;
+pub use self::http_secrets::HttpSecrets;
+pub use self::matrix::Matrix;
 pub use self::telegram::Telegram;
@@ -0,0 +1,24 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize, Default)]
+#[serde(transparent)]
+pub struct HttpSecrets(pub HashMap<String, String>);
+
+impl HttpSecrets {
+	#[must_use]
+	pub fn decode_from_conf(self) -> HashMap<String, String> {
+		self.0
+	}
+
+	#[must_use]
+	pub fn encode_into_conf(secrets: HashMap<String, String>) -> Self {
+		Self(secrets)
+	}
+}
@@ -0,0 +1,27 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Matrix {
+	pub access_token: String,
+}
+
+impl Matrix {
+	#[must_use]
+	pub fn decode_from_conf(self) -> String {
+		let Self { access_token } = self;
+
+		access_token
+	}
+
+	#[must_use]
+	pub fn encode_into_conf(access_token: String) -> Self {
+		Self { access_token }
+	}
+}
@@ -6,12 +6,13 @@
 
 //! This module contains all [`Actions`](`Action`) that a list of [`Entry`]'s can be run through to view/modify/filter it out
 
+pub mod branch;
 pub mod filter;
 pub mod transform;
 
 use crate::sink::Sink;
 
-use self::{filter::Filter, transform::Transform};
+use self::{branch::Branch, filter::Filter, transform::Transform};
 
 /// An action that modifies a list of entries in some way
 #[derive(Debug)]
@@ -24,6 +25,9 @@ pub enum Action {
 
 	/// Send entries to the Sink
 	Sink(Box<dyn Sink>),
+
+	/// Run entries through different actions depending on a condition
+	Branch(Branch),
 }
 
 impl From<Box<dyn Filter>> for Action {
@@ -43,3 +47,9 @@ impl From<Box<dyn Sink>> for Action {
 		Action::Sink(sink)
 	}
 }
+
+impl From<Branch> for Action {
+	fn from(branch: Branch) -> Self {
+		Action::Branch(branch)
+	}
+}
@@ -0,0 +1,36 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains [`Branch`]
+
+use super::{Action, filter::Condition};
+use crate::entry::Entry;
+
+/// Split entries into the ones that match the condition and the ones that don't,
+/// and run them through separate lists of actions.
+///
+/// The entries that are left after both lists of actions are then passed down to the next action in their original order
+#[derive(Debug)]
+pub struct Branch {
+	/// The condition the entries are split by
+	pub condition: Box<dyn Condition>,
+
+	/// Actions to run the matching entries through
+	pub then: Vec<Action>,
+
+	/// Actions to run the rest of the entries through
+	pub otherwise: Vec<Action>,
+}
+
+impl Branch {
+	/// Split `entries` into ones that match the condition and the ones that don't, preserving their order
+	#[must_use]
+	pub fn split(&self, entries: Vec<Entry>) -> (Vec<Entry>, Vec<Entry>) {
+		entries
+			.into_iter()
+			.partition(|ent| self.condition.matches(ent))
+	}
+}
@@ -6,10 +6,19 @@
 
 //! This module contains the [`Filter`] trait that can be implemented in filters as well as all types that implement it
 
+pub mod compare;
+pub mod condition;
 pub mod contains;
+pub mod exists;
 pub mod take;
 
-pub use self::{contains::Contains, take::Take};
+pub use self::{
+	compare::Compare,
+	condition::{All, Any, Condition, Not},
+	contains::Contains,
+	exists::Exists,
+	take::Take,
+};
 
 use crate::entry::Entry;
 
@@ -0,0 +1,139 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`Compare`] filter and the [`CompareOp`] enum that specifies how the values should be compared
+
+use super::condition::{Condition, impl_filter_for_condition};
+use crate::{action::transform::field::Field, entry::Entry};
+
+/// Filter out all entries whose field isn't a number or doesn't compare to the value as specified.
+///
+/// Usually used after extracting the number from a field with [`Extract`](`crate::action::transform::field::Extract`)
+#[derive(Clone, Debug)]
+pub struct Compare {
+	/// The field that contains the number to compare
+	pub field: Field,
+
+	/// How the number in the field should compare to the value, i.e. `field < value`
+	pub op: CompareOp,
+
+	/// The value to compare against
+	pub value: f64,
+}
+
+#[expect(missing_docs, reason = "names are self-documenting")]
+#[derive(Clone, Copy, Debug)]
+pub enum CompareOp {
+	Less,
+	LessOrEqual,
+	Greater,
+	GreaterOrEqual,
+	Equal,
+	NotEqual,
+}
+
+impl Condition for Compare {
+	fn matches(&self, entry: &Entry) -> bool {
+		let Some(num) = self
+			.field
+			.get(entry)
+			.and_then(|val| val.trim().parse::<f64>().ok())
+		else {
+			return false;
+		};
+
+		match self.op {
+			CompareOp::Less => num < self.value,
+			CompareOp::LessOrEqual => num <= self.value,
+			CompareOp::Greater => num > self.value,
+			CompareOp::GreaterOrEqual => num >= self.value,
+			#[expect(clippy::float_cmp, reason = "exact comparison is what was asked for")]
+			CompareOp::Equal => num == self.value,
+			#[expect(clippy::float_cmp, reason = "exact comparison is what was asked for")]
+			CompareOp::NotEqual => num != self.value,
+		}
+	}
+}
+
+impl_filter_for_condition!(Compare);
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+	use crate::{
+		action::filter::{
+			Contains, Filter,
+			condition::{All, Any, Not},
+			exists::Exists,
+		},
+		sink::message::Message,
+	};
+
+	fn entry(title: &str, body: Option<&str>) -> Entry {
+		Entry {
+			msg: Message {
+				title: Some(title.to_owned()),
+				body: body.map(ToOwned::to_owned),
+				..Default::default()
+			},
+			..Default::default()
+		}
+	}
+
+	fn titles(entries: &[Entry]) -> Vec<&str> {
+		entries
+			.iter()
+			.map(|e| e.msg.title.as_deref().unwrap())
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn compare() {
+		let mut entries = vec![
+			entry("cheap", Some("99.5")),
+			entry("exact", Some(" 100 ")),
+			entry("expensive", Some("150")),
+			entry("not a number", Some("free")),
+			entry("no price", None),
+		];
+
+		let filter = Compare {
+			field: Field::Body,
+			op: CompareOp::Less,
+			value: 100.0,
+		};
+
+		filter.filter(&mut entries).await;
+		assert_eq!(titles(&entries), ["cheap"]);
+	}
+
+	#[tokio::test]
+	async fn combinators() {
+		let mut entries = vec![
+			entry("rust release", Some("1")),
+			entry("rust ad", Some("2")),
+			entry("go release", None),
+			entry("python news", Some("3")),
+		];
+
+		// (title contains "release" or body exists) and title doesn't contain "ad"
+		let filter = All(vec![
+			Box::new(Any(vec![
+				Box::new(Contains::new("release", Field::Title).unwrap()),
+				Box::new(Exists(Field::Body)),
+			])),
+			Box::new(Not(Box::new(Contains::new("ad", Field::Title).unwrap()))),
+		]);
+
+		filter.filter(&mut entries).await;
+		assert_eq!(
+			titles(&entries),
+			["rust release", "go release", "python news"]
+		);
+	}
+}
@@ -0,0 +1,66 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`Condition`] trait and [`Not`], [`Any`], and [`All`] conditions that combine other conditions
+
+use super::Filter;
+use crate::entry::Entry;
+
+/// A [`Filter`] that decides whether to keep an entry by looking at only that single entry.
+///
+/// Conditions can be combined with [`Not`], [`Any`], and [`All`], and used to [branch](`crate::action::branch::Branch`) between different actions
+pub trait Condition: Filter {
+	/// Check if the `entry` matches the condition
+	fn matches(&self, entry: &Entry) -> bool;
+}
+
+/// Implement [`Filter`] for types that implement [`Condition`] that keeps only the entries that match it
+macro_rules! impl_filter_for_condition {
+	($($t:ty),+) => {
+		$(
+			#[async_trait::async_trait]
+			impl $crate::action::filter::Filter for $t {
+				async fn filter(&self, entries: &mut Vec<$crate::entry::Entry>) {
+					entries.retain(|ent| $crate::action::filter::condition::Condition::matches(self, ent));
+				}
+			}
+		)+
+	};
+}
+
+pub(crate) use impl_filter_for_condition;
+
+/// Matches all entries that don't match the inner condition
+#[derive(Debug)]
+pub struct Not(pub Box<dyn Condition>);
+
+/// Matches all entries that match at least one of the inner conditions
+#[derive(Debug)]
+pub struct Any(pub Vec<Box<dyn Condition>>);
+
+/// Matches all entries that match every one of the inner conditions
+#[derive(Debug)]
+pub struct All(pub Vec<Box<dyn Condition>>);
+
+impl Condition for Not {
+	fn matches(&self, entry: &Entry) -> bool {
+		!self.0.matches(entry)
+	}
+}
+
+impl Condition for Any {
+	fn matches(&self, entry: &Entry) -> bool {
+		self.0.iter().any(|c| c.matches(entry))
+	}
+}
+
+impl Condition for All {
+	fn matches(&self, entry: &Entry) -> bool {
+		self.0.iter().all(|c| c.matches(entry))
+	}
+}
+
+impl_filter_for_condition!(Not, Any, All);
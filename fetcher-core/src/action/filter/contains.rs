@@ -6,11 +6,9 @@
 
 //! This module contains the [`Contains`] filter
 
-use async_trait::async_trait;
 use regex::Regex;
-use std::borrow::Cow;
 
-use super::Filter;
+use super::condition::{Condition, impl_filter_for_condition};
 use crate::{action::transform::field::Field, entry::Entry, error::BadRegexError};
 
 /// Filter out all entries whose field doesn't match the regular expression
@@ -36,24 +34,15 @@ impl Contains {
 	}
 }
 
-#[async_trait]
-impl Filter for Contains {
-	/// Filter out some entries out of the `entries` vector
-	async fn filter(&self, entries: &mut Vec<Entry>) {
-		entries.retain(|ent| {
-			let field = match self.field {
-				Field::Title => ent.msg.title.as_deref().map(Cow::Borrowed),
-				Field::Body => ent.msg.body.as_deref().map(Cow::Borrowed),
-				Field::Link => ent.msg.link.as_ref().map(|s| Cow::Owned(s.to_string())),
-				Field::Id => ent.id.as_ref().map(|id| Cow::Borrowed(id.0.as_str())),
-				Field::ReplyTo => ent.reply_to.as_ref().map(|id| Cow::Borrowed(id.0.as_str())),
-				Field::RawContets => ent.raw_contents.as_deref().map(Cow::Borrowed),
-			};
-
-			match field {
-				Some(field) => self.re.is_match(&field),
-				None => false,
-			}
-		});
+impl Condition for Contains {
+	/// Check if the field of the `entry` matches the regular expression.
+	/// Always returns false if the field is not set
+	fn matches(&self, entry: &Entry) -> bool {
+		match self.field.get(entry) {
+			Some(field) => self.re.is_match(&field),
+			None => false,
+		}
 	}
 }
+
+impl_filter_for_condition!(Contains);
@@ -0,0 +1,22 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`Exists`] filter
+
+use super::condition::{Condition, impl_filter_for_condition};
+use crate::{action::transform::field::Field, entry::Entry};
+
+/// Filter out all entries whose field is not set or is empty
+#[derive(Clone, Debug)]
+pub struct Exists(pub Field);
+
+impl Condition for Exists {
+	fn matches(&self, entry: &Entry) -> bool {
+		self.0.get(entry).is_some_and(|val| !val.is_empty())
+	}
+}
+
+impl_filter_for_condition!(Exists);
@@ -166,6 +166,16 @@ impl Html {
 	}
 }
 
+/// Find the first string matching the `query` anywhere in the HTML `page`, e.g. a link to the next page
+pub(crate) fn find_string(
+	page: &str,
+	query: &ElementDataQuery,
+) -> Result<Option<String>, HtmlError> {
+	let dom = Soup::new(page).get_handle();
+
+	Ok(extract_data(&dom, query)?.and_then(|mut it| it.next()))
+}
+
 /// Extract data from the provided HTML tags
 fn extract_data<'a>(
 	html: &HtmlNode,
@@ -7,7 +7,6 @@
 //! This module contains the [`Http`] transform that fetches a web page from a link located in a field of the passed [`Entry`]
 
 use async_trait::async_trait;
-use reqwest::Client;
 use url::Url;
 
 use super::TransformEntry;
@@ -18,7 +17,7 @@ use crate::{
 	},
 	entry::Entry,
 	error::InvalidUrlError,
-	source::{self, http::HttpError as SourceHttpError, http::Request},
+	source::http::{HttpClient, HttpError as SourceHttpError, Request},
 	utils::OptionExt,
 };
 
@@ -27,7 +26,7 @@ use crate::{
 pub struct Http {
 	/// The field to get the URL from
 	pub from_field: Field,
-	client: Client,
+	client: HttpClient,
 }
 
 #[expect(missing_docs, reason = "error message is self-documenting")]
@@ -49,16 +48,13 @@ impl Http {
 	/// # Errors
 	/// This method fails if TLS couldn't be initialized
 	pub fn new(from_field: Field) -> Result<Self, SourceHttpError> {
-		let client = source::http::CLIENT
-			.get_or_try_init(|| {
-				reqwest::ClientBuilder::new()
-					.timeout(std::time::Duration::from_secs(30))
-					.build()
-					.map_err(SourceHttpError::TlsInitFailed)
-			})?
-			.clone();
+		Ok(Self::with_client(from_field, HttpClient::shared()?))
+	}
 
-		Ok(Self { from_field, client })
+	/// Create a new [`Http`] transform that sends its requests using `client`
+	#[must_use]
+	pub const fn with_client(from_field: Field, client: HttpClient) -> Self {
+		Self { from_field, client }
 	}
 }
 
@@ -98,7 +94,7 @@ impl TransformEntry for Http {
 
 		let url = url.ok_or_else(|| HttpError::MissingUrl(self.from_field))?;
 
-		let new_page = source::http::send_request(&self.client, &Request::Get, &url).await?;
+		let new_page = self.client.send(&Request::default(), &url).await?;
 
 		Ok(vec![TransformedEntry {
 			raw_contents: TransformResult::New(new_page),
@@ -171,6 +171,13 @@ impl Json {
 	}
 }
 
+/// Find the string matching the `query` in the JSON `page`, e.g. a link to the next page
+pub(crate) fn find_string(page: &str, query: &StringQuery) -> Result<Option<String>, JsonError> {
+	let json: Value = serde_json::from_str(page)?;
+
+	extract_string(&json, query)
+}
+
 fn extract_data<'a>(json: &'a Value, query: &Query) -> Result<Option<&'a Value>, JsonError> {
 	let data = query.keys.iter().enumerate().try_fold(json, |val, (i, q)| {
 		let res_val = match q {
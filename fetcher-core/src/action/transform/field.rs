@@ -22,7 +22,10 @@ pub use self::{
 };
 
 use async_trait::async_trait;
-use std::fmt::{self, Debug};
+use std::{
+	borrow::Cow,
+	fmt::{self, Debug},
+};
 use url::Url;
 
 use super::{Transform, result::TransformResult};
@@ -155,6 +158,24 @@ pub enum Field {
 	RawContets,
 }
 
+impl Field {
+	/// Get the value of this field of the `entry`
+	#[must_use]
+	pub fn get(self, entry: &Entry) -> Option<Cow<'_, str>> {
+		match self {
+			Self::Title => entry.msg.title.as_deref().map(Cow::Borrowed),
+			Self::Body => entry.msg.body.as_deref().map(Cow::Borrowed),
+			Self::Link => entry.msg.link.as_ref().map(|s| Cow::Owned(s.to_string())),
+			Self::Id => entry.id.as_ref().map(|id| Cow::Borrowed(id.0.as_str())),
+			Self::ReplyTo => entry
+				.reply_to
+				.as_ref()
+				.map(|id| Cow::Borrowed(id.0.as_str())),
+			Self::RawContets => entry.raw_contents.as_deref().map(Cow::Borrowed),
+		}
+	}
+}
+
 impl fmt::Display for Field {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let name = match self {
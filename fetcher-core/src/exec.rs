@@ -7,15 +7,25 @@
 //! This module contains [`Exec`] source and sink. It is re-exported in the [`crate::sink`] and [`crate::source`] modules
 
 use async_trait::async_trait;
-use std::{io, process::Stdio, string::FromUtf8Error};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{
+	collections::HashMap,
+	io,
+	path::PathBuf,
+	process::{ExitStatus, Output, Stdio},
+	string::FromUtf8Error,
+	time::Duration,
+};
 use tokio::{io::AsyncWriteExt, process::Command};
+use url::Url;
 
 use crate::{
-	entry::Entry,
+	entry::{Entry, EntryId},
 	sink::{
 		Sink,
 		error::SinkError,
-		message::{Message, MessageId},
+		message::{Media, Message, MessageId},
 	},
 	source::{Fetch, error::SourceError},
 };
@@ -26,16 +36,67 @@ const SHELL: &str = "sh";
 const SHELL: &str = "cmd";
 
 #[cfg(not(target_os = "windows"))]
-const SHELL_RUN_ARG: &str = r"\C";
+const SHELL_RUN_ARG: &str = "-c";
 #[cfg(target_os = "windows")]
-const SHELL: &str = "-c";
+const SHELL_RUN_ARG: &str = "/C";
 
-/// Exec source. It can execute a shell command and source its stdout
+/// Exec source and sink. It can execute a shell command and source its stdout, or pass messages to its stdin
+///
+/// A process that exits with a non-zero exit code is considered to have failed
 #[derive(Debug)]
 pub struct Exec {
 	/// The command to execute
-	pub cmd: String,
+	pub cmd: Cmd,
+
+	/// The format of the data exchanged with the process
+	pub format: Format,
+
+	/// Environment variables to set for the process in addition to the ones inherited from fetcher
+	pub env: HashMap<String, String>,
+
+	/// The working directory of the process. The current working directory of fetcher if not set
+	pub cwd: Option<PathBuf>,
+
+	/// Kill the process if it hasn't exited after this long
+	pub timeout: Option<Duration>,
+}
+
+/// How to start the process of an [`Exec`]
+#[derive(Clone, Debug)]
+pub enum Cmd {
+	/// Run the command in a shell, i.e. `sh -c <cmd>` (`cmd /C <cmd>` on Windows)
+	Shell(String),
+
+	/// Run the program directly with these arguments, without a shell in between
+	Program {
+		/// The name of or the path to the program
+		program: String,
+
+		/// The arguments to pass to the program as is
+		args: Vec<String>,
+	},
+}
+
+/// The format of the data exchanged with an [`Exec`] process
+#[derive(Clone, Copy, Default, Debug)]
+pub enum Format {
+	/// As a source, all of stdout is put into the [`Entry.raw_contents`] of a single entry.
+	/// As a sink, only the body of the message is written to stdin
+	#[default]
+	Text,
+
+	/// As a source, stdout should contain either a JSON array of entries, or a JSON object of an entry per line (JSON Lines).
+	/// An entry is an object with optional `id`, `title`, `body`, `link`, and `media` fields,
+	/// with `media` being a list of objects of one of `{ "photo": <url> }`, `{ "video": <url> }`,
+	/// `{ "animation": <url> }`, `{ "audio": <url> }`, or `{ "document": <url> }`.
+	///
+	/// As a sink, the message is written to stdin as an object with the same fields (except for `id`),
+	/// as well as the `tag`, `entry_id`, and the `reply_to` message id fields.
+	/// The process can print the id of the sent message to stdout as a JSON number or string
+	/// to let the next messages reply to it
+	Json,
 }
+
 /// Errors that happened while executing a process
 #[expect(missing_docs, reason = "error message is self-documenting")]
 #[derive(thiserror::Error, Debug)]
@@ -51,68 +112,407 @@ pub enum ExecError {
 
 	#[error("Can't pass data to the stdin of the process")]
 	CantWriteStdin(#[source] io::Error),
+
+	#[error("Process exited with {status}. Stderr: {stderr:?}")]
+	Failed { status: ExitStatus, stderr: String },
+
+	#[error("Process didn't exit in {0:?} and was killed")]
+	TimedOut(Duration),
+
+	#[error("Process printed invalid JSON")]
+	BadJson(#[from] serde_json::Error),
+
+	#[error("Process printed an invalid message id {0:?}. It should be a JSON number or string")]
+	BadMessageId(String),
+}
+
+/// An entry printed by a process in [`Format::Json`]
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct JsonEntry {
+	id: Option<JsonId>,
+	title: Option<String>,
+	body: Option<String>,
+	link: Option<Url>,
+	#[serde(default)]
+	media: Vec<JsonMedia>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum JsonId {
+	String(String),
+	Int(i64),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum JsonMedia {
+	Photo(Url),
+	Video(Url),
+	Animation(Url),
+	Audio(Url),
+	Document(Url),
 }
 
 #[async_trait]
 impl Fetch for Exec {
 	async fn fetch(&mut self) -> Result<Vec<Entry>, SourceError> {
-		tracing::debug!("Spawning a shell with command {:?}", self.cmd);
-		let out = Command::new(SHELL)
-			.arg(SHELL_RUN_ARG)
-			.arg(&self.cmd)
-			.output()
-			.await
-			.map_err(ExecError::BadCommand)?
-			.stdout;
-
-		let out = String::from_utf8(out).map_err(ExecError::BadUtf8)?;
+		let out = self.run(None).await?;
 		tracing::debug!("Got {out:?} from the command");
 
-		Ok(vec![Entry {
-			raw_contents: Some(out),
-			..Default::default()
-		}])
+		match self.format {
+			Format::Text => Ok(vec![Entry {
+				raw_contents: Some(out),
+				..Default::default()
+			}]),
+			Format::Json => Ok(parse_json_entries(&out)?),
+		}
 	}
 }
 
 #[async_trait]
 impl Sink for Exec {
-	/// Passes message's body to the stdin of the process. The tag parameter is ignored
+	/// Passes the message to the stdin of the process without an entry id
 	///
 	/// # Errors
 	/// * if the process couldn't be started
 	/// * if the data couldn't be passed to the stdin pipe of the process
+	/// * if the process exited with an error
+	/// * if the process printed an invalid message id
 	async fn send(
 		&self,
 		message: &Message,
-		_reply_to: Option<&MessageId>,
-		_tag: Option<&str>,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
 	) -> Result<Option<MessageId>, SinkError> {
-		let Some(body) = &message.body else {
-			return Ok(None);
+		self.send_entry(message, None, reply_to, tag).await
+	}
+
+	/// Passes the message to the stdin of the process.
+	/// In [`Format::Text`], only the body is passed and the other parameters are ignored
+	///
+	/// # Errors
+	/// * if the process couldn't be started
+	/// * if the data couldn't be passed to the stdin pipe of the process
+	/// * if the process exited with an error
+	/// * if the process printed an invalid message id
+	async fn send_entry(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		let stdin = match self.format {
+			Format::Text => match &message.body {
+				Some(body) => body.clone(),
+				None => return Ok(None),
+			},
+			Format::Json => message_to_json(message, entry_id, reply_to, tag).to_string(),
 		};
 
+		let out = self.run(Some(stdin)).await?;
+
+		match self.format {
+			Format::Text => Ok(None),
+			Format::Json => Ok(parse_message_id(&out)?),
+		}
+	}
+}
+
+impl Exec {
+	/// Runs the process, passes `stdin` to it if it's set, and returns its stdout
+	async fn run(&self, stdin: Option<String>) -> Result<String, ExecError> {
 		tracing::debug!("Spawning process {:?}", self.cmd);
-		let mut shell = Command::new(SHELL)
-			.arg(SHELL_RUN_ARG)
-			.arg(&self.cmd)
-			.stdin(Stdio::piped())
-			.stdout(Stdio::null())
-			.spawn()
-			.map_err(ExecError::CantStart)?;
-
-		if let Some(stdin) = &mut shell.stdin {
-			tracing::debug!("Writing {body:?} to stdin of the process");
-			stdin
-				.write_all(body.as_bytes())
-				.await
-				.map_err(ExecError::CantWriteStdin)?;
+
+		let mut command = match &self.cmd {
+			Cmd::Shell(cmd) => {
+				let mut command = Command::new(SHELL);
+				command.arg(SHELL_RUN_ARG).arg(cmd);
+				command
+			}
+			Cmd::Program { program, args } => {
+				let mut command = Command::new(program);
+				command.args(args);
+				command
+			}
+		};
+
+		command
+			.envs(&self.env)
+			.stdin(if stdin.is_some() {
+				Stdio::piped()
+			} else {
+				Stdio::null()
+			})
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			// make sure the process doesn't outlive its timeout
+			.kill_on_drop(true);
+
+		if let Some(cwd) = &self.cwd {
+			command.current_dir(cwd);
 		}
 
-		tracing::trace!("Waiting for the process to exit");
-		shell.wait().await.map_err(ExecError::CantStart)?;
+		let mut process = command.spawn().map_err(|e| match e.kind() {
+			io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => ExecError::BadCommand(e),
+			_ => ExecError::CantStart(e),
+		})?;
+
+		let mut stdin_pipe = process.stdin.take();
+		let write_stdin = async {
+			if let (Some(pipe), Some(stdin)) = (&mut stdin_pipe, &stdin) {
+				tracing::debug!("Writing {stdin:?} to stdin of the process");
+				pipe.write_all(stdin.as_bytes())
+					.await
+					.map_err(ExecError::CantWriteStdin)?;
+			}
+
+			// close stdin to let the process know there's nothing more to read
+			drop(stdin_pipe);
+			Ok(())
+		};
+
+		let wait = async {
+			tracing::trace!("Waiting for the process to exit");
+			let ((), output) = tokio::try_join!(write_stdin, async {
+				process
+					.wait_with_output()
+					.await
+					.map_err(ExecError::CantStart)
+			})?;
+
+			Ok::<_, ExecError>(output)
+		};
+
+		let output = match self.timeout {
+			// the process is killed when the future that owns it is dropped on timeout
+			Some(timeout) => tokio::time::timeout(timeout, wait)
+				.await
+				.map_err(|_| ExecError::TimedOut(timeout))??,
+			None => wait.await?,
+		};
+
+		let out = check_output(output)?;
 		tracing::trace!("Process successfully exited");
 
-		Ok(None)
+		Ok(out)
+	}
+}
+
+/// Returns the stdout of the process if it has exited successfully
+fn check_output(output: Output) -> Result<String, ExecError> {
+	if !output.status.success() {
+		return Err(ExecError::Failed {
+			status: output.status,
+			stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+		});
+	}
+
+	Ok(String::from_utf8(output.stdout)?)
+}
+
+fn parse_json_entries(out: &str) -> Result<Vec<Entry>, ExecError> {
+	let out = out.trim();
+
+	let entries: Vec<JsonEntry> = if out.starts_with('[') {
+		serde_json::from_str(out)?
+	} else {
+		out.lines()
+			.filter(|line| !line.trim().is_empty())
+			.map(serde_json::from_str)
+			.collect::<Result<_, _>>()?
+	};
+
+	Ok(entries
+		.into_iter()
+		.map(|entry| {
+			let media = entry
+				.media
+				.into_iter()
+				.map(|media| match media {
+					JsonMedia::Photo(url) => Media::Photo(url),
+					JsonMedia::Video(url) => Media::Video(url),
+					JsonMedia::Animation(url) => Media::Animation(url),
+					JsonMedia::Audio(url) => Media::Audio(url),
+					JsonMedia::Document(url) => Media::Document(url),
+				})
+				.collect::<Vec<_>>();
+
+			Entry {
+				id: entry.id.map(|id| match id {
+					JsonId::String(s) => s.into(),
+					JsonId::Int(i) => i.to_string().into(),
+				}),
+				raw_contents: entry.body.clone(),
+				msg: Message {
+					title: entry.title,
+					body: entry.body,
+					link: entry.link,
+					media: (!media.is_empty()).then_some(media),
+				},
+				..Default::default()
+			}
+		})
+		.collect())
+}
+
+fn message_to_json(
+	message: &Message,
+	entry_id: Option<&EntryId>,
+	reply_to: Option<&MessageId>,
+	tag: Option<&str>,
+) -> Value {
+	let media = message
+		.media
+		.iter()
+		.flatten()
+		.filter_map(|media| match media {
+			Media::Photo(url) => Some(json!({ "photo": url.as_str() })),
+			Media::Video(url) => Some(json!({ "video": url.as_str() })),
+			Media::Animation(url) => Some(json!({ "animation": url.as_str() })),
+			Media::Audio(url) => Some(json!({ "audio": url.as_str() })),
+			Media::Document(url) => Some(json!({ "document": url.as_str() })),
+			// files aren't passed to the process
+			Media::File(_) => None,
+		})
+		.collect::<Vec<_>>();
+
+	let reply_to = reply_to.map(|id| match id {
+		MessageId::Int(i) => json!(i),
+		MessageId::Str(s) => json!(s),
+	});
+
+	json!({
+		"title": message.title,
+		"body": message.body,
+		"link": message.link.as_ref().map(Url::as_str),
+		"media": media,
+		"tag": tag,
+		"entry_id": entry_id.map(|id| id.0.as_str()),
+		"reply_to": reply_to,
+	})
+}
+
+fn parse_message_id(out: &str) -> Result<Option<MessageId>, ExecError> {
+	let out = out.trim();
+	if out.is_empty() {
+		return Ok(None);
+	}
+
+	match serde_json::from_str::<Value>(out) {
+		Ok(Value::Number(num)) if num.is_i64() => Ok(num.as_i64().map(MessageId::Int)),
+		Ok(Value::String(s)) => Ok(Some(MessageId::Str(s))),
+		_ => Err(ExecError::BadMessageId(out.to_owned())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	fn shell(cmd: &str, format: Format) -> Exec {
+		Exec {
+			cmd: Cmd::Shell(cmd.to_owned()),
+			format,
+			env: HashMap::new(),
+			cwd: None,
+			timeout: None,
+		}
+	}
+
+	#[tokio::test]
+	async fn json_source() {
+		let mut exec = shell(
+			r#"echo '{"id": 1, "title": "a", "media": [{"video": "https://example.com/1.mp4"}]}'; echo '{"id": "two", "body": "b"}'"#,
+			Format::Json,
+		);
+
+		let entries = exec.fetch().await.unwrap();
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].id.as_ref().unwrap().0, "1");
+		assert_eq!(entries[0].msg.title.as_deref(), Some("a"));
+		assert!(matches!(
+			entries[0].msg.media.as_deref(),
+			Some([Media::Video(_)])
+		));
+		assert_eq!(entries[1].id.as_ref().unwrap().0, "two");
+		assert_eq!(entries[1].raw_contents.as_deref(), Some("b"));
+
+		exec.cmd = Cmd::Shell(r#"echo '[{"title": "a"}, {"title": "b"}]'"#.to_owned());
+		assert_eq!(exec.fetch().await.unwrap().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn json_sink() {
+		let message = Message {
+			body: Some("body".to_owned()),
+			..Default::default()
+		};
+
+		let exec = shell(r#"grep -q '"tag":"news"'"#, Format::Json);
+		assert_eq!(exec.send(&message, None, Some("news")).await.unwrap(), None);
+		assert!(exec.send(&message, None, Some("other")).await.is_err());
+
+		// reply with the id of the message being replied to plus one
+		let exec = shell(
+			r#"sed 's/.*"reply_to":\([0-9]*\).*/\1/' | xargs expr 1 +"#,
+			Format::Json,
+		);
+		assert_eq!(
+			exec.send(&message, Some(&MessageId::Int(41)), None)
+				.await
+				.unwrap(),
+			Some(MessageId::Int(42))
+		);
+	}
+
+	#[tokio::test]
+	async fn failed() {
+		let mut exec = shell("echo oops >&2; exit 3", Format::Text);
+
+		let err = exec.fetch().await.unwrap_err();
+		assert!(matches!(
+			err,
+			SourceError::Exec(ExecError::Failed { ref stderr, .. }) if stderr == "oops"
+		));
+	}
+
+	#[tokio::test]
+	async fn program_with_env_and_cwd() {
+		let mut exec = Exec {
+			cmd: Cmd::Program {
+				program: "sh".to_owned(),
+				args: vec![
+					"-c".to_owned(),
+					r#"printf '%s %s %s' "$0" "$FETCHER_TEST" "$(pwd)""#.to_owned(),
+					"not a shell; word".to_owned(),
+				],
+			},
+			format: Format::Text,
+			env: HashMap::from([("FETCHER_TEST".to_owned(), "value".to_owned())]),
+			cwd: Some("/".into()),
+			timeout: None,
+		};
+
+		let entries = exec.fetch().await.unwrap();
+		assert_eq!(
+			entries[0].raw_contents.as_deref(),
+			Some("not a shell; word value /")
+		);
+	}
+
+	#[tokio::test]
+	async fn timeout() {
+		let mut exec = Exec {
+			timeout: Some(Duration::from_millis(100)),
+			..shell("sleep 10", Format::Text)
+		};
+
+		let err = exec.fetch().await.unwrap_err();
+		assert!(matches!(err, SourceError::Exec(ExecError::TimedOut(_))));
 	}
 }
@@ -14,7 +14,8 @@ use std::{
 };
 
 use crate::{
-	entry::EntryId, read_filter::ReadFilter, sink::message::MessageId, utils::DisplayDebug,
+	entry::EntryId, read_filter::ReadFilter, sink::message::MessageId,
+	source::http::cache::Validators, utils::DisplayDebug,
 };
 
 /// This trait represent some kind of external save destination.
@@ -30,10 +31,20 @@ pub trait ExternalSave: Debug + Send + Sync {
 		read_filter: &dyn ReadFilter,
 	) -> Result<(), ExternalSaveError>;
 
-	/// Save the entry id to message id map (see [`Task.entry_to_msg_map`]) enternally
+	/// Save the entry id to message id map (see [`Task.entry_to_msg_map`]) enternally,
+	/// alongside the content hashes of the sent messages, if they are known,
+	/// and the ids of the other messages the entries were split into
 	async fn save_entry_to_msg_map(
 		&mut self,
 		map: &HashMap<EntryId, MessageId>,
+		content_hashes: &HashMap<EntryId, u64>,
+		parts: &HashMap<EntryId, Vec<MessageId>>,
+	) -> Result<(), ExternalSaveError>;
+
+	/// Save the URL to HTTP cache validators map (see [`HttpCache`](`crate::source::http::cache::HttpCache`)) externally
+	async fn save_http_cache(
+		&mut self,
+		map: &HashMap<String, Validators>,
 	) -> Result<(), ExternalSaveError>;
 }
 
@@ -8,7 +8,11 @@
 
 pub mod timepoint;
 
-use futures::future::join_all;
+use futures::{
+	FutureExt,
+	future::{join_all, select_all},
+};
+use std::pin::pin;
 use tokio::time::sleep;
 
 use self::timepoint::TimePoint;
@@ -30,9 +34,36 @@ impl Job {
 	/// # Errors
 	/// if any of the inner tasks return an error, refer to [`Task`] documentation
 	pub async fn run(&mut self) -> Result<(), Vec<FetcherError>> {
+		self.run_until(std::future::pending()).await
+	}
+
+	/// Run this job like [`Job::run`] until `stop` completes.
+	///
+	/// A run that's already in progress isn't interrupted, the job is stopped right after it has finished
+	/// to not leave, e.g. an entry sent but not marked as read
+	///
+	/// # Errors
+	/// if any of the inner tasks return an error, refer to [`Task`] documentation
+	pub async fn run_until(
+		&mut self,
+		stop: impl Future<Output = ()>,
+	) -> Result<(), Vec<FetcherError>> {
+		let mut stop = pin!(stop.fuse());
+		let mut stopped = false;
+
 		loop {
-			let tasks = self.tasks.iter_mut().map(Task::run);
-			let results = join_all(tasks).await;
+			let results = {
+				let mut tasks = pin!(join_all(self.tasks.iter_mut().map(Task::run)));
+
+				tokio::select! {
+					results = &mut tasks => results,
+					() = &mut stop => {
+						tracing::info!("Waiting for the current run to finish before stopping");
+						stopped = true;
+						tasks.await
+					}
+				}
+			};
 
 			let errors = results
 				.into_iter()
@@ -46,6 +77,10 @@ impl Job {
 				return Err(errors);
 			}
 
+			if stopped {
+				return Ok(());
+			}
+
 			match &self.refresh_time {
 				Some(refresh_time) => {
 					let remaining_time = refresh_time.remaining_from_now();
@@ -54,10 +89,31 @@ impl Job {
 						"Putting job to sleep for {}m",
 						remaining_time.as_secs() / 60
 					);
-					sleep(remaining_time).await;
+
+					// wake up early if any of the sources has been notified about new entries
+					tokio::select! {
+						() = sleep(remaining_time) => (),
+						() = wait_for_new_entries(&mut self.tasks) => {
+							tracing::debug!("Got notified about new entries, running the job early");
+						}
+						() = &mut stop => return Ok(()),
+					}
 				}
 				None => return Ok(()),
 			}
 		}
 	}
 }
+
+async fn wait_for_new_entries(tasks: &mut [Task]) {
+	if tasks.is_empty() {
+		return std::future::pending().await;
+	}
+
+	select_all(
+		tasks
+			.iter_mut()
+			.map(|task| Box::pin(task.wait_for_new_entries())),
+	)
+	.await;
+}
@@ -4,10 +4,16 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-//! This module defines the [`TimePoint`] enum that specifies either a duration or a time of day a job should be refreshed
+//! This module defines the [`TimePoint`] enum that specifies either a duration, a time of day, or a [`Cron`] schedule a job should be refreshed at
 
-use chrono::{NaiveDateTime, NaiveTime, offset::Local as LocalTime};
-use std::time::Duration;
+use chrono::{
+	DateTime, NaiveDateTime, NaiveTime, TimeZone, Utc,
+	offset::{Local as LocalTime, LocalResult},
+};
+use chrono_tz::Tz;
+use cron::Schedule;
+use either::Either;
+use std::{str::FromStr, time::Duration};
 
 /// A point in time of a day
 #[derive(Debug)]
@@ -17,8 +23,26 @@ pub enum TimePoint {
 
 	/// A point in time of a day
 	Time(NaiveTime),
+
+	/// A cron-like schedule, e.g. "every weekday at 9:00 and 17:30" or "every 1st day of the month at noon"
+	Cron(Box<Cron>),
+}
+
+/// A cron-like schedule that supports multiple fire times, day of the week and day of the month restrictions,
+/// optionally in a time zone different from the local one
+#[derive(Clone, Debug)]
+pub struct Cron {
+	schedule: Schedule,
+
+	/// The time zone the schedule is in. Local time zone if None
+	timezone: Option<Tz>,
 }
 
+#[expect(missing_docs, reason = "error message is self-documenting")]
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid cron expression: {1:?}")]
+pub struct BadCronError(#[source] pub cron::error::Error, pub String);
+
 impl TimePoint {
 	/// Returns the duration that is left to the next appropriate point in the day from now
 	#[must_use]
@@ -55,10 +79,86 @@ impl TimePoint {
 						),
 				}
 			}
+			TimePoint::Cron(cron) => {
+				let Some(next) = cron.next_after(now) else {
+					tracing::warn!("Cron schedule {cron:?} will never fire again");
+					return Duration::MAX;
+				};
+
+				// can't be negative since the next fire time is always after now
+				(next - local_to_utc(now)).to_std().unwrap_or_default()
+			}
 		}
 	}
 }
 
+impl Cron {
+	/// Creates a new [`Cron`] schedule from a cron `expression` in the `timezone`, or in the local time zone if it's None.
+	///
+	/// Both the standard 5-field format (`minute hour day-of-month month day-of-week`),
+	/// and the extended 6 or 7-field format with seconds in front and an optional year at the end are supported,
+	/// e.g. `30 9,17 * * Mon-Fri` fires every weekday at 9:30 and 17:30
+	///
+	/// # Errors
+	/// if the cron expression is invalid
+	pub fn new(expression: &str, timezone: Option<Tz>) -> Result<Self, BadCronError> {
+		// the cron crate requires the seconds field to be present
+		let schedule = if expression.split_whitespace().count() == 5 {
+			Schedule::from_str(&format!("0 {expression}"))
+		} else {
+			Schedule::from_str(expression)
+		}
+		.map_err(|e| BadCronError(e, expression.to_owned()))?;
+
+		Ok(Self { schedule, timezone })
+	}
+
+	/// Returns the first time the schedule fires strictly after the local time `now`,
+	/// or None if it will never fire again
+	#[must_use]
+	pub fn next_after(&self, now: NaiveDateTime) -> Option<DateTime<Utc>> {
+		self.fire_times_after(now).next()
+	}
+
+	/// Returns all times the schedule fires strictly after the local time `from` and up to and including the local time `to`
+	pub fn fire_times_between(
+		&self,
+		from: NaiveDateTime,
+		to: NaiveDateTime,
+	) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+		let to = local_to_utc(to);
+
+		self.fire_times_after(from)
+			.take_while(move |fire_time| *fire_time <= to)
+	}
+
+	fn fire_times_after(&self, after: NaiveDateTime) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+		let after = local_to_utc(after);
+
+		match self.timezone {
+			Some(tz) => Either::Left(
+				self.schedule
+					.after(&after.with_timezone(&tz))
+					.map(|dt| dt.to_utc()),
+			),
+			None => Either::Right(
+				self.schedule
+					.after(&after.with_timezone(&LocalTime))
+					.map(|dt| dt.to_utc()),
+			),
+		}
+	}
+}
+
+/// Converts a local date and time to UTC, picking the earliest one if it's ambiguous.
+/// If the local time doesn't exist (e.g. was skipped due to DST), it's assumed to already be in UTC
+fn local_to_utc(local: NaiveDateTime) -> DateTime<Utc> {
+	match LocalTime.from_local_datetime(&local) {
+		LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => dt.to_utc(),
+		LocalResult::None => local.and_utc(),
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	#![allow(clippy::unwrap_used)]
@@ -98,4 +198,41 @@ mod tests {
 
 		assert_eq!(at_10_am.remaining_from(*NOW), HOUR * 22);
 	}
+
+	// NOW is a saturday
+	#[test]
+	fn cron_weekdays() {
+		let weekdays = TimePoint::Cron(Box::new(Cron::new("30 9,17 * * Mon-Fri", None).unwrap()));
+
+		// monday at 9:30
+		assert_eq!(weekdays.remaining_from(*NOW), HOUR * 45 + HOUR / 2);
+	}
+
+	#[test]
+	fn cron_multiple_times_a_day() {
+		let twice_a_day = TimePoint::Cron(Box::new(Cron::new("0 0 9,17 * * *", None).unwrap()));
+
+		assert_eq!(twice_a_day.remaining_from(*NOW), HOUR * 5);
+	}
+
+	#[test]
+	fn cron_day_of_month() {
+		let first_of_the_month = TimePoint::Cron(Box::new(Cron::new("0 12 1 * *", None).unwrap()));
+
+		// NOW is the 1st of January at 12:00 exactly, so the next one is the 1st of February
+		assert_eq!(first_of_the_month.remaining_from(*NOW), HOUR * 24 * 31);
+	}
+
+	#[test]
+	fn cron_fire_times_between() {
+		let hourly = Cron::new("0 * * * *", None).unwrap();
+		let in_3_hours = *NOW + chrono::Duration::hours(3);
+
+		assert_eq!(hourly.fire_times_between(*NOW, in_3_hours).count(), 3);
+	}
+
+	#[test]
+	fn cron_invalid() {
+		assert!(Cron::new("not a cron expression", None).is_err());
+	}
 }
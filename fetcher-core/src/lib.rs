@@ -15,6 +15,7 @@ pub mod error;
 pub mod exec;
 pub mod external_save;
 pub mod job;
+pub mod metrics;
 pub mod read_filter;
 pub mod sink;
 pub mod source;
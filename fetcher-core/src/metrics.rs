@@ -0,0 +1,63 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the names of all metrics that [`fetcher`](`crate`) records via the [`metrics`] facade.
+//!
+//! They are only collected if the application has installed a recorder, e.g. a Prometheus exporter.
+//! All of them are labeled with [`Task::metrics_labels`](`crate::task::Task::metrics_labels`)
+
+use crate::error::FetcherError;
+
+/// Counter of task runs
+pub const TASK_RUNS: &str = "fetcher_task_runs_total";
+
+/// Counter of task runs that have finished with an error. Labeled with the kind of the error
+pub const TASK_ERRORS: &str = "fetcher_task_errors_total";
+
+/// Gauge of the unix timestamp of the last task run that has finished successfully
+pub const TASK_LAST_SUCCESS: &str = "fetcher_task_last_success_timestamp_seconds";
+
+/// Counter of entries fetched from the source
+pub const ENTRIES_FETCHED: &str = "fetcher_entries_fetched_total";
+
+/// Counter of entries filtered out by a read filter because they had already been read
+pub const ENTRIES_READ_FILTERED: &str = "fetcher_entries_read_filtered_total";
+
+/// Counter of entries sent to a sink. Labeled with the name of the sink
+pub const ENTRIES_SENT: &str = "fetcher_entries_sent_total";
+
+/// Describe all metrics above. Should be called after the recorder has been installed
+pub fn describe() {
+	use metrics::{Unit, describe_counter, describe_gauge};
+
+	describe_counter!(TASK_RUNS, "Number of times a task has been run");
+	describe_counter!(
+		TASK_ERRORS,
+		"Number of times a task has finished with an error, by the kind of the error"
+	);
+	describe_gauge!(
+		TASK_LAST_SUCCESS,
+		Unit::Seconds,
+		"Unix timestamp of the last time a task has finished successfully"
+	);
+	describe_counter!(ENTRIES_FETCHED, "Number of entries fetched from the source");
+	describe_counter!(
+		ENTRIES_READ_FILTERED,
+		"Number of already read entries filtered out by the read filter"
+	);
+	describe_counter!(ENTRIES_SENT, "Number of entries sent, by the sink");
+}
+
+/// Name of the kind of the error to use as a metric label
+pub(crate) const fn error_kind(err: &FetcherError) -> &'static str {
+	match err {
+		FetcherError::Source(_) => "source",
+		FetcherError::Transform(_) => "transform",
+		FetcherError::Sink(_) => "sink",
+		FetcherError::GoogleOAuth2(_) => "google_oauth2",
+		FetcherError::ExternalSave(_) => "external_save",
+	}
+}
@@ -10,18 +10,27 @@
 pub mod message;
 
 pub mod discord;
+pub mod email;
+pub mod feed;
+pub mod file;
+pub mod matrix;
 pub mod stdout;
 pub mod telegram;
+pub mod webhook;
 
 pub mod error;
 
-pub use self::{discord::Discord, stdout::Stdout, telegram::Telegram};
+pub use self::{
+	discord::Discord, email::Email, feed::Feed, file::File, matrix::Matrix, stdout::Stdout,
+	telegram::Telegram, webhook::Webhook,
+};
 pub use crate::exec::Exec;
 
 use self::{
 	error::SinkError,
 	message::{Message, MessageId},
 };
+use crate::entry::EntryId;
 
 use async_trait::async_trait;
 use std::fmt::Debug;
@@ -36,4 +45,74 @@ pub trait Sink: Debug + Send + Sync {
 		reply_to: Option<&MessageId>,
 		tag: Option<&str>,
 	) -> Result<Option<MessageId>, SinkError>;
+
+	/// Send the message of the entry with `entry_id`.
+	/// Sinks that need the id of the entry, e.g. to include it in the sent data, should override this method.
+	/// Defaults to [`Sink::send`]
+	async fn send_entry(
+		&self,
+		message: &Message,
+		_entry_id: Option<&EntryId>,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		self.send(message, reply_to, tag).await
+	}
+
+	/// Send the message of the entry with `entry_id` like [`Sink::send_entry`] but return the ids of all messages it has been sent as,
+	/// starting with the one [`Sink::send_entry`] would've returned, so that all of them can be deleted later.
+	/// Sinks that may split a message into several ones should override this method.
+	/// Defaults to [`Sink::send_entry`]
+	async fn send_entry_parts(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Vec<MessageId>, SinkError> {
+		let msgid = self.send_entry(message, entry_id, reply_to, tag).await?;
+		Ok(msgid.into_iter().collect())
+	}
+
+	/// Edit the already sent message with `message_id` to contain `message` instead, e.g. if the entry it was sent for has changed.
+	/// Defaults to sending `message` as a reply to the original one for sinks that can't edit messages
+	async fn edit(
+		&self,
+		message: &Message,
+		message_id: &MessageId,
+		tag: Option<&str>,
+	) -> Result<(), SinkError> {
+		self.send(message, Some(message_id), tag).await?;
+		Ok(())
+	}
+
+	/// Edit the already sent message with `message_id` of the entry with `entry_id`.
+	/// Sinks that need the id of the entry, e.g. to include it in the sent data, should override this method.
+	/// Defaults to [`Sink::edit`]
+	async fn edit_entry(
+		&self,
+		message: &Message,
+		_entry_id: Option<&EntryId>,
+		message_id: &MessageId,
+		tag: Option<&str>,
+	) -> Result<(), SinkError> {
+		self.edit(message, message_id, tag).await
+	}
+
+	/// Delete the already sent message with `message_id`, e.g. if the entry it was sent for has been removed from the source.
+	/// Defaults to leaving the message as is for sinks that can't delete messages
+	async fn delete(&self, message_id: &MessageId) -> Result<(), SinkError> {
+		tracing::warn!(
+			"{} sink can't delete messages, leaving message {message_id:?} as is",
+			self.name()
+		);
+		Ok(())
+	}
+
+	/// Name of the sink, e.g. to label metrics with. Defaults to the name of the type without the module path
+	fn name(&self) -> &'static str {
+		let name = std::any::type_name::<Self>();
+		let name = name.split('<').next().unwrap_or(name);
+		name.rsplit("::").next().unwrap_or(name)
+	}
 }
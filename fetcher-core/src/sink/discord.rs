@@ -6,63 +6,194 @@
 
 //! This module contains the [`Discord`] sink
 
-use std::num::TryFromIntError;
-
 use async_trait::async_trait;
 use serenity::{
-	all::{CreateEmbed, CreateEmbedFooter},
-	builder::CreateMessage,
+	all::{
+		CreateAttachment, CreateEmbed, CreateEmbedFooter, CreateForumPost, CreateThread,
+		EditWebhookMessage, ExecuteWebhook,
+	},
+	builder::{Builder, CreateMessage, EditMessage},
 	http::Http as Bot,
 	model::{
-		channel::Message as DcMessage,
-		id::{ChannelId, MessageId as DcMessageId, UserId},
+		channel::{Channel, ChannelType},
+		id::{ChannelId, GuildId, MessageId as DcMessageId, UserId, WebhookId},
 	},
 };
+use std::{
+	collections::HashMap,
+	num::NonZeroU64,
+	sync::{Mutex, PoisonError},
+};
+use tokio::sync::OnceCell;
+use url::Url;
 
 use super::{
 	Sink,
-	error::SinkError,
+	error::{InvalidMessageIdTypeError, SinkError},
 	message::{Media, Message, MessageId, length_limiter::MessageLengthLimiter},
 };
-use crate::utils::OptionExt;
+use crate::{entry::EntryId, utils::OptionExt};
 
 // https://discord.com/developers/docs/resources/channel#create-message
 const MAX_MSG_LEN: usize = 2000;
 const MAX_EMBED_DESCIPTION_LEN: usize = 2000;
+const MAX_EMBEDS: usize = 10;
+
+// https://discord.com/developers/docs/resources/channel#start-thread-without-message
+const MAX_THREAD_NAME_LEN: usize = 100;
 
-/// Discord sink. Supports both text channels and DMs with a user
+/// Discord sink. Supports text and forum channels, DMs with a user, and webhooks.
+///
+/// Messages can also be posted into threads, see [`Threads`]
 #[derive(Debug)]
 pub struct Discord {
 	bot: Bot,
 	target: TargetInner,
+	threads: Option<Threads>,
+
+	/// Info about the target channel, fetched the first time a thread is created or looked up
+	channel_info: OnceCell<ChannelInfo>,
+
+	/// Tag threads that have already been created or found, by their names
+	tag_threads: Mutex<HashMap<String, ChannelId>>,
 }
 
 /// Target for the [`Discord`] sink where it sends message to
 #[derive(Clone, Copy, Debug)]
 pub enum Target {
-	/// A text channel ID
+	/// A text or a forum channel ID
 	Channel(u64),
 
 	/// A user ID, whose DMs to send messages into
 	User(u64),
 }
 
+/// A Discord webhook that can send messages without a bot
+#[derive(Clone, Debug)]
+pub struct Webhook {
+	/// URL of the webhook, e.g. `https://discord.com/api/webhooks/<id>/<token>`
+	pub url: Url,
+
+	/// Name to post messages under instead of the name of the webhook
+	pub username: Option<String>,
+
+	/// URL of the avatar to post messages with instead of the avatar of the webhook
+	pub avatar_url: Option<String>,
+}
+
+/// Which threads to post messages into instead of the channel itself.
+/// Posts are created instead of threads in forum channels.
+///
+/// Replies are always posted into the thread of the message they are replying to.
+/// DMs don't support threads, and webhooks can only create posts in forum channels
+#[derive(Clone, Copy, Debug)]
+pub enum Threads {
+	/// Create a new thread for every message, named after its title or tag
+	PerEntry,
+
+	/// Post all messages with the same tag into a single thread named after the tag, or "untagged" if there's none.
+	/// Bots look for an active thread with the same name in the channel before creating a new one,
+	/// while webhooks create a new thread for each tag every time fetcher is restarted
+	PerTag,
+}
+
+/// The URL isn't a valid Discord webhook URL
+#[derive(thiserror::Error, Debug)]
+#[error("{0} is not a valid Discord webhook URL")]
+pub struct InvalidWebhookUrlError(pub Url);
+
 #[derive(Debug)]
 enum TargetInner {
 	Channel(ChannelId),
 	User(UserId),
+	Webhook(WebhookInner),
+}
+
+#[derive(Debug)]
+struct WebhookInner {
+	id: WebhookId,
+	token: String,
+	username: Option<String>,
+	avatar_url: Option<String>,
+}
+
+#[derive(Debug)]
+struct ChannelInfo {
+	guild_id: Option<GuildId>,
+	is_forum: bool,
+}
+
+/// Where a sent message is located, i.e. its id and the thread it has been posted into.
+///
+/// Saved as a [`MessageId::Int`] if the message isn't in a thread, and as a "`thread_id`/`message_id`" [`MessageId::Str`] otherwise
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Location {
+	thread: Option<ChannelId>,
+	message: DcMessageId,
+}
+
+/// Where to post a message
+#[derive(Debug)]
+enum Destination {
+	Target,
+	Thread(ChannelId),
+	NewThread(String),
+}
+
+/// A single Discord message composed out of a [`Message`] that can be sent by both a bot and a webhook
+#[derive(Default, Debug)]
+struct Composed {
+	content: Option<String>,
+	embeds: Vec<CreateEmbed>,
+	files: Vec<CreateAttachment>,
 }
 
 impl Discord {
 	/// Create a new [`Discord`] sink. Needs a valid Discord bot `token` and a `target` where to send messages to
 	#[must_use]
 	pub fn new(token: &str, target: Target) -> Self {
-		Self {
-			bot: Bot::new(token),
-			target: match target {
+		Self::new_with_target(
+			Bot::new(token),
+			match target {
 				Target::Channel(i) => TargetInner::Channel(i.into()),
 				Target::User(i) => TargetInner::User(i.into()),
 			},
+		)
+	}
+
+	/// Create a new [`Discord`] sink that sends messages via a `webhook`
+	///
+	/// # Errors
+	/// if the URL of the webhook isn't a valid Discord webhook URL
+	pub fn new_webhook(webhook: Webhook) -> Result<Self, InvalidWebhookUrlError> {
+		let (id, token) = serenity::utils::parse_webhook(&webhook.url)
+			.ok_or_else(|| InvalidWebhookUrlError(webhook.url.clone()))?;
+
+		let target = TargetInner::Webhook(WebhookInner {
+			id,
+			token: token.to_owned(),
+			username: webhook.username,
+			avatar_url: webhook.avatar_url,
+		});
+
+		// webhooks are authenticated via the token in their URL
+		Ok(Self::new_with_target(Bot::new(""), target))
+	}
+
+	/// Post messages into [`Threads`]
+	#[must_use]
+	pub fn with_threads(mut self, threads: Threads) -> Self {
+		self.threads = Some(threads);
+		self
+	}
+
+	fn new_with_target(bot: Bot, target: TargetInner) -> Self {
+		Self {
+			bot,
+			target,
+			threads: None,
+			channel_info: OnceCell::new(),
+			tag_threads: Mutex::new(HashMap::new()),
 		}
 	}
 }
@@ -75,131 +206,851 @@ impl Sink for Discord {
 		reply_to: Option<&MessageId>,
 		tag: Option<&str>,
 	) -> Result<Option<MessageId>, SinkError> {
-		let mut last_message = reply_to.try_map(|msgid| {
-			let dc_msgid = DcMessageId::from(u64::try_from(msgid.0)?);
-
-			Ok::<_, TryFromIntError>(dc_msgid)
-		})?;
-
-		let Message {
-			title,
-			body,
-			link,
-			media,
-		} = msg.clone(); // clone is to be able to include the message if an error happens
-
-		// if the body of the message won't fit into an embed, then just send as regular messages
-		if body.as_ref().map_or(0, |s| s.chars().count()) > MAX_EMBED_DESCIPTION_LEN {
-			let mut head = title;
-
-			// add tag as a hashtag on top of the message
-			if let Some(tag) = tag {
-				let tag = tag.replace(
-					|c| match c {
-						'_' => false,
-						c if c.is_alphabetic() || c.is_ascii_digit() => false,
-						_ => true,
-					},
-					"_",
-				);
-
-				head = Some({
-					let mut head = head
-						// add more padding between tag and title if both are present
-						.map(|mut s| {
-							s.insert(0, '\n');
-							s
-						})
-						.unwrap_or_default();
+		let msg_ids = self.send_entry_parts(msg, None, reply_to, tag).await?;
+		Ok(msg_ids.into_iter().next())
+	}
+
+	/// Sends the message and returns the ids of all the messages it has been split into, starting with the last one
+	async fn send_entry_parts(
+		&self,
+		msg: &Message,
+		_entry_id: Option<&EntryId>,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Vec<MessageId>, SinkError> {
+		let reply_to = reply_to.try_map(Location::from_message_id)?;
+
+		// include the message if an error happens
+		let err = |e| SinkError::Discord {
+			source: e,
+			msg: Box::new(msg.clone()),
+		};
+
+		let mut destination = self.destination(msg, reply_to, tag).await.map_err(err)?;
+		let mut sent_messages = Vec::new();
+
+		for composed in compose(msg, tag) {
+			let sent = self.deliver(composed, &destination).await.map_err(err)?;
+
+			// post the rest of the message into the newly created thread
+			if let Destination::NewThread(name) = &destination
+				&& let Some(thread) = sent.thread
+			{
+				if let Some(Threads::PerTag) = self.threads {
+					self.tag_threads
+						.lock()
+						.unwrap_or_else(PoisonError::into_inner)
+						.insert(name.clone(), thread);
+				}
 
-					head.insert_str(0, &format!("#{tag}\n"));
-					head
-				});
+				destination = Destination::Thread(thread);
 			}
 
-			let link = link.map(|s| s.to_string());
+			sent_messages.push(sent);
+		}
+
+		// the last message is the one to reply to
+		Ok(sent_messages
+			.into_iter()
+			.rev()
+			.map(Location::into_message_id)
+			.collect())
+	}
+
+	/// Replaces the embeds of the message. Attached files are left as is.
+	///
+	/// Sends the message as a reply instead if it doesn't fit into a single message with embeds or if Discord refused to edit it
+	#[tracing::instrument(level = "debug", skip(msg))]
+	async fn edit(
+		&self,
+		msg: &Message,
+		message_id: &MessageId,
+		tag: Option<&str>,
+	) -> Result<(), SinkError> {
+		let location = Location::from_message_id(message_id)?;
+		let embeds = embeds(msg, tag);
+
+		if !fits_into_embed(msg) || embeds.len() > MAX_EMBEDS {
+			tracing::info!(
+				"Edited message doesn't fit into a single message with embeds, sending it as a reply"
+			);
+			self.send(msg, Some(message_id), tag).await?;
+			return Ok(());
+		}
+
+		tracing::info!("Editing message");
+		let res = match &self.target {
+			TargetInner::Webhook(webhook) => {
+				let mut edit = EditWebhookMessage::new().content("").embeds(embeds);
 
-			let mut composed_msg = MessageLengthLimiter {
-				head: head.as_deref(),
-				body: body.as_deref(),
-				tail: link.as_deref(),
-			};
+				if let Some(thread) = location.thread {
+					edit = edit.in_thread(thread);
+				}
 
-			while let Some(text) = composed_msg.split_at(MAX_MSG_LEN) {
-				let msg = self
-					.target
-					.send_message(&self.bot, CreateMessage::new().content(&text))
+				edit.execute(&self.bot, (webhook.id, &webhook.token, location.message))
 					.await
-					.map_err(|e| SinkError::Discord {
-						source: e,
-						msg: Box::new(text),
-					})?;
+					.map(drop)
+			}
+			_ => {
+				let edit = EditMessage::new().content("").embeds(embeds);
 
-				last_message = Some(msg.id);
+				match self.channel_of(location).await {
+					Ok(channel) => channel
+						.edit_message(&self.bot, location.message, edit)
+						.await
+						.map(drop),
+					Err(e) => Err(e),
+				}
 			}
-		}
-		// send as an embed (much pretty, so wow!)
-		else {
-			let mut embed = CreateEmbed::new();
+		};
 
-			if let Some(title) = title {
-				embed = embed.title(title);
+		match res {
+			Ok(()) => Ok(()),
+			Err(serenity::Error::Http(e)) if e.is_unsuccessful_request() => {
+				tracing::warn!("Can't edit the message, sending it as a reply instead: {e}");
+				self.send(msg, Some(message_id), tag).await?;
+				Ok(())
 			}
+			Err(e) => Err(SinkError::Discord {
+				source: e,
+				msg: Box::new(msg.clone()),
+			}),
+		}
+	}
 
-			if let Some(body) = body {
-				embed = embed.description(body);
+	/// Deletes the message alongside its attachments. Threads are left as is.
+	///
+	/// Leaves the message as is if Discord refused to delete it, e.g. because it has already been deleted by someone else
+	#[tracing::instrument(level = "debug")]
+	async fn delete(&self, message_id: &MessageId) -> Result<(), SinkError> {
+		let location = Location::from_message_id(message_id)?;
+
+		tracing::info!("Deleting message");
+		let res = match &self.target {
+			TargetInner::Webhook(webhook) => {
+				self.bot
+					.delete_webhook_message(
+						webhook.id,
+						location.thread,
+						&webhook.token,
+						location.message,
+					)
+					.await
 			}
+			_ => match self.channel_of(location).await {
+				Ok(channel) => channel.delete_message(&self.bot, location.message).await,
+				Err(e) => Err(e),
+			},
+		};
 
-			if let Some(link) = link {
-				embed = embed.url(link);
+		match res {
+			Ok(()) => Ok(()),
+			Err(serenity::Error::Http(e)) if e.is_unsuccessful_request() => {
+				tracing::warn!("Can't delete the message, leaving it as is: {e}");
+				Ok(())
 			}
+			Err(e) => Err(SinkError::Discord {
+				source: e,
+				msg: Box::new(message_id.clone()),
+			}),
+		}
+	}
+}
+
+impl Discord {
+	/// Decides where to post the message depending on [`Discord::threads`]
+	async fn destination(
+		&self,
+		msg: &Message,
+		reply_to: Option<Location>,
+		tag: Option<&str>,
+	) -> Result<Destination, serenity::Error> {
+		// keep replies together with the message they are replying to
+		if let Some(thread) = reply_to.and_then(|loc| loc.thread) {
+			return Ok(Destination::Thread(thread));
+		}
+
+		if let TargetInner::User(_) = self.target {
+			return Ok(Destination::Target);
+		}
 
-			if let Some(tag) = tag {
-				embed = embed.footer(CreateEmbedFooter::new(tag));
+		let destination = match self.threads {
+			None => Destination::Target,
+			Some(Threads::PerEntry) => {
+				let name = msg.title.as_deref().or(tag).unwrap_or("untitled");
+				Destination::NewThread(thread_name(name))
 			}
+			Some(Threads::PerTag) => {
+				let name = thread_name(tag.unwrap_or("untagged"));
 
-			if let Some(media) = media {
-				for media in media {
-					if let Media::Photo(image) = media {
-						embed = embed.image(image);
-					}
+				match self.find_tag_thread(&name).await? {
+					Some(thread) => Destination::Thread(thread),
+					None => Destination::NewThread(name),
 				}
 			}
+		};
 
-			let msg = self
-				.target
-				.send_message(&self.bot, CreateMessage::new().embed(embed))
-				.await
-				.map_err(|e| SinkError::Discord {
-					source: e,
-					msg: Box::new(msg.clone()),
-				})?;
+		Ok(destination)
+	}
+
+	/// Finds an already existing thread of a tag named `name`
+	async fn find_tag_thread(&self, name: &str) -> Result<Option<ChannelId>, serenity::Error> {
+		if let Some(thread) = self
+			.tag_threads
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.get(name)
+		{
+			return Ok(Some(*thread));
+		}
+
+		// webhooks can't list threads
+		let TargetInner::Channel(channel) = self.target else {
+			return Ok(None);
+		};
+
+		let Some(guild_id) = self.channel_info(channel).await?.guild_id else {
+			return Ok(None);
+		};
+
+		tracing::debug!("Looking for an active thread named {name:?}");
+		let thread = guild_id
+			.get_active_threads(&self.bot)
+			.await?
+			.threads
+			.into_iter()
+			.find(|thread| thread.parent_id == Some(channel) && thread.name == name)
+			.map(|thread| thread.id);
 
-			last_message = Some(msg.id);
+		if let Some(thread) = thread {
+			self.tag_threads
+				.lock()
+				.unwrap_or_else(PoisonError::into_inner)
+				.insert(name.to_owned(), thread);
 		}
 
-		// If it does, we should crash and think of a new solution anyways
-		let msgid = last_message.map(|id| i64::try_from(id.get()).expect("not sure if Discord will ever return an ID that doesn't fit into MessageId. It shouldn't do that, probably...").into());
-		Ok(msgid)
+		Ok(thread)
 	}
-}
 
-impl TargetInner {
-	async fn send_message(
+	async fn channel_info(&self, channel: ChannelId) -> Result<&ChannelInfo, serenity::Error> {
+		self.channel_info
+			.get_or_try_init(|| async {
+				let info = match channel.to_channel(&self.bot).await? {
+					Channel::Guild(channel) => ChannelInfo {
+						guild_id: Some(channel.guild_id),
+						is_forum: channel.kind == ChannelType::Forum,
+					},
+					_ => ChannelInfo {
+						guild_id: None,
+						is_forum: false,
+					},
+				};
+
+				Ok(info)
+			})
+			.await
+	}
+
+	/// Returns the channel the bot has sent the message at `location` to
+	async fn channel_of(&self, location: Location) -> Result<ChannelId, serenity::Error> {
+		if let Some(thread) = location.thread {
+			return Ok(thread);
+		}
+
+		match &self.target {
+			TargetInner::Channel(channel) => Ok(*channel),
+			TargetInner::User(user) => Ok(user.create_dm_channel(&self.bot).await?.id),
+			TargetInner::Webhook(_) => Err(serenity::Error::Other(
+				"webhooks don't send messages to a channel directly",
+			)),
+		}
+	}
+
+	/// Sends a single composed message to the `destination`
+	async fn deliver(
 		&self,
-		bot: &Bot,
-		message: CreateMessage,
-	) -> Result<DcMessage, serenity::Error> {
-		let msg = match self {
-			TargetInner::Channel(chan) => chan.send_message(bot, message).await?,
-			TargetInner::User(user) => {
-				user.create_dm_channel(bot)
+		composed: Composed,
+		destination: &Destination,
+	) -> Result<Location, serenity::Error> {
+		match &self.target {
+			TargetInner::Webhook(webhook) => {
+				let mut execute = ExecuteWebhook::new()
+					.embeds(composed.embeds)
+					.add_files(composed.files);
+
+				if let Some(content) = composed.content {
+					execute = execute.content(content);
+				}
+
+				if let Some(username) = &webhook.username {
+					execute = execute.username(username);
+				}
+
+				if let Some(avatar_url) = &webhook.avatar_url {
+					execute = execute.avatar_url(avatar_url);
+				}
+
+				execute = match destination {
+					Destination::Target => execute,
+					Destination::Thread(thread) => execute.in_thread(*thread),
+					// webhooks can only create posts in forum channels
+					Destination::NewThread(name) => execute.thread_name(name.clone()),
+				};
+
+				let msg = execute
+					.execute(&self.bot, (webhook.id, &webhook.token, true))
 					.await?
-					.send_message(bot, message)
+					.ok_or(serenity::Error::Other(
+						"Discord hasn't returned the message sent via the webhook",
+					))?;
+
+				Ok(Location {
+					thread: (!matches!(destination, Destination::Target)).then_some(msg.channel_id),
+					message: msg.id,
+				})
+			}
+			TargetInner::Channel(channel) => {
+				let message = composed.into_create_message();
+
+				match destination {
+					Destination::Target => {
+						let msg = channel.send_message(&self.bot, message).await?;
+						Ok(Location {
+							thread: None,
+							message: msg.id,
+						})
+					}
+					Destination::Thread(thread) => {
+						let msg = thread.send_message(&self.bot, message).await?;
+						Ok(Location {
+							thread: Some(*thread),
+							message: msg.id,
+						})
+					}
+					Destination::NewThread(name) => {
+						self.create_thread(*channel, name, message).await
+					}
+				}
+			}
+			TargetInner::User(user) => {
+				let msg = user
+					.create_dm_channel(&self.bot)
 					.await?
+					.send_message(&self.bot, composed.into_create_message())
+					.await?;
+
+				Ok(Location {
+					thread: None,
+					message: msg.id,
+				})
+			}
+		}
+	}
+
+	/// Creates a thread, or a post in a forum channel, named `name` that starts with the `message`
+	async fn create_thread(
+		&self,
+		channel: ChannelId,
+		name: &str,
+		message: CreateMessage,
+	) -> Result<Location, serenity::Error> {
+		tracing::info!("Creating thread {name:?}");
+
+		if self.channel_info(channel).await?.is_forum {
+			let post = channel
+				.create_forum_post(&self.bot, CreateForumPost::new(name, message))
+				.await?;
+
+			// the first message of a post has the same id as the post itself
+			return Ok(Location {
+				thread: Some(post.id),
+				message: DcMessageId::new(post.id.get()),
+			});
+		}
+
+		let thread = channel
+			.create_thread(
+				&self.bot,
+				CreateThread::new(name).kind(ChannelType::PublicThread),
+			)
+			.await?;
+
+		let msg = thread.id.send_message(&self.bot, message).await?;
+
+		Ok(Location {
+			thread: Some(thread.id),
+			message: msg.id,
+		})
+	}
+}
+
+impl Location {
+	fn from_message_id(message_id: &MessageId) -> Result<Self, InvalidMessageIdTypeError> {
+		let parse_id = |id: &str| {
+			id.parse::<NonZeroU64>()
+				.map_err(|_| InvalidMessageIdTypeError(message_id.clone()))
+		};
+
+		match message_id {
+			MessageId::Int(_) => Ok(Self {
+				thread: None,
+				message: NonZeroU64::new(message_id.to_int()?)
+					.ok_or_else(|| InvalidMessageIdTypeError(message_id.clone()))?
+					.into(),
+			}),
+			MessageId::Str(id) => {
+				let (thread, message) = id
+					.split_once('/')
+					.ok_or_else(|| InvalidMessageIdTypeError(message_id.clone()))?;
+
+				Ok(Self {
+					thread: Some(parse_id(thread)?.into()),
+					message: parse_id(message)?.into(),
+				})
 			}
+		}
+	}
+
+	fn into_message_id(self) -> MessageId {
+		match self.thread {
+			Some(thread) => MessageId::Str(format!("{thread}/{}", self.message)),
+			// If it does, we should crash and think of a new solution anyways
+			None => i64::try_from(self.message.get()).expect("not sure if Discord will ever return an ID that doesn't fit into MessageId. It shouldn't do that, probably...").into(),
+		}
+	}
+}
+
+impl Composed {
+	fn into_create_message(self) -> CreateMessage {
+		let mut message = CreateMessage::new()
+			.embeds(self.embeds)
+			.add_files(self.files);
+
+		if let Some(content) = self.content {
+			message = message.content(content);
+		}
+
+		message
+	}
+}
+
+/// Composes the Discord messages to send the `msg` as.
+///
+/// The message is sent as embeds (much pretty, so wow!) if it fits into them, or as regular text messages otherwise.
+/// Files are uploaded as attachments of the first message
+fn compose(msg: &Message, tag: Option<&str>) -> Vec<Composed> {
+	let mut composed = if fits_into_embed(msg) {
+		embeds(msg, tag)
+			.chunks(MAX_EMBEDS)
+			.map(|embeds| Composed {
+				embeds: embeds.to_vec(),
+				..Default::default()
+			})
+			.collect::<Vec<_>>()
+	} else {
+		text_messages(msg, tag)
+			.into_iter()
+			.map(|text| Composed {
+				content: Some(text),
+				..Default::default()
+			})
+			.collect()
+	};
+
+	if let Some(first) = composed.first_mut() {
+		first.files = attachments(msg.media.as_deref());
+	}
+
+	composed
+}
+
+fn fits_into_embed(msg: &Message) -> bool {
+	msg.body.as_ref().map_or(0, |s| s.chars().count()) <= MAX_EMBED_DESCIPTION_LEN
+}
+
+/// Splits the message into text messages of at most [`MAX_MSG_LEN`] characters
+fn text_messages(msg: &Message, tag: Option<&str>) -> Vec<String> {
+	let mut head = msg.title.clone();
+
+	// add tag as a hashtag on top of the message
+	if let Some(tag) = tag {
+		let tag = tag.replace(
+			|c| match c {
+				'_' => false,
+				c if c.is_alphabetic() || c.is_ascii_digit() => false,
+				_ => true,
+			},
+			"_",
+		);
+
+		head = Some({
+			let mut head = head
+				// add more padding between tag and title if both are present
+				.map(|mut s| {
+					s.insert(0, '\n');
+					s
+				})
+				.unwrap_or_default();
+
+			head.insert_str(0, &format!("#{tag}\n"));
+			head
+		});
+	}
+
+	let link = msg.link.as_ref().map(Url::to_string);
+
+	let mut composed_msg = MessageLengthLimiter {
+		head: head.as_deref(),
+		body: msg.body.as_deref(),
+		tail: link.as_deref(),
+	};
+
+	std::iter::from_fn(|| composed_msg.split_at(MAX_MSG_LEN)).collect()
+}
+
+/// Returns the main embed containing the whole message and its first image, followed by an embed for each other image.
+///
+/// All embeds share the link of the message, which makes Discord show all the images together in the main embed
+fn embeds(msg: &Message, tag: Option<&str>) -> Vec<CreateEmbed> {
+	let mut embed = CreateEmbed::new();
+
+	if let Some(title) = &msg.title {
+		embed = embed.title(title);
+	}
+
+	if let Some(body) = &msg.body {
+		embed = embed.description(body);
+	}
+
+	if let Some(link) = &msg.link {
+		embed = embed.url(link.as_str());
+	}
+
+	if let Some(tag) = tag {
+		embed = embed.footer(CreateEmbedFooter::new(tag));
+	}
+
+	let mut images = msg.media.iter().flatten().filter_map(|media| match media {
+		Media::Photo(image) | Media::Animation(image) => Some(image.to_string()),
+		Media::File(file) if file.is_image() => Some(format!("attachment://{}", file.name)),
+		_ => None,
+	});
+
+	if let Some(image) = images.next() {
+		embed = embed.image(image);
+	}
+
+	let mut embeds = vec![embed];
+	embeds.extend(images.map(|image| {
+		let embed = CreateEmbed::new().image(image);
+
+		match &msg.link {
+			Some(link) => embed.url(link.as_str()),
+			None => embed,
+		}
+	}));
+
+	embeds
+}
+
+/// Makes `name` a valid thread name
+fn thread_name(name: &str) -> String {
+	let name = name.lines().next().unwrap_or_default().trim();
+
+	if name.is_empty() {
+		return "untitled".to_owned();
+	}
+
+	name.chars().take(MAX_THREAD_NAME_LEN).collect()
+}
+
+fn attachments(media: Option<&[Media]>) -> Vec<CreateAttachment> {
+	media
+		.into_iter()
+		.flatten()
+		.filter_map(|media| match media {
+			Media::File(file) => Some(CreateAttachment::bytes(
+				file.data.to_vec(),
+				file.name.clone(),
+			)),
+			_ => None,
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	use serde_json::json;
+	use serenity::http::HttpBuilder;
+	use wiremock::{
+		Mock, MockServer, ResponseTemplate,
+		matchers::{body_partial_json, method, path, query_param, query_param_is_missing},
+	};
+
+	const WEBHOOK_ID: &str = "123456789012345678";
+	const WEBHOOK_TOKEN: &str = "tttttttttttttttttttttttttttttttttttttttttttttttttttttttttttttttt";
+
+	/// A bot that sends all requests to the mock `server` instead of Discord
+	fn bot(server: &MockServer) -> Bot {
+		HttpBuilder::new("token")
+			.proxy(server.uri())
+			.ratelimiter_disabled(true)
+			.build()
+	}
+
+	fn webhook(server: &MockServer, threads: Option<Threads>) -> Discord {
+		let discord = Discord::new_webhook(Webhook {
+			url: format!("https://discord.com/api/webhooks/{WEBHOOK_ID}/{WEBHOOK_TOKEN}")
+				.parse()
+				.unwrap(),
+			username: Some("fetcher".to_owned()),
+			avatar_url: Some("https://example.com/avatar.png".to_owned()),
+		})
+		.unwrap();
+
+		Discord {
+			bot: bot(server),
+			threads,
+			..discord
+		}
+	}
+
+	fn message_json(id: u64, channel_id: u64) -> serde_json::Value {
+		json!({
+			"id": id.to_string(),
+			"channel_id": channel_id.to_string(),
+			"author": { "id": "1", "username": "fetcher", "discriminator": "0000", "avatar": null },
+			"content": "",
+			"timestamp": "2024-01-01T00:00:00+00:00",
+			"edited_timestamp": null,
+			"tts": false,
+			"mention_everyone": false,
+			"mentions": [],
+			"mention_roles": [],
+			"attachments": [],
+			"embeds": [],
+			"pinned": false,
+			"type": 0,
+		})
+	}
+
+	fn channel_json(id: u64, kind: u8, name: &str) -> serde_json::Value {
+		json!({
+			"id": id.to_string(),
+			"guild_id": "100",
+			"parent_id": "5",
+			"type": kind,
+			"name": name,
+			"position": 0,
+			"permission_overwrites": [],
+			"nsfw": false,
+		})
+	}
+
+	fn message(title: &str) -> Message {
+		Message {
+			title: Some(title.to_owned()),
+			body: Some("Body".to_owned()),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn location_roundtrip() {
+		let in_channel = Location {
+			thread: None,
+			message: DcMessageId::new(2),
 		};
+		let in_thread = Location {
+			thread: Some(ChannelId::new(1)),
+			message: DcMessageId::new(2),
+		};
+
+		assert_eq!(in_channel.into_message_id(), MessageId::Int(2));
+		assert_eq!(
+			in_thread.into_message_id(),
+			MessageId::Str("1/2".to_owned())
+		);
+
+		for location in [in_channel, in_thread] {
+			assert_eq!(
+				Location::from_message_id(&location.into_message_id()).unwrap(),
+				location
+			);
+		}
 
-		Ok(msg)
+		assert!(Location::from_message_id(&MessageId::Str("!room:matrix.org".to_owned())).is_err());
+	}
+
+	#[test]
+	fn several_images() {
+		let msg = Message {
+			title: Some("Title".to_owned()),
+			link: Some("https://example.com/".parse().unwrap()),
+			media: Some(
+				(0..12)
+					.map(|i| Media::Photo(format!("https://example.com/{i}.png").parse().unwrap()))
+					.collect(),
+			),
+			..Default::default()
+		};
+
+		let composed = compose(&msg, Some("tag"));
+		assert_eq!(composed.len(), 2);
+		assert_eq!(composed[0].embeds.len(), MAX_EMBEDS);
+		assert_eq!(composed[1].embeds.len(), 2);
+
+		let embeds = serde_json::to_value(&composed[0].embeds).unwrap();
+		assert_eq!(embeds[0]["title"], "Title");
+		assert_eq!(embeds[0]["image"]["url"], "https://example.com/0.png");
+		assert_eq!(embeds[1]["title"], serde_json::Value::Null);
+		assert_eq!(embeds[1]["url"], "https://example.com/");
+		assert_eq!(embeds[1]["image"]["url"], "https://example.com/1.png");
+	}
+
+	#[tokio::test]
+	async fn webhook_with_username_and_avatar() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.and(path(format!(
+				"/api/v10/webhooks/{WEBHOOK_ID}/{WEBHOOK_TOKEN}"
+			)))
+			.and(query_param("wait", "true"))
+			.and(query_param_is_missing("thread_id"))
+			.and(body_partial_json(json!({
+				"username": "fetcher",
+				"avatar_url": "https://example.com/avatar.png",
+				"embeds": [{ "title": "Title", "description": "Body" }],
+			})))
+			.respond_with(ResponseTemplate::new(200).set_body_json(message_json(2, 5)))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let discord = webhook(&server, None);
+		let msg_id = discord.send(&message("Title"), None, None).await.unwrap();
+		assert_eq!(msg_id, Some(MessageId::Int(2)));
+	}
+
+	#[tokio::test]
+	async fn webhook_forum_post_per_entry() {
+		let server = MockServer::start().await;
+
+		// the forum post is created and named after the title of the message
+		Mock::given(method("POST"))
+			.and(path(format!(
+				"/api/v10/webhooks/{WEBHOOK_ID}/{WEBHOOK_TOKEN}"
+			)))
+			.and(body_partial_json(json!({ "thread_name": "Title" })))
+			.respond_with(ResponseTemplate::new(200).set_body_json(message_json(11, 10)))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let discord = webhook(&server, Some(Threads::PerEntry));
+		let msg_id = discord.send(&message("Title"), None, None).await.unwrap();
+		assert_eq!(msg_id, Some(MessageId::Str("10/11".to_owned())));
+	}
+
+	#[tokio::test]
+	async fn webhook_reuses_tag_thread() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.and(path(format!(
+				"/api/v10/webhooks/{WEBHOOK_ID}/{WEBHOOK_TOKEN}"
+			)))
+			.and(query_param_is_missing("thread_id"))
+			.and(body_partial_json(json!({ "thread_name": "news" })))
+			.respond_with(ResponseTemplate::new(200).set_body_json(message_json(11, 10)))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path(format!(
+				"/api/v10/webhooks/{WEBHOOK_ID}/{WEBHOOK_TOKEN}"
+			)))
+			.and(query_param("thread_id", "10"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(message_json(12, 10)))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let discord = webhook(&server, Some(Threads::PerTag));
+
+		let first = discord
+			.send(&message("First"), None, Some("news"))
+			.await
+			.unwrap();
+		let second = discord
+			.send(&message("Second"), None, Some("news"))
+			.await
+			.unwrap();
+
+		assert_eq!(first, Some(MessageId::Str("10/11".to_owned())));
+		assert_eq!(second, Some(MessageId::Str("10/12".to_owned())));
+	}
+
+	#[tokio::test]
+	async fn bot_reuses_tag_thread() {
+		let server = MockServer::start().await;
+
+		// a text channel
+		Mock::given(method("GET"))
+			.and(path("/api/v10/channels/5"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(channel_json(5, 0, "feed")))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		// with an already existing thread for one of the tags
+		Mock::given(method("GET"))
+			.and(path("/api/v10/guilds/100/threads/active"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"threads": [channel_json(20, 11, "news")],
+				"members": [],
+			})))
+			.expect(2)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path("/api/v10/channels/20/messages"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(message_json(21, 20)))
+			.expect(2)
+			.mount(&server)
+			.await;
+
+		// and a new one for the other tag
+		Mock::given(method("POST"))
+			.and(path("/api/v10/channels/5/threads"))
+			.and(body_partial_json(json!({ "name": "other" })))
+			.respond_with(ResponseTemplate::new(200).set_body_json(channel_json(30, 11, "other")))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path("/api/v10/channels/30/messages"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(message_json(31, 30)))
+			.expect(2)
+			.mount(&server)
+			.await;
+
+		let discord = Discord::new_with_target(bot(&server), TargetInner::Channel(5.into()))
+			.with_threads(Threads::PerTag);
+
+		for tag in ["news", "other", "news", "other"] {
+			discord
+				.send(&message("Title"), None, Some(tag))
+				.await
+				.unwrap();
+		}
 	}
 }
@@ -0,0 +1,575 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`Email`] sink that sends messages as emails via SMTP
+
+use async_trait::async_trait;
+use lettre::{
+	AsyncSmtpTransport, AsyncTransport, Message as MailMessage, Tokio1Executor,
+	address::AddressError,
+	message::{Attachment, Mailbox, MultiPart, header::ContentType},
+	transport::smtp::authentication::{Credentials, Mechanism},
+};
+use std::{
+	fmt::{Debug, Write as _},
+	time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+
+use super::{
+	Sink,
+	error::SinkError,
+	message::{Media, Message, MessageId},
+};
+use crate::{
+	auth::{Google as GoogleAuth, google::GoogleOAuth2Error as GoogleAuthError},
+	utils::OptionExt,
+};
+
+const GMAIL_SMTP: &str = "smtp.gmail.com";
+
+// the max length of the subject if it has to be made out of the body of the message
+const MAX_SUBJECT_LEN: usize = 100;
+
+/// Email sink. Sends every message as an email with a text and an HTML body via SMTP
+pub struct Email {
+	smtp: String,
+	port: Option<u16>,
+	security: Security,
+	login: String,
+	auth: Mutex<Auth>,
+	from: Mailbox,
+	to: Vec<Mailbox>,
+}
+
+/// Authentication type for SMTP
+pub enum Auth {
+	#[expect(clippy::doc_markdown, reason = "false positive")]
+	/// Google OAuth2 with full access to Gmail
+	GmailOAuth2(GoogleAuth),
+	/// An insecure pure text password
+	Password(String),
+}
+
+/// How to secure the connection to the SMTP server
+#[derive(Clone, Copy, Default, Debug)]
+pub enum Security {
+	/// Connect via TLS right away, port 465 by default
+	#[default]
+	Tls,
+
+	/// Connect unencrypted and then upgrade the connection with STARTTLS, port 587 by default
+	StartTls,
+
+	/// Don't encrypt the connection at all, port 25 by default. Insecure and should only ever be used with a local server
+	None,
+}
+
+#[expect(missing_docs, reason = "error message is self-documenting")]
+#[derive(thiserror::Error, Debug)]
+pub enum SmtpError {
+	#[error("Invalid email address {1:?}")]
+	InvalidAddress(#[source] AddressError, String),
+
+	#[error(transparent)]
+	GoogleOAuth2(#[from] GoogleAuthError),
+
+	#[error("Can't compose the email")]
+	Compose(#[from] lettre::error::Error),
+
+	#[error("Can't send the email")]
+	Send(#[from] lettre::transport::smtp::Error),
+}
+
+impl Email {
+	/// Creates an [`Email`] sink for use with Gmail that uses [`Google OAuth2`](`crate::auth::Google`) to authenticate and sends emails from `email` to all of `to`
+	///
+	/// # Errors
+	/// if `email` or any of `to` is not a valid email address
+	pub fn new_gmail(email: String, auth: GoogleAuth, to: Vec<String>) -> Result<Self, SmtpError> {
+		Self::new(
+			GMAIL_SMTP.to_owned(),
+			None,
+			Security::Tls,
+			email,
+			Auth::GmailOAuth2(auth),
+			to,
+		)
+	}
+
+	/// Creates an [`Email`] sink that uses a password to authenticate via SMTP and sends emails from `email` to all of `to`.
+	/// The port is chosen depending on the `security` if it's not provided
+	///
+	/// # Errors
+	/// if `email` or any of `to` is not a valid email address
+	pub fn new_generic(
+		smtp: String,
+		port: Option<u16>,
+		security: Security,
+		email: String,
+		password: String,
+		to: Vec<String>,
+	) -> Result<Self, SmtpError> {
+		Self::new(smtp, port, security, email, Auth::Password(password), to)
+	}
+
+	fn new(
+		smtp: String,
+		port: Option<u16>,
+		security: Security,
+		email: String,
+		auth: Auth,
+		to: Vec<String>,
+	) -> Result<Self, SmtpError> {
+		let parse_mailbox = |addr: &str| {
+			addr.parse()
+				.map_err(|e| SmtpError::InvalidAddress(e, addr.to_owned()))
+		};
+
+		let from = parse_mailbox(&email)?;
+		let to = to
+			.into_iter()
+			.map(|addr| parse_mailbox(&addr))
+			.collect::<Result<_, _>>()?;
+
+		Ok(Self {
+			smtp,
+			port,
+			security,
+			login: email,
+			auth: Mutex::new(auth),
+			from,
+			to,
+		})
+	}
+}
+
+#[async_trait]
+impl Sink for Email {
+	/// Sends the message as an email with the title as the subject.
+	/// If `reply_to` is present, the email is marked as a reply to the email with that Message-ID, so that they are threaded together
+	///
+	/// # Errors
+	/// * if the SMTP server refused the email
+	/// * if there's no internet connection
+	#[tracing::instrument(level = "debug", skip(message))]
+	async fn send(
+		&self,
+		message: &Message,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		let reply_to = reply_to.try_map(MessageId::as_str)?;
+
+		let message_id = self.new_message_id();
+		let mail = self
+			.compose(message, &message_id, reply_to, tag)
+			.map_err(|e| SinkError::Smtp {
+				source: e,
+				msg: Box::new(message.clone()),
+			})?;
+
+		self.send_mail(mail).await.map_err(|e| SinkError::Smtp {
+			source: e,
+			msg: Box::new(message.clone()),
+		})?;
+
+		Ok(Some(MessageId::Str(message_id)))
+	}
+}
+
+impl Email {
+	fn compose(
+		&self,
+		message: &Message,
+		message_id: &str,
+		reply_to: Option<&str>,
+		tag: Option<&str>,
+	) -> Result<MailMessage, SmtpError> {
+		let (plain, html) = render_body(message);
+
+		let mut builder = MailMessage::builder()
+			.from(self.from.clone())
+			.subject(subject(message, tag))
+			.message_id(Some(message_id.to_owned()));
+
+		for to in &self.to {
+			builder = builder.to(to.clone());
+		}
+
+		if let Some(reply_to) = reply_to {
+			builder = builder
+				.in_reply_to(reply_to.to_owned())
+				.references(reply_to.to_owned());
+		}
+
+		let body = MultiPart::alternative_plain_html(plain, html);
+
+		let files = message
+			.media
+			.iter()
+			.flatten()
+			.filter_map(|media| match media {
+				Media::File(file) => Some(file),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		if files.is_empty() {
+			return Ok(builder.multipart(body)?);
+		}
+
+		let body = files
+			.into_iter()
+			.fold(MultiPart::mixed().multipart(body), |body, file| {
+				let content_type = ContentType::parse(&file.mime_type).unwrap_or_else(|_| {
+					ContentType::parse("application/octet-stream")
+						.expect("should be a valid MIME type")
+				});
+
+				body.singlepart(
+					Attachment::new(file.name.clone()).body(file.data.to_vec(), content_type),
+				)
+			});
+
+		Ok(builder.multipart(body)?)
+	}
+
+	async fn send_mail(&self, mail: MailMessage) -> Result<(), SmtpError> {
+		let mut auth = self.auth.lock().await;
+
+		match &mut *auth {
+			Auth::GmailOAuth2(google) => {
+				tracing::trace!("Logging in to SMTP with Google OAuth2");
+
+				let credentials =
+					Credentials::new(self.login.clone(), google.access_token().await?.to_owned());
+
+				match self
+					.transport(credentials, vec![Mechanism::Xoauth2])?
+					.send(mail.clone())
+					.await
+				{
+					Ok(_) => (),
+					// refresh access token and retry
+					Err(e) if e.is_permanent() => {
+						tracing::error!("Denied access to SMTP via OAuth2: {e}");
+						tracing::info!("Refreshing OAuth2 access token and trying again");
+
+						let credentials = Credentials::new(
+							self.login.clone(),
+							google.get_new_access_token().await?.token.clone(),
+						);
+
+						self.transport(credentials, vec![Mechanism::Xoauth2])?
+							.send(mail)
+							.await?;
+					}
+					Err(e) => return Err(e.into()),
+				}
+			}
+			Auth::Password(password) => {
+				let credentials = Credentials::new(self.login.clone(), password.clone());
+
+				self.transport(credentials, vec![Mechanism::Plain, Mechanism::Login])?
+					.send(mail)
+					.await?;
+			}
+		}
+		drop(auth);
+
+		Ok(())
+	}
+
+	fn transport(
+		&self,
+		credentials: Credentials,
+		mechanisms: Vec<Mechanism>,
+	) -> Result<AsyncSmtpTransport<Tokio1Executor>, SmtpError> {
+		let builder = match self.security {
+			Security::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.smtp)?,
+			Security::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.smtp)?,
+			Security::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.smtp),
+		};
+
+		let builder = match self.port {
+			Some(port) => builder.port(port),
+			None => builder,
+		};
+
+		Ok(builder
+			.credentials(credentials)
+			.authentication(mechanisms)
+			.build())
+	}
+
+	/// Generate a new unique Message-ID for an email sent from our address
+	fn new_message_id(&self) -> String {
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_nanos();
+
+		format!(
+			"<{timestamp}.{:x}@{}>",
+			rand::random::<u64>(),
+			self.from.email.domain()
+		)
+	}
+}
+
+/// Use the title as the subject, or the first line of the body if there's no title.
+/// The tag, if present, is prepended in brackets
+fn subject(message: &Message, tag: Option<&str>) -> String {
+	let subject = match (&message.title, &message.body) {
+		(Some(title), _) => title.clone(),
+		(None, Some(body)) => body
+			.lines()
+			.next()
+			.unwrap_or_default()
+			.chars()
+			.take(MAX_SUBJECT_LEN)
+			.collect(),
+		(None, None) => String::new(),
+	};
+
+	match tag {
+		Some(tag) => format!("[{tag}] {subject}"),
+		None => subject,
+	}
+}
+
+/// Returns (text, HTML) bodies of the email
+fn render_body(message: &Message) -> (String, String) {
+	let mut plain = Vec::new();
+	let mut html = Vec::new();
+
+	if let Some(body) = &message.body {
+		plain.push(body.clone());
+		html.push(format!(
+			"<p>{}</p>",
+			html_escape::encode_text(body).replace('\n', "<br>")
+		));
+	}
+
+	if let Some(link) = &message.link {
+		plain.push(link.to_string());
+		html.push(format!(
+			"<p><a href=\"{0}\">{0}</a></p>",
+			html_escape::encode_double_quoted_attribute(link.as_str())
+		));
+	}
+
+	if let Some(media) = &message.media {
+		let mut plain_media = String::new();
+		let mut html_media = String::new();
+
+		for media in media {
+			// files are attached to the email instead
+			let Some(url) = media.url() else {
+				continue;
+			};
+			let url_attr = html_escape::encode_double_quoted_attribute(url.as_str());
+
+			_ = writeln!(plain_media, "{url}");
+			match media {
+				Media::Photo(_) | Media::Animation(_) => {
+					_ = write!(
+						html_media,
+						"<p><img src=\"{url_attr}\" style=\"max-width: 100%\"></p>"
+					);
+				}
+				Media::Video(_) | Media::Audio(_) | Media::Document(_) | Media::File(_) => {
+					_ = write!(html_media, "<p><a href=\"{url_attr}\">{url_attr}</a></p>");
+				}
+			}
+		}
+
+		if !plain_media.is_empty() {
+			plain.push(plain_media.trim_end().to_owned());
+			html.push(html_media);
+		}
+	}
+
+	(plain.join("\n\n"), html.concat())
+}
+
+impl Debug for Email {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Email")
+			.field("smtp", &self.smtp)
+			.field("port", &self.port)
+			.field("security", &self.security)
+			.field("login", &self.login)
+			.field("to", &self.to)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	use std::sync::Arc;
+	use tokio::{
+		io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+		net::TcpListener,
+		sync::Mutex as AsyncMutex,
+	};
+
+	/// A bare-bones SMTP server that accepts a single email and stores its contents
+	async fn smtp_stub() -> (u16, Arc<AsyncMutex<String>>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let received = Arc::new(AsyncMutex::new(String::new()));
+
+		let received_clone = Arc::clone(&received);
+		tokio::spawn(async move {
+			let (stream, _) = listener.accept().await.unwrap();
+			let (read, mut write) = stream.into_split();
+			let mut lines = BufReader::new(read).lines();
+
+			write.write_all(b"220 localhost\r\n").await.unwrap();
+
+			let mut in_data = false;
+			while let Some(line) = lines.next_line().await.unwrap() {
+				if in_data {
+					if line == "." {
+						in_data = false;
+						write.write_all(b"250 OK\r\n").await.unwrap();
+					} else {
+						let mut received = received_clone.lock().await;
+						received.push_str(&line);
+						received.push('\n');
+					}
+
+					continue;
+				}
+
+				let reply: &[u8] = match line.split(' ').next().unwrap() {
+					"EHLO" => b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n",
+					"AUTH" => b"235 OK\r\n",
+					"DATA" => {
+						in_data = true;
+						b"354 Go ahead\r\n"
+					}
+					"QUIT" => {
+						write.write_all(b"221 Bye\r\n").await.unwrap();
+						break;
+					}
+					_ => b"250 OK\r\n",
+				};
+
+				write.write_all(reply).await.unwrap();
+			}
+		});
+
+		(port, received)
+	}
+
+	fn sink(port: u16) -> Email {
+		Email::new_generic(
+			"127.0.0.1".to_owned(),
+			Some(port),
+			Security::None,
+			"fetcher@example.com".to_owned(),
+			"password".to_owned(),
+			vec!["user@example.com".to_owned()],
+		)
+		.unwrap()
+	}
+
+	#[tokio::test]
+	async fn send_email() {
+		let (port, received) = smtp_stub().await;
+
+		let msg = Message {
+			title: Some("Title".to_owned()),
+			body: Some("Body".to_owned()),
+			link: Some("https://example.com/".parse().unwrap()),
+			media: Some(vec![Media::Photo(
+				"https://example.com/image.png".parse().unwrap(),
+			)]),
+		};
+
+		let msgid = sink(port).send(&msg, None, Some("tag")).await.unwrap();
+		let Some(MessageId::Str(msgid)) = msgid else {
+			panic!("email sink should return a Message-ID");
+		};
+
+		let received = received.lock().await.clone();
+		assert!(received.contains("From: fetcher@example.com"));
+		assert!(received.contains("To: user@example.com"));
+		assert!(received.contains("Subject: [tag] Title"));
+		assert!(received.contains(&format!("Message-ID: {msgid}")));
+		assert!(received.contains("Content-Type: text/plain"));
+		assert!(received.contains("Content-Type: text/html"));
+		assert!(received.contains("https://example.com/image.png"));
+		assert!(!received.contains("In-Reply-To"));
+	}
+
+	#[tokio::test]
+	async fn reply_to_email() {
+		let (port, received) = smtp_stub().await;
+
+		let msg = Message {
+			body: Some("Reply".to_owned()),
+			..Default::default()
+		};
+
+		sink(port)
+			.send(
+				&msg,
+				Some(&MessageId::Str("<original@example.com>".to_owned())),
+				None,
+			)
+			.await
+			.unwrap();
+
+		let received = received.lock().await.clone();
+		assert!(received.contains("Subject: Reply"));
+		assert!(received.contains("In-Reply-To: <original@example.com>"));
+		assert!(received.contains("References: <original@example.com>"));
+	}
+
+	#[test]
+	fn invalid_address() {
+		let res = Email::new_generic(
+			"127.0.0.1".to_owned(),
+			None,
+			Security::None,
+			"not an email".to_owned(),
+			"password".to_owned(),
+			Vec::new(),
+		);
+
+		assert!(matches!(res, Err(SmtpError::InvalidAddress(..))));
+	}
+
+	#[test]
+	fn render() {
+		let msg = Message {
+			body: Some("a < b\nc".to_owned()),
+			media: Some(vec![
+				Media::Photo("https://example.com/1.png".parse().unwrap()),
+				Media::Video("https://example.com/2.mp4".parse().unwrap()),
+			]),
+			..Default::default()
+		};
+
+		let (plain, html) = render_body(&msg);
+		assert_eq!(
+			plain,
+			"a < b\nc\n\nhttps://example.com/1.png\nhttps://example.com/2.mp4"
+		);
+		assert_eq!(
+			html,
+			"<p>a &lt; b<br>c</p><p><img src=\"https://example.com/1.png\" style=\"max-width: 100%\"></p><p><a href=\"https://example.com/2.mp4\">https://example.com/2.mp4</a></p>"
+		);
+	}
+}
@@ -8,13 +8,18 @@
 
 pub use crate::exec::ExecError;
 
-use std::{error::Error as StdError, fmt::Debug, num::TryFromIntError};
+use super::{
+	email::SmtpError, feed::FeedError, file::FileError, matrix::MatrixError, message::MessageId,
+	webhook::WebhookError,
+};
+
+use std::{error::Error as StdError, fmt::Debug};
 
 #[expect(missing_docs, reason = "error message is self-documenting")]
 #[derive(thiserror::Error, Debug)]
 pub enum SinkError {
-	#[error("Invalid message ID type. It has probably been copied from an incompatible sink type")]
-	InvalidMessageIdType(#[from] TryFromIntError),
+	#[error(transparent)]
+	InvalidMessageIdType(#[from] InvalidMessageIdTypeError),
 
 	#[error("Can't send via Telegram. Message contents: {msg:?}")]
 	Telegram {
@@ -28,6 +33,30 @@ pub enum SinkError {
 		msg: Box<dyn Debug + Send + Sync>,
 	},
 
+	#[error("Can't send via Matrix. Message contents: {msg:?}")]
+	Matrix {
+		source: MatrixError,
+		msg: Box<dyn Debug + Send + Sync>,
+	},
+
+	#[error("Can't send via email. Message contents: {msg:?}")]
+	Smtp {
+		source: SmtpError,
+		msg: Box<dyn Debug + Send + Sync>,
+	},
+
+	#[error("Can't send to a webhook. Message contents: {msg:?}")]
+	Webhook {
+		source: WebhookError,
+		msg: Box<dyn Debug + Send + Sync>,
+	},
+
+	#[error("Can't write to a feed")]
+	Feed(#[from] FeedError),
+
+	#[error("Can't write to a file")]
+	File(#[from] FileError),
+
 	#[error("Can't pass message to a process")]
 	Exec(#[from] ExecError),
 
@@ -35,6 +64,11 @@ pub enum SinkError {
 	Stdout(#[source] std::io::Error),
 }
 
+/// The message ID is of a type that the sink doesn't support
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid message ID {0:?}. It has probably been copied from an incompatible sink type")]
+pub struct InvalidMessageIdTypeError(pub MessageId);
+
 impl SinkError {
 	pub(crate) fn is_connection_err(&self) -> Option<&(dyn StdError + Send + Sync)> {
 		match self {
@@ -42,6 +76,22 @@ impl SinkError {
 				source: teloxide::RequestError::Network(_),
 				..
 			} => Some(self),
+			SinkError::Matrix {
+				source: MatrixError::Http(e),
+				..
+			} if e.is_connect() || e.is_timeout() => Some(self),
+			SinkError::Smtp {
+				source: SmtpError::Send(e),
+				..
+			} if e.is_timeout() || e.is_transient() => Some(self),
+			SinkError::Smtp {
+				source: SmtpError::GoogleOAuth2(e),
+				..
+			} => e.is_connection_err(),
+			SinkError::Webhook {
+				source: WebhookError::Http(e),
+				..
+			} if e.is_connect() || e.is_timeout() => Some(self),
 			_ => None,
 		}
 	}
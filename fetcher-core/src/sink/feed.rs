@@ -0,0 +1,559 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`Feed`] sink that writes messages to an Atom or RSS feed file.
+//! It's the reverse of the [`Feed`](`crate::action::transform::Feed`) parser
+
+mod server;
+
+use super::{
+	Sink,
+	error::SinkError,
+	message::{Fnv1a, Media, Message, MessageId},
+};
+use crate::entry::EntryId;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{
+	collections::VecDeque,
+	io,
+	net::SocketAddr,
+	path::{Path, PathBuf},
+};
+use tokio::sync::Mutex;
+use url::Url;
+
+/// The max amount of items kept in the feed by default
+pub const DEFAULT_MAX_ITEMS: usize = 50;
+
+/// Feed sink. Keeps the last [`max_items`](`Feed::new`) messages and writes them to an Atom or RSS 2.0 file.
+///
+/// Items already present in the file are read on the first send and kept in the feed.
+/// The id of the entry is used as the id/guid of the item, with the link or a hash of the contents used in its stead if it's not set.
+/// An item with the same id as a new one is replaced with it
+#[derive(Debug)]
+pub struct Feed {
+	path: PathBuf,
+	format: Format,
+	title: String,
+	link: Option<Url>,
+	max_items: usize,
+
+	/// Newest first. [`None`] until the existing items are read from the file on the first send
+	items: Mutex<Option<VecDeque<Item>>>,
+
+	/// The path the file is served on, if it is
+	route: Option<server::Route>,
+}
+
+/// The format of the feed file
+#[derive(Clone, Copy, Debug)]
+pub enum Format {
+	/// Atom 1.0
+	Atom,
+
+	/// RSS 2.0
+	Rss,
+}
+
+#[expect(missing_docs, reason = "error message is self-documenting")]
+#[derive(thiserror::Error, Debug)]
+pub enum FeedError {
+	#[error("Can't read the feed file {}", .1.display())]
+	Read(#[source] io::Error, PathBuf),
+
+	#[error("Can't parse the existing feed file {}", .1.display())]
+	Parse(#[source] feed_rs::parser::ParseFeedError, PathBuf),
+
+	#[error("Can't write the feed file {}", .1.display())]
+	Write(#[source] io::Error, PathBuf),
+
+	#[error("Can't generate the Atom feed")]
+	Atom(#[from] atom_syndication::Error),
+
+	#[error("Can't generate the RSS feed")]
+	Rss(#[from] rss::Error),
+
+	#[error("Can't serve the feed on {1}")]
+	Serve(#[source] io::Error, SocketAddr),
+}
+
+#[derive(Clone, Debug)]
+struct Item {
+	id: String,
+	title: Option<String>,
+
+	/// The body of the message as HTML
+	content: Option<String>,
+	link: Option<String>,
+	media: Vec<Media>,
+	published: DateTime<Utc>,
+}
+
+impl Feed {
+	/// Creates a new [`Feed`] sink that writes the last `max_items` messages to the file at `path`
+	/// with the feed itself titled `title` and linking to `link`
+	#[must_use]
+	pub fn new(
+		path: PathBuf,
+		format: Format,
+		title: String,
+		link: Option<Url>,
+		max_items: usize,
+	) -> Self {
+		Self {
+			path,
+			format,
+			title,
+			link,
+			max_items,
+			items: Mutex::new(None),
+			route: None,
+		}
+	}
+
+	/// Serve the feed file on `http://<addr><path>` until the sink is dropped
+	///
+	/// A new server is started on `addr` if there isn't one already, otherwise the `path` is added to the existing one.
+	/// The file served on a `path` that's already in use is replaced until this feed is dropped.
+	///
+	/// # Errors
+	/// if the server couldn't be started on `addr`
+	///
+	/// # Panics
+	/// if called outside of a tokio runtime
+	pub fn serve(&mut self, addr: SocketAddr, path: String) -> Result<(), FeedError> {
+		self.route = Some(server::serve(addr, path, self.path.clone(), self.format)?);
+		Ok(())
+	}
+
+	/// The path of the feed file
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// The format of the feed file
+	#[must_use]
+	pub fn format(&self) -> Format {
+		self.format
+	}
+}
+
+#[async_trait]
+impl Sink for Feed {
+	/// Adds the message to the feed without an entry id
+	///
+	/// # Errors
+	/// * if the existing feed file couldn't be read
+	/// * if the feed file couldn't be written to
+	async fn send(
+		&self,
+		message: &Message,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		self.send_entry(message, None, reply_to, tag).await
+	}
+
+	/// Adds the message to the feed and rewrites the feed file. The `reply_to` and `tag` parameters are ignored
+	///
+	/// # Errors
+	/// * if the existing feed file couldn't be read
+	/// * if the feed file couldn't be written to
+	#[tracing::instrument(level = "debug", skip(self, message))]
+	async fn send_entry(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		_reply_to: Option<&MessageId>,
+		_tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		let item = Item::new(message, entry_id);
+		tracing::info!("Adding item {:?} to feed {}", item.id, self.path.display());
+
+		// keep the lock until the file has been written to avoid racing with other sends
+		let mut items_guard = self.items.lock().await;
+		if items_guard.is_none() {
+			*items_guard = Some(self.read_items().await?);
+		}
+
+		let items = items_guard
+			.as_mut()
+			.expect("items should've been read just now");
+		items.retain(|old| old.id != item.id);
+		items.push_front(item);
+		items.truncate(self.max_items);
+
+		self.write(items).await?;
+		drop(items_guard);
+
+		Ok(None)
+	}
+}
+
+impl Feed {
+	/// Read the items present in the existing feed file, if it exists
+	async fn read_items(&self) -> Result<VecDeque<Item>, FeedError> {
+		let contents = match tokio::fs::read(&self.path).await {
+			Ok(contents) => contents,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {
+				tracing::debug!("Feed file {} doesn't exist yet", self.path.display());
+				return Ok(VecDeque::new());
+			}
+			Err(e) => return Err(FeedError::Read(e, self.path.clone())),
+		};
+
+		let feed = feed_rs::parser::parse(contents.as_slice())
+			.map_err(|e| FeedError::Parse(e, self.path.clone()))?;
+
+		Ok(feed
+			.entries
+			.into_iter()
+			.map(Item::from_parsed)
+			.take(self.max_items)
+			.collect())
+	}
+
+	async fn write(&self, items: &VecDeque<Item>) -> Result<(), FeedError> {
+		let xml = match self.format {
+			Format::Atom => self.to_atom(items).write_to(Vec::new())?,
+			Format::Rss => self.to_rss(items).write_to(Vec::new())?,
+		};
+
+		if let Some(parent) = self.path.parent()
+			&& !parent.as_os_str().is_empty()
+		{
+			tokio::fs::create_dir_all(parent)
+				.await
+				.map_err(|e| FeedError::Write(e, self.path.clone()))?;
+		}
+
+		// write to a temporary file first to never leave a half-written feed to be read
+		let mut tmp_path = self.path.clone().into_os_string();
+		tmp_path.push(".tmp");
+
+		tokio::fs::write(&tmp_path, xml)
+			.await
+			.map_err(|e| FeedError::Write(e, self.path.clone()))?;
+
+		tokio::fs::rename(&tmp_path, &self.path)
+			.await
+			.map_err(|e| FeedError::Write(e, self.path.clone()))?;
+
+		Ok(())
+	}
+
+	fn to_atom(&self, items: &VecDeque<Item>) -> atom_syndication::Feed {
+		use atom_syndication::{Content, Entry, Link, Text};
+
+		let link = |href: String, rel: &str, mime_type: Option<&str>| Link {
+			href,
+			rel: rel.to_owned(),
+			mime_type: mime_type.map(ToOwned::to_owned),
+			..Default::default()
+		};
+
+		let entries = items
+			.iter()
+			.map(|item| {
+				let mut links = item
+					.link
+					.iter()
+					.map(|href| link(href.clone(), "alternate", None))
+					.collect::<Vec<_>>();
+
+				links.extend(item.media.iter().map(|media| {
+					link(
+						media_url(media).to_string(),
+						"enclosure",
+						Some(media_mime_type(media)),
+					)
+				}));
+
+				Entry {
+					title: Text::plain(item.title.clone().unwrap_or_default()),
+					id: item.id.clone(),
+					updated: item.published.fixed_offset(),
+					published: Some(item.published.fixed_offset()),
+					links,
+					content: item.content.as_ref().map(|content| Content {
+						value: Some(content.clone()),
+						content_type: Some("html".to_owned()),
+						..Default::default()
+					}),
+					..Default::default()
+				}
+			})
+			.collect();
+
+		atom_syndication::Feed {
+			title: Text::plain(self.title.clone()),
+			id: self.id(),
+			updated: items
+				.iter()
+				.map(|item| item.published)
+				.max()
+				.unwrap_or_else(Utc::now)
+				.fixed_offset(),
+			links: self
+				.link
+				.iter()
+				.map(|href| link(href.to_string(), "alternate", None))
+				.collect(),
+			entries,
+			..Default::default()
+		}
+	}
+
+	fn to_rss(&self, items: &VecDeque<Item>) -> rss::Channel {
+		use rss::{Channel, Enclosure, Guid, Item as RssItem};
+
+		let items = items
+			.iter()
+			.map(|item| RssItem {
+				title: item.title.clone(),
+				link: item.link.clone(),
+				description: item.content.clone(),
+				// RSS supports only a single enclosure per item
+				enclosure: item.media.first().map(|media| Enclosure {
+					url: media_url(media).to_string(),
+					length: "0".to_owned(),
+					mime_type: media_mime_type(media).to_owned(),
+				}),
+				guid: Some(Guid {
+					value: item.id.clone(),
+					permalink: false,
+				}),
+				pub_date: Some(item.published.to_rfc2822()),
+				..Default::default()
+			})
+			.collect();
+
+		Channel {
+			title: self.title.clone(),
+			link: self.link.as_ref().map(Url::to_string).unwrap_or_default(),
+			description: self.title.clone(),
+			generator: Some("fetcher".to_owned()),
+			items,
+			..Default::default()
+		}
+	}
+
+	/// The id of the feed itself. Atom requires it to be an IRI
+	fn id(&self) -> String {
+		match &self.link {
+			Some(link) => link.to_string(),
+			None => format!(
+				"urn:fetcher:{}",
+				url::form_urlencoded::byte_serialize(self.title.as_bytes()).collect::<String>()
+			),
+		}
+	}
+}
+
+impl Item {
+	fn new(message: &Message, entry_id: Option<&EntryId>) -> Self {
+		let link = message.link.as_ref().map(Url::to_string);
+
+		let id = match (entry_id, &link) {
+			(Some(id), _) => id.0.clone(),
+			(None, Some(link)) => link.clone(),
+			(None, None) => {
+				// a stable hash to keep the id the same across Rust versions
+				let mut hasher = Fnv1a::new();
+				hasher.write_field(message.title.as_deref().map(str::as_bytes));
+				hasher.write_field(message.body.as_deref().map(str::as_bytes));
+
+				format!("{:x}", hasher.finish())
+			}
+		};
+
+		Self {
+			id,
+			title: message.title.clone(),
+			content: message
+				.body
+				.as_deref()
+				.map(|body| html_escape::encode_text(body).replace('\n', "<br>\n")),
+			link,
+			// files can't be linked to from a feed
+			media: message
+				.media
+				.iter()
+				.flatten()
+				.filter(|media| media.url().is_some())
+				.cloned()
+				.collect(),
+			published: Utc::now(),
+		}
+	}
+
+	fn from_parsed(entry: feed_rs::model::Entry) -> Self {
+		let link = entry
+			.links
+			.iter()
+			.find(|link| link.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+			.map(|link| link.href.clone());
+
+		// RSS enclosures are parsed as media objects, while Atom ones are left as links
+		let media_objects = entry
+			.media
+			.iter()
+			.flat_map(|obj| &obj.content)
+			.filter_map(|content| {
+				let url = content.url.clone()?;
+				let mime = content.content_type.as_ref().map(ToString::to_string);
+
+				Some((url, mime))
+			});
+
+		let enclosure_links = entry
+			.links
+			.iter()
+			.filter(|link| link.rel.as_deref() == Some("enclosure"))
+			.filter_map(|link| {
+				let url = Url::parse(&link.href).ok()?;
+
+				Some((url, link.media_type.clone()))
+			});
+
+		let media = media_objects
+			.chain(enclosure_links)
+			.map(|(url, mime)| match mime.as_deref() {
+				Some("image/gif") => Media::Animation(url),
+				Some(mime) if mime.starts_with("video/") => Media::Video(url),
+				Some(mime) if mime.starts_with("audio/") => Media::Audio(url),
+				Some(mime) if !mime.starts_with("image/") => Media::Document(url),
+				_ => Media::Photo(url),
+			})
+			.collect();
+
+		Self {
+			id: entry.id,
+			title: entry.title.map(|title| title.content),
+			content: entry
+				.content
+				.and_then(|content| content.body)
+				.or_else(|| entry.summary.map(|summary| summary.content)),
+			link,
+			media,
+			published: entry.published.or(entry.updated).unwrap_or_else(Utc::now),
+		}
+	}
+}
+
+fn media_url(media: &Media) -> &Url {
+	media
+		.url()
+		.expect("only media with a URL should be kept in feed items")
+}
+
+/// Guess the MIME type of the media from the extension of its URL
+fn media_mime_type(media: &Media) -> &'static str {
+	let extension = media_url(media)
+		.path()
+		.rsplit_once('.')
+		.map(|(_, ext)| ext.to_ascii_lowercase());
+
+	match (media, extension.as_deref()) {
+		(_, Some("png")) => "image/png",
+		(_, Some("gif")) => "image/gif",
+		(_, Some("webp")) => "image/webp",
+		(_, Some("webm")) => "video/webm",
+		(_, Some("mp4")) | (Media::Video(_), _) => "video/mp4",
+		(_, Some("pdf")) => "application/pdf",
+		(_, Some("ogg")) => "audio/ogg",
+		(Media::Photo(_), _) => "image/jpeg",
+		(Media::Animation(_), _) => "image/gif",
+		(Media::Audio(_), _) => "audio/mpeg",
+		(Media::Document(_) | Media::File(_), _) => "application/octet-stream",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	fn message(n: u32) -> Message {
+		Message {
+			title: Some(format!("Title {n}")),
+			body: Some(format!("Body {n}\n<b>not bold</b>")),
+			link: Some(format!("https://example.com/{n}").parse().unwrap()),
+			media: Some(vec![
+				Media::Photo(format!("https://example.com/{n}.png").parse().unwrap()),
+				Media::Video(format!("https://example.com/{n}.mp4").parse().unwrap()),
+			]),
+		}
+	}
+
+	async fn send_and_reread(format: Format, file_name: &str) {
+		let path = std::env::temp_dir()
+			.join(format!("fetcher-test-{}", std::process::id()))
+			.join(file_name);
+		_ = std::fs::remove_file(&path);
+
+		let feed = Feed::new(path.clone(), format, "Test".to_owned(), None, 2);
+
+		for n in 1..=3 {
+			feed.send_entry(&message(n), Some(&EntryId(format!("id{n}"))), None, None)
+				.await
+				.unwrap();
+		}
+
+		// the same id replaces the older item
+		feed.send_entry(&message(3), Some(&EntryId("id3".to_owned())), None, None)
+			.await
+			.unwrap();
+
+		// a new sink starts with the items already present in the file
+		let feed = Feed::new(path.clone(), format, "Test".to_owned(), None, 2);
+		let items = feed.read_items().await.unwrap();
+		_ = std::fs::remove_file(&path);
+
+		let ids = items
+			.iter()
+			.map(|item| item.id.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(ids, ["id3", "id2"]);
+
+		let newest = &items[0];
+		assert_eq!(newest.title.as_deref(), Some("Title 3"));
+		assert_eq!(
+			newest.content.as_deref(),
+			Some("Body 3<br>\n&lt;b&gt;not bold&lt;/b&gt;")
+		);
+		assert_eq!(newest.link.as_deref(), Some("https://example.com/3"));
+		assert!(
+			matches!(newest.media.first(), Some(Media::Photo(url)) if url.as_str() == "https://example.com/3.png")
+		);
+	}
+
+	#[tokio::test]
+	async fn atom() {
+		send_and_reread(Format::Atom, "feed.atom").await;
+	}
+
+	#[tokio::test]
+	async fn rss() {
+		send_and_reread(Format::Rss, "feed.rss").await;
+	}
+
+	#[test]
+	fn stable_item_id_without_entry_id_and_link() {
+		let message = Message {
+			title: Some("Title".to_owned()),
+			body: Some("Body".to_owned()),
+			..Default::default()
+		};
+
+		// must never change, otherwise feed readers show all items without an id as new ones
+		assert_eq!(Item::new(&message, None).id, "c9f1b166af86b9b4");
+	}
+}
@@ -0,0 +1,355 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains a tiny HTTP server that serves the files written by [`Feed`] sinks
+
+use super::{FeedError, Format};
+
+use once_cell::sync::Lazy;
+use std::{
+	collections::HashMap,
+	net::SocketAddr,
+	path::PathBuf,
+	sync::{
+		Arc, Mutex, PoisonError, RwLock,
+		atomic::{AtomicU64, Ordering},
+	},
+	time::Duration,
+};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+	net::{TcpListener, TcpStream},
+	task::JoinHandle,
+};
+
+/// Max length of the request line. Longer requests are rejected
+const MAX_REQUEST_LINE_LEN: u64 = 8192;
+
+/// How long a client has to send its request and read the response before the connection is closed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// URL paths mapped to the feed files served on them, the one that is actually served last.
+///
+/// The previous files are kept to be served again if the newer route is dropped,
+/// e.g. when a reloaded config has created a copy of a feed that's still running
+type Routes = Arc<RwLock<HashMap<String, Vec<Target>>>>;
+
+/// All running servers. Feeds served on the same address share the same server
+static SERVERS: Lazy<Mutex<HashMap<SocketAddr, Server>>> = Lazy::new(Mutex::default);
+
+/// Id of the next [`Route`], to tell apart a route that has replaced another one on the same path
+static NEXT_ROUTE_ID: AtomicU64 = AtomicU64::new(0);
+
+struct Server {
+	routes: Routes,
+	task: JoinHandle<()>,
+}
+
+/// The feed file served on a path
+#[derive(Clone)]
+struct Target {
+	route_id: u64,
+	file: PathBuf,
+	format: Format,
+}
+
+/// A feed file served on a path of a server. The path stops being served when this is dropped,
+/// and the server is stopped if it was the last one
+#[derive(Debug)]
+pub(super) struct Route {
+	id: u64,
+	addr: SocketAddr,
+	path: String,
+}
+
+/// Serve the `file` on `http://<addr><path>`
+///
+/// A new server is started on `addr` if there isn't one already, otherwise the `path` is added to the existing one.
+/// The file served on a `path` that's already in use is replaced until the returned [`Route`] is dropped.
+///
+/// # Errors
+/// if the server couldn't be started on `addr`
+///
+/// # Panics
+/// if called outside of a tokio runtime
+pub(super) fn serve(
+	addr: SocketAddr,
+	path: String,
+	file: PathBuf,
+	format: Format,
+) -> Result<Route, FeedError> {
+	let mut servers = SERVERS.lock().expect("servers mutex shouldn't be poisoned");
+
+	let routes = match servers.get(&addr) {
+		Some(server) => Arc::clone(&server.routes),
+		None => {
+			let listener = std::net::TcpListener::bind(addr)
+				.and_then(|listener| {
+					listener.set_nonblocking(true)?;
+					TcpListener::from_std(listener)
+				})
+				.map_err(|e| FeedError::Serve(e, addr))?;
+
+			tracing::info!("Serving feeds on http://{addr}");
+
+			let routes = Routes::default();
+			let task = tokio::spawn(run(listener, Arc::clone(&routes)));
+			servers.insert(
+				addr,
+				Server {
+					routes: Arc::clone(&routes),
+					task,
+				},
+			);
+
+			routes
+		}
+	};
+
+	tracing::debug!("Serving {} on {path}", file.display());
+	let route_id = NEXT_ROUTE_ID.fetch_add(1, Ordering::Relaxed);
+	routes
+		.write()
+		.expect("routes lock shouldn't be poisoned")
+		.entry(path.clone())
+		.or_default()
+		.push(Target {
+			route_id,
+			file,
+			format,
+		});
+
+	// only now, otherwise the server could've been stopped by its last route being dropped in between
+	drop(servers);
+
+	Ok(Route {
+		id: route_id,
+		addr,
+		path,
+	})
+}
+
+impl Drop for Route {
+	fn drop(&mut self) {
+		let mut servers = SERVERS.lock().unwrap_or_else(PoisonError::into_inner);
+		let Some(server) = servers.get(&self.addr) else {
+			return;
+		};
+
+		let mut routes = server
+			.routes
+			.write()
+			.unwrap_or_else(PoisonError::into_inner);
+
+		// the path might still be used by another feed, e.g. the old one of a reloaded config
+		if let Some(targets) = routes.get_mut(&self.path) {
+			targets.retain(|target| target.route_id != self.id);
+
+			if targets.is_empty() {
+				tracing::debug!("No longer serving {}", self.path);
+				routes.remove(&self.path);
+			}
+		}
+
+		if routes.is_empty() {
+			drop(routes);
+
+			tracing::info!("No more feeds to serve on http://{}, stopping", self.addr);
+			if let Some(server) = servers.remove(&self.addr) {
+				server.task.abort();
+			}
+		}
+	}
+}
+
+async fn run(listener: TcpListener, routes: Routes) {
+	loop {
+		match listener.accept().await {
+			Ok((stream, _)) => {
+				tokio::spawn(respond(stream, Arc::clone(&routes)));
+			}
+			Err(e) => tracing::warn!("Error accepting a feed server connection: {e}"),
+		}
+	}
+}
+
+async fn respond(stream: TcpStream, routes: Routes) {
+	match tokio::time::timeout(REQUEST_TIMEOUT, respond_impl(stream, &routes)).await {
+		Ok(Ok(())) => (),
+		Ok(Err(e)) => tracing::debug!("Error responding to a feed request: {e}"),
+		Err(_) => tracing::debug!("Feed request has timed out"),
+	}
+}
+
+async fn respond_impl(stream: TcpStream, routes: &Routes) -> std::io::Result<()> {
+	let mut stream = BufReader::new(stream);
+
+	// e.g. "GET /feed.xml HTTP/1.1". The headers aren't needed
+	let mut request_line = String::new();
+	(&mut stream)
+		.take(MAX_REQUEST_LINE_LEN)
+		.read_line(&mut request_line)
+		.await?;
+
+	let mut parts = request_line.split_whitespace();
+	let method = parts.next().unwrap_or_default();
+	let path = parts.next().unwrap_or_default();
+
+	// ignore the query, if any
+	let path = path.split_once('?').map_or(path, |(path, _)| path);
+
+	let route = routes
+		.read()
+		.expect("routes lock shouldn't be poisoned")
+		.get(path)
+		.and_then(|targets| targets.last())
+		.cloned();
+
+	let (status, content_type, body) = match (method, route) {
+		// the request line didn't fit into the limit
+		_ if !request_line.ends_with('\n') => {
+			("400 Bad Request", "text/plain", b"Bad Request".to_vec())
+		}
+		("GET" | "HEAD", Some(target)) => match tokio::fs::read(&target.file).await {
+			Ok(contents) => ("200 OK", content_type(target.format), contents),
+			Err(e) => {
+				tracing::debug!("Feed file {} can't be read: {e}", target.file.display());
+				("404 Not Found", "text/plain", b"Not Found".to_vec())
+			}
+		},
+		("GET" | "HEAD", None) => ("404 Not Found", "text/plain", b"Not Found".to_vec()),
+		_ => (
+			"405 Method Not Allowed",
+			"text/plain",
+			b"Method Not Allowed".to_vec(),
+		),
+	};
+
+	let head = format!(
+		"HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+		body.len()
+	);
+
+	let stream = stream.get_mut();
+	stream.write_all(head.as_bytes()).await?;
+	if method != "HEAD" {
+		stream.write_all(&body).await?;
+	}
+
+	stream.shutdown().await
+}
+
+fn content_type(format: Format) -> &'static str {
+	match format {
+		Format::Atom => "application/atom+xml; charset=utf-8",
+		Format::Rss => "application/rss+xml; charset=utf-8",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	/// Sends a raw HTTP `request` and returns the whole response
+	async fn request(addr: SocketAddr, request: &str) -> String {
+		let mut stream = TcpStream::connect(addr).await.unwrap();
+		stream.write_all(request.as_bytes()).await.unwrap();
+
+		let mut response = String::new();
+		stream.read_to_string(&mut response).await.unwrap();
+		response
+	}
+
+	fn free_addr() -> SocketAddr {
+		std::net::TcpListener::bind("127.0.0.1:0")
+			.unwrap()
+			.local_addr()
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn respond_to_requests() {
+		let dir = std::env::temp_dir().join(format!("fetcher-test-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let file = dir.join("server-respond.xml");
+		std::fs::write(&file, "<feed/>").unwrap();
+
+		let addr = free_addr();
+		let _route = serve(addr, "/feed.xml".to_owned(), file, Format::Atom).unwrap();
+
+		let response = request(addr, "GET /feed.xml?query HTTP/1.1\r\n\r\n").await;
+		assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+		assert!(response.contains("Content-Type: application/atom+xml"));
+		assert!(response.ends_with("\r\n\r\n<feed/>"));
+
+		let response = request(addr, "HEAD /feed.xml HTTP/1.1\r\n\r\n").await;
+		assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+		assert!(response.ends_with("\r\n\r\n"));
+
+		let response = request(addr, "GET /other.xml HTTP/1.1\r\n\r\n").await;
+		assert!(
+			response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+			"{response}"
+		);
+
+		let response = request(addr, "POST /feed.xml HTTP/1.1\r\n\r\n").await;
+		assert!(
+			response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+			"{response}"
+		);
+
+		let long_path = "a".repeat(usize::try_from(MAX_REQUEST_LINE_LEN).unwrap());
+		let response = request(addr, &format!("GET /{long_path} HTTP/1.1\r\n\r\n")).await;
+		assert!(
+			response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+			"{response}"
+		);
+	}
+
+	#[tokio::test]
+	async fn remove_route_on_drop() {
+		let addr = free_addr();
+		let first = serve(addr, "/first".to_owned(), PathBuf::new(), Format::Atom).unwrap();
+		let second = serve(addr, "/second".to_owned(), PathBuf::new(), Format::Atom).unwrap();
+
+		// e.g. the feed of a reloaded config that has replaced the old one
+		let replaced = serve(addr, "/second".to_owned(), PathBuf::new(), Format::Rss).unwrap();
+		drop(second);
+
+		let routes = Arc::clone(&SERVERS.lock().unwrap()[&addr].routes);
+		assert!(routes.read().unwrap().contains_key("/second"));
+
+		drop(first);
+		assert!(!routes.read().unwrap().contains_key("/first"));
+
+		drop(replaced);
+		assert!(!SERVERS.lock().unwrap().contains_key(&addr));
+	}
+
+	#[tokio::test]
+	async fn serve_replaced_route_again_on_drop() {
+		let dir = std::env::temp_dir().join(format!("fetcher-test-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let old_file = dir.join("server-old.xml");
+		let new_file = dir.join("server-new.xml");
+		std::fs::write(&old_file, "<old/>").unwrap();
+		std::fs::write(&new_file, "<new/>").unwrap();
+
+		let addr = free_addr();
+		let _old = serve(addr, "/feed.xml".to_owned(), old_file, Format::Atom).unwrap();
+
+		// e.g. a copy of an unchanged feed created by a config reload
+		let new = serve(addr, "/feed.xml".to_owned(), new_file, Format::Atom).unwrap();
+		let response = request(addr, "GET /feed.xml HTTP/1.1\r\n\r\n").await;
+		assert!(response.ends_with("<new/>"), "{response}");
+
+		drop(new);
+		let response = request(addr, "GET /feed.xml HTTP/1.1\r\n\r\n").await;
+		assert!(response.ends_with("<old/>"), "{response}");
+	}
+}
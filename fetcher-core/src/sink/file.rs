@@ -0,0 +1,411 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`File`] sink that appends messages to a file
+
+use super::{
+	Sink,
+	error::SinkError,
+	message::{Media, Message, MessageId},
+};
+use crate::entry::EntryId;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use serde_json::json;
+use std::{
+	fmt::Write as _,
+	io,
+	path::{Path, PathBuf},
+};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+use url::Url;
+
+/// File sink. Appends every message to a file in the chosen [`Format`]
+///
+/// The path can contain these placeholders:
+/// * `{date}`: the current local date, e.g. `2024-01-31`
+/// * `{tag}`: the tag of the message, or `untagged` if it doesn't have one
+///
+/// The file can also be rotated, i.e. renamed to make place for a new one, once it gets too large or too old
+#[derive(Debug)]
+pub struct File {
+	path: String,
+	format: Format,
+	rotation: Option<Rotation>,
+
+	// don't let concurrent sends interleave their writes or rotate the file while it's being written to
+	lock: Mutex<()>,
+}
+
+/// How to format messages written to a [`File`]
+#[derive(Clone, Copy, Debug)]
+pub enum Format {
+	/// Human-readable plain text, separated with blank lines
+	Text,
+
+	/// A JSON object with the title, body, link, media, tag, entry id, and timestamp on each line
+	JsonLines,
+
+	/// A Markdown section per message
+	Markdown,
+}
+
+/// When to rotate a [`File`]. A rotated file gets renamed to `<path>.<date>`, or `<path>.<date>.<n>` if that name is already taken
+#[derive(Clone, Copy, Debug)]
+pub enum Rotation {
+	/// Rotate the file once it gets larger than this amount of bytes. It's renamed to `<path>.<date>T<time>`
+	Size(u64),
+
+	/// Rotate the file once a day. It's renamed to `<path>.<date>` of the day it was last written to
+	Daily,
+}
+
+#[expect(missing_docs, reason = "error message is self-documenting")]
+#[derive(thiserror::Error, Debug)]
+pub enum FileError {
+	#[error("Can't write to file {}", .1.display())]
+	Write(#[source] io::Error, PathBuf),
+
+	#[error("Can't rotate file {}", .1.display())]
+	Rotate(#[source] io::Error, PathBuf),
+}
+
+impl File {
+	/// Creates a new [`File`] sink that appends messages to the file at the `path` template in `format`
+	/// and optionally rotates it according to `rotation`. See [`File`] for the supported placeholders
+	#[must_use]
+	pub fn new(path: String, format: Format, rotation: Option<Rotation>) -> Self {
+		Self {
+			path,
+			format,
+			rotation,
+			lock: Mutex::new(()),
+		}
+	}
+}
+
+#[async_trait]
+impl Sink for File {
+	/// Appends the message to the file without an entry id
+	///
+	/// # Errors
+	/// if the file couldn't be rotated or written to
+	async fn send(
+		&self,
+		message: &Message,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		self.send_entry(message, None, reply_to, tag).await
+	}
+
+	/// Appends the message to the file. The `reply_to` parameter is ignored
+	///
+	/// # Errors
+	/// if the file couldn't be rotated or written to
+	#[tracing::instrument(level = "debug", skip(self, message))]
+	async fn send_entry(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		_reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		let now = Local::now();
+		let path = self.path(now, tag);
+		let formatted = self.format(message, entry_id, tag, now);
+
+		let lock = self.lock.lock().await;
+
+		self.rotate_if_needed(&path, now).await?;
+		append(&path, formatted.as_bytes()).await?;
+
+		drop(lock);
+
+		Ok(None)
+	}
+}
+
+impl File {
+	#[expect(
+		clippy::literal_string_with_formatting_args,
+		reason = "these are the placeholders of the path template"
+	)]
+	fn path(&self, now: DateTime<Local>, tag: Option<&str>) -> PathBuf {
+		// a tag shouldn't be able to escape into a different directory
+		let tag = tag.map_or_else(
+			|| "untagged".to_owned(),
+			|tag| tag.replace(['/', '\\'], "_"),
+		);
+
+		self.path
+			.replace("{date}", &now.format("%Y-%m-%d").to_string())
+			.replace("{tag}", &tag)
+			.into()
+	}
+
+	fn format(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		tag: Option<&str>,
+		now: DateTime<Local>,
+	) -> String {
+		let media = message
+			.media
+			.iter()
+			.flatten()
+			.filter_map(Media::url)
+			.map(Url::as_str)
+			.collect::<Vec<_>>();
+
+		match self.format {
+			Format::Text => {
+				let mut s = format!("[{}]", now.to_rfc3339());
+				if let Some(tag) = tag {
+					_ = write!(s, " #{tag}");
+				}
+				s.push('\n');
+
+				for line in [message.title.as_deref(), message.body.as_deref()]
+					.into_iter()
+					.chain([message.link.as_ref().map(Url::as_str)])
+					.flatten()
+					.chain(media)
+				{
+					s.push_str(line);
+					s.push('\n');
+				}
+
+				s.push('\n');
+				s
+			}
+			Format::JsonLines => {
+				let line = json!({
+					"timestamp": now.to_rfc3339(),
+					"title": message.title,
+					"body": message.body,
+					"link": message.link.as_ref().map(Url::as_str),
+					"media": media,
+					"tag": tag,
+					"entry_id": entry_id.map(|id| id.0.as_str()),
+				});
+
+				format!("{line}\n")
+			}
+			Format::Markdown => {
+				let title = message.title.as_deref().unwrap_or("Untitled");
+				let mut s = match &message.link {
+					Some(link) => format!("## [{title}]({link})\n\n"),
+					None => format!("## {title}\n\n"),
+				};
+
+				_ = write!(s, "*{}*", now.format("%Y-%m-%d %H:%M"));
+				if let Some(tag) = tag {
+					_ = write!(s, " #{tag}");
+				}
+				s.push_str("\n\n");
+
+				if let Some(body) = &message.body {
+					s.push_str(body);
+					s.push_str("\n\n");
+				}
+
+				for media in message.media.iter().flatten() {
+					match media {
+						Media::Photo(url) | Media::Animation(url) => _ = writeln!(s, "![]({url})"),
+						Media::Video(url) => _ = writeln!(s, "[Video]({url})"),
+						Media::Audio(url) => _ = writeln!(s, "[Audio]({url})"),
+						Media::Document(url) => _ = writeln!(s, "[Document]({url})"),
+						Media::File(file) => _ = writeln!(s, "Attachment: {}", file.name),
+					}
+				}
+
+				s.push_str("\n---\n\n");
+				s
+			}
+		}
+	}
+
+	async fn rotate_if_needed(&self, path: &Path, now: DateTime<Local>) -> Result<(), FileError> {
+		let Some(rotation) = self.rotation else {
+			return Ok(());
+		};
+
+		let metadata = match tokio::fs::metadata(path).await {
+			Ok(metadata) => metadata,
+			// nothing to rotate yet
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+			Err(e) => return Err(FileError::Rotate(e, path.to_owned())),
+		};
+
+		let suffix = match rotation {
+			Rotation::Size(max_size) => {
+				if metadata.len() < max_size {
+					return Ok(());
+				}
+
+				now.format("%Y-%m-%dT%H-%M-%S").to_string()
+			}
+			Rotation::Daily => {
+				let modified: DateTime<Local> = metadata
+					.modified()
+					.map_err(|e| FileError::Rotate(e, path.to_owned()))?
+					.into();
+
+				if modified.date_naive() == now.date_naive() {
+					return Ok(());
+				}
+
+				modified.format("%Y-%m-%d").to_string()
+			}
+		};
+
+		let mut rotated_path = path.as_os_str().to_owned();
+		rotated_path.push(format!(".{suffix}"));
+
+		// the file might've already been rotated within the same second, or the same day if it has been moved back since.
+		// Renaming would overwrite the older file in that case, so add a counter to the name of the new one
+		let mut counter = 0;
+		while tokio::fs::try_exists(&rotated_path)
+			.await
+			.map_err(|e| FileError::Rotate(e, path.to_owned()))?
+		{
+			counter += 1;
+			rotated_path = path.as_os_str().to_owned();
+			rotated_path.push(format!(".{suffix}.{counter}"));
+		}
+
+		tracing::info!(
+			"Rotating {} to {}",
+			path.display(),
+			rotated_path.to_string_lossy()
+		);
+
+		tokio::fs::rename(path, rotated_path)
+			.await
+			.map_err(|e| FileError::Rotate(e, path.to_owned()))
+	}
+}
+
+async fn append(path: &Path, contents: &[u8]) -> Result<(), FileError> {
+	if let Some(parent) = path.parent()
+		&& !parent.as_os_str().is_empty()
+	{
+		tokio::fs::create_dir_all(parent)
+			.await
+			.map_err(|e| FileError::Write(e, path.to_owned()))?;
+	}
+
+	let mut file = OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(path)
+		.await
+		.map_err(|e| FileError::Write(e, path.to_owned()))?;
+
+	file.write_all(contents)
+		.await
+		.map_err(|e| FileError::Write(e, path.to_owned()))?;
+
+	file.flush()
+		.await
+		.map_err(|e| FileError::Write(e, path.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir()
+			.join(format!("fetcher-test-{}", std::process::id()))
+			.join(name);
+		_ = std::fs::remove_dir_all(&dir);
+
+		dir
+	}
+
+	fn message() -> Message {
+		Message {
+			title: Some("Title".to_owned()),
+			body: Some("Body".to_owned()),
+			link: Some("https://example.com/post".parse().unwrap()),
+			media: Some(vec![Media::Photo(
+				"https://example.com/1.png".parse().unwrap(),
+			)]),
+		}
+	}
+
+	#[tokio::test]
+	async fn json_lines_with_tag_in_path() {
+		let dir = test_dir("file-jsonl");
+		let file = File::new(
+			format!("{}/{{tag}}.jsonl", dir.display()),
+			Format::JsonLines,
+			None,
+		);
+
+		for _ in 0..2 {
+			file.send_entry(
+				&message(),
+				Some(&EntryId("id".to_owned())),
+				None,
+				Some("news"),
+			)
+			.await
+			.unwrap();
+		}
+
+		let contents = std::fs::read_to_string(dir.join("news.jsonl")).unwrap();
+		let lines = contents.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 2);
+
+		let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+		assert_eq!(line["title"], "Title");
+		assert_eq!(line["link"], "https://example.com/post");
+		assert_eq!(line["media"], json!(["https://example.com/1.png"]));
+		assert_eq!(line["tag"], "news");
+		assert_eq!(line["entry_id"], "id");
+		assert!(line["timestamp"].is_string());
+
+		_ = std::fs::remove_dir_all(&dir);
+	}
+
+	#[tokio::test]
+	async fn rotate_by_size() {
+		let dir = test_dir("file-rotate");
+		let path = dir.join("log.md");
+		let file = File::new(
+			path.display().to_string(),
+			Format::Markdown,
+			Some(Rotation::Size(1)),
+		);
+
+		// rotated several times within the same second
+		for _ in 0..3 {
+			file.send(&message(), None, None).await.unwrap();
+		}
+
+		let contents = std::fs::read_to_string(&path).unwrap();
+		assert!(contents.starts_with("## [Title](https://example.com/post)\n"));
+		assert_eq!(
+			contents.matches("## ").count(),
+			1,
+			"the first messages should've been rotated away"
+		);
+		assert_eq!(
+			std::fs::read_dir(&dir).unwrap().count(),
+			3,
+			"rotated files shouldn't overwrite each other"
+		);
+
+		_ = std::fs::remove_dir_all(&dir);
+	}
+}
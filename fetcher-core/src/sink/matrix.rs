@@ -0,0 +1,739 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`Matrix`] sink
+
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{Client, RequestBuilder, StatusCode, header::CONTENT_TYPE};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::{
+	borrow::Cow,
+	fmt::Debug,
+	sync::atomic::{AtomicU64, Ordering},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::sleep;
+use url::Url;
+
+use super::{
+	Sink,
+	error::SinkError,
+	message::{Media, Message, MessageId, length_limiter::MessageLengthLimiter},
+};
+use crate::utils::OptionExt;
+
+// Events are limited to 65536 bytes, and the text is included in them twice: as plain text and as HTML
+const MAX_MSG_LEN: usize = 16_000;
+
+// used if the homeserver asked us to slow down but didn't specify for how long
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+// give up on a request if the homeserver keeps rate limiting us after this many retries
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Max size of a media file to download and upload, in bytes. The default upload limit of Synapse
+const MAX_UPLOAD_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Matrix sink. Sends messages into a room as a user with the provided access token.
+/// Text is sent formatted as HTML, and media are uploaded to the homeserver and sent as separate image or video events
+pub struct Matrix {
+	client: Client,
+	homeserver: Url,
+	access_token: String,
+	room_id: String,
+
+	// transaction ids should be unique for every event sent with the same access token
+	txn_prefix: String,
+	txn_counter: AtomicU64,
+}
+
+#[expect(missing_docs, reason = "error message is self-documenting")]
+#[derive(thiserror::Error, Debug)]
+pub enum MatrixError {
+	#[error("Can't send a request to the homeserver")]
+	Http(#[from] reqwest::Error),
+
+	#[error("Homeserver returned an error ({status}): {errcode}: {error}")]
+	Homeserver {
+		status: StatusCode,
+		errcode: String,
+		error: String,
+	},
+
+	#[error("{0} is not a valid homeserver URL, it should be an http(s) URL")]
+	InvalidHomeserver(Url),
+
+	#[error("Media is larger than {0} bytes")]
+	MediaTooLarge(u64),
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+	errcode: String,
+	#[serde(default)]
+	error: String,
+	retry_after_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SendResponse {
+	event_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct UploadResponse {
+	content_uri: String,
+}
+
+impl Matrix {
+	/// Creates a new Matrix sink that logs into the `homeserver` using the `access_token` and sends messages to the room with `room_id`, e.g. `!abcdef:matrix.org`
+	///
+	/// # Errors
+	/// * if the `homeserver` isn't an http(s) URL
+	/// * if the HTTP client couldn't be set up
+	pub fn new(
+		homeserver: Url,
+		access_token: String,
+		room_id: String,
+	) -> Result<Self, MatrixError> {
+		if !matches!(homeserver.scheme(), "http" | "https") || homeserver.cannot_be_a_base() {
+			return Err(MatrixError::InvalidHomeserver(homeserver));
+		}
+
+		let txn_prefix = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_millis()
+			.to_string();
+
+		Ok(Self {
+			client: Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+			homeserver,
+			access_token,
+			room_id,
+			txn_prefix,
+			txn_counter: AtomicU64::new(0),
+		})
+	}
+}
+
+#[async_trait]
+impl Sink for Matrix {
+	/// Sends a message to a Matrix room
+	///
+	/// # Errors
+	/// * if the homeserver returned an error
+	/// * if there's no internet connection
+	#[tracing::instrument(level = "debug", skip(message))]
+	async fn send(
+		&self,
+		message: &Message,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		let mut last_event = reply_to.try_map(|msgid| msgid.as_str().map(ToOwned::to_owned))?;
+
+		if let Some(media) = &message.media {
+			for media in media {
+				if let Some(event_id) = self.send_media(media, last_event.as_deref()).await? {
+					last_event = Some(event_id);
+				}
+			}
+		}
+
+		let (head, body, tail) = process_msg(message, tag);
+
+		let mut composed_msg = MessageLengthLimiter {
+			head: head.as_deref(),
+			body: body.as_deref(),
+			tail: tail.as_deref(),
+		};
+
+		while let Some(text) = composed_msg.split_at(MAX_MSG_LEN) {
+			let content = json!({
+				"msgtype": "m.text",
+				"body": html_to_plain_text(&text),
+				"format": "org.matrix.custom.html",
+				"formatted_body": text.replace('\n', "<br>"),
+			});
+
+			let event_id = self
+				.send_event(content, last_event.as_deref())
+				.await
+				.map_err(|e| SinkError::Matrix {
+					source: e,
+					msg: Box::new(text),
+				})?;
+
+			last_event = Some(event_id);
+		}
+
+		Ok(last_event.map(MessageId::Str))
+	}
+}
+
+impl Matrix {
+	/// Returns None if the media couldn't be downloaded from its source
+	#[tracing::instrument(level = "trace", skip(self))]
+	async fn send_media(
+		&self,
+		media: &Media,
+		reply_to: Option<&str>,
+	) -> Result<Option<String>, SinkError> {
+		let (msgtype, contents, mimetype, filename) = match media {
+			Media::Photo(url)
+			| Media::Video(url)
+			| Media::Animation(url)
+			| Media::Audio(url)
+			| Media::Document(url) => {
+				let msgtype = match media {
+					Media::Photo(_) | Media::Animation(_) => "m.image",
+					Media::Video(_) => "m.video",
+					Media::Audio(_) => "m.audio",
+					_ => "m.file",
+				};
+
+				let (contents, mimetype) = match self.download(url, MAX_UPLOAD_SIZE).await {
+					Ok(v) => v,
+					Err(e) => {
+						tracing::warn!(
+							"Can't download media from {:?}, skipping it: {e}",
+							url.as_str()
+						);
+						return Ok(None);
+					}
+				};
+
+				let filename = url
+					.path_segments()
+					.and_then(Iterator::last)
+					.filter(|s| !s.is_empty())
+					.unwrap_or("media")
+					.to_owned();
+
+				(msgtype, contents, mimetype, filename)
+			}
+			Media::File(file) => {
+				let msgtype = if file.is_image() {
+					"m.image"
+				} else if file.is_video() {
+					"m.video"
+				} else {
+					"m.file"
+				};
+
+				(
+					msgtype,
+					file.data.to_vec(),
+					file.mime_type.clone(),
+					file.name.clone(),
+				)
+			}
+		};
+		let size = contents.len();
+
+		let mut upload_url = self.endpoint(&["_matrix", "media", "v3", "upload"]);
+		upload_url
+			.query_pairs_mut()
+			.append_pair("filename", &filename);
+
+		tracing::info!("Uploading media");
+
+		let upload = self
+			.request::<UploadResponse>(|| {
+				self.client
+					.post(upload_url.clone())
+					.header(CONTENT_TYPE, &mimetype)
+					.body(contents.clone())
+			})
+			.await
+			.map_err(|e| SinkError::Matrix {
+				source: e,
+				msg: Box::new(media.clone()),
+			})?;
+
+		let content = json!({
+			"msgtype": msgtype,
+			"body": filename,
+			"url": upload.content_uri,
+			"info": {
+				"mimetype": mimetype,
+				"size": size,
+			},
+		});
+
+		let event_id = self
+			.send_event(content, reply_to)
+			.await
+			.map_err(|e| SinkError::Matrix {
+				source: e,
+				msg: Box::new(media.clone()),
+			})?;
+
+		Ok(Some(event_id))
+	}
+
+	/// Sends an `m.room.message` event and returns its event id
+	async fn send_event(
+		&self,
+		mut content: Value,
+		reply_to: Option<&str>,
+	) -> Result<String, MatrixError> {
+		if let Some(reply_to) = reply_to {
+			content["m.relates_to"] = json!({
+				"m.in_reply_to": {
+					"event_id": reply_to,
+				},
+			});
+		}
+
+		let txn_id = format!(
+			"fetcher-{}-{}",
+			self.txn_prefix,
+			self.txn_counter.fetch_add(1, Ordering::Relaxed)
+		);
+
+		let url = self.endpoint(&[
+			"_matrix",
+			"client",
+			"v3",
+			"rooms",
+			&self.room_id,
+			"send",
+			"m.room.message",
+			&txn_id,
+		]);
+
+		tracing::info!("Sending message event");
+		tracing::trace!("Event contents: {content:?}");
+
+		let sent = self
+			.request::<SendResponse>(|| self.client.put(url.clone()).json(&content))
+			.await?;
+
+		Ok(sent.event_id)
+	}
+
+	/// Sends an authorized request to the homeserver, retrying it up to [`MAX_RATE_LIMIT_RETRIES`] times if we got rate limited
+	async fn request<T>(
+		&self,
+		request: impl Fn() -> RequestBuilder + Send + Sync,
+	) -> Result<T, MatrixError>
+	where
+		T: DeserializeOwned,
+	{
+		let mut retries = 0;
+
+		loop {
+			let response = request().bearer_auth(&self.access_token).send().await?;
+			let status = response.status();
+
+			if status.is_success() {
+				return Ok(response.json().await?);
+			}
+
+			let ErrorResponse {
+				errcode,
+				error,
+				retry_after_ms,
+			} = response.json().await.unwrap_or_else(|_| ErrorResponse {
+				errcode: "M_UNKNOWN".to_owned(),
+				error: status.canonical_reason().unwrap_or_default().to_owned(),
+				retry_after_ms: None,
+			});
+
+			if errcode == "M_LIMIT_EXCEEDED" && retries < MAX_RATE_LIMIT_RETRIES {
+				retries += 1;
+				let retry_after = retry_after_ms.map_or(DEFAULT_RETRY_AFTER, Duration::from_millis);
+
+				tracing::warn!(
+					"Exceeded the homeserver rate limit. Retrying in {}s",
+					retry_after.as_secs()
+				);
+				sleep(retry_after).await;
+
+				continue;
+			}
+
+			return Err(MatrixError::Homeserver {
+				status,
+				errcode,
+				error,
+			});
+		}
+	}
+
+	/// Download the media and return its contents together with its mimetype.
+	/// The download is aborted as soon as it's known to be larger than `max_size` bytes
+	async fn download(&self, url: &Url, max_size: u64) -> Result<(Vec<u8>, String), MatrixError> {
+		let mut response = self
+			.client
+			.get(url.as_str())
+			.send()
+			.await?
+			.error_for_status()?;
+
+		if response.content_length().is_some_and(|len| len > max_size) {
+			return Err(MatrixError::MediaTooLarge(max_size));
+		}
+
+		let mimetype = response
+			.headers()
+			.get(CONTENT_TYPE)
+			.and_then(|v| v.to_str().ok())
+			.unwrap_or("application/octet-stream")
+			.to_owned();
+
+		let mut contents = Vec::new();
+		while let Some(chunk) = response.chunk().await? {
+			if (contents.len() + chunk.len()) as u64 > max_size {
+				return Err(MatrixError::MediaTooLarge(max_size));
+			}
+
+			contents.extend_from_slice(&chunk);
+		}
+
+		Ok((contents, mimetype))
+	}
+
+	fn endpoint(&self, segments: &[&str]) -> Url {
+		let mut url = self.homeserver.clone();
+		url.path_segments_mut()
+			.expect("homeserver URL should've been checked to be an http(s) URL in Matrix::new()")
+			.pop_if_empty()
+			.extend(segments);
+
+		url
+	}
+}
+
+// format and sanitize all message fields. Returns (head, body, tail)
+fn process_msg(
+	msg: &Message,
+	tag: Option<&str>,
+) -> (Option<String>, Option<String>, Option<String>) {
+	let Message {
+		title,
+		body,
+		link,
+		media: _,
+	} = msg;
+
+	let title = title.as_deref().map(html_escape::encode_text);
+	let body = body.as_deref().map(html_escape::encode_text);
+
+	// put the link into the title if it's present, or at the bottom of the message otherwise
+	let (mut head, tail) = match (title, link) {
+		(Some(title), Some(link)) => (
+			Some(format!(
+				"<b><a href=\"{}\">{title}</a></b>",
+				html_escape::encode_double_quoted_attribute(link.as_str())
+			)),
+			None,
+		),
+		(Some(title), None) => (Some(format!("<b>{title}</b>")), None),
+		(None, Some(link)) => (
+			None,
+			Some(format!(
+				"<a href=\"{}\">Link</a>",
+				html_escape::encode_double_quoted_attribute(link.as_str())
+			)),
+		),
+		(None, None) => (None, None),
+	};
+
+	// add tag as a hashtag on top of the message
+	if let Some(tag) = tag {
+		let tag = tag.replace(
+			|c| match c {
+				'_' => false,
+				c if c.is_alphabetic() || c.is_ascii_digit() => false,
+				_ => true,
+			},
+			"_",
+		);
+
+		head = Some({
+			let mut head = head
+				// add more padding between tag and title if both are present
+				.map(|mut s| {
+					s.insert(0, '\n');
+					s
+				})
+				.unwrap_or_default();
+
+			head.insert_str(0, &format!("#{tag}\n"));
+			head
+		});
+	}
+
+	(head, body.map(Cow::into_owned), tail)
+}
+
+/// Strip all HTML out of the formatted message for clients that don't support HTML
+fn html_to_plain_text(html: &str) -> String {
+	static TAG_RE: Lazy<Regex> =
+		Lazy::new(|| Regex::new("<[^>]*>").expect("tag regex should always be valid"));
+
+	let text = TAG_RE.replace_all(html, "");
+	html_escape::decode_html_entities(&text).into_owned()
+}
+
+impl Debug for Matrix {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Matrix")
+			.field("homeserver", &self.homeserver.as_str())
+			.field("room_id", &self.room_id)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	use wiremock::{
+		Mock, MockServer, ResponseTemplate,
+		matchers::{body_partial_json, header, method, path, path_regex, query_param},
+	};
+
+	const ROOM_ID: &str = "!room:localhost";
+
+	async fn homeserver() -> (MockServer, Matrix) {
+		let server = MockServer::start().await;
+		let sink = Matrix::new(
+			server.uri().parse().unwrap(),
+			"token".to_owned(),
+			ROOM_ID.to_owned(),
+		)
+		.unwrap();
+
+		(server, sink)
+	}
+
+	#[tokio::test]
+	async fn send_html_text() {
+		let (server, sink) = homeserver().await;
+
+		Mock::given(method("PUT"))
+			.and(path_regex(
+				r"^/_matrix/client/v3/rooms/!room:localhost/send/m\.room\.message/.+$",
+			))
+			.and(header("authorization", "Bearer token"))
+			.and(body_partial_json(json!({
+				"msgtype": "m.text",
+				"body": "#tag\n\nTitle & more\nline 1\nline 2",
+				"format": "org.matrix.custom.html",
+				"formatted_body": "#tag<br><br><b><a href=\"https://example.com/\">Title &amp; more</a></b><br>line 1<br>line 2",
+			})))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({ "event_id": "$sent" })))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let msg = Message {
+			title: Some("Title & more".to_owned()),
+			body: Some("line 1\nline 2".to_owned()),
+			link: Some("https://example.com".parse().unwrap()),
+			media: None,
+		};
+
+		let msgid = sink.send(&msg, None, Some("tag")).await.unwrap();
+		assert_eq!(msgid, Some(MessageId::Str("$sent".to_owned())));
+	}
+
+	#[tokio::test]
+	async fn reply_to_event() {
+		let (server, sink) = homeserver().await;
+
+		Mock::given(method("PUT"))
+			.and(body_partial_json(json!({
+				"m.relates_to": { "m.in_reply_to": { "event_id": "$original" } },
+			})))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({ "event_id": "$reply" })))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let msg = Message {
+			body: Some("reply".to_owned()),
+			..Default::default()
+		};
+
+		let msgid = sink
+			.send(&msg, Some(&MessageId::Str("$original".to_owned())), None)
+			.await
+			.unwrap();
+		assert_eq!(msgid, Some(MessageId::Str("$reply".to_owned())));
+	}
+
+	#[tokio::test]
+	async fn reply_to_int_message_id() {
+		let (_server, sink) = homeserver().await;
+
+		let msg = Message {
+			body: Some("reply".to_owned()),
+			..Default::default()
+		};
+
+		let err = sink
+			.send(&msg, Some(&MessageId::Int(1)), None)
+			.await
+			.unwrap_err();
+		assert!(matches!(err, SinkError::InvalidMessageIdType(_)));
+	}
+
+	#[tokio::test]
+	async fn upload_image() {
+		let (server, sink) = homeserver().await;
+
+		Mock::given(method("GET"))
+			.and(path("/cat.png"))
+			.respond_with(ResponseTemplate::new(200).set_body_raw(b"meow".to_vec(), "image/png"))
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path("/_matrix/media/v3/upload"))
+			.and(query_param("filename", "cat.png"))
+			.and(header("content-type", "image/png"))
+			.respond_with(
+				ResponseTemplate::new(200)
+					.set_body_json(json!({ "content_uri": "mxc://localhost/cat" })),
+			)
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("PUT"))
+			.and(body_partial_json(json!({
+				"msgtype": "m.image",
+				"body": "cat.png",
+				"url": "mxc://localhost/cat",
+				"info": { "mimetype": "image/png", "size": 4 },
+			})))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({ "event_id": "$image" })))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("PUT"))
+			.and(body_partial_json(json!({
+				"msgtype": "m.text",
+				"m.relates_to": { "m.in_reply_to": { "event_id": "$image" } },
+			})))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({ "event_id": "$text" })))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let msg = Message {
+			body: Some("look at this cat".to_owned()),
+			media: Some(vec![Media::Photo(
+				format!("{}/cat.png", server.uri()).parse().unwrap(),
+			)]),
+			..Default::default()
+		};
+
+		let msgid = sink.send(&msg, None, None).await.unwrap();
+		assert_eq!(msgid, Some(MessageId::Str("$text".to_owned())));
+	}
+
+	#[tokio::test]
+	async fn homeserver_error() {
+		let (server, sink) = homeserver().await;
+
+		Mock::given(method("PUT"))
+			.respond_with(ResponseTemplate::new(403).set_body_json(json!({
+				"errcode": "M_FORBIDDEN",
+				"error": "You are not in this room",
+			})))
+			.mount(&server)
+			.await;
+
+		let msg = Message {
+			body: Some("hi".to_owned()),
+			..Default::default()
+		};
+
+		let err = sink.send(&msg, None, None).await.unwrap_err();
+		assert!(matches!(
+			err,
+			SinkError::Matrix {
+				source: MatrixError::Homeserver { ref errcode, .. },
+				..
+			} if errcode == "M_FORBIDDEN"
+		));
+	}
+
+	#[tokio::test]
+	async fn give_up_when_rate_limited() {
+		let (server, sink) = homeserver().await;
+
+		Mock::given(method("PUT"))
+			.respond_with(ResponseTemplate::new(429).set_body_json(json!({
+				"errcode": "M_LIMIT_EXCEEDED",
+				"error": "Too many requests",
+				"retry_after_ms": 1,
+			})))
+			.expect(u64::from(MAX_RATE_LIMIT_RETRIES) + 1)
+			.mount(&server)
+			.await;
+
+		let msg = Message {
+			body: Some("hi".to_owned()),
+			..Default::default()
+		};
+
+		let err = sink.send(&msg, None, None).await.unwrap_err();
+		assert!(matches!(
+			err,
+			SinkError::Matrix {
+				source: MatrixError::Homeserver { ref errcode, .. },
+				..
+			} if errcode == "M_LIMIT_EXCEEDED"
+		));
+	}
+
+	#[tokio::test]
+	async fn download_too_large() {
+		let (server, sink) = homeserver().await;
+
+		Mock::given(method("GET"))
+			.and(path("/cat.png"))
+			.respond_with(ResponseTemplate::new(200).set_body_raw(b"meow".to_vec(), "image/png"))
+			.mount(&server)
+			.await;
+
+		let url = format!("{}/cat.png", server.uri()).parse().unwrap();
+		assert!(matches!(
+			sink.download(&url, 3).await,
+			Err(MatrixError::MediaTooLarge(3))
+		));
+		assert_eq!(sink.download(&url, 4).await.unwrap().0, b"meow");
+	}
+
+	#[test]
+	fn reject_invalid_homeserver() {
+		for url in [
+			"mailto:admin@example.com",
+			"data:text/plain,hi",
+			"ftp://example.com",
+		] {
+			assert!(matches!(
+				Matrix::new(url.parse().unwrap(), "token".to_owned(), ROOM_ID.to_owned()),
+				Err(MatrixError::InvalidHomeserver(_))
+			));
+		}
+	}
+}
@@ -8,9 +8,12 @@
 
 pub(crate) mod length_limiter;
 
+use bytes::Bytes;
 use std::fmt::Debug;
 use url::Url;
 
+use super::error::InvalidMessageIdTypeError;
+
 /// The finalized and composed message meant to be sent to a sink
 #[derive(Clone, Default)]
 pub struct Message {
@@ -27,17 +30,42 @@ pub struct Message {
 // TODO: the type of the message id could be probably stored as an associated type inside Sink
 // This would allow to specify what types support message ids and which ones don't, as well as avoid conversions between different int types or even str
 /// An id of a sent message
-#[derive(Clone, Copy, Debug)]
-pub struct MessageId(pub i64);
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MessageId {
+	/// A numeric id, e.g. of a Telegram or a Discord message
+	Int(i64),
+
+	/// A string id, e.g. of a Matrix event
+	Str(String),
+}
 
 // TODO: rename photo to image mb?
-/// A link to some kind of external media
+/// A link to some kind of external media, or a file included in the message itself
 #[derive(Clone)]
 pub enum Media {
 	/// A link to a photo
 	Photo(Url),
 	/// A link to a video
 	Video(Url),
+	/// A link to an animation, e.g. a GIF
+	Animation(Url),
+	/// A link to an audio file
+	Audio(Url),
+	/// A link to any other kind of file, e.g. a PDF
+	Document(Url),
+	/// A file included in the message itself, e.g. an email attachment
+	File(MediaFile),
+}
+
+/// A file kept in memory
+#[derive(Clone)]
+pub struct MediaFile {
+	/// Name of the file, e.g. `image.png`
+	pub name: String,
+	/// MIME type of the file, e.g. `image/png` or `application/pdf`
+	pub mime_type: String,
+	/// Contents of the file
+	pub data: Bytes,
 }
 
 impl Message {
@@ -46,11 +74,121 @@ impl Message {
 	pub const fn is_empty(&self) -> bool {
 		self.title.is_none() && self.body.is_none() && self.link.is_none() && self.media.is_none()
 	}
+
+	/// A hash of the contents of the message, used to check if it has changed since it was sent.
+	///
+	/// Unlike [`std::hash::Hash`], it's guaranteed to stay the same across runs and versions of Rust, so it can be saved externally
+	#[must_use]
+	pub fn content_hash(&self) -> u64 {
+		let mut hasher = Fnv1a::new();
+
+		for field in [self.title.as_deref(), self.body.as_deref()] {
+			hasher.write_field(field.map(str::as_bytes));
+		}
+		hasher.write_field(self.link.as_ref().map(|link| link.as_str().as_bytes()));
+
+		for media in self.media.iter().flatten() {
+			match media {
+				Media::File(file) => {
+					hasher.write_field(Some(file.name.as_bytes()));
+					hasher.write_field(Some(&file.data));
+				}
+				_ => hasher.write_field(media.url().map(|url| url.as_str().as_bytes())),
+			}
+		}
+
+		hasher.finish()
+	}
+}
+
+/// The 64-bit FNV-1a hash function. It's simple and, most importantly, stable
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+	pub(crate) const fn new() -> Self {
+		Self(0xcbf2_9ce4_8422_2325)
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= u64::from(*byte);
+			self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+		}
+	}
+
+	/// Writes an optional field, making sure different fields can't run into each other
+	pub(crate) fn write_field(&mut self, field: Option<&[u8]>) {
+		match field {
+			Some(field) => {
+				self.write(&[1]);
+				self.write(&(field.len() as u64).to_le_bytes());
+				self.write(field);
+			}
+			None => self.write(&[0]),
+		}
+	}
+
+	pub(crate) const fn finish(&self) -> u64 {
+		self.0
+	}
+}
+
+impl Media {
+	/// Get the URL of the media, or [`None`] if the media isn't hosted anywhere, e.g. if it's a [`Media::File`]
+	#[must_use]
+	pub const fn url(&self) -> Option<&Url> {
+		match self {
+			Self::Photo(url)
+			| Self::Video(url)
+			| Self::Animation(url)
+			| Self::Audio(url)
+			| Self::Document(url) => Some(url),
+			Self::File(_) => None,
+		}
+	}
+}
+
+impl MediaFile {
+	/// Check if the file is an image judging by its MIME type
+	#[must_use]
+	pub fn is_image(&self) -> bool {
+		self.mime_type.starts_with("image/")
+	}
+
+	/// Check if the file is a video judging by its MIME type
+	#[must_use]
+	pub fn is_video(&self) -> bool {
+		self.mime_type.starts_with("video/")
+	}
+}
+
+impl MessageId {
+	/// Convert a numeric message id into an integer of a sink specific type
+	pub(crate) fn to_int<T: TryFrom<i64>>(&self) -> Result<T, InvalidMessageIdTypeError> {
+		match self {
+			Self::Int(id) => T::try_from(*id).map_err(|_| InvalidMessageIdTypeError(self.clone())),
+			Self::Str(_) => Err(InvalidMessageIdTypeError(self.clone())),
+		}
+	}
+
+	/// Get the string message id
+	pub(crate) fn as_str(&self) -> Result<&str, InvalidMessageIdTypeError> {
+		match self {
+			Self::Str(id) => Ok(id),
+			Self::Int(_) => Err(InvalidMessageIdTypeError(self.clone())),
+		}
+	}
 }
 
 impl From<i64> for MessageId {
 	fn from(value: i64) -> Self {
-		Self(value)
+		Self::Int(value)
+	}
+}
+
+impl From<String> for MessageId {
+	fn from(value: String) -> Self {
+		Self::Str(value)
 	}
 }
 
@@ -70,6 +208,20 @@ impl Debug for Media {
 		match self {
 			Self::Photo(x) => f.debug_tuple("Photo").field(&x.as_str()).finish(),
 			Self::Video(x) => f.debug_tuple("Video").field(&x.as_str()).finish(),
+			Self::Animation(x) => f.debug_tuple("Animation").field(&x.as_str()).finish(),
+			Self::Audio(x) => f.debug_tuple("Audio").field(&x.as_str()).finish(),
+			Self::Document(x) => f.debug_tuple("Document").field(&x.as_str()).finish(),
+			Self::File(x) => f.debug_tuple("File").field(x).finish(),
 		}
 	}
 }
+
+impl Debug for MediaFile {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("MediaFile")
+			.field("name", &self.name)
+			.field("mime_type", &self.mime_type)
+			.field("size", &self.data.len())
+			.finish()
+	}
+}
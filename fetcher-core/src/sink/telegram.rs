@@ -7,31 +7,42 @@
 //! This module contains the [`Telegram`] sink, as well as [`LinkLocation`] enum that specifies where to put a link in a telegram message
 
 use crate::{
+	entry::EntryId,
 	sink::{
 		Sink,
 		error::SinkError,
 		message::{Media, Message, MessageId, length_limiter::MessageLengthLimiter},
 	},
+	source::http::{HttpClient, HttpError},
 	utils::OptionExt,
 };
 
 use async_trait::async_trait;
-use std::{fmt::Debug, num::TryFromIntError, time::Duration};
+use std::fmt::Debug;
 use teloxide::{
 	Bot, RequestError,
 	adaptors::{Throttle, throttle::Limits},
-	payloads::{SendMediaGroupSetters, SendMessageSetters},
+	payloads::{
+		EditMessageCaptionSetters, EditMessageTextSetters, SendAnimationSetters,
+		SendMediaGroupSetters, SendMessageSetters,
+	},
 	requests::{Request, Requester, RequesterExt},
 	types::{
-		ChatId, InputFile, InputMedia, InputMediaPhoto, InputMediaVideo, LinkPreviewOptions,
-		Message as TelMessage, MessageId as TelMessageId, ParseMode, ReplyParameters,
+		ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, InputMedia,
+		InputMediaAnimation, InputMediaAudio, InputMediaDocument, InputMediaPhoto, InputMediaVideo,
+		LinkPreviewOptions, Message as TelMessage, MessageId as TelMessageId, ParseMode, Recipient,
+		ReplyParameters, ThreadId,
 	},
 };
-use tokio::time::sleep;
+use tokio::{sync::OnceCell, time::sleep};
+use url::Url;
 
 const MAX_TEXT_MSG_LEN: usize = 4096;
 const MAX_MEDIA_MSG_LEN: usize = 1024;
 
+/// Max size of a file a bot can upload, in bytes
+const MAX_UPLOAD_SIZE: u64 = 50 * 1024 * 1024;
+
 const LINK_PREVIEW_DISABLED: LinkPreviewOptions = LinkPreviewOptions {
 	is_disabled: true,
 	url: None,
@@ -43,8 +54,55 @@ const LINK_PREVIEW_DISABLED: LinkPreviewOptions = LinkPreviewOptions {
 /// Telegram sink. Supports text and media messages and embeds text into media captions if present. Automatically splits the text into separate messages if it's too long
 pub struct Telegram {
 	bot: Throttle<Bot>,
-	chat_id: ChatId,
+	chat: Chat,
 	link_location: LinkLocation,
+	options: Options,
+
+	/// [`Telegram::chat`] resolved to its id
+	chat_id: OnceCell<ChatId>,
+}
+
+/// The chat to send messages to
+#[derive(Clone, Debug)]
+pub enum Chat {
+	/// Id of a group, a channel, or a user. Users have to start a conversation with the bot first
+	Id(i64),
+
+	/// Public handle of a group or a channel, with or without the leading @. Resolved to its id before sending the first message
+	Username(String),
+}
+
+/// Options that apply to every sent message
+#[derive(Clone, Default, Debug)]
+pub struct Options {
+	/// Id of the forum topic to send messages to
+	pub message_thread_id: Option<i32>,
+
+	/// Send messages silently, i.e. without a notification sound
+	pub disable_notification: bool,
+
+	/// Protect messages from being forwarded and saved
+	pub protect_content: bool,
+
+	/// Show a preview of the first link in text messages
+	pub link_preview: bool,
+
+	/// Inline keyboard buttons shown below the message in a single row.
+	/// Since media groups can't have buttons, the text of media messages is sent as a separate message below the media if there are any buttons
+	pub buttons: Vec<Button>,
+}
+
+/// An inline keyboard button that opens a URL.
+///
+/// Both the text and the URL can contain placeholders `{title}`, `{link}`, `{tag}`, and `{entry_id}` that are replaced with the values of the message,
+/// or an empty string if they are missing. Buttons with an empty text or an invalid URL are skipped
+#[derive(Clone, Debug)]
+pub struct Button {
+	/// Text of the button
+	pub text: String,
+
+	/// URL the button opens
+	pub url: String,
 }
 
 /// Where to put `message.link`
@@ -59,35 +117,67 @@ pub enum LinkLocation {
 }
 
 impl Telegram {
-	/// Creates a new Telegram sink using the bot `token` that sends messages to chat with `chat_id` with `Message.link` put at `link_location`
+	/// Creates a new Telegram sink using the bot `token` that sends messages to `chat` with `Message.link` put at `link_location`
 	#[must_use]
-	pub fn new(token: String, chat_id: i64, link_location: LinkLocation) -> Self {
+	pub fn new(token: String, chat: Chat, link_location: LinkLocation, options: Options) -> Self {
 		Self {
 			bot: Bot::new(token).throttle(Limits::default()),
-			chat_id: ChatId(chat_id),
+			chat,
 			link_location,
+			options,
+			chat_id: OnceCell::new(),
 		}
 	}
 }
 
 #[async_trait]
 impl Sink for Telegram {
-	/// Sends a message to a Telegram chat
+	/// Sends a message to a Telegram chat without an entry id
 	///
 	/// # Errors
 	/// * if Telegram returned an error
 	/// * if there's no internet connection
-	#[tracing::instrument(level = "debug", skip(message))]
 	async fn send(
 		&self,
 		message: &Message,
 		reply_to: Option<&MessageId>,
 		tag: Option<&str>,
 	) -> Result<Option<MessageId>, SinkError> {
-		let reply_to = reply_to.try_map(|msgid| {
-			let tel_msg_id = TelMessageId(msgid.0.try_into()?);
-			Ok::<_, TryFromIntError>(tel_msg_id)
-		})?;
+		self.send_entry(message, None, reply_to, tag).await
+	}
+
+	/// Sends a message to a Telegram chat. The `entry_id` is only used to fill in the placeholders of the buttons
+	///
+	/// # Errors
+	/// * if Telegram returned an error
+	/// * if there's no internet connection
+	async fn send_entry(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		let msg_ids = self
+			.send_entry_parts(message, entry_id, reply_to, tag)
+			.await?;
+		Ok(msg_ids.into_iter().next())
+	}
+
+	/// Sends a message to a Telegram chat and returns the ids of all the media and text messages it has been split into
+	///
+	/// # Errors
+	/// * if Telegram returned an error
+	/// * if there's no internet connection
+	#[tracing::instrument(level = "debug", skip(message))]
+	async fn send_entry_parts(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Vec<MessageId>, SinkError> {
+		let reply_to = reply_to.try_map(|msgid| msgid.to_int().map(TelMessageId))?;
 
 		let (head, body, tail, media) = process_msg(message, tag, self.link_location);
 
@@ -97,77 +187,385 @@ impl Sink for Telegram {
 			tail: tail.as_deref(),
 		};
 
-		let msg_id = self.send_processed(processed_msg, media, reply_to).await?;
-		Ok(msg_id.map(|tel_msgid| i64::from(tel_msgid.0).into()))
+		let keyboard = self.keyboard(message, tag, entry_id);
+
+		let msg_ids = self
+			.send_processed(processed_msg, media, reply_to, keyboard.as_ref())
+			.await?;
+		Ok(msg_ids
+			.into_iter()
+			.map(|tel_msgid| i64::from(tel_msgid.0).into())
+			.collect())
+	}
+
+	/// Edits the message without an entry id
+	async fn edit(
+		&self,
+		message: &Message,
+		message_id: &MessageId,
+		tag: Option<&str>,
+	) -> Result<(), SinkError> {
+		self.edit_entry(message, None, message_id, tag).await
+	}
+
+	/// Edits the text of the message, or its caption if it's a media message. The media itself is left as is.
+	/// The `entry_id` is only used to fill in the placeholders of the buttons.
+	///
+	/// Sends the message as a reply instead if it doesn't fit into a single Telegram message or if Telegram refused to edit it,
+	/// e.g. because it's too old
+	#[tracing::instrument(level = "debug", skip(message))]
+	async fn edit_entry(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		message_id: &MessageId,
+		tag: Option<&str>,
+	) -> Result<(), SinkError> {
+		let tel_msg_id = TelMessageId(message_id.to_int()?);
+		let chat_id = self.chat_id().await?;
+
+		// the text of media messages with buttons is sent as a separate message.
+		// It might also have ended up in a text message if the media couldn't be sent or if there was too much of it,
+		// so edit the other kind of message if Telegram says there's nothing to edit
+		let is_caption = message.media.is_some() && self.options.buttons.is_empty();
+		let res = match self
+			.edit_as(message, entry_id, tag, chat_id, tel_msg_id, is_caption)
+			.await
+		{
+			Err(e) if is_wrong_message_kind_error(&e) => {
+				tracing::debug!("Guessed the kind of the message wrong, editing it the other way");
+				self.edit_as(message, entry_id, tag, chat_id, tel_msg_id, !is_caption)
+					.await
+			}
+			res => res,
+		};
+
+		match res {
+			Ok(EditOutcome::Edited) => Ok(()),
+			Ok(EditOutcome::NoText) => {
+				tracing::debug!("There's no text to edit the message with, skipping");
+				Ok(())
+			}
+			Ok(EditOutcome::TooLong) => {
+				tracing::info!(
+					"Edited message doesn't fit into a single message, sending it as a reply"
+				);
+				self.send_entry(message, entry_id, Some(message_id), tag)
+					.await?;
+				Ok(())
+			}
+			Err(e)
+				if e.to_string()
+					.to_lowercase()
+					.contains("message is not modified") =>
+			{
+				tracing::debug!("Message is already up to date");
+				Ok(())
+			}
+			Err(e @ RequestError::Api(_)) => {
+				tracing::warn!("Can't edit the message, sending it as a reply instead: {e}");
+				self.send_entry(message, entry_id, Some(message_id), tag)
+					.await?;
+				Ok(())
+			}
+			Err(e) => Err(SinkError::Telegram {
+				source: e,
+				msg: Box::new(message.clone()),
+			}),
+		}
+	}
+
+	/// Deletes the message. Each of the messages a message has been split into is deleted separately.
+	///
+	/// Leaves the message as is if Telegram refused to delete it, e.g. because it's older than 48 hours and was sent to a group
+	#[tracing::instrument(level = "debug")]
+	async fn delete(&self, message_id: &MessageId) -> Result<(), SinkError> {
+		let tel_msg_id = TelMessageId(message_id.to_int()?);
+		let chat_id = self.chat_id().await?;
+
+		tracing::info!("Deleting message");
+		match self.bot.delete_message(chat_id, tel_msg_id).send().await {
+			Ok(_) => Ok(()),
+			Err(e @ RequestError::Api(_)) => {
+				tracing::warn!("Can't delete the message, leaving it as is: {e}");
+				Ok(())
+			}
+			Err(e) => Err(SinkError::Telegram {
+				source: e,
+				msg: Box::new(message_id.clone()),
+			}),
+		}
 	}
 }
 
 impl Telegram {
+	/// Returns the id of [`Telegram::chat`], resolving its username the first time it's needed
+	async fn chat_id(&self) -> Result<ChatId, SinkError> {
+		self.chat_id
+			.get_or_try_init(|| async {
+				match &self.chat {
+					Chat::Id(id) => Ok(ChatId(*id)),
+					Chat::Username(username) => {
+						let username = format!("@{}", username.trim_start_matches('@'));
+						tracing::debug!("Resolving chat {username}");
+
+						let chat = self
+							.bot
+							.get_chat(Recipient::ChannelUsername(username.clone()))
+							.send()
+							.await
+							.map_err(|e| SinkError::Telegram {
+								source: e,
+								msg: Box::new(username),
+							})?;
+
+						tracing::debug!("Resolved chat to id {}", chat.id);
+						Ok(chat.id)
+					}
+				}
+			})
+			.await
+			.copied()
+	}
+
+	fn thread_id(&self) -> Option<ThreadId> {
+		self.options
+			.message_thread_id
+			.map(|id| ThreadId(TelMessageId(id)))
+	}
+
+	/// Builds an inline keyboard out of [`Options::buttons`], or returns [`None`] if there are no buttons to show
+	fn keyboard(
+		&self,
+		message: &Message,
+		tag: Option<&str>,
+		entry_id: Option<&EntryId>,
+	) -> Option<InlineKeyboardMarkup> {
+		let buttons = self
+			.options
+			.buttons
+			.iter()
+			.filter_map(|button| {
+				let text = fill_placeholders(&button.text, message, tag, entry_id);
+				let url = fill_placeholders(&button.url, message, tag, entry_id);
+
+				if text.is_empty() {
+					tracing::debug!("Skipping button with an empty text");
+					return None;
+				}
+
+				match Url::parse(&url) {
+					Ok(url) => Some(InlineKeyboardButton::url(text, url)),
+					Err(e) => {
+						tracing::debug!(
+							"Skipping button {text:?} with an invalid URL {url:?}: {e}"
+						);
+						None
+					}
+				}
+			})
+			.collect::<Vec<_>>();
+
+		(!buttons.is_empty()).then(|| InlineKeyboardMarkup::new([buttons]))
+	}
+
+	/// Edits the caption of a media message or the text of a text message, if the new text fits into it
+	async fn edit_as(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		tag: Option<&str>,
+		chat_id: ChatId,
+		tel_msg_id: TelMessageId,
+		is_caption: bool,
+	) -> Result<EditOutcome, RequestError> {
+		let (head, body, tail, _) = process_msg(message, tag, self.link_location);
+		let max_len = if is_caption {
+			MAX_MEDIA_MSG_LEN
+		} else {
+			MAX_TEXT_MSG_LEN
+		};
+
+		let mut processed_msg = MessageLengthLimiter {
+			head: head.as_deref(),
+			body: body.as_deref(),
+			tail: tail.as_deref(),
+		};
+
+		let Some(text) = processed_msg.split_at(max_len) else {
+			return Ok(EditOutcome::NoText);
+		};
+
+		if processed_msg.split_at(max_len).is_some() {
+			return Ok(EditOutcome::TooLong);
+		}
+
+		tracing::info!("Editing message");
+		if is_caption {
+			self.bot
+				.edit_message_caption(chat_id, tel_msg_id)
+				.caption(text)
+				.parse_mode(ParseMode::Html)
+				.send()
+				.await?;
+		} else {
+			let mut edit_cmd = self
+				.bot
+				.edit_message_text(chat_id, tel_msg_id, text)
+				.parse_mode(ParseMode::Html);
+
+			if !self.options.link_preview {
+				edit_cmd = edit_cmd.link_preview_options(LINK_PREVIEW_DISABLED);
+			}
+
+			// the buttons are removed if they aren't included in the edit
+			if let Some(keyboard) = self.keyboard(message, tag, entry_id) {
+				edit_cmd = edit_cmd.reply_markup(keyboard);
+			}
+
+			edit_cmd.send().await?;
+		}
+
+		Ok(EditOutcome::Edited)
+	}
+}
+
+/// What happened to a message when trying to edit it
+enum EditOutcome {
+	Edited,
+	/// There's no text to edit the message with
+	NoText,
+	/// The new text doesn't fit into a single message
+	TooLong,
+}
+
+impl Telegram {
+	/// Returns the ids of all sent messages, starting with the media message with the caption if there is one, or with the last sent message otherwise
 	// replace option with custom error
 	async fn send_processed(
 		&self,
 		mut msg: MessageLengthLimiter<'_>,
 		media: Option<&[Media]>,
 		reply_to: Option<TelMessageId>,
-	) -> Result<Option<TelMessageId>, SinkError> {
+		keyboard: Option<&InlineKeyboardMarkup>,
+	) -> Result<Vec<TelMessageId>, SinkError> {
 		let mut last_message = reply_to;
+		let mut sent = Vec::new();
+
+		// the caption is the start of the text, so edit it rather than the text left over after it later
+		let mut captioned_message = None;
 
 		// if the message contains media, send it and MAX_MEDIA_MSG_LEN chars first
 		if let Some(media) = media {
-			// send media only (i.e. without caption) if all the media wouldn't fit in a single message
-			if media.len() > 10 {
-				for ch in media.chunks(10) {
-					let sent_msg = self.send_media(ch, None, last_message).await?;
-					last_message = sent_msg.and_then(|v| v.first().map(|m| m.id));
+			// media of different kinds can't be grouped together, so send a separate group for each kind.
+			// Media groups can't have buttons, so the text is sent separately below them if there are any
+			let mut captioned = !self.options.buttons.is_empty();
+			for kind in [
+				MediaKind::Visual,
+				MediaKind::Animation,
+				MediaKind::Audio,
+				MediaKind::Document,
+			] {
+				let group = media
+					.iter()
+					.filter(|m| MediaKind::of(m) == kind)
+					.cloned()
+					.collect::<Vec<_>>();
+
+				if group.is_empty() {
+					continue;
 				}
-			} else {
-				let media_caption = msg.split_at(MAX_MEDIA_MSG_LEN).expect(
-					"should always return a valid split at least once since msg char len is > max_char_limit",
-				);
 
-				let sent_msg = self
-					.send_media(media, Some(&media_caption), last_message)
-					.await?;
-				last_message = sent_msg.and_then(|v| v.first().map(|m| m.id));
+				// send media only (i.e. without caption) if all the media wouldn't fit in a single message
+				if captioned || group.len() > kind.max_group_len() {
+					for ch in group.chunks(kind.max_group_len()) {
+						let sent_msg = self
+							.send_media(ch, None, last_message)
+							.await?
+							.unwrap_or_default();
+						sent.extend(sent_msg.iter().map(|m| m.id));
+						last_message = sent_msg.first().map(|m| m.id);
+					}
+				} else {
+					let media_caption = msg.split_at(MAX_MEDIA_MSG_LEN).expect(
+						"should always return a valid split at least once since msg char len is > max_char_limit",
+					);
+
+					let sent_msg = self
+						.send_media(&group, Some(&media_caption), last_message)
+						.await?
+						.unwrap_or_default();
+					sent.extend(sent_msg.iter().map(|m| m.id));
+					last_message = sent_msg.first().map(|m| m.id);
+					captioned_message = last_message;
+					captioned = true;
+				}
 			}
 		}
 
 		// send all remaining text in splits of MAX_TEXT_MSG_LEN
 		// whether we sent a media message first is not important
-		while let Some(text) = msg.split_at(MAX_TEXT_MSG_LEN) {
-			let sent_msg = self.send_text(&text, last_message).await?;
+		let texts = std::iter::from_fn(|| msg.split_at(MAX_TEXT_MSG_LEN)).collect::<Vec<_>>();
+
+		if keyboard.is_some() && texts.is_empty() {
+			tracing::debug!("There's no text to attach the buttons to, skipping them");
+		}
+
+		for (i, text) in texts.iter().enumerate() {
+			// only the last message has the buttons
+			let keyboard = keyboard.filter(|_| i == texts.len() - 1);
+
+			let sent_msg = self.send_text(text, last_message, keyboard).await?;
+			sent.push(sent_msg.id);
 			last_message = Some(sent_msg.id);
 		}
 
-		Ok(last_message)
+		let main = captioned_message.or(last_message);
+		sent.retain(|&id| Some(id) != main);
+
+		Ok(main.into_iter().chain(sent).collect())
 	}
 }
 
 impl Telegram {
-	#[tracing::instrument(level = "trace", skip(self, message))]
+	#[tracing::instrument(level = "trace", skip(self, message, keyboard))]
 	async fn send_text(
 		&self,
 		message: &str,
 		mut reply_to: Option<TelMessageId>,
+		keyboard: Option<&InlineKeyboardMarkup>,
 	) -> Result<TelMessage, SinkError> {
 		tracing::debug!(
 			"About to send a text message with contents: {message:?}, replying to {reply_to:?}"
 		);
 
+		let chat_id = self.chat_id().await?;
+
 		loop {
 			tracing::info!("Sending text message");
 
-			let send_msg_cmd = self
+			let mut send_msg_cmd = self
 				.bot
-				.send_message(self.chat_id, message)
+				.send_message(chat_id, message)
 				.parse_mode(ParseMode::Html)
-				.link_preview_options(LINK_PREVIEW_DISABLED);
+				.disable_notification(self.options.disable_notification)
+				.protect_content(self.options.protect_content);
 
-			let send_msg_cmd = if let Some(id) = reply_to {
-				send_msg_cmd.reply_parameters(ReplyParameters::new(id))
-			} else {
-				send_msg_cmd
-			};
+			if let Some(thread_id) = self.thread_id() {
+				send_msg_cmd = send_msg_cmd.message_thread_id(thread_id);
+			}
+
+			if !self.options.link_preview {
+				send_msg_cmd = send_msg_cmd.link_preview_options(LINK_PREVIEW_DISABLED);
+			}
+
+			if let Some(keyboard) = keyboard {
+				send_msg_cmd = send_msg_cmd.reply_markup(keyboard.clone());
+			}
+
+			if let Some(id) = reply_to {
+				send_msg_cmd = send_msg_cmd.reply_parameters(ReplyParameters::new(id));
+			}
 
 			match send_msg_cmd.send().await {
 				Ok(message) => return Ok(message),
@@ -198,14 +596,17 @@ impl Telegram {
 		}
 	}
 
-	/// Returns None if Media couldn't be sent but it's Telegram's fault
+	/// Returns None if Media couldn't be sent but it's Telegram's fault.
+	///
+	/// If Telegram can't get the media from its URL, the media is downloaded and uploaded to Telegram directly instead.
+	/// Only the caption is sent if that fails too, e.g. because the media is larger than a bot can upload
 	/// # Panics
 	/// if media.len() is more than 10
 	#[tracing::instrument(level = "trace", skip(self))]
 	async fn send_media(
 		&self,
 		media: &[Media],
-		mut caption: Option<&str>,
+		caption: Option<&str>,
 		mut reply_to: Option<TelMessageId>,
 	) -> Result<Option<Vec<TelMessage>>, SinkError> {
 		assert!(
@@ -218,115 +619,55 @@ impl Telegram {
 			"About to send a media message with caption: {caption:?}, and media: {media:?}, replying to {reply_to:?}"
 		);
 
-		let media = media
+		let mut input_media = media
 			.iter()
-			.map(|m| {
-				macro_rules! input_media {
-					// $type example: Photo
-					// $full_type example: InputMediaPhoto
-					($type:tt, $full_type:tt, $url:expr) => {{
-						let input_media = $full_type::new(InputFile::url($url.clone()))
-							.parse_mode(ParseMode::Html);
-
-						let input_media = if let Some(caption) = caption.take() {
-							input_media.caption(caption)
-						} else {
-							input_media
-						};
-
-						InputMedia::$type(input_media)
-					}};
-				}
+			.enumerate()
+			.map(|(i, m)| {
+				let file = match m {
+					Media::File(file) => {
+						InputFile::memory(file.data.clone()).file_name(file.name.clone())
+					}
+					_ => InputFile::url(
+						m.url()
+							.expect("all media except files should have a URL")
+							.clone(),
+					),
+				};
 
-				match m {
-					Media::Photo(url) => input_media!(Photo, InputMediaPhoto, url),
-					Media::Video(url) => input_media!(Video, InputMediaVideo, url),
-				}
+				// only the first media item has a caption which is shown below the whole media group
+				to_input_media(m, file, caption.filter(|_| i == 0))
 			})
 			.collect::<Vec<_>>();
 
-		// number of "failed to get url content" error retried tries
-		let mut retry_counter = 0;
+		let chat_id = self.chat_id().await?;
+		let mut reuploaded = false;
 
 		loop {
 			tracing::info!("Sending media message");
 
-			let msg_cmd = self.bot.send_media_group(self.chat_id, media.clone());
-
-			let msg_cmd = if let Some(id) = reply_to {
-				msg_cmd.reply_parameters(ReplyParameters::new(id))
-			} else {
-				msg_cmd
-			};
-
-			// don't forget to return from a branch, dummy, otherwise you'll end up in an infinite loop
-			#[expect(clippy::redundant_else, reason = "improves control flow visualization")]
-			match msg_cmd.send().await {
+			match self
+				.send_input_media(input_media.clone(), reply_to, chat_id)
+				.await
+			{
 				Ok(messages) => return Ok(Some(messages)),
-				Err(e)
-					if e.to_string()
-						.to_lowercase()
-						.contains("failed to get http url content") =>
-				{
-					if retry_counter > 5 {
-						tracing::warn!("Telegram failed to get URL content too many times");
-
-						if let Some(caption) = caption {
-							tracing::info!("Sending the message as pure text...");
-
-							let msg = self.send_text(caption, reply_to).await?;
-
-							return Ok(Some(vec![msg]));
-						} else {
-							tracing::warn!("There's no text to send, skipping this message...");
-							return Ok(None);
-						}
+				Err(e) if is_bad_media_error(&e) => {
+					if reuploaded {
+						tracing::warn!("Telegram disliked the reuploaded media too: {e}");
+						return self.send_caption_only(caption, reply_to).await;
 					}
 
-					tracing::warn!("Telegram failed to get URL content. Retrying in 30 seconds");
-					sleep(Duration::from_secs(30)).await;
-
-					retry_counter += 1;
-				}
-				Err(e)
-					if e.to_string()
-						.to_lowercase()
-						.contains("wrong file identifier/http url specified") =>
-				{
-					// TODO: reupload the image manually if this happens
-					if let Some(caption) = caption {
-						tracing::warn!(
-							"Telegram disliked the media URL (\"Wrong file identifier/HTTP URL specified\"), sending the message as pure text"
-						);
-						let msg = self.send_text(caption, reply_to).await?;
-
-						return Ok(Some(vec![msg]));
-					} else {
-						tracing::warn!(
-							"Telegram disliked the media URL (\"Wrong file identifier/HTTP URL specified\") but the caption was empty, skipping..."
-						);
-						return Ok(None);
-					}
-				}
-				Err(e)
-					if e.to_string()
-						.to_lowercase()
-						.contains("wrong type of the web page content") =>
-				{
-					// TODO: reupload the image manually if this happens
-					if let Some(caption) = caption {
-						tracing::warn!(
-							"Telegram disliked the media URL (\"Wrong type of the web page content\"), sending the message as pure text"
-						);
-						let msg = self.send_text(caption, reply_to).await?;
+					tracing::warn!(
+						"Telegram couldn't get the media from its URL ({e}), downloading and uploading it manually"
+					);
 
-						return Ok(Some(vec![msg]));
-					} else {
-						tracing::warn!(
-							"Telegram disliked the media URL (\"Wrong type of the web page content\") but the caption was empty, skipping..."
-						);
-						return Ok(None);
-					}
+					input_media = match reupload(media, caption).await {
+						Ok(input_media) => input_media,
+						Err(e) => {
+							tracing::warn!("Can't download the media: {e}");
+							return self.send_caption_only(caption, reply_to).await;
+						}
+					};
+					reuploaded = true;
 				}
 				Err(e)
 					if e.to_string()
@@ -348,12 +689,216 @@ impl Telegram {
 				Err(e) => {
 					return Err(SinkError::Telegram {
 						source: e,
-						msg: Box::new(media),
+						msg: Box::new(input_media),
 					});
 				}
 			}
 		}
 	}
+
+	/// Sends the media as a media group, or as an animation if it's a single animation since they can't be a part of a media group
+	async fn send_input_media(
+		&self,
+		media: Vec<InputMedia>,
+		reply_to: Option<TelMessageId>,
+		chat_id: ChatId,
+	) -> Result<Vec<TelMessage>, RequestError> {
+		if let [InputMedia::Animation(animation)] = media.as_slice() {
+			let mut msg_cmd = self
+				.bot
+				.send_animation(chat_id, animation.media.clone())
+				.parse_mode(ParseMode::Html)
+				.disable_notification(self.options.disable_notification)
+				.protect_content(self.options.protect_content);
+
+			if let Some(caption) = &animation.caption {
+				msg_cmd = msg_cmd.caption(caption.clone());
+			}
+
+			if let Some(thread_id) = self.thread_id() {
+				msg_cmd = msg_cmd.message_thread_id(thread_id);
+			}
+
+			if let Some(id) = reply_to {
+				msg_cmd = msg_cmd.reply_parameters(ReplyParameters::new(id));
+			}
+
+			return msg_cmd.send().await.map(|msg| vec![msg]);
+		}
+
+		let mut msg_cmd = self
+			.bot
+			.send_media_group(chat_id, media)
+			.disable_notification(self.options.disable_notification)
+			.protect_content(self.options.protect_content);
+
+		if let Some(thread_id) = self.thread_id() {
+			msg_cmd = msg_cmd.message_thread_id(thread_id);
+		}
+
+		if let Some(id) = reply_to {
+			msg_cmd = msg_cmd.reply_parameters(ReplyParameters::new(id));
+		}
+
+		msg_cmd.send().await
+	}
+
+	/// Sends the caption of a media message that couldn't be sent as pure text, or skips it if there's none
+	async fn send_caption_only(
+		&self,
+		caption: Option<&str>,
+		reply_to: Option<TelMessageId>,
+	) -> Result<Option<Vec<TelMessage>>, SinkError> {
+		if let Some(caption) = caption {
+			tracing::info!("Sending the message as pure text...");
+
+			let msg = self.send_text(caption, reply_to, None).await?;
+			Ok(Some(vec![msg]))
+		} else {
+			tracing::warn!("There's no text to send, skipping this message...");
+			Ok(None)
+		}
+	}
+}
+
+/// Kinds of media that can only be sent in a media group with media of the same kind
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MediaKind {
+	/// Photos and videos
+	Visual,
+	/// Can't be sent in a media group at all
+	Animation,
+	Audio,
+	Document,
+}
+
+impl MediaKind {
+	fn of(media: &Media) -> Self {
+		match media {
+			Media::Photo(_) | Media::Video(_) => Self::Visual,
+			Media::Animation(_) => Self::Animation,
+			Media::Audio(_) => Self::Audio,
+			Media::File(file) if file.mime_type == "image/gif" => Self::Animation,
+			Media::File(file) if file.is_image() || file.is_video() => Self::Visual,
+			Media::File(file) if file.mime_type.starts_with("audio/") => Self::Audio,
+			Media::Document(_) | Media::File(_) => Self::Document,
+		}
+	}
+
+	/// Max amount of media of this kind that can be sent in a single message
+	const fn max_group_len(self) -> usize {
+		match self {
+			Self::Animation => 1,
+			Self::Visual | Self::Audio | Self::Document => 10,
+		}
+	}
+}
+
+fn to_input_media(media: &Media, file: InputFile, caption: Option<&str>) -> InputMedia {
+	macro_rules! input_media {
+		// $type example: Photo
+		// $full_type example: InputMediaPhoto
+		($type:tt, $full_type:tt) => {{
+			let input_media = $full_type::new(file).parse_mode(ParseMode::Html);
+
+			let input_media = if let Some(caption) = caption {
+				input_media.caption(caption)
+			} else {
+				input_media
+			};
+
+			InputMedia::$type(input_media)
+		}};
+	}
+
+	let is_video = match media {
+		Media::Video(_) => true,
+		Media::File(file) => file.is_video(),
+		_ => false,
+	};
+
+	match MediaKind::of(media) {
+		MediaKind::Visual if is_video => input_media!(Video, InputMediaVideo),
+		MediaKind::Visual => input_media!(Photo, InputMediaPhoto),
+		MediaKind::Animation => input_media!(Animation, InputMediaAnimation),
+		MediaKind::Audio => input_media!(Audio, InputMediaAudio),
+		MediaKind::Document => input_media!(Document, InputMediaDocument),
+	}
+}
+
+/// Downloads all media hosted elsewhere to upload it to Telegram directly
+async fn reupload(media: &[Media], caption: Option<&str>) -> Result<Vec<InputMedia>, HttpError> {
+	let client = HttpClient::shared()?;
+
+	let mut input_media = Vec::with_capacity(media.len());
+	for (i, m) in media.iter().enumerate() {
+		let file = match m {
+			Media::File(file) => InputFile::memory(file.data.clone()).file_name(file.name.clone()),
+			_ => {
+				let url = m.url().expect("all media except files should have a URL");
+
+				tracing::debug!("Downloading {:?}", url.as_str());
+				let data = client.download(url, MAX_UPLOAD_SIZE).await?;
+
+				let file_name = url
+					.path_segments()
+					.and_then(Iterator::last)
+					.filter(|s| !s.is_empty())
+					.unwrap_or("media")
+					.to_owned();
+
+				InputFile::memory(data).file_name(file_name)
+			}
+		};
+
+		input_media.push(to_input_media(m, file, caption.filter(|_| i == 0)));
+	}
+
+	Ok(input_media)
+}
+
+/// Checks if Telegram couldn't send the media because of the media itself, e.g. if it couldn't get it from its URL
+fn is_bad_media_error(e: &RequestError) -> bool {
+	let e = e.to_string().to_lowercase();
+
+	[
+		"failed to get http url content",
+		"wrong file identifier/http url specified",
+		"wrong type of the web page content",
+		"request entity too large",
+	]
+	.into_iter()
+	.any(|msg| e.contains(msg))
+}
+
+/// Checks if Telegram couldn't edit the message because it's a media message when editing its text or vice versa
+fn is_wrong_message_kind_error(e: &RequestError) -> bool {
+	let e = e.to_string().to_lowercase();
+
+	[
+		"there is no text in the message to edit",
+		"there is no caption in the message to edit",
+	]
+	.into_iter()
+	.any(|msg| e.contains(msg))
+}
+
+/// Replaces the placeholders in a [`Button`] template with the values of the message
+#[expect(
+	clippy::literal_string_with_formatting_args,
+	reason = "these are the placeholders of the button template"
+)]
+fn fill_placeholders(
+	template: &str,
+	message: &Message,
+	tag: Option<&str>,
+	entry_id: Option<&EntryId>,
+) -> String {
+	template
+		.replace("{title}", message.title.as_deref().unwrap_or_default())
+		.replace("{link}", message.link.as_ref().map_or("", Url::as_str))
+		.replace("{tag}", tag.unwrap_or_default())
+		.replace("{entry_id}", entry_id.map_or("", |id| id.0.as_str()))
 }
 
 type HeadBodyTailMedia<'a> = (
@@ -427,8 +972,446 @@ fn process_msg<'a>(
 impl Debug for Telegram {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("Telegram")
-			.field("chat_id", &self.chat_id)
+			.field("chat", &self.chat)
 			.field("link_location", &self.link_location)
+			.field("options", &self.options)
 			.finish_non_exhaustive()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	use serde_json::json;
+	use wiremock::{
+		Mock, MockServer, ResponseTemplate,
+		matchers::{body_partial_json, body_string_contains, method, path, path_regex},
+	};
+
+	#[tokio::test]
+	async fn reupload_media_telegram_cant_get() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("GET"))
+			.and(path("/1.png"))
+			.respond_with(ResponseTemplate::new(200).set_body_bytes(b"png bytes".as_slice()))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmediagroup$"))
+			.respond_with(ResponseTemplate::new(400).set_body_json(json!({
+				"ok": false,
+				"error_code": 400,
+				"description": "Bad Request: wrong file identifier/HTTP URL specified",
+			})))
+			.up_to_n_times(1)
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmediagroup$"))
+			.and(body_string_contains("png bytes"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"ok": true,
+				"result": [{
+					"message_id": 42,
+					"date": 0,
+					"chat": { "id": 1, "type": "private", "first_name": "Test" },
+					"text": "Body",
+				}],
+			})))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let telegram = Telegram {
+			bot: Bot::new("token")
+				.set_api_url(server.uri().parse().unwrap())
+				.throttle(Limits::default()),
+			chat: Chat::Id(1),
+			link_location: LinkLocation::default(),
+			options: Options::default(),
+			chat_id: OnceCell::new(),
+		};
+
+		let message = Message {
+			body: Some("Body".to_owned()),
+			media: Some(vec![Media::Photo(
+				format!("{}/1.png", server.uri()).parse().unwrap(),
+			)]),
+			..Default::default()
+		};
+
+		let msg_id = telegram.send(&message, None, None).await.unwrap();
+		assert_eq!(msg_id, Some(MessageId::Int(42)));
+	}
+
+	#[tokio::test]
+	async fn return_ids_of_all_parts() {
+		let server = MockServer::start().await;
+
+		let sent = |id: i64| {
+			json!({
+				"message_id": id,
+				"date": 0,
+				"chat": { "id": 1, "type": "private", "first_name": "Test" },
+				"text": "Body",
+			})
+		};
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmediagroup$"))
+			.respond_with(
+				ResponseTemplate::new(200)
+					.set_body_json(json!({ "ok": true, "result": [sent(42), sent(43)] })),
+			)
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmessage$"))
+			.respond_with(
+				ResponseTemplate::new(200).set_body_json(json!({ "ok": true, "result": sent(44) })),
+			)
+			.up_to_n_times(1)
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmessage$"))
+			.respond_with(
+				ResponseTemplate::new(200).set_body_json(json!({ "ok": true, "result": sent(45) })),
+			)
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let telegram = Telegram {
+			bot: Bot::new("token")
+				.set_api_url(server.uri().parse().unwrap())
+				.throttle(Limits::default()),
+			chat: Chat::Id(1),
+			link_location: LinkLocation::default(),
+			options: Options::default(),
+			chat_id: OnceCell::new(),
+		};
+
+		// the caption and two more text messages
+		let message = Message {
+			body: Some("a".repeat(MAX_MEDIA_MSG_LEN + MAX_TEXT_MSG_LEN + 1)),
+			media: Some(vec![
+				Media::Photo("https://example.com/1.png".parse().unwrap()),
+				Media::Photo("https://example.com/2.png".parse().unwrap()),
+			]),
+			..Default::default()
+		};
+
+		let msg_ids = telegram
+			.send_entry_parts(&message, None, None, None)
+			.await
+			.unwrap();
+
+		// the captioned media message comes first
+		assert_eq!(
+			msg_ids,
+			[
+				MessageId::Int(42),
+				MessageId::Int(43),
+				MessageId::Int(44),
+				MessageId::Int(45)
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn resolve_username_and_add_buttons() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/getchat$"))
+			.and(body_partial_json(json!({ "chat_id": "@channel" })))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"ok": true,
+				"result": { "id": -100, "type": "channel", "title": "Channel" },
+			})))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmessage$"))
+			.and(body_partial_json(json!({
+				"chat_id": -100,
+				"message_thread_id": 7,
+				"disable_notification": true,
+				"protect_content": true,
+				"reply_markup": {
+					"inline_keyboard": [[
+						{ "text": "Open Title", "url": "https://example.com/1" },
+					]],
+				},
+			})))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"ok": true,
+				"result": {
+					"message_id": 42,
+					"date": 0,
+					"chat": { "id": -100, "type": "channel", "title": "Channel" },
+					"text": "Title",
+				},
+			})))
+			.expect(2)
+			.mount(&server)
+			.await;
+
+		let telegram = Telegram {
+			bot: Bot::new("token")
+				.set_api_url(server.uri().parse().unwrap())
+				.throttle(Limits::default()),
+			chat: Chat::Username("channel".to_owned()),
+			link_location: LinkLocation::default(),
+			options: Options {
+				message_thread_id: Some(7),
+				disable_notification: true,
+				protect_content: true,
+				link_preview: false,
+				buttons: vec![
+					Button {
+						text: "Open {title}".to_owned(),
+						url: "{link}".to_owned(),
+					},
+					// skipped since there's no entry id
+					Button {
+						text: "Entry".to_owned(),
+						url: "{entry_id}".to_owned(),
+					},
+				],
+			},
+			chat_id: OnceCell::new(),
+		};
+
+		let message = Message {
+			title: Some("Title".to_owned()),
+			link: Some("https://example.com/1".parse().unwrap()),
+			..Default::default()
+		};
+
+		// the username is resolved only once
+		for _ in 0..2 {
+			let msg_id = telegram.send(&message, None, None).await.unwrap();
+			assert_eq!(msg_id, Some(MessageId::Int(42)));
+		}
+	}
+
+	#[tokio::test]
+	async fn edit_captioned_message() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmediagroup$"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"ok": true,
+				"result": [{
+					"message_id": 42,
+					"date": 0,
+					"chat": { "id": 1, "type": "private", "first_name": "Test" },
+					"photo": [],
+				}],
+			})))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		// the text that didn't fit into the caption
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmessage$"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"ok": true,
+				"result": {
+					"message_id": 43,
+					"date": 0,
+					"chat": { "id": 1, "type": "private", "first_name": "Test" },
+					"text": "Body",
+				},
+			})))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/editmessagecaption$"))
+			.and(body_partial_json(
+				json!({ "message_id": 42, "caption": "Edited" }),
+			))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"ok": true,
+				"result": {
+					"message_id": 42,
+					"date": 0,
+					"chat": { "id": 1, "type": "private", "first_name": "Test" },
+					"text": "Edited",
+				},
+			})))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let telegram = Telegram {
+			bot: Bot::new("token")
+				.set_api_url(server.uri().parse().unwrap())
+				.throttle(Limits::default()),
+			chat: Chat::Id(1),
+			link_location: LinkLocation::default(),
+			options: Options::default(),
+			chat_id: OnceCell::new(),
+		};
+
+		let mut message = Message {
+			body: Some("a".repeat(MAX_MEDIA_MSG_LEN + 1)),
+			media: Some(vec![Media::Photo(
+				"https://example.com/1.png".parse().unwrap(),
+			)]),
+			..Default::default()
+		};
+
+		let msg_id = telegram.send(&message, None, None).await.unwrap().unwrap();
+		assert_eq!(msg_id, MessageId::Int(42));
+
+		message.body = Some("Edited".to_owned());
+		telegram.edit(&message, &msg_id, None).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn edit_media_message_sent_as_text() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/editmessagecaption$"))
+			.respond_with(ResponseTemplate::new(400).set_body_json(json!({
+				"ok": false,
+				"error_code": 400,
+				"description": "Bad Request: there is no caption in the message to edit",
+			})))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/editmessagetext$"))
+			.and(body_partial_json(
+				json!({ "message_id": 42, "text": "Edited" }),
+			))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"ok": true,
+				"result": {
+					"message_id": 42,
+					"date": 0,
+					"chat": { "id": 1, "type": "private", "first_name": "Test" },
+					"text": "Edited",
+				},
+			})))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		// editing shouldn't fall back to sending a reply
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/sendmessage$"))
+			.respond_with(ResponseTemplate::new(500))
+			.expect(0)
+			.mount(&server)
+			.await;
+
+		let telegram = Telegram {
+			bot: Bot::new("token")
+				.set_api_url(server.uri().parse().unwrap())
+				.throttle(Limits::default()),
+			chat: Chat::Id(1),
+			link_location: LinkLocation::default(),
+			options: Options::default(),
+			chat_id: OnceCell::new(),
+		};
+
+		// e.g. if Telegram couldn't get the media and only the text was sent
+		let message = Message {
+			body: Some("Edited".to_owned()),
+			media: Some(vec![Media::Photo(
+				"https://example.com/1.png".parse().unwrap(),
+			)]),
+			..Default::default()
+		};
+
+		telegram
+			.edit(&message, &MessageId::Int(42), None)
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn edit_keeps_entry_id_buttons() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.and(path_regex("(?i)/editmessagetext$"))
+			.and(body_partial_json(json!({
+				"message_id": 42,
+				"text": "Edited",
+				"reply_markup": {
+					"inline_keyboard": [[
+						{ "text": "Entry", "url": "https://example.com/entry/1" },
+					]],
+				},
+			})))
+			.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+				"ok": true,
+				"result": {
+					"message_id": 42,
+					"date": 0,
+					"chat": { "id": 1, "type": "private", "first_name": "Test" },
+					"text": "Edited",
+				},
+			})))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let telegram = Telegram {
+			bot: Bot::new("token")
+				.set_api_url(server.uri().parse().unwrap())
+				.throttle(Limits::default()),
+			chat: Chat::Id(1),
+			link_location: LinkLocation::default(),
+			options: Options {
+				buttons: vec![Button {
+					text: "Entry".to_owned(),
+					url: "https://example.com/entry/{entry_id}".to_owned(),
+				}],
+				..Default::default()
+			},
+			chat_id: OnceCell::new(),
+		};
+
+		let message = Message {
+			body: Some("Edited".to_owned()),
+			..Default::default()
+		};
+
+		telegram
+			.edit_entry(
+				&message,
+				Some(&EntryId("1".to_owned())),
+				&MessageId::Int(42),
+				None,
+			)
+			.await
+			.unwrap();
+	}
+}
@@ -0,0 +1,459 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`Webhook`] sink that sends messages to an HTTP endpoint as JSON
+
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use reqwest::{
+	Client, StatusCode,
+	header::{HeaderMap, HeaderName, HeaderValue, InvalidHeaderName, InvalidHeaderValue},
+};
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::time::sleep;
+use url::Url;
+
+use super::{
+	Sink,
+	error::SinkError,
+	message::{Media, Message, MessageId},
+};
+use crate::entry::EntryId;
+
+// the delay before the first retry. It's doubled after every failed attempt up to MAX_RETRY_DELAY
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_mins(5);
+
+// a webhook that doesn't respond in time is treated like any other failed request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Matches all placeholders that can be used in a body template
+static PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| {
+	Regex::new(r"\{(title|body|link|media|tag|entry_id)\}")
+		.expect("placeholder regex should always be valid")
+});
+
+/// Webhook sink. Sends a JSON body built from a template to an HTTP endpoint
+///
+/// The body template is an arbitrary JSON value where all strings can contain these placeholders:
+/// `{title}`, `{body}`, `{link}`, `{media}`, `{tag}`, and `{entry_id}`.
+/// If a string consists of a single placeholder, it is replaced with the value itself,
+/// i.e. a JSON string, an array of media URLs for `{media}`, or `null` if the value is missing.
+/// Otherwise the placeholders are interpolated into the string, with missing values left empty and media URLs separated by newlines
+#[derive(Debug)]
+pub struct Webhook {
+	client: Client,
+	url: Url,
+	method: Method,
+	headers: HeaderMap,
+	body: Value,
+	retries: u32,
+}
+
+/// HTTP method used to send the request
+#[derive(Clone, Copy, Default, Debug)]
+pub enum Method {
+	/// POST request
+	#[default]
+	Post,
+
+	/// PUT request
+	Put,
+}
+
+#[expect(missing_docs, reason = "error message is self-documenting")]
+#[derive(thiserror::Error, Debug)]
+pub enum WebhookError {
+	#[error("Invalid header name {1:?}")]
+	InvalidHeaderName(#[source] InvalidHeaderName, String),
+
+	#[error("Invalid value of header {1:?}")]
+	InvalidHeaderValue(#[source] InvalidHeaderValue, String),
+
+	#[error("Can't send a request to the webhook")]
+	Http(#[from] reqwest::Error),
+
+	#[error("Webhook returned an error ({status}): {body:?}")]
+	BadStatus { status: StatusCode, body: String },
+}
+
+impl Webhook {
+	/// Creates a new [`Webhook`] sink that sends a request with `method` to `url` with additional `headers`.
+	/// The body is built from the `body` template, or contains all message fields if it's `None`. See [`Webhook`] for details.
+	/// Requests that failed with a server error (5xx) are retried up to `retries` times
+	///
+	/// # Errors
+	/// * if any of the `headers` is not a valid HTTP header
+	/// * if the HTTP client couldn't be set up
+	pub fn new(
+		url: Url,
+		method: Method,
+		headers: impl IntoIterator<Item = (String, String)>,
+		body: Option<Value>,
+		retries: u32,
+	) -> Result<Self, WebhookError> {
+		let headers = headers
+			.into_iter()
+			.map(|(name, value)| {
+				let header_name = HeaderName::try_from(name.as_str())
+					.map_err(|e| WebhookError::InvalidHeaderName(e, name.clone()))?;
+				let header_value = HeaderValue::try_from(value)
+					.map_err(|e| WebhookError::InvalidHeaderValue(e, name))?;
+
+				Ok((header_name, header_value))
+			})
+			.collect::<Result<HeaderMap, WebhookError>>()?;
+
+		let body = body.unwrap_or_else(|| {
+			json!({
+				"title": "{title}",
+				"body": "{body}",
+				"link": "{link}",
+				"media": "{media}",
+				"tag": "{tag}",
+				"entry_id": "{entry_id}",
+			})
+		});
+
+		Ok(Self {
+			client: Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+			url,
+			method,
+			headers,
+			body,
+			retries,
+		})
+	}
+}
+
+#[async_trait]
+impl Sink for Webhook {
+	/// Sends the message to the webhook without an entry id
+	///
+	/// # Errors
+	/// * if the request couldn't be sent
+	/// * if the webhook responded with a non-2xx status code
+	async fn send(
+		&self,
+		message: &Message,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		self.send_entry(message, None, reply_to, tag).await
+	}
+
+	/// Sends the message to the webhook. The `reply_to` parameter is ignored
+	///
+	/// # Errors
+	/// * if the request couldn't be sent
+	/// * if the webhook responded with a non-2xx status code
+	#[tracing::instrument(level = "debug", skip(self, message))]
+	async fn send_entry(
+		&self,
+		message: &Message,
+		entry_id: Option<&EntryId>,
+		_reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		let body = render(&self.body, &|name| {
+			placeholder_value(name, message, entry_id, tag)
+		});
+
+		self.send_body(&body)
+			.await
+			.map_err(|e| SinkError::Webhook {
+				source: e,
+				msg: Box::new(body),
+			})?;
+
+		Ok(None)
+	}
+}
+
+impl Webhook {
+	async fn send_body(&self, body: &Value) -> Result<(), WebhookError> {
+		let mut retries_left = self.retries;
+		let mut retry_delay = RETRY_DELAY;
+
+		loop {
+			tracing::info!("Sending a request to the webhook");
+			tracing::trace!("Request body: {body:?}");
+
+			let request = match self.method {
+				Method::Post => self.client.post(self.url.as_str()),
+				Method::Put => self.client.put(self.url.as_str()),
+			};
+
+			let response = request
+				.headers(self.headers.clone())
+				.json(body)
+				.send()
+				.await?;
+
+			let status = response.status();
+			if status.is_success() {
+				return Ok(());
+			}
+
+			if status.is_server_error() && retries_left > 0 {
+				tracing::warn!(
+					"Webhook returned {status}. Retrying in {}s",
+					retry_delay.as_secs()
+				);
+
+				sleep(retry_delay).await;
+				retries_left -= 1;
+				retry_delay = next_retry_delay(retry_delay);
+
+				continue;
+			}
+
+			return Err(WebhookError::BadStatus {
+				status,
+				body: response.text().await.unwrap_or_default(),
+			});
+		}
+	}
+}
+
+fn next_retry_delay(delay: Duration) -> Duration {
+	delay.saturating_mul(2).min(MAX_RETRY_DELAY)
+}
+
+fn placeholder_value(
+	name: &str,
+	message: &Message,
+	entry_id: Option<&EntryId>,
+	tag: Option<&str>,
+) -> Value {
+	let string_or_null = |s: Option<&str>| s.map_or(Value::Null, |s| Value::String(s.to_owned()));
+
+	match name {
+		"title" => string_or_null(message.title.as_deref()),
+		"body" => string_or_null(message.body.as_deref()),
+		"link" => string_or_null(message.link.as_ref().map(Url::as_str)),
+		"media" => Value::Array(
+			message
+				.media
+				.iter()
+				.flatten()
+				.filter_map(Media::url)
+				.map(|url| Value::String(url.to_string()))
+				.collect(),
+		),
+		"tag" => string_or_null(tag),
+		"entry_id" => string_or_null(entry_id.map(|id| id.0.as_str())),
+		_ => unreachable!("all placeholders matched by the regex should be handled"),
+	}
+}
+
+/// Replace all placeholders in the `template` with values provided by `value_of`
+fn render(template: &Value, value_of: &impl Fn(&str) -> Value) -> Value {
+	match template {
+		Value::String(s) => render_str(s, value_of),
+		Value::Array(arr) => Value::Array(arr.iter().map(|v| render(v, value_of)).collect()),
+		Value::Object(obj) => Value::Object(
+			obj.iter()
+				.map(|(k, v)| (k.clone(), render(v, value_of)))
+				.collect(),
+		),
+		other => other.clone(),
+	}
+}
+
+fn render_str(s: &str, value_of: &impl Fn(&str) -> Value) -> Value {
+	// keep the JSON type of the value if the string is just a single placeholder
+	if let Some(caps) = PLACEHOLDER_RE.captures(s)
+		&& caps[0].len() == s.len()
+	{
+		return value_of(&caps[1]);
+	}
+
+	let rendered = PLACEHOLDER_RE.replace_all(s, |caps: &Captures<'_>| match value_of(&caps[1]) {
+		Value::String(s) => s,
+		Value::Array(arr) => arr
+			.iter()
+			.filter_map(Value::as_str)
+			.collect::<Vec<_>>()
+			.join("\n"),
+		_ => String::new(),
+	});
+
+	Value::String(rendered.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	use wiremock::{
+		Mock, MockServer, ResponseTemplate,
+		matchers::{body_json, header, method, path},
+	};
+
+	fn message() -> Message {
+		Message {
+			title: Some("Title".to_owned()),
+			body: Some("Body".to_owned()),
+			link: Some("https://example.com/post".parse().unwrap()),
+			media: Some(vec![
+				Media::Photo("https://example.com/1.png".parse().unwrap()),
+				Media::Video("https://example.com/2.mp4".parse().unwrap()),
+			]),
+		}
+	}
+
+	#[tokio::test]
+	async fn default_body() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.and(path("/hook"))
+			.and(body_json(json!({
+				"title": "Title",
+				"body": "Body",
+				"link": "https://example.com/post",
+				"media": ["https://example.com/1.png", "https://example.com/2.mp4"],
+				"tag": "tag",
+				"entry_id": "entry",
+			})))
+			.respond_with(ResponseTemplate::new(200))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let webhook = Webhook::new(
+			format!("{}/hook", server.uri()).parse().unwrap(),
+			Method::Post,
+			[],
+			None,
+			0,
+		)
+		.unwrap();
+
+		webhook
+			.send_entry(
+				&message(),
+				Some(&EntryId("entry".to_owned())),
+				None,
+				Some("tag"),
+			)
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn templated_body_with_headers() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("PUT"))
+			.and(header("authorization", "Bearer secret"))
+			.and(body_json(json!({
+				"text": "*Title*\nBody\nhttps://example.com/post",
+				"attachments": [{ "text": "media: https://example.com/1.png\nhttps://example.com/2.mp4" }],
+				"tag": null,
+				"unknown": "{unknown}",
+				"number": 5,
+			})))
+			.respond_with(ResponseTemplate::new(204))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let webhook = Webhook::new(
+			server.uri().parse().unwrap(),
+			Method::Put,
+			[("Authorization".to_owned(), "Bearer secret".to_owned())],
+			Some(json!({
+				"text": "*{title}*\n{body}\n{link}",
+				"attachments": [{ "text": "media: {media}" }],
+				"tag": "{tag}",
+				"unknown": "{unknown}",
+				"number": 5,
+			})),
+			0,
+		)
+		.unwrap();
+
+		webhook.send(&message(), None, None).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn retry_on_server_error() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.respond_with(ResponseTemplate::new(503))
+			.up_to_n_times(1)
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("POST"))
+			.respond_with(ResponseTemplate::new(200))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let webhook =
+			Webhook::new(server.uri().parse().unwrap(), Method::Post, [], None, 1).unwrap();
+
+		webhook.send(&message(), None, None).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn client_error_is_not_retried() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("POST"))
+			.respond_with(ResponseTemplate::new(400).set_body_string("bad payload"))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let webhook =
+			Webhook::new(server.uri().parse().unwrap(), Method::Post, [], None, 3).unwrap();
+
+		let err = webhook.send(&message(), None, None).await.unwrap_err();
+		assert!(matches!(
+			err,
+			SinkError::Webhook {
+				source: WebhookError::BadStatus {
+					status: StatusCode::BAD_REQUEST,
+					ref body,
+				},
+				..
+			} if body == "bad payload"
+		));
+	}
+
+	#[test]
+	fn retry_delay_is_capped() {
+		let mut delay = RETRY_DELAY;
+		for _ in 0..100 {
+			delay = next_retry_delay(delay);
+		}
+
+		assert_eq!(delay, MAX_RETRY_DELAY);
+	}
+
+	#[test]
+	fn invalid_header() {
+		let res = Webhook::new(
+			"https://example.com".parse().unwrap(),
+			Method::Post,
+			[("bad header".to_owned(), "value".to_owned())],
+			None,
+			0,
+		);
+
+		assert!(matches!(res, Err(WebhookError::InvalidHeaderName(..))));
+	}
+}
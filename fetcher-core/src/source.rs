@@ -26,6 +26,7 @@ use crate::{
 };
 
 use async_trait::async_trait;
+use futures::future::select_all;
 use std::fmt::Debug;
 
 /// A trait that defines a way to fetch entries as well as mark them as read afterwards
@@ -36,6 +37,36 @@ pub trait Source: Fetch + MarkAsRead + Debug + Send + Sync {}
 pub trait Fetch: Debug + Send + Sync {
 	/// Fetch all available entries from the source
 	async fn fetch(&mut self) -> Result<Vec<Entry>, SourceError>;
+
+	/// Save the state of the last fetch to the cache, if the source has one.
+	/// This is called only after all fetched entries have been processed successfully,
+	/// to make sure entries that have failed to be sent are fetched again next time
+	///
+	/// # Errors
+	/// if the cache couldn't be saved externally
+	async fn save_cache(&mut self) -> Result<(), FetcherError> {
+		Ok(())
+	}
+
+	/// Stop using caches that make the source skip fetching entries that haven't changed since the last time,
+	/// e.g. when all entries should be fetched regardless of whether they have already been read
+	fn disable_cache(&mut self) {}
+
+	/// Whether the last fetch has returned all entries that are currently available from the source.
+	/// It hasn't if some of them have been skipped, e.g. because the page they are on hasn't changed since the last fetch
+	/// or because no more pages have been fetched after an already read one.
+	///
+	/// Used to tell entries that have been removed from the source apart from the ones that just haven't been fetched this time.
+	/// Defaults to `true`
+	fn fetched_everything(&self) -> bool {
+		true
+	}
+
+	/// Wait until the source gets notified that new entries are available, e.g. via IMAP IDLE.
+	/// Sources that can't be notified never return, which is the default, and are fetched only when the job is refreshed
+	async fn wait_for_new_entries(&mut self) {
+		std::future::pending::<()>().await;
+	}
 }
 
 /// A wrapper around a [`Fetch`] that uses an external way to filter read entries,
@@ -62,6 +93,22 @@ where
 	async fn fetch(&mut self) -> Result<Vec<Entry>, SourceError> {
 		self.source.fetch().await
 	}
+
+	async fn save_cache(&mut self) -> Result<(), FetcherError> {
+		self.source.save_cache().await
+	}
+
+	fn disable_cache(&mut self) {
+		self.source.disable_cache();
+	}
+
+	fn fetched_everything(&self) -> bool {
+		self.source.fetched_everything()
+	}
+
+	async fn wait_for_new_entries(&mut self) {
+		self.source.wait_for_new_entries().await;
+	}
 }
 
 #[async_trait]
@@ -116,4 +163,30 @@ where
 
 		Ok(entries)
 	}
+
+	async fn save_cache(&mut self) -> Result<(), FetcherError> {
+		for fetch in self {
+			fetch.save_cache().await?;
+		}
+
+		Ok(())
+	}
+
+	fn disable_cache(&mut self) {
+		for fetch in self {
+			fetch.disable_cache();
+		}
+	}
+
+	fn fetched_everything(&self) -> bool {
+		self.iter().all(Fetch::fetched_everything)
+	}
+
+	async fn wait_for_new_entries(&mut self) {
+		if self.is_empty() {
+			return std::future::pending().await;
+		}
+
+		select_all(self.iter_mut().map(Fetch::wait_for_new_entries)).await;
+	}
 }
@@ -6,50 +6,94 @@
 
 //! A email source that uses IMAP to connect to an email server
 //!
-//! This module includes the [`Email`] source, the [`ViewMode`] enum, and the [`Filters`] struct
+//! This module includes the [`Email`] source, the [`ViewMode`] and [`Security`] enums, and the [`Filters`] struct
 
 mod auth;
 mod filters;
+mod parse;
+mod security;
 mod view_mode;
 
 pub use auth::Auth;
-pub use filters::Filters;
-use imap::TlsKind;
+pub use filters::{DateBound, Filters};
+pub use security::Security;
 pub use view_mode::ViewMode;
 
-use self::auth::GoogleAuthExt;
+use self::auth::{GoogleAuthExt, ImapOAuth2};
 use super::{Fetch, MarkAsRead, Source};
 use crate::{
 	auth::Google as GoogleAuth,
 	auth::google::GoogleOAuth2Error as GoogleAuthError,
 	entry::{Entry, EntryId},
 	error::FetcherError,
-	sink::message::Message,
 	source::error::SourceError,
 };
 
 use async_trait::async_trait;
-use mailparse::ParsedMail;
-use std::fmt::{Debug, Write as _};
+use imap::{ConnectionMode, TlsKind, extensions::idle};
+use std::{
+	fmt::Debug,
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::Duration,
+};
+use tokio::task::{JoinError, JoinHandle};
+
+/// How long to IDLE for before checking if the IDLE should be stopped and re-issuing it.
+/// This is also how long a fetch might have to wait for the IDLE to give its session back
+const IDLE_INTERVAL: Duration = if cfg!(test) {
+	Duration::from_millis(500)
+} else {
+	Duration::from_secs(10)
+};
 
-const IMAP_PORT: u16 = 993;
+type Session = imap::Session<imap::Connection>;
 
 /// Email source. Fetches an email's subject and body fields using IMAP
+///
+/// The IMAP connection is kept open between fetches and is only re-established if it has been closed.
+/// All IMAP operations run on a blocking thread to avoid stalling the async runtime
 pub struct Email {
-	/// IMAP server URL
+	/// IMAP server address
 	pub imap: String,
 
+	/// IMAP server port. Depends on [`Email::security`] if not set
+	pub port: Option<u16>,
+
+	/// How to secure the connection to the IMAP server
+	pub security: Security,
+
 	/// Email address/IMAP login
 	pub email: String,
 
 	/// Authentication type
 	pub auth: Auth,
 
+	/// The mailbox to fetch emails from, e.g. `INBOX`. Gmail exposes its labels as mailboxes, too
+	pub mailbox: String,
+
 	/// IMAP search filters
 	pub filters: Filters,
 
 	/// IMAP view mode, e.g. read only
 	pub view_mode: ViewMode,
+
+	/// Use IMAP IDLE to get notified about new email as soon as it arrives instead of waiting for the next refresh of the job
+	pub idle: bool,
+
+	// the session isn't Sync but is only ever accessed via &mut self, so the mutex is never actually locked
+	session: Mutex<Option<Session>>,
+	idle_task: Option<IdleTask>,
+}
+
+/// An IDLE command running on a blocking thread
+struct IdleTask {
+	task: JoinHandle<(Session, Result<(), imap::Error>)>,
+
+	/// Tells the task to stop idling and give the session back the next time it wakes up
+	stop: Arc<AtomicBool>,
 }
 
 #[expect(missing_docs, reason = "error message is self-documenting")]
@@ -75,59 +119,16 @@ pub enum ImapError {
 	#[error("Authentication error")]
 	Auth(#[source] imap::Error),
 
+	#[error("IMAP thread has panicked")]
+	Panicked(#[source] JoinError),
+
 	#[error(transparent)]
 	Other(#[from] imap::Error),
 }
 
-// I'd make that a function but the imap crate didn't want to agree with me
-macro_rules! authenticate {
-	($login:expr, $auth:expr, $client:expr) => {{
-		let auth = $auth;
-
-		match auth {
-			Auth::GmailOAuth2(auth) => {
-				tracing::trace!("Logging in to IMAP with Google OAuth2");
-
-				let session = $client.authenticate(
-					"XOAUTH2",
-					&auth
-						.as_imap_oauth2($login)
-						.await
-						.map_err(ImapError::GoogleOAuth2)?,
-				);
-
-				match session {
-					Ok(session) => session,
-					// refresh access token and retry
-					Err((e, client)) => {
-						tracing::error!("Denied access to IMAP via OAuth2: {e}");
-						tracing::info!("Refreshing OAuth2 access token and trying again");
-
-						auth.get_new_access_token()
-							.await
-							.map_err(ImapError::GoogleOAuth2)?;
-
-						client
-							.authenticate(
-								"XOAUTH2",
-								&auth
-									.as_imap_oauth2($login)
-									.await
-									.map_err(ImapError::GoogleOAuth2)?,
-							)
-							.map_err(|(e, _)| ImapError::Auth(e))?
-					}
-				}
-			}
-			Auth::Password(password) => {
-				tracing::warn!("Logging in to IMAP with a password, this is insecure");
-
-				$client
-					.login($login, password)
-					.map_err(|(e, _)| ImapError::Auth(e))?
-			}
-		}
-	}};
+enum Credentials {
+	OAuth2(ImapOAuth2),
+	Password(String),
 }
 
 impl Email {
@@ -139,42 +140,84 @@ impl Email {
 		filters: Filters,
 		view_mode: ViewMode,
 	) -> Self {
-		Self {
-			imap: "imap.gmail.com".to_owned(),
+		Self::new(
+			"imap.gmail.com".to_owned(),
+			None,
+			Security::Tls,
 			email,
-			auth: Auth::GmailOAuth2(auth),
+			Auth::GmailOAuth2(auth),
 			filters,
 			view_mode,
-		}
+		)
 	}
 
 	/// Creates an [`Email`] source that uses a password to authenticate via IMAP
 	#[must_use]
-	pub const fn new_generic(
+	pub fn new_generic(
 		imap: String,
+		port: Option<u16>,
+		security: Security,
 		email: String,
 		password: String,
 		filters: Filters,
 		view_mode: ViewMode,
+	) -> Self {
+		Self::new(
+			imap,
+			port,
+			security,
+			email,
+			Auth::Password(password),
+			filters,
+			view_mode,
+		)
+	}
+
+	fn new(
+		imap: String,
+		port: Option<u16>,
+		security: Security,
+		email: String,
+		auth: Auth,
+		filters: Filters,
+		view_mode: ViewMode,
 	) -> Self {
 		Self {
 			imap,
+			port,
+			security,
 			email,
-			auth: Auth::Password(password),
+			auth,
+			mailbox: "INBOX".to_owned(),
 			filters,
 			view_mode,
+			idle: false,
+			session: Mutex::new(None),
+			idle_task: None,
 		}
 	}
 }
 
 #[async_trait]
 impl Fetch for Email {
-	/// Even though it's marked async, the fetching itself is not async yet
-	/// It should be used with spawn_blocking probs
-	/// TODO: make it async lol
 	async fn fetch(&mut self) -> Result<Vec<Entry>, SourceError> {
 		self.fetch_impl().await.map_err(Into::into)
 	}
+
+	/// Waits until new email arrives using IMAP IDLE, if enabled
+	async fn wait_for_new_entries(&mut self) {
+		if !self.idle {
+			return std::future::pending().await;
+		}
+
+		if let Err(e) = self.idle_impl().await {
+			tracing::warn!(
+				"Error waiting for new email with IMAP IDLE, checking for it on the next refresh instead: {e}"
+			);
+
+			std::future::pending::<()>().await;
+		}
+	}
 }
 
 #[async_trait]
@@ -195,157 +238,495 @@ impl Source for Email {}
 impl Email {
 	async fn fetch_impl(&mut self) -> Result<Vec<Entry>, EmailError> {
 		tracing::debug!("Fetching emails");
-		let client = imap::ClientBuilder::new(&self.imap, IMAP_PORT)
-			.tls_kind(TlsKind::Rust)
-			.connect()
-			.map_err(ImapError::ConnectionFailed)?;
 
-		let mut session = authenticate!(&self.email, &mut self.auth, client);
+		let search_query = self.filters.search_query();
+		let mailbox = self.mailbox.clone();
+
+		let mails = self
+			.with_session(move |session| {
+				session.examine(&mailbox)?;
+
+				tracing::trace!("Searching for emails in {mailbox} with {search_query:?}");
+				let mail_ids = session
+					.uid_search(&search_query)?
+					.into_iter()
+					.map(|x| x.to_string())
+					.collect::<Vec<_>>()
+					.join(",");
+
+				let unread_num = mail_ids.len();
+				if unread_num > 0 {
+					tracing::info!("Got {unread_num} unread filtered mails");
+				} else {
+					tracing::debug!(
+						"All email for the search query have already been read, none remaining to send"
+					);
+				}
 
-		session.examine("INBOX").map_err(ImapError::Other)?;
+				if mail_ids.is_empty() {
+					return Ok(Vec::new());
+				}
 
-		let search_string = {
-			let mut tmp = "UNSEEN ".to_owned();
+				let mails = session.uid_fetch(&mail_ids, "BODY[]")?;
+
+				mails
+					.iter()
+					.map(|x| {
+						let body = x
+							.body()
+							.expect("Body should always be present because we explicitly requested it")
+							.to_vec();
+
+						let uid =
+							x.uid.expect("UIDs should always be present because we used uid_fetch(). The server probably doesn't support them which isn't something ~we~ support for now").to_string();
+
+						// find the email this one is a reply to to be able to thread them
+						let reply_to = match parse::in_reply_to(&body) {
+							Some(message_id) => session
+								.uid_search(format!("HEADER Message-ID {}", filters::quote(&message_id)))?
+								.into_iter()
+								.min()
+								.map(|uid| uid.to_string()),
+							None => None,
+						};
+
+						Ok((body, uid, reply_to))
+					})
+					.collect::<Result<Vec<_>, imap::Error>>()
+			})
+			.await?;
 
-			if let Some(sender) = &self.filters.sender {
-				_ = write!(tmp, r#"FROM "{sender}" "#);
-			}
+		mails
+			.iter()
+			.map(|(body, uid, reply_to)| {
+				let mut entry = parse::parse(&mailparse::parse_mail(body)?, uid.clone())?;
+				entry.reply_to = reply_to.clone().map(Into::into);
 
-			if let Some(subjects) = &self.filters.subjects {
-				for s in subjects {
-					_ = write!(tmp, r#"SUBJECT "{s}" "#);
-				}
-			}
+				Ok(entry)
+			})
+			.collect::<Result<Vec<Entry>, EmailError>>()
+	}
 
-			if let Some(ex_subjects) = &self.filters.exclude_subjects {
-				for exs in ex_subjects {
-					_ = write!(tmp, r#"NOT SUBJECT "{exs}" "#);
-				}
+	async fn mark_as_read_impl(&mut self, id: &str) -> Result<(), ImapError> {
+		if let ViewMode::ReadOnly = self.view_mode {
+			return Ok(());
+		}
+
+		let delete = matches!(self.view_mode, ViewMode::Delete);
+		let id = id.to_owned();
+		let mailbox = self.mailbox.clone();
+
+		self.with_session(move |session| {
+			session.select(&mailbox)?;
+
+			if delete {
+				session.uid_store(&id, "+FLAGS.SILENT (\\Deleted)")?;
+				session.uid_expunge(&id)?;
+				tracing::debug!("Deleted email uid {id}");
+			} else {
+				session.uid_store(&id, "+FLAGS.SILENT (\\Seen)")?;
+				tracing::debug!("Marked email uid {id} as read");
 			}
 
-			tmp.trim_end().to_owned()
-		};
+			Ok(())
+		})
+		.await
+	}
 
-		let mail_ids = session
-			.uid_search(&search_string)
-			.map_err(ImapError::Other)?
-			.into_iter()
-			.map(|x| x.to_string())
-			.collect::<Vec<_>>()
-			.join(",");
-
-		let unread_num = mail_ids.len();
-		if unread_num > 0 {
-			tracing::info!("Got {unread_num} unread filtered mails");
-		} else {
-			tracing::debug!(
-				"All email for the search query have already been read, none remaining to send"
-			);
-		}
+	async fn idle_impl(&mut self) -> Result<(), ImapError> {
+		if self.idle_task.is_none() {
+			let mut session = self.take_session().await?;
+			let stop = Arc::new(AtomicBool::new(false));
+			let mailbox = self.mailbox.clone();
 
-		if mail_ids.is_empty() {
-			return Ok(Vec::new());
+			let task = tokio::task::spawn_blocking({
+				let stop = Arc::clone(&stop);
+
+				move || {
+					let res = idle_blocking(&mut session, &mailbox, &stop);
+					(session, res)
+				}
+			});
+
+			self.idle_task = Some(IdleTask { task, stop });
 		}
 
-		let mails = session
-			.uid_fetch(&mail_ids, "BODY[]")
-			.map_err(ImapError::Other)?;
-		session.logout().map_err(ImapError::Other)?;
+		let idle_task = self
+			.idle_task
+			.as_mut()
+			.expect("idle task should've just been started if it wasn't already running");
 
-		mails
-			.iter()
-			.map(|x| {
-				let body = x
-					.body()
-					.expect("Body should always be present because we explicitly requested it");
+		// if this future gets dropped, e.g. because the refresh time of the job has come first,
+		// the task is kept around and is dealt with when the session is needed next time
+		let res = (&mut idle_task.task).await;
+		self.idle_task = None;
 
-let uid =
-					x.uid.expect("UIDs should always be present because we used uid_fetch(). The server probably doesn't support them which isn't something ~we~ support for now").to_string();
+		let (session, res) = res.map_err(ImapError::Panicked)?;
+		res?;
 
-				parse(
-					&mailparse::parse_mail(body)?,
-					uid,
-				)
-			})
-			.collect::<Result<Vec<Entry>, EmailError>>()
+		tracing::debug!("Got notified about new email via IMAP IDLE");
+		*self.session_mut() = Some(session);
+
+		Ok(())
 	}
 
-	async fn mark_as_read_impl(&mut self, id: &str) -> Result<(), ImapError> {
-		if let ViewMode::ReadOnly = self.view_mode {
-			return Ok(());
+	/// Runs `f` with the IMAP session on a blocking thread, reconnecting and retrying once if the existing session has been disconnected
+	async fn with_session<T, F>(&mut self, f: F) -> Result<T, ImapError>
+	where
+		F: Fn(&mut Session) -> Result<T, imap::Error> + Clone + Send + 'static,
+		T: Send + 'static,
+	{
+		let is_reused = self.session_mut().is_some() || self.idle_task.is_some();
+		let session = self.take_session().await?;
+
+		match run_blocking(session, f.clone()).await? {
+			(session, Ok(res)) => {
+				*self.session_mut() = Some(session);
+				Ok(res)
+			}
+			(_, Err(imap::Error::Io(_) | imap::Error::ConnectionLost)) if is_reused => {
+				tracing::debug!("IMAP connection has been closed, reconnecting");
+
+				let session = self.connect().await?;
+				let (session, res) = run_blocking(session, f).await?;
+				*self.session_mut() = Some(session);
+
+				Ok(res?)
+			}
+			// the session might be in an inconsistent state after an error, drop it to reconnect next time
+			(_, Err(e)) => Err(e.into()),
 		}
+	}
 
-		let client = imap::ClientBuilder::new(&self.imap, IMAP_PORT)
-			.tls_kind(TlsKind::Rust)
-			.connect()
-			.map_err(ImapError::ConnectionFailed)?;
+	/// Takes the existing session, stopping the IDLE that uses it if there is one, or opens a new one if there isn't one
+	async fn take_session(&mut self) -> Result<Session, ImapError> {
+		if let Some(idle_task) = self.idle_task.take() {
+			// the task notices it should stop at most after IDLE_INTERVAL
+			idle_task.stop.store(true, Ordering::Relaxed);
 
-		let mut session = authenticate!(&self.email, &mut self.auth, client);
+			match idle_task.task.await.map_err(ImapError::Panicked)? {
+				(session, Ok(())) => return Ok(session),
+				(_, Err(e)) => {
+					tracing::debug!("IMAP IDLE has failed, reconnecting: {e}");
+				}
+			}
+		}
 
-		session.select("INBOX")?;
+		if let Some(session) = self.session_mut().take() {
+			return Ok(session);
+		}
 
-		match self.view_mode {
-			ViewMode::MarkAsRead => {
-				session.uid_store(id, "+FLAGS.SILENT (\\Seen)")?;
-				tracing::debug!("Marked email uid {id} as read");
+		self.connect().await
+	}
+
+	fn session_mut(&mut self) -> &mut Option<Session> {
+		self.session
+			.get_mut()
+			.unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+
+	async fn connect(&mut self) -> Result<Session, ImapError> {
+		let credentials = match &mut self.auth {
+			Auth::GmailOAuth2(auth) => {
+				tracing::trace!("Logging in to IMAP with Google OAuth2");
+				Credentials::OAuth2(auth.as_imap_oauth2(&self.email).await?)
 			}
-			ViewMode::Delete => {
-				session.uid_store(id, "+FLAGS.SILENT (\\Deleted)")?;
-				session.uid_expunge(id)?;
-				tracing::debug!("Deleted email uid {id}");
+			Auth::Password(password) => {
+				tracing::warn!("Logging in to IMAP with a password, this is insecure");
+				Credentials::Password(password.clone())
 			}
-			ViewMode::ReadOnly => unreachable!(),
 		};
 
-		session.logout()?;
+		match self.connect_with(credentials).await {
+			Err(ImapError::Auth(e)) if let Auth::GmailOAuth2(auth) = &mut self.auth => {
+				// refresh access token and retry
+				tracing::error!("Denied access to IMAP via OAuth2: {e}");
+				tracing::info!("Refreshing OAuth2 access token and trying again");
 
-		Ok(())
+				auth.get_new_access_token().await?;
+				let credentials = Credentials::OAuth2(auth.as_imap_oauth2(&self.email).await?);
+
+				self.connect_with(credentials).await
+			}
+			res => res,
+		}
 	}
+
+	async fn connect_with(&self, credentials: Credentials) -> Result<Session, ImapError> {
+		let host = self.imap.clone();
+		let port = self.port.unwrap_or_else(|| self.security.default_port());
+		let mode = match self.security {
+			Security::Tls => ConnectionMode::Tls,
+			Security::StartTls => ConnectionMode::StartTls,
+			Security::None => ConnectionMode::Plaintext,
+		};
+		let login = self.email.clone();
+
+		tracing::debug!("Connecting to IMAP server {host}:{port}");
+		tokio::task::spawn_blocking(move || {
+			let client = imap::ClientBuilder::new(&host, port)
+				.mode(mode)
+				.tls_kind(TlsKind::Rust)
+				.connect()
+				.map_err(ImapError::ConnectionFailed)?;
+
+			match credentials {
+				Credentials::OAuth2(auth) => client
+					.authenticate("XOAUTH2", &auth)
+					.map_err(|(e, _)| ImapError::Auth(e)),
+				Credentials::Password(password) => client
+					.login(&login, &password)
+					.map_err(|(e, _)| ImapError::Auth(e)),
+			}
+		})
+		.await
+		.map_err(ImapError::Panicked)?
+	}
+}
+
+async fn run_blocking<T, F>(
+	mut session: Session,
+	f: F,
+) -> Result<(Session, Result<T, imap::Error>), ImapError>
+where
+	F: FnOnce(&mut Session) -> Result<T, imap::Error> + Send + 'static,
+	T: Send + 'static,
+{
+	tokio::task::spawn_blocking(move || {
+		let res = f(&mut session);
+		(session, res)
+	})
+	.await
+	.map_err(ImapError::Panicked)
 }
 
-fn parse(mail: &ParsedMail, id: String) -> Result<Entry, EmailError> {
-	let subject = mail.headers.iter().find_map(|x| {
-		if x.get_key_ref() == "Subject" {
-			Some(x.get_value())
-		} else {
-			None
+/// Blocks until the mailbox changes or `stop` is set, leaving the session ready to be used again
+fn idle_blocking(
+	session: &mut Session,
+	mailbox: &str,
+	stop: &AtomicBool,
+) -> Result<(), imap::Error> {
+	session.examine(mailbox)?;
+
+	loop {
+		tracing::trace!("Waiting for new email with IMAP IDLE");
+		let outcome = session
+			.idle()
+			.timeout(IDLE_INTERVAL)
+			.keepalive(false)
+			.wait_while(idle::stop_on_any)?;
+
+		if let idle::WaitOutcome::MailboxChanged = outcome {
+			return Ok(());
 		}
-	});
-
-	let body = {
-		if mail.subparts.is_empty() {
-			mail
-		} else {
-			mail.subparts
-				.iter()
-				.find(|x| x.ctype.mimetype == "text/plain")
-				.unwrap_or(&mail.subparts[0])
+
+		if stop.load(Ordering::Relaxed) {
+			tracing::trace!("Stopping an IMAP IDLE that's no longer needed");
+			return Ok(());
 		}
-		.get_body()?
-	};
+	}
+}
 
-	Ok(Entry {
-		id: Some(id.into()),
-		msg: Message {
-			title: subject,
-			body: Some(body),
-			..Default::default()
-		},
-		..Default::default()
-	})
+impl Drop for Email {
+	fn drop(&mut self) {
+		// otherwise the IDLE would be re-issued forever, keeping both the blocking thread and the connection alive.
+		// The connection is closed when the task drops the session
+		if let Some(idle_task) = &self.idle_task {
+			idle_task.stop.store(true, Ordering::Relaxed);
+		}
+	}
 }
 
 impl Debug for Email {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("Email")
 			.field("imap", &self.imap)
-			.field("auth_type", match self.auth {
-				Auth::Password(_) => &"password",
-				Auth::GmailOAuth2(_) => &"gmail_oauth2",
-			})
+			.field(
+				"auth_type",
+				match self.auth {
+					Auth::Password(_) => &"password",
+					Auth::GmailOAuth2(_) => &"gmail_oauth2",
+				},
+			)
 			.field("email", &self.email)
+			.field("port", &self.port)
+			.field("security", &self.security)
+			.field("mailbox", &self.mailbox)
 			.field("filters", &self.filters)
 			.field("view_mode", &self.view_mode)
-			.finish()
+			.field("idle", &self.idle)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	use std::{
+		io::{BufRead, BufReader, Write},
+		net::TcpListener,
+		sync::atomic::AtomicUsize,
+	};
+
+	const MAIL: &str = "Subject: Hello\r\n\r\nWorld\r\n";
+
+	struct ImapServer {
+		port: u16,
+		connections: Arc<AtomicUsize>,
+		closed: Arc<AtomicUsize>,
+	}
+
+	/// A tiny IMAP server that knows just enough to serve a single unread email and to notify about new email when IDLE'ing if `notify` is set
+	fn imap_server(notify: bool) -> ImapServer {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let connections = Arc::new(AtomicUsize::new(0));
+		let closed = Arc::new(AtomicUsize::new(0));
+
+		std::thread::spawn({
+			let connections = Arc::clone(&connections);
+			let closed = Arc::clone(&closed);
+
+			move || {
+				for stream in listener.incoming() {
+					connections.fetch_add(1, Ordering::Relaxed);
+
+					let closed = Arc::clone(&closed);
+					std::thread::spawn(move || {
+						serve(stream.unwrap(), notify);
+						closed.fetch_add(1, Ordering::Relaxed);
+					});
+				}
+			}
+		});
+
+		ImapServer {
+			port,
+			connections,
+			closed,
+		}
+	}
+
+	fn serve(stream: std::net::TcpStream, notify: bool) {
+		let mut reader = BufReader::new(stream.try_clone().unwrap());
+		let mut stream = stream;
+		stream.write_all(b"* OK IMAP4rev1 ready\r\n").unwrap();
+
+		let mut line = String::new();
+		while reader.read_line(&mut line).unwrap_or(0) > 0 {
+			let (tag, command) = line.trim_end().split_once(' ').unwrap();
+			let command = command.to_ascii_uppercase();
+
+			let response = if command.starts_with("LOGIN") {
+				format!("{tag} OK LOGIN completed\r\n")
+			} else if command.starts_with("EXAMINE") || command.starts_with("SELECT") {
+				format!(
+					"* FLAGS (\\Seen)\r\n* 1 EXISTS\r\n* 1 RECENT\r\n{tag} OK [READ-WRITE] completed\r\n"
+				)
+			} else if command.starts_with("UID SEARCH") {
+				format!("* SEARCH 1\r\n{tag} OK SEARCH completed\r\n")
+			} else if command.starts_with("UID FETCH") {
+				format!(
+					"* 1 FETCH (UID 1 BODY[] {{{}}}\r\n{MAIL})\r\n{tag} OK FETCH completed\r\n",
+					MAIL.len()
+				)
+			} else if command.starts_with("IDLE") {
+				stream.write_all(b"+ idling\r\n").unwrap();
+				if notify {
+					std::thread::sleep(Duration::from_millis(100));
+					stream.write_all(b"* 2 EXISTS\r\n").unwrap();
+				}
+
+				// wait for DONE
+				if reader.read_line(&mut String::new()).unwrap_or(0) == 0 {
+					return;
+				}
+				format!("{tag} OK IDLE terminated\r\n")
+			} else if command.starts_with("LOGOUT") {
+				format!("* BYE\r\n{tag} OK LOGOUT completed\r\n")
+			} else {
+				format!("{tag} BAD unknown command\r\n")
+			};
+
+			stream.write_all(response.as_bytes()).unwrap();
+			line.clear();
+		}
+	}
+
+	fn email(port: u16) -> Email {
+		let mut email = Email::new_generic(
+			"127.0.0.1".to_owned(),
+			Some(port),
+			Security::None,
+			"user@example.com".to_owned(),
+			"password".to_owned(),
+			Filters::default(),
+			ViewMode::ReadOnly,
+		);
+		email.idle = true;
+
+		email
+	}
+
+	#[tokio::test]
+	async fn fetch_reuses_connection() {
+		let server = imap_server(true);
+		let mut email = email(server.port);
+
+		for _ in 0..2 {
+			let entries = email.fetch().await.unwrap();
+			assert_eq!(entries.len(), 1);
+			assert_eq!(entries[0].id.as_ref().unwrap().0, "1");
+			assert_eq!(entries[0].msg.title.as_deref(), Some("Hello"));
+			assert_eq!(entries[0].msg.body.as_deref(), Some("World\r\n"));
+		}
+
+		assert_eq!(server.connections.load(Ordering::Relaxed), 1);
+	}
+
+	#[tokio::test]
+	async fn idle() {
+		let server = imap_server(true);
+		let mut email = email(server.port);
+
+		tokio::time::timeout(Duration::from_secs(5), email.wait_for_new_entries())
+			.await
+			.expect("should've been notified about the new email");
+
+		// the session used for IDLE should be reused
+		email.fetch().await.unwrap();
+		assert_eq!(server.connections.load(Ordering::Relaxed), 1);
+	}
+
+	#[tokio::test]
+	async fn fetch_stops_idle() {
+		let server = imap_server(false);
+		let mut email = email(server.port);
+
+		// the refresh time of the job has come before any new email
+		tokio::time::timeout(Duration::from_millis(100), email.wait_for_new_entries())
+			.await
+			.unwrap_err();
+
+		// the session used for IDLE should be taken back instead of opening a new connection
+		email.fetch().await.unwrap();
+		assert_eq!(server.connections.load(Ordering::Relaxed), 1);
+	}
+
+	#[tokio::test]
+	async fn drop_stops_idle() {
+		let server = imap_server(false);
+		let mut email = email(server.port);
+
+		tokio::time::timeout(Duration::from_millis(100), email.wait_for_new_entries())
+			.await
+			.unwrap_err();
+		drop(email);
+
+		tokio::time::sleep(IDLE_INTERVAL * 3).await;
+		assert_eq!(server.closed.load(Ordering::Relaxed), 1);
 	}
 }
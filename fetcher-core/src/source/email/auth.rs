@@ -15,12 +15,12 @@ pub enum Auth {
 	Password(String),
 }
 
-pub(super) struct ImapOAuth2<'a> {
-	email: &'a str,
-	token: &'a str,
+pub(super) struct ImapOAuth2 {
+	email: String,
+	token: String,
 }
 
-impl imap::Authenticator for ImapOAuth2<'_> {
+impl imap::Authenticator for ImapOAuth2 {
 	type Response = String;
 
 	fn process(&self, _challenge: &[u8]) -> Self::Response {
@@ -30,21 +30,15 @@ impl imap::Authenticator for ImapOAuth2<'_> {
 
 #[async_trait::async_trait]
 pub(super) trait GoogleAuthExt {
-	async fn as_imap_oauth2<'a>(
-		&'a mut self,
-		email: &'a str,
-	) -> Result<ImapOAuth2<'a>, GoogleAuthError>;
+	async fn as_imap_oauth2(&mut self, email: &str) -> Result<ImapOAuth2, GoogleAuthError>;
 }
 
 #[async_trait::async_trait]
 impl GoogleAuthExt for GoogleAuth {
-	async fn as_imap_oauth2<'a>(
-		&'a mut self,
-		email: &'a str,
-	) -> Result<ImapOAuth2<'a>, GoogleAuthError> {
+	async fn as_imap_oauth2(&mut self, email: &str) -> Result<ImapOAuth2, GoogleAuthError> {
 		Ok(ImapOAuth2 {
-			email,
-			token: self.access_token().await?,
+			email: email.to_owned(),
+			token: self.access_token().await?.to_owned(),
 		})
 	}
 }
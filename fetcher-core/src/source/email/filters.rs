@@ -4,13 +4,246 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-/// A list of filters passed to the IMAP server
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use std::time::Duration;
+
+/// A list of filters passed to the IMAP server as a SEARCH query.
+/// All of them have to match for an email to be fetched
 #[derive(Debug)]
 pub struct Filters {
-	/// Get emails only from this sender
-	pub sender: Option<String>,
+	/// Get emails only from any of these senders
+	pub senders: Vec<String>,
+	/// Get emails only sent to, or CC'ed to, any of these recipients
+	pub recipients: Vec<String>,
 	/// Get emails only containing these strings in the subject
-	pub subjects: Option<Vec<String>>,
+	pub subjects: Vec<String>,
 	/// Get all emails matching all above criteria but not containing any of these strings in the subject
-	pub exclude_subjects: Option<Vec<String>>,
+	pub exclude_subjects: Vec<String>,
+	/// Get emails only containing these strings in the body
+	pub body: Vec<String>,
+	/// Get emails only received on or after this date
+	pub since: Option<DateBound>,
+	/// Get emails only received before this date
+	pub before: Option<DateBound>,
+	/// Get only emails that haven't been read yet
+	pub unseen: bool,
+	/// Get only flagged (starred) emails if true, or only not flagged ones if false
+	pub flagged: Option<bool>,
+	/// A raw IMAP SEARCH query that's added to the other criteria as is, e.g. `LARGER 10000`
+	pub raw: Option<String>,
+}
+
+/// A date used in [`Filters`]
+#[derive(Clone, Copy, Debug)]
+pub enum DateBound {
+	/// This exact date
+	Date(NaiveDate),
+
+	/// The date this long ago from the moment of fetching
+	Ago(Duration),
+}
+
+impl Default for Filters {
+	/// Fetch all unread emails
+	fn default() -> Self {
+		Self {
+			senders: Vec::new(),
+			recipients: Vec::new(),
+			subjects: Vec::new(),
+			exclude_subjects: Vec::new(),
+			body: Vec::new(),
+			since: None,
+			before: None,
+			unseen: true,
+			flagged: None,
+			raw: None,
+		}
+	}
+}
+
+impl Filters {
+	/// Builds an IMAP SEARCH query out of the filters
+	pub(super) fn search_query(&self) -> String {
+		let mut criteria = Vec::new();
+
+		if self.unseen {
+			criteria.push("UNSEEN".to_owned());
+		}
+
+		match self.flagged {
+			Some(true) => criteria.push("FLAGGED".to_owned()),
+			Some(false) => criteria.push("UNFLAGGED".to_owned()),
+			None => (),
+		}
+
+		criteria.extend(any_of(
+			self.senders
+				.iter()
+				.map(|sender| format!("FROM {}", quote(sender))),
+		));
+
+		criteria.extend(any_of(self.recipients.iter().map(|recipient| {
+			let recipient = quote(recipient);
+			format!("(OR TO {recipient} CC {recipient})")
+		})));
+
+		criteria.extend(
+			self.subjects
+				.iter()
+				.map(|s| format!("SUBJECT {}", quote(s))),
+		);
+		criteria.extend(
+			self.exclude_subjects
+				.iter()
+				.map(|s| format!("NOT SUBJECT {}", quote(s))),
+		);
+		criteria.extend(self.body.iter().map(|s| format!("BODY {}", quote(s))));
+
+		if let Some(since) = self.since {
+			criteria.push(format!("SINCE {}", since.to_imap_date()));
+		}
+
+		if let Some(before) = self.before {
+			criteria.push(format!("BEFORE {}", before.to_imap_date()));
+		}
+
+		if let Some(raw) = &self.raw {
+			criteria.push(format!("({raw})"));
+		}
+
+		if criteria.is_empty() {
+			return "ALL".to_owned();
+		}
+
+		let query = criteria.join(" ");
+
+		// quoted strings can only contain non-ASCII characters if the charset is specified
+		if query.is_ascii() {
+			query
+		} else {
+			format!("CHARSET UTF-8 {query}")
+		}
+	}
+}
+
+impl DateBound {
+	fn to_imap_date(self) -> String {
+		let date = match self {
+			Self::Date(date) => date,
+			Self::Ago(ago) => chrono::Duration::from_std(ago)
+				.ok()
+				.and_then(|ago| Local::now().checked_sub_signed(ago))
+				.map_or(
+					// too long ago to be represented, but there isn't any email older than the epoch anyway
+					NaiveDateTime::UNIX_EPOCH.date(),
+					|date| date.date_naive(),
+				),
+		};
+
+		// e.g. 1-Feb-2024
+		date.format("%-d-%b-%Y").to_string()
+	}
+}
+
+/// Makes an IMAP quoted string out of `s`
+pub(super) fn quote(s: &str) -> String {
+	let mut quoted = String::with_capacity(s.len() + 2);
+	quoted.push('"');
+
+	for c in s.chars() {
+		match c {
+			'"' | '\\' => {
+				quoted.push('\\');
+				quoted.push(c);
+			}
+			// can't be present in a quoted string at all
+			'\r' | '\n' => quoted.push(' '),
+			c => quoted.push(c),
+		}
+	}
+
+	quoted.push('"');
+	quoted
+}
+
+/// Combines all criteria with OR. Returns [`None`] if there are no criteria
+fn any_of(criteria: impl DoubleEndedIterator<Item = String>) -> Option<String> {
+	// IMAP OR takes exactly 2 arguments, so nest them: OR a (OR b c)
+	criteria
+		.rev()
+		.reduce(|acc, criterion| format!("(OR {criterion} {acc})"))
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	#[test]
+	fn default_is_unseen() {
+		assert_eq!(Filters::default().search_query(), "UNSEEN");
+	}
+
+	#[test]
+	fn quoting() {
+		let filters = Filters {
+			subjects: vec![r#"say "hi" \o/"#.to_owned()],
+			unseen: false,
+			..Default::default()
+		};
+
+		assert_eq!(filters.search_query(), r#"SUBJECT "say \"hi\" \\o/""#);
+	}
+
+	#[test]
+	fn all_filters() {
+		let filters = Filters {
+			senders: vec!["a@example.com".to_owned(), "b@example.com".to_owned()],
+			recipients: vec!["me@example.com".to_owned()],
+			subjects: vec!["news".to_owned()],
+			exclude_subjects: vec!["ad".to_owned()],
+			body: vec!["unsubscribe".to_owned()],
+			since: Some(DateBound::Date(
+				NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+			)),
+			before: None,
+			unseen: true,
+			flagged: Some(false),
+			raw: Some("LARGER 100".to_owned()),
+		};
+
+		assert_eq!(
+			filters.search_query(),
+			concat!(
+				r#"UNSEEN UNFLAGGED (OR FROM "a@example.com" FROM "b@example.com") "#,
+				r#"(OR TO "me@example.com" CC "me@example.com") SUBJECT "news" NOT SUBJECT "ad" "#,
+				r#"BODY "unsubscribe" SINCE 1-Feb-2024 (LARGER 100)"#
+			)
+		);
+	}
+
+	#[test]
+	fn non_ascii() {
+		let filters = Filters {
+			subjects: vec!["привет".to_owned()],
+			..Default::default()
+		};
+
+		assert_eq!(
+			filters.search_query(),
+			r#"CHARSET UTF-8 UNSEEN SUBJECT "привет""#
+		);
+	}
+
+	#[test]
+	fn too_long_ago() {
+		let filters = Filters {
+			since: Some(DateBound::Ago(Duration::MAX)),
+			unseen: false,
+			..Default::default()
+		};
+
+		assert_eq!(filters.search_query(), "SINCE 1-Jan-1970");
+	}
 }
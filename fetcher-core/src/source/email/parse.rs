@@ -0,0 +1,317 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Parsing of fetched emails into [`Entry`]s
+
+use super::EmailError;
+use crate::{
+	entry::Entry,
+	sink::message::{Media, MediaFile, Message},
+};
+
+use mailparse::{DispositionType, MailHeaderMap, ParsedMail};
+use soup_kuchiki::{Handle as HtmlNode, NodeExt, QueryBuilderExt, Soup};
+use std::fmt::Write as _;
+
+/// Headers that are included in [`Entry::raw_contents`] above the text of the email
+const RAW_HEADERS: &[&str] = &["From", "Date", "Message-ID", "In-Reply-To"];
+
+/// HTML elements that are separated from the surrounding text with an empty line
+const BLOCK_ELEMENTS: &[&str] = &[
+	"p",
+	"div",
+	"h1",
+	"h2",
+	"h3",
+	"h4",
+	"h5",
+	"h6",
+	"table",
+	"blockquote",
+	"ul",
+	"ol",
+];
+
+/// Parses an email into an [`Entry`] with id `id`.
+///
+/// The body is the text/plain part of the email or, if there's none, the text/html part converted to plain text.
+/// Attachments, as well as inline images and PDFs, are added as [`Media::File`]s.
+/// The raw contents contain the [`RAW_HEADERS`] followed by an empty line and the body
+pub(super) fn parse(mail: &ParsedMail, id: String) -> Result<Entry, EmailError> {
+	let subject = mail.headers.get_first_value("Subject");
+
+	let body = if let Some(plain) = find_part(mail, "text/plain") {
+		Some(plain.get_body()?)
+	} else if let Some(html) = find_part(mail, "text/html") {
+		Some(html_to_text(&html.get_body()?))
+	} else {
+		None
+	};
+
+	let media = mail
+		.parts()
+		.filter(|part| is_attachment(part))
+		.map(|part| {
+			Ok(Media::File(MediaFile {
+				name: attachment_name(part),
+				mime_type: part.ctype.mimetype.clone(),
+				data: part.get_body_raw()?.into(),
+			}))
+		})
+		.collect::<Result<Vec<_>, EmailError>>()?;
+
+	let mut raw_contents = String::new();
+	for header in RAW_HEADERS {
+		if let Some(value) = mail.headers.get_first_value(header) {
+			_ = writeln!(raw_contents, "{header}: {value}");
+		}
+	}
+	raw_contents.push('\n');
+	raw_contents.push_str(body.as_deref().unwrap_or_default());
+
+	Ok(Entry {
+		id: Some(id.into()),
+		raw_contents: Some(raw_contents),
+		msg: Message {
+			title: subject,
+			body,
+			media: (!media.is_empty()).then_some(media),
+			..Default::default()
+		},
+		..Default::default()
+	})
+}
+
+/// Returns the Message-ID of the email this email is a reply to, if any
+pub(super) fn in_reply_to(raw_mail: &[u8]) -> Option<String> {
+	let (headers, _) = mailparse::parse_headers(raw_mail).ok()?;
+
+	headers
+		.get_first_value("In-Reply-To")
+		.and_then(|ids| ids.split_whitespace().next().map(ToOwned::to_owned))
+}
+
+/// Finds the first part with the `mimetype` that isn't an attachment
+fn find_part<'a>(mail: &'a ParsedMail<'a>, mimetype: &str) -> Option<&'a ParsedMail<'a>> {
+	mail.parts()
+		.find(|part| part.ctype.mimetype == mimetype && !is_attachment(part))
+}
+
+fn is_attachment(part: &ParsedMail) -> bool {
+	if !part.subparts.is_empty() {
+		return false;
+	}
+
+	let mimetype = part.ctype.mimetype.as_str();
+	let is_supported = mimetype.starts_with("image/") || mimetype == "application/pdf";
+
+	match part.get_content_disposition().disposition {
+		DispositionType::Attachment => true,
+		// inline images, e.g. the ones embedded into HTML emails
+		_ => is_supported,
+	}
+}
+
+fn attachment_name(part: &ParsedMail) -> String {
+	part.get_content_disposition()
+		.params
+		.get("filename")
+		.or_else(|| part.ctype.params.get("name"))
+		.cloned()
+		.unwrap_or_else(|| {
+			let extension = part
+				.ctype
+				.mimetype
+				.split_once('/')
+				.map_or("bin", |(_, subtype)| subtype);
+
+			format!("attachment.{extension}")
+		})
+}
+
+/// Converts an HTML document into readable plain text, keeping paragraphs and list items on separate lines
+fn html_to_text(html: &str) -> String {
+	let dom = Soup::new(html).get_handle();
+
+	let mut text = String::new();
+	push_text(&dom, &mut text);
+
+	// collapse all the empty lines left behind by nested block elements
+	let mut result = String::with_capacity(text.len());
+	let mut empty_lines = 0;
+	for line in text.lines().map(str::trim) {
+		if line.is_empty() {
+			empty_lines += 1;
+			continue;
+		}
+
+		if !result.is_empty() {
+			result.push_str(if empty_lines > 1 { "\n\n" } else { "\n" });
+		}
+
+		result.push_str(line);
+		empty_lines = 0;
+	}
+
+	result
+}
+
+fn push_text(node: &HtmlNode, text: &mut String) {
+	if let Some(node_text) = node.as_text() {
+		// whitespace in HTML is insignificant, newlines included
+		let node_text = node_text.borrow();
+		let mut words = node_text.split_whitespace().peekable();
+
+		if node_text.starts_with(char::is_whitespace) && words.peek().is_some() {
+			text.push(' ');
+		}
+
+		for (i, word) in words.enumerate() {
+			if i > 0 {
+				text.push(' ');
+			}
+			text.push_str(word);
+		}
+
+		if node_text.ends_with(char::is_whitespace) {
+			text.push(' ');
+		}
+
+		return;
+	}
+
+	let name = if node.is_element() { node.name() } else { "" };
+
+	match name {
+		"head" | "script" | "style" | "title" => return,
+		"br" => {
+			text.push('\n');
+			return;
+		}
+		"li" => text.push_str("\n- "),
+		"tr" => text.push('\n'),
+		name if BLOCK_ELEMENTS.contains(&name) => text.push_str("\n\n"),
+		_ => (),
+	}
+
+	for child in node.children() {
+		push_text(&child, text);
+	}
+
+	if BLOCK_ELEMENTS.contains(&name) {
+		text.push_str("\n\n");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	#[test]
+	fn html_only() {
+		let mail = concat!(
+			"Subject: News\r\n",
+			"From: Sender <sender@example.com>\r\n",
+			"Message-ID: <2@example.com>\r\n",
+			"In-Reply-To: <1@example.com>\r\n",
+			"Content-Type: text/html; charset=utf-8\r\n",
+			"\r\n",
+			"<html><head><style>p { color: red; }</style></head><body>",
+			"<h1>Hello</h1><p>First\r\nline<br>Second line</p>",
+			"<ul><li>one</li><li><b>two</b></li></ul>",
+			"</body></html>\r\n",
+		);
+
+		let entry = parse(
+			&mailparse::parse_mail(mail.as_bytes()).unwrap(),
+			"2".to_owned(),
+		)
+		.unwrap();
+
+		assert_eq!(entry.msg.title.as_deref(), Some("News"));
+		assert_eq!(
+			entry.msg.body.as_deref(),
+			Some("Hello\n\nFirst line\nSecond line\n\n- one\n- two")
+		);
+		assert!(entry.raw_contents.unwrap().starts_with(
+			"From: Sender <sender@example.com>\nMessage-ID: <2@example.com>\nIn-Reply-To: <1@example.com>\n\nHello"
+		));
+		assert_eq!(
+			in_reply_to(mail.as_bytes()).as_deref(),
+			Some("<1@example.com>")
+		);
+	}
+
+	#[test]
+	fn attachments() {
+		let mail = concat!(
+			"Subject: Report\r\n",
+			"Content-Type: multipart/mixed; boundary=\"b1\"\r\n",
+			"\r\n",
+			"--b1\r\n",
+			"Content-Type: multipart/alternative; boundary=\"b2\"\r\n",
+			"\r\n",
+			"--b2\r\n",
+			"Content-Type: text/html\r\n",
+			"\r\n",
+			"<p>See attached</p>\r\n",
+			"--b2\r\n",
+			"Content-Type: text/plain\r\n",
+			"\r\n",
+			"See attached\r\n",
+			"--b2--\r\n",
+			"--b1\r\n",
+			"Content-Type: application/pdf\r\n",
+			"Content-Disposition: attachment; filename=\"report.pdf\"\r\n",
+			"Content-Transfer-Encoding: base64\r\n",
+			"\r\n",
+			"JVBERi0=\r\n",
+			"--b1\r\n",
+			"Content-Type: image/png; name=\"chart.png\"\r\n",
+			"Content-Disposition: inline\r\n",
+			"\r\n",
+			"png\r\n",
+			"--b1--\r\n",
+		);
+
+		let entry = parse(
+			&mailparse::parse_mail(mail.as_bytes()).unwrap(),
+			"1".to_owned(),
+		)
+		.unwrap();
+
+		assert_eq!(entry.msg.body.as_deref(), Some("See attached\r\n"));
+
+		let files = entry
+			.msg
+			.media
+			.unwrap()
+			.into_iter()
+			.map(|media| match media {
+				Media::File(file) => (file.name, file.mime_type, file.data.to_vec()),
+				other => panic!("{other:?} isn't a file"),
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			files,
+			[
+				(
+					"report.pdf".to_owned(),
+					"application/pdf".to_owned(),
+					b"%PDF-".to_vec()
+				),
+				(
+					"chart.png".to_owned(),
+					"image/png".to_owned(),
+					b"png\r\n".to_vec()
+				),
+			]
+		);
+	}
+}
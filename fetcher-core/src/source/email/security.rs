@@ -0,0 +1,28 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// How to secure the connection to the IMAP server
+#[derive(Clone, Copy, Default, Debug)]
+pub enum Security {
+	/// Connect via TLS right away, port 993 by default
+	#[default]
+	Tls,
+
+	/// Connect unencrypted and then upgrade the connection with STARTTLS, port 143 by default
+	StartTls,
+
+	/// Don't encrypt the connection at all, port 143 by default. Insecure and should only ever be used with a local server
+	None,
+}
+
+impl Security {
+	pub(super) const fn default_port(self) -> u16 {
+		match self {
+			Self::Tls => 993,
+			Self::StartTls | Self::None => 143,
+		}
+	}
+}
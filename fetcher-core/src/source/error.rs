@@ -10,7 +10,7 @@ pub use crate::exec::ExecError;
 
 use super::{
 	email::{EmailError, ImapError},
-	http::HttpError,
+	http::{HttpError, paginated::PaginatedError},
 	reddit::RedditError,
 };
 
@@ -26,6 +26,9 @@ pub enum SourceError {
 	#[error("HTTP error")]
 	Http(#[from] HttpError),
 
+	#[error("Paginated HTTP error")]
+	Paginated(#[from] Box<PaginatedError>),
+
 	#[error("Email error")]
 	Email(#[from] Box<EmailError>),
 
@@ -50,6 +53,10 @@ impl SourceError {
 		#[expect(clippy::match_same_arms, reason = "clearer code")]
 		match self {
 			Self::Http(_) => Some(self),
+			Self::Paginated(paginated_err) => match &**paginated_err {
+				PaginatedError::Http(_) => Some(self),
+				_ => None,
+			},
 			Self::Email(email_err) => match &**email_err {
 				EmailError::Imap(ImapError::ConnectionFailed(_)) => Some(self),
 				_ => None,
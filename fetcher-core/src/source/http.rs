@@ -8,27 +8,45 @@
 //!
 //! This module contains the [`Http`] struct, that is a source as well as a transform
 
-use crate::{entry::Entry, sink::message::Message, source::error::SourceError};
+pub mod cache;
+pub mod client;
+pub mod paginated;
+
+pub use self::{
+	client::{Auth, Body, ClientOptions, HttpClient, Method, Request},
+	paginated::Paginated,
+};
+
+use self::cache::{HttpCache, Validators};
+use crate::{
+	entry::Entry, error::FetcherError, sink::message::Message, source::error::SourceError,
+};
 
 use async_trait::async_trait;
-use once_cell::sync::OnceCell;
-use reqwest::Client;
-use std::{fmt::Debug, time::Duration};
+use reqwest::header::{InvalidHeaderName, InvalidHeaderValue};
+use std::{fmt::Debug, sync::Arc};
+use tokio::sync::Mutex;
 use url::Url;
 
 use super::Fetch;
 
-const USER_AGENT: &str =
-	"Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:96.0) Gecko/20100101 Firefox/96.0";
-
-pub(crate) static CLIENT: OnceCell<reqwest::Client> = OnceCell::new();
-
 /// A source that fetches from the [`URL`](`url`)
 pub struct Http {
 	/// The URL to fetch from
 	pub url: Url,
+
+	/// The cache of the `ETag` and `Last-Modified` headers, usually shared between all HTTP sources of a task.
+	/// If set, the page isn't re-downloaded and no entries are returned if it hasn't changed since the last fetch
+	pub cache: Option<Arc<Mutex<HttpCache>>>,
+
 	request: Request,
-	client: reqwest::Client,
+	client: HttpClient,
+
+	/// Validators of the last fetched page that will be saved to the cache on [`Fetch::save_cache()`]
+	unsaved_validators: Option<Validators>,
+
+	/// The page hasn't been modified since the last fetch, so it wasn't downloaded this time
+	not_modified: bool,
 }
 
 #[expect(missing_docs, reason = "error message is self-documenting")]
@@ -37,26 +55,45 @@ pub enum HttpError {
 	#[error("Invalid JSON for the POST request")]
 	BadJson(#[from] serde_json::Error),
 
+	#[error("Invalid header name {1:?}")]
+	InvalidHeaderName(#[source] InvalidHeaderName, String),
+
+	#[error("Invalid value of header {1:?}")]
+	InvalidHeaderValue(#[source] InvalidHeaderValue, String),
+
+	#[error("Invalid proxy {1:?}")]
+	BadProxy(#[source] reqwest::Error, String),
+
 	#[error("Failed to init TLS")]
 	TlsInitFailed(#[source] reqwest::Error),
 
 	#[error("Can't send an HTTP request to {1:?}")]
 	BadRequest(#[source] reqwest::Error, String),
-}
 
-#[derive(Debug)]
-pub(crate) enum Request {
-	Get,
-	Post(serde_json::Value),
+	#[error("File at {0:?} is larger than {1} bytes")]
+	TooLarge(String, u64),
 }
 
 impl Http {
+	/// Create a new HTTP source that sends `request` to `url` using `client`
+	#[must_use]
+	pub fn new(url: Url, request: Request, client: HttpClient) -> Self {
+		Self {
+			url,
+			cache: None,
+			request,
+			client,
+			unsaved_validators: None,
+			not_modified: false,
+		}
+	}
+
 	/// Create a new HTTP client that sends GET requests
 	///
 	/// # Errors
 	/// This method fails if TLS couldn't be initialized
 	pub fn new_get(url: Url) -> Result<Self, HttpError> {
-		Self::new(url, Request::Get)
+		Ok(Self::new(url, Request::default(), HttpClient::shared()?))
 	}
 
 	/// Create a new HTTP client that sends POST requests
@@ -64,95 +101,205 @@ impl Http {
 	/// # Errors
 	/// This method fails if body isn't valid JSON or TLS couldn't be initialized
 	pub fn new_post(url: Url, body: &str) -> Result<Self, HttpError> {
-		Self::new(url, Request::Post(serde_json::from_str(body)?))
+		let request = Request {
+			method: Method::Post,
+			body: Some(Body::Json(serde_json::from_str(body)?)),
+		};
+
+		Ok(Self::new(url, request, HttpClient::shared()?))
 	}
 }
 
 #[async_trait]
 impl Fetch for Http {
-	/// Send a request to the [`URL`](`self.url`) and return the result in the [`Entry.raw_contents`] field
+	/// Send a request to the [`URL`](`self.url`) and return the result in the [`Entry.raw_contents`] field.
+	/// Returns no entries if the page hasn't been modified since the last time
 	#[tracing::instrument(skip_all)]
 	async fn fetch(&mut self) -> Result<Vec<Entry>, SourceError> {
-		self.fetch_impl().await.map(|x| vec![x]).map_err(Into::into)
+		self.fetch_impl()
+			.await
+			.map(|x| x.into_iter().collect())
+			.map_err(Into::into)
 	}
-}
 
-impl Http {
-	fn new(url: Url, request: Request) -> Result<Self, HttpError> {
-		let client = CLIENT
-			.get_or_try_init(|| {
-				reqwest::ClientBuilder::new()
-					.timeout(Duration::from_secs(30))
-					.build()
-					.map_err(HttpError::TlsInitFailed)
-			})?
-			.clone();
-
-		Ok(Self {
-			url,
-			request,
-			client,
-		})
+	async fn save_cache(&mut self) -> Result<(), FetcherError> {
+		if let Some(cache) = &self.cache
+			&& let Some(validators) = self.unsaved_validators.take()
+		{
+			cache
+				.lock()
+				.await
+				.insert(self.url.as_str().to_owned(), validators)
+				.await
+				.map_err(FetcherError::ExternalSave)?;
+		}
+
+		Ok(())
+	}
+
+	fn disable_cache(&mut self) {
+		self.cache = None;
 	}
 
-	async fn fetch_impl(&self) -> Result<Entry, HttpError> {
+	/// Returns `false` if the page hasn't been modified since the last fetch and thus its entries weren't returned
+	fn fetched_everything(&self) -> bool {
+		!self.not_modified
+	}
+}
+
+impl Http {
+	async fn fetch_impl(&mut self) -> Result<Option<Entry>, HttpError> {
 		tracing::debug!("Sending an HTTP request");
+		self.not_modified = false;
 
-		let page = send_request(&self.client, &self.request, &self.url).await?;
+		let page = if let Some(cache) = &self.cache {
+			let validators = cache.lock().await.get(self.url.as_str()).cloned();
+
+			let Some((page, new_validators)) = self
+				.client
+				.send_conditional(&self.request, &self.url, validators.as_ref())
+				.await?
+			else {
+				tracing::debug!("The page hasn't been modified since the last fetch");
+				self.unsaved_validators = None;
+				self.not_modified = true;
+
+				return Ok(None);
+			};
+
+			self.unsaved_validators = Some(new_validators);
+			page
+		} else {
+			self.client.send(&self.request, &self.url).await?
+		};
 
 		// tracing::trace!("Done. Body: ----------------------------------------\n{page:?}\n----------------------------------------\n");
 
-		Ok(Entry {
+		Ok(Some(Entry {
 			raw_contents: Some(page),
 			msg: Message {
 				link: Some(self.url.clone()),
 				..Default::default()
 			},
 			..Default::default()
-		})
+		}))
 	}
 }
 
-pub(crate) async fn send_request(
-	client: &Client,
-	request: &Request,
-	url: &Url,
-) -> Result<String, HttpError> {
-	let request = match request {
-		Request::Get => {
-			tracing::trace!("Making an HTTP GET request to {:?}", url.as_str());
-
-			client.get(url.as_str())
-		}
-		Request::Post(json) => {
-			tracing::trace!(
-				"Making an HTTP POST request to {:?} with {:#?}",
-				url.as_str(),
-				json
-			);
-
-			client.post(url.as_str()).json(json)
-		}
-	};
-
-	let response = request
-		.header(reqwest::header::USER_AGENT, USER_AGENT)
-		.send()
-		.await
-		.map_err(|e| HttpError::BadRequest(e, url.to_string()))?;
-
-	tracing::trace!("Getting text body of the response");
-	response
-		.text()
-		.await
-		.map_err(|e| HttpError::BadRequest(e, url.to_string()))
-}
-
 impl Debug for Http {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("Http")
 			.field("url", &self.url.as_str())
 			.field("request", &self.request)
+			.field("cache", &self.cache)
+			.field("client", &self.client)
 			.finish_non_exhaustive()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	use wiremock::{
+		Mock, MockServer, ResponseTemplate,
+		matchers::{body_string, header, method, path},
+	};
+
+	#[tokio::test]
+	async fn not_modified() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("GET"))
+			.and(path("/feed"))
+			.and(header("if-none-match", "\"v1\""))
+			.respond_with(ResponseTemplate::new(304))
+			.with_priority(1)
+			.mount(&server)
+			.await;
+
+		Mock::given(method("GET"))
+			.and(path("/feed"))
+			.respond_with(
+				ResponseTemplate::new(200)
+					.insert_header("etag", "\"v1\"")
+					.set_body_string("page"),
+			)
+			.mount(&server)
+			.await;
+
+		let mut http = Http::new_get(format!("{}/feed", server.uri()).parse().unwrap()).unwrap();
+		http.cache = Some(Arc::default());
+
+		let entries = http.fetch().await.unwrap();
+		assert_eq!(entries.len(), 1, "nothing has been cached yet");
+		assert_eq!(entries[0].raw_contents.as_deref(), Some("page"));
+		assert!(http.fetched_everything());
+
+		// the validators aren't used until the entries have been processed and the cache saved
+		assert_eq!(http.fetch().await.unwrap().len(), 1);
+
+		http.save_cache().await.unwrap();
+		assert!(
+			http.fetch().await.unwrap().is_empty(),
+			"the page should've been reported as not modified"
+		);
+		assert!(!http.fetched_everything());
+
+		http.disable_cache();
+		assert_eq!(http.fetch().await.unwrap().len(), 1);
+		assert!(http.fetched_everything());
+	}
+
+	#[tokio::test]
+	async fn client_options() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("PUT"))
+			.and(path("/api"))
+			.and(header("x-api-key", "key"))
+			.and(header("authorization", "Bearer token"))
+			.and(header("content-type", "application/x-www-form-urlencoded"))
+			.and(body_string("a=1&b=2"))
+			.respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let client = HttpClient::new(ClientOptions {
+			headers: vec![("X-Api-Key".to_owned(), "key".to_owned())],
+			auth: Some(Auth::Bearer("token".to_owned())),
+			..Default::default()
+		})
+		.unwrap();
+
+		let request = Request {
+			method: Method::Put,
+			body: Some(Body::Form(vec![
+				("a".to_owned(), "1".to_owned()),
+				("b".to_owned(), "2".to_owned()),
+			])),
+		};
+
+		let mut http = Http::new(
+			format!("{}/api", server.uri()).parse().unwrap(),
+			request,
+			client,
+		);
+
+		let entries = http.fetch().await.unwrap();
+		assert_eq!(entries[0].raw_contents.as_deref(), Some("ok"));
+	}
+
+	#[test]
+	fn invalid_header() {
+		let res = HttpClient::new(ClientOptions {
+			headers: vec![("Bad Header".to_owned(), "value".to_owned())],
+			..Default::default()
+		});
+
+		assert!(matches!(res, Err(HttpError::InvalidHeaderName(..))));
+	}
+}
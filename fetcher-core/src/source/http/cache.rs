@@ -0,0 +1,90 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains [`HttpCache`]
+
+use crate::external_save::{ExternalSave, ExternalSaveError};
+
+use std::collections::HashMap;
+
+/// The `ETag` and `Last-Modified` headers of the last response from a URL,
+/// sent back in the `If-None-Match` and `If-Modified-Since` headers to avoid re-downloading an unchanged page
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Validators {
+	/// The `ETag` header of the last response
+	pub etag: Option<String>,
+
+	/// The `Last-Modified` header of the last response
+	pub last_modified: Option<String>,
+}
+
+/// Map URLs to the [`Validators`] of the last response from them
+#[derive(Default, Debug)]
+pub struct HttpCache {
+	/// External save location for the cache.
+	/// It's called every time on [`Self::insert()`]
+	pub external_save: Option<Box<dyn ExternalSave>>,
+
+	map: HashMap<String, Validators>,
+}
+
+impl HttpCache {
+	/// Create a new empty cache but with [`Self::external_save`] set to `external_save`.
+	/// Use [`HttpCache::default()`] if you don't want to set [`Self::external_save`]
+	#[must_use]
+	pub fn new<E>(external_save: E) -> Self
+	where
+		E: ExternalSave + 'static,
+	{
+		Self::new_with_map(HashMap::new(), external_save)
+	}
+
+	/// Create a new [`HttpCache`] with the provided `map` and `external_save` parameters
+	#[must_use]
+	pub fn new_with_map<E>(map: HashMap<String, Validators>, external_save: E) -> Self
+	where
+		E: ExternalSave + 'static,
+	{
+		Self {
+			external_save: Some(Box::new(external_save)),
+			map,
+		}
+	}
+
+	/// Get the [`Validators`] of the last response from `url`
+	#[must_use]
+	pub fn get(&self, url: &str) -> Option<&Validators> {
+		self.map.get(url)
+	}
+
+	/// Replace the [`Validators`] of `url` and save the cache externally if they have changed
+	///
+	/// # Errors
+	/// if external save has failed
+	pub async fn insert(
+		&mut self,
+		url: String,
+		validators: Validators,
+	) -> Result<(), ExternalSaveError> {
+		if self.map.get(&url) == Some(&validators) {
+			return Ok(());
+		}
+
+		self.map.insert(url, validators);
+
+		if let Some(ext_save) = &mut self.external_save {
+			ext_save.save_http_cache(&self.map).await?;
+		}
+
+		Ok(())
+	}
+
+	/// Returns the inner map
+	#[must_use]
+	pub fn inner(&self) -> &HashMap<String, Validators> {
+		&self.map
+	}
+}
@@ -0,0 +1,376 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains [`HttpClient`] and the [`Request`]s it can send
+
+use super::{HttpError, cache::Validators};
+
+use bytes::Bytes;
+use once_cell::sync::OnceCell;
+use reqwest::{
+	Client, Proxy, RequestBuilder, Response, StatusCode,
+	header::{self, HeaderMap, HeaderName, HeaderValue},
+};
+use std::{fmt::Debug, time::Duration};
+use url::Url;
+
+const USER_AGENT: &str =
+	"Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:96.0) Gecko/20100101 Firefox/96.0";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+static SHARED: OnceCell<HttpClient> = OnceCell::new();
+
+/// An HTTP client that sends all of its requests with the same headers, authentication, timeout, and proxy
+#[derive(Clone)]
+pub struct HttpClient {
+	client: Client,
+	auth: Option<Auth>,
+}
+
+/// Options of an [`HttpClient`]
+#[derive(Clone, Default, Debug)]
+pub struct ClientOptions {
+	/// Headers sent with every request. They override the default `User-Agent` header if it's specified
+	pub headers: Vec<(String, String)>,
+
+	/// Authentication used for every request
+	pub auth: Option<Auth>,
+
+	/// How long to wait for a response before giving up. 30 seconds if not set
+	pub timeout: Option<Duration>,
+
+	/// Send all requests through this proxy
+	pub proxy: Option<Url>,
+}
+
+/// HTTP authentication
+#[derive(Clone)]
+pub enum Auth {
+	/// Basic authentication
+	Basic {
+		#[expect(missing_docs, reason = "self-documenting")]
+		username: String,
+		#[expect(missing_docs, reason = "self-documenting")]
+		password: Option<String>,
+	},
+
+	/// Bearer token authentication
+	Bearer(String),
+}
+
+/// An HTTP request to send
+#[derive(Clone, Default, Debug)]
+pub struct Request {
+	/// The method of the request
+	pub method: Method,
+
+	/// The body of the request, if any
+	pub body: Option<Body>,
+}
+
+/// HTTP request method
+#[expect(missing_docs, reason = "self-documenting")]
+#[derive(Clone, Copy, Default, Debug)]
+pub enum Method {
+	#[default]
+	Get,
+	Post,
+	Put,
+	Patch,
+	Delete,
+}
+
+/// The body of an HTTP request
+#[derive(Clone, Debug)]
+pub enum Body {
+	/// A JSON body with the `Content-Type` header set to `application/json`
+	Json(serde_json::Value),
+
+	/// A URL-encoded form body with the `Content-Type` header set to `application/x-www-form-urlencoded`
+	Form(Vec<(String, String)>),
+
+	/// A body sent as is
+	Raw(String),
+}
+
+impl HttpClient {
+	/// Create a new [`HttpClient`] with the provided `options`
+	///
+	/// # Errors
+	/// * if any of the headers is invalid
+	/// * if the proxy URL is invalid
+	/// * if TLS couldn't be initialized
+	pub fn new(options: ClientOptions) -> Result<Self, HttpError> {
+		let ClientOptions {
+			headers,
+			auth,
+			timeout,
+			proxy,
+		} = options;
+
+		let headers = headers
+			.into_iter()
+			.map(|(name, value)| {
+				let header_name = HeaderName::try_from(name.as_str())
+					.map_err(|e| HttpError::InvalidHeaderName(e, name.clone()))?;
+				let header_value = HeaderValue::try_from(value)
+					.map_err(|e| HttpError::InvalidHeaderValue(e, name))?;
+
+				Ok((header_name, header_value))
+			})
+			.collect::<Result<HeaderMap, HttpError>>()?;
+
+		let mut builder = Client::builder()
+			.user_agent(USER_AGENT)
+			.default_headers(headers)
+			.timeout(timeout.unwrap_or(DEFAULT_TIMEOUT));
+
+		if let Some(proxy) = proxy {
+			builder = builder.proxy(
+				Proxy::all(proxy.as_str())
+					.map_err(|e| HttpError::BadProxy(e, proxy.to_string()))?,
+			);
+		}
+
+		Ok(Self {
+			client: builder.build().map_err(HttpError::TlsInitFailed)?,
+			auth,
+		})
+	}
+
+	/// Returns a client with the default options, shared between everything that doesn't need any custom ones
+	///
+	/// # Errors
+	/// if TLS couldn't be initialized
+	pub fn shared() -> Result<Self, HttpError> {
+		SHARED
+			.get_or_try_init(|| Self::new(ClientOptions::default()))
+			.cloned()
+	}
+
+	/// Send the `request` to the `url` and return the text of the response
+	///
+	/// # Errors
+	/// if the request couldn't be sent or the response couldn't be read
+	pub async fn send(&self, request: &Request, url: &Url) -> Result<String, HttpError> {
+		let response = self
+			.build_request(request, url)
+			.send()
+			.await
+			.map_err(|e| HttpError::BadRequest(e, url.to_string()))?;
+
+		tracing::trace!("Getting text body of the response");
+		response
+			.text()
+			.await
+			.map_err(|e| HttpError::BadRequest(e, url.to_string()))
+	}
+
+	/// Download the file at `url` with a GET request and return its contents as is.
+	/// The download is aborted as soon as it's known to be larger than `max_size` bytes
+	///
+	/// # Errors
+	/// if the request couldn't be sent, the server responded with an error status, the response couldn't be read,
+	/// or the file is larger than `max_size`
+	pub async fn download(&self, url: &Url, max_size: u64) -> Result<Bytes, HttpError> {
+		let mut response = self
+			.build_request(&Request::default(), url)
+			.send()
+			.await
+			.and_then(Response::error_for_status)
+			.map_err(|e| HttpError::BadRequest(e, url.to_string()))?;
+
+		if response.content_length().is_some_and(|len| len > max_size) {
+			return Err(HttpError::TooLarge(url.to_string(), max_size));
+		}
+
+		let mut data = Vec::new();
+		while let Some(chunk) = response
+			.chunk()
+			.await
+			.map_err(|e| HttpError::BadRequest(e, url.to_string()))?
+		{
+			if (data.len() + chunk.len()) as u64 > max_size {
+				return Err(HttpError::TooLarge(url.to_string(), max_size));
+			}
+
+			data.extend_from_slice(&chunk);
+		}
+
+		Ok(data.into())
+	}
+
+	/// Send the `request` with the `If-None-Match` and `If-Modified-Since` headers set from `validators`.
+	///
+	/// Returns the page and its new validators, or [`None`] if it hasn't been modified
+	pub(crate) async fn send_conditional(
+		&self,
+		request: &Request,
+		url: &Url,
+		validators: Option<&Validators>,
+	) -> Result<Option<(String, Validators)>, HttpError> {
+		let mut request = self.build_request(request, url);
+
+		if let Some(validators) = validators {
+			if let Some(etag) = &validators.etag {
+				request = request.header(header::IF_NONE_MATCH, etag);
+			}
+
+			if let Some(last_modified) = &validators.last_modified {
+				request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+			}
+		}
+
+		let response = request
+			.send()
+			.await
+			.map_err(|e| HttpError::BadRequest(e, url.to_string()))?;
+
+		if response.status() == StatusCode::NOT_MODIFIED {
+			return Ok(None);
+		}
+
+		let get_header = |name| {
+			response
+				.headers()
+				.get(name)
+				.and_then(|value| value.to_str().ok())
+				.map(ToOwned::to_owned)
+		};
+
+		let new_validators = Validators {
+			etag: get_header(header::ETAG),
+			last_modified: get_header(header::LAST_MODIFIED),
+		};
+
+		tracing::trace!("Getting text body of the response");
+		let page = response
+			.text()
+			.await
+			.map_err(|e| HttpError::BadRequest(e, url.to_string()))?;
+
+		Ok(Some((page, new_validators)))
+	}
+
+	fn build_request(&self, request: &Request, url: &Url) -> RequestBuilder {
+		tracing::trace!(
+			"Making an HTTP {:?} request to {:?} with {:#?}",
+			request.method,
+			url.as_str(),
+			request.body
+		);
+
+		let method = match request.method {
+			Method::Get => reqwest::Method::GET,
+			Method::Post => reqwest::Method::POST,
+			Method::Put => reqwest::Method::PUT,
+			Method::Patch => reqwest::Method::PATCH,
+			Method::Delete => reqwest::Method::DELETE,
+		};
+
+		let mut builder = self.client.request(method, url.as_str());
+
+		builder = match &self.auth {
+			Some(Auth::Basic { username, password }) => {
+				builder.basic_auth(username, password.as_ref())
+			}
+			Some(Auth::Bearer(token)) => builder.bearer_auth(token),
+			None => builder,
+		};
+
+		match &request.body {
+			Some(Body::Json(json)) => builder.json(json),
+			Some(Body::Form(form)) => builder.form(form),
+			Some(Body::Raw(raw)) => builder.body(raw.clone()),
+			None => builder,
+		}
+	}
+}
+
+impl Debug for HttpClient {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("HttpClient")
+			.field("auth", &self.auth)
+			.finish_non_exhaustive()
+	}
+}
+
+impl Debug for Auth {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		// don't leak the secrets to the logs
+		match self {
+			Self::Basic { username, .. } => f
+				.debug_struct("Basic")
+				.field("username", username)
+				.finish_non_exhaustive(),
+			Self::Bearer(_) => f.write_str("Bearer"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+	use wiremock::{
+		Mock, MockServer, ResponseTemplate,
+		matchers::{method, path},
+	};
+
+	#[tokio::test]
+	async fn download_too_large() {
+		let server = MockServer::start().await;
+
+		Mock::given(method("GET"))
+			.and(path("/file"))
+			.respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0; 10]))
+			.mount(&server)
+			.await;
+
+		let client = HttpClient::new(ClientOptions::default()).unwrap();
+		let url = format!("{}/file", server.uri()).parse().unwrap();
+
+		assert_eq!(client.download(&url, 10).await.unwrap().len(), 10);
+		assert!(matches!(
+			client.download(&url, 9).await,
+			Err(HttpError::TooLarge(..))
+		));
+	}
+
+	#[tokio::test]
+	async fn download_too_large_without_content_length() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}/file", listener.local_addr().unwrap())
+			.parse()
+			.unwrap();
+
+		tokio::spawn(async move {
+			let (mut stream, _) = listener.accept().await.unwrap();
+			// skip the request, it always fits
+			let _request_len = stream.read(&mut [0; 1024]).await.unwrap();
+
+			// a chunk of 5 bytes sent over and over again
+			stream
+				.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n")
+				.await
+				.unwrap();
+			while stream.write_all(b"5\r\nbytes\r\n").await.is_ok() {}
+		});
+
+		let client = HttpClient::new(ClientOptions::default()).unwrap();
+		assert!(matches!(
+			client.download(&url, 1024).await,
+			Err(HttpError::TooLarge(..))
+		));
+	}
+}
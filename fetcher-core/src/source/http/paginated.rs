@@ -0,0 +1,435 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains the [`Paginated`] source
+
+use super::{Http, HttpError};
+use crate::{
+	action::{
+		filter::Filter,
+		transform::{
+			Transform,
+			entry::{
+				html::{self, HtmlError, query::ElementDataQuery},
+				json::{self, JsonError, StringQuery},
+			},
+			error::TransformError,
+		},
+	},
+	entry::Entry,
+	error::{FetcherError, InvalidUrlError},
+	sink::message::Message,
+	source::{Fetch, error::SourceError},
+};
+
+use async_trait::async_trait;
+use url::Url;
+
+/// The max amount of pages fetched by default
+pub const DEFAULT_MAX_PAGES: usize = 10;
+
+/// A source that fetches several pages of a paginated web page or API with [`Http`]
+/// and parses each of them into entries with the [`parser`](`Paginated::parser`).
+///
+/// Pages are fetched until either
+/// * [`max_pages`](`Paginated::max_pages`) pages have been fetched,
+/// * a page without any entries is found,
+/// * a page contains an entry that has already been read, according to the [`read_filter`](`Paginated::read_filter`),
+/// * or there's no next page.
+///
+/// Entries of all pages are returned together, in order
+#[derive(Debug)]
+pub struct Paginated {
+	/// The source of the first page. All other pages are fetched with the same request and client
+	pub http: Http,
+
+	/// Parses a page into entries, usually either a [`Json`](`crate::action::transform::Json`)
+	/// or an [`Html`](`crate::action::transform::Html`) transform
+	pub parser: Box<dyn Transform>,
+
+	/// How to get the next page
+	pub next_page: NextPage,
+
+	/// The max amount of pages to fetch
+	pub max_pages: usize,
+
+	/// The read filter of the task. No more pages are fetched once it has filtered out any entries of a page
+	pub read_filter: Option<Box<dyn Filter>>,
+
+	/// The last fetch has stopped before the last page
+	truncated: bool,
+}
+
+/// A way to get the URL of the next page
+#[derive(Debug)]
+pub enum NextPage {
+	/// Follow the link found in the current page. Relative links are resolved against the URL of the current page
+	Link(LinkQuery),
+
+	/// Increase the query parameter with this name by 1 with every page.
+	/// It starts at 1 if it's not present in the URL of the first page
+	Page(String),
+
+	/// Increase the query parameter with this name by the number of entries on the current page.
+	/// It starts at 0 if it's not present in the URL of the first page
+	Offset(String),
+}
+
+/// A query to find the link to the next page with.
+/// It should match the format of the pages, i.e. [`LinkQuery::Json`] for JSON and [`LinkQuery::Html`] for HTML
+#[derive(Debug)]
+pub enum LinkQuery {
+	#[expect(missing_docs, reason = "self-documenting")]
+	Json(StringQuery),
+
+	#[expect(missing_docs, reason = "self-documenting")]
+	Html(ElementDataQuery),
+}
+
+#[expect(missing_docs, reason = "error message is self-documenting")]
+#[derive(thiserror::Error, Debug)]
+pub enum PaginatedError {
+	#[error(transparent)]
+	Http(#[from] HttpError),
+
+	#[error("Can't parse page {1}")]
+	Parse(#[source] Box<TransformError>, String),
+
+	#[error("Can't find the link to the next page in JSON")]
+	JsonLink(#[from] JsonError),
+
+	#[error("Can't find the link to the next page in HTML")]
+	HtmlLink(#[from] HtmlError),
+
+	#[error("The link to the next page is invalid")]
+	InvalidLink(#[from] InvalidUrlError),
+
+	#[error("Query parameter {name:?} of {url} is not a number")]
+	BadParam { name: String, url: String },
+}
+
+impl Paginated {
+	/// Create a new [`Paginated`] source that fetches at most [`DEFAULT_MAX_PAGES`] pages and doesn't use a read filter
+	#[must_use]
+	pub fn new(http: Http, parser: Box<dyn Transform>, next_page: NextPage) -> Self {
+		Self {
+			http,
+			parser,
+			next_page,
+			max_pages: DEFAULT_MAX_PAGES,
+			read_filter: None,
+			truncated: false,
+		}
+	}
+}
+
+#[async_trait]
+impl Fetch for Paginated {
+	/// Fetch all pages and parse them into entries
+	#[tracing::instrument(skip_all)]
+	async fn fetch(&mut self) -> Result<Vec<Entry>, SourceError> {
+		self.fetch_impl()
+			.await
+			.map_err(|e| SourceError::Paginated(Box::new(e)))
+	}
+
+	async fn save_cache(&mut self) -> Result<(), FetcherError> {
+		self.http.save_cache().await
+	}
+
+	fn disable_cache(&mut self) {
+		self.http.disable_cache();
+	}
+
+	/// Returns `false` if the first page hasn't been modified since the last fetch
+	/// or if no more pages have been fetched after an already read entry or after [`max_pages`](`Paginated::max_pages`)
+	fn fetched_everything(&self) -> bool {
+		self.http.fetched_everything() && !self.truncated
+	}
+}
+
+impl Paginated {
+	async fn fetch_impl(&mut self) -> Result<Vec<Entry>, PaginatedError> {
+		self.truncated = false;
+
+		// the first page goes through the cache. If it hasn't changed, the rest of them surely haven't either
+		let Some(mut page) = self.http.fetch_impl().await? else {
+			return Ok(Vec::new());
+		};
+
+		let mut url = self.http.url.clone();
+		let mut entries = Vec::new();
+
+		for page_num in 1..=self.max_pages {
+			tracing::debug!("Parsing page #{page_num}: {}", url.as_str());
+
+			let contents = page.raw_contents.clone().unwrap_or_default();
+			let page_entries = self
+				.parser
+				.transform(page)
+				.await
+				.map_err(|e| PaginatedError::Parse(Box::new(e), url.to_string()))?;
+
+			if page_entries.is_empty() {
+				tracing::debug!("Page #{page_num} is empty, stopping");
+				break;
+			}
+
+			let entry_count = page_entries.len();
+			let contains_read = match &self.read_filter {
+				Some(rf) => {
+					let mut unread = page_entries.clone();
+					rf.filter(&mut unread).await;
+
+					unread.len() < entry_count
+				}
+				None => false,
+			};
+
+			entries.extend(page_entries);
+
+			if contains_read {
+				tracing::debug!("Page #{page_num} contains already read entries, stopping");
+				self.truncated = true;
+				break;
+			}
+
+			if page_num == self.max_pages {
+				tracing::debug!("Reached the max amount of pages, stopping");
+				self.truncated = true;
+				break;
+			}
+
+			let Some(next_url) = self.next_page_url(&url, &contents, entry_count)? else {
+				tracing::debug!("Page #{page_num} is the last one");
+				break;
+			};
+
+			if next_url == url {
+				tracing::warn!("The next page has the same URL as the current one, stopping");
+				break;
+			}
+
+			page = Entry {
+				raw_contents: Some(self.http.client.send(&self.http.request, &next_url).await?),
+				msg: Message {
+					link: Some(next_url.clone()),
+					..Default::default()
+				},
+				..Default::default()
+			};
+			url = next_url;
+		}
+
+		tracing::debug!("Got {} entries in total", entries.len());
+		Ok(entries)
+	}
+
+	fn next_page_url(
+		&self,
+		url: &Url,
+		contents: &str,
+		entry_count: usize,
+	) -> Result<Option<Url>, PaginatedError> {
+		let (param, default, step) = match &self.next_page {
+			NextPage::Link(query) => {
+				let link = match query {
+					LinkQuery::Json(query) => json::find_string(contents, query)?,
+					LinkQuery::Html(query) => html::find_string(contents, query)?,
+				};
+
+				return link
+					.filter(|link| !link.is_empty())
+					.map(|link| url.join(&link).map_err(|e| InvalidUrlError(e, link).into()))
+					.transpose();
+			}
+			NextPage::Page(param) => (param, 1, 1),
+			NextPage::Offset(param) => (param, 0, entry_count),
+		};
+
+		let current = match url.query_pairs().find(|(name, _)| name == param) {
+			Some((_, value)) => value
+				.parse::<usize>()
+				.map_err(|_| PaginatedError::BadParam {
+					name: param.clone(),
+					url: url.to_string(),
+				})?,
+			None => default,
+		};
+
+		let other_pairs = url
+			.query_pairs()
+			.filter(|(name, _)| name != param)
+			.map(|(name, value)| (name.into_owned(), value.into_owned()))
+			.collect::<Vec<_>>();
+
+		let mut next_url = url.clone();
+		next_url
+			.query_pairs_mut()
+			.clear()
+			.extend_pairs(other_pairs)
+			.append_pair(param, &(current + step).to_string());
+
+		Ok(Some(next_url))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+	use crate::{
+		action::transform::{
+			Json,
+			entry::json::{Key, Query},
+		},
+		read_filter::{MarkAsRead, NotPresent},
+	};
+
+	use std::sync::Arc;
+	use tokio::sync::RwLock;
+	use wiremock::{
+		Mock, MockServer, ResponseTemplate,
+		matchers::{method, path, query_param},
+	};
+
+	fn query(keys: &[&str]) -> StringQuery {
+		StringQuery {
+			query: Query {
+				keys: keys.iter().map(|&k| Key::String(k.to_owned())).collect(),
+				optional: true,
+			},
+			regex: None,
+		}
+	}
+
+	fn parser() -> Box<dyn Transform> {
+		Box::new(Json {
+			item: Some(Query {
+				keys: vec![Key::String("items".to_owned())],
+				optional: false,
+			}),
+			title: None,
+			text: None,
+			id: Some(query(&["id"])),
+			link: None,
+			img: None,
+		})
+	}
+
+	async fn mount_page(server: &MockServer, page: &str, body: serde_json::Value) {
+		Mock::given(method("GET"))
+			.and(path("/api"))
+			.and(query_param("page", page))
+			.respond_with(ResponseTemplate::new(200).set_body_json(body))
+			.mount(server)
+			.await;
+	}
+
+	fn ids(entries: &[Entry]) -> Vec<&str> {
+		entries
+			.iter()
+			.map(|e| e.id.as_ref().unwrap().0.as_str())
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn follows_links() {
+		let server = MockServer::start().await;
+
+		mount_page(
+			&server,
+			"1",
+			serde_json::json!({ "items": [{ "id": "1" }, { "id": "2" }], "next": "api?page=2" }),
+		)
+		.await;
+		mount_page(
+			&server,
+			"2",
+			serde_json::json!({ "items": [{ "id": "3" }], "next": format!("{}/api?page=3", server.uri()) }),
+		)
+		.await;
+		mount_page(&server, "3", serde_json::json!({ "items": [] })).await;
+
+		let mut paginated = Paginated::new(
+			Http::new_get(format!("{}/api?page=1", server.uri()).parse().unwrap()).unwrap(),
+			parser(),
+			NextPage::Link(LinkQuery::Json(query(&["next"]))),
+		);
+
+		let entries = paginated.fetch().await.unwrap();
+		assert_eq!(ids(&entries), ["1", "2", "3"]);
+		assert!(paginated.fetched_everything());
+	}
+
+	#[tokio::test]
+	async fn stops_at_max_pages_and_read_entries() {
+		let server = MockServer::start().await;
+
+		for page in 2..=3 {
+			mount_page(
+				&server,
+				&page.to_string(),
+				serde_json::json!({ "items": [{ "id": format!("{page}a") }, { "id": format!("{page}b") }] }),
+			)
+			.await;
+		}
+
+		let mut paginated = Paginated::new(
+			Http::new_get(format!("{}/api", server.uri()).parse().unwrap()).unwrap(),
+			parser(),
+			NextPage::Page("page".to_owned()),
+		);
+		paginated.max_pages = 2;
+
+		// the first page doesn't have the param set and is requested as is
+		Mock::given(method("GET"))
+			.and(path("/api"))
+			.respond_with(
+				ResponseTemplate::new(200).set_body_json(
+					serde_json::json!({ "items": [{ "id": "1a" }, { "id": "1b" }] }),
+				),
+			)
+			.with_priority(10)
+			.mount(&server)
+			.await;
+
+		assert_eq!(
+			ids(&paginated.fetch().await.unwrap()),
+			["1a", "1b", "2a", "2b"]
+		);
+		assert!(!paginated.fetched_everything());
+
+		let mut rf = NotPresent::new();
+		rf.mark_as_read(&"2b".to_owned().into()).await.unwrap();
+
+		paginated.max_pages = DEFAULT_MAX_PAGES;
+		paginated.read_filter = Some(Box::new(Arc::new(RwLock::new(rf))));
+
+		// stops after page 2 instead of fetching page 3
+		assert_eq!(
+			ids(&paginated.fetch().await.unwrap()),
+			["1a", "1b", "2a", "2b"]
+		);
+		assert!(!paginated.fetched_everything());
+	}
+
+	#[tokio::test]
+	async fn offset() {
+		let paginated = Paginated::new(
+			Http::new_get("https://example.com/api?limit=2&offset=4".parse().unwrap()).unwrap(),
+			parser(),
+			NextPage::Offset("offset".to_owned()),
+		);
+
+		let next = paginated
+			.next_page_url(&paginated.http.url, "", 2)
+			.unwrap()
+			.unwrap();
+		assert_eq!(next.as_str(), "https://example.com/api?limit=2&offset=6");
+	}
+}
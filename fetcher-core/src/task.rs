@@ -6,13 +6,15 @@
 
 //! This module contains the basic block of [`fetcher`](`crate`) that is a [`Task`]
 
+pub mod digest;
 pub mod entry_to_msg_map;
 
-use self::entry_to_msg_map::EntryToMsgMap;
+use self::{digest::Digest, entry_to_msg_map::EntryToMsgMap};
 use crate::{
 	action::Action,
 	entry::{Entry, EntryId},
 	error::FetcherError,
+	metrics as metrics_names,
 	sink::{
 		Sink,
 		message::{Message, MessageId},
@@ -20,13 +22,18 @@ use crate::{
 	source::Source,
 };
 
-use std::{borrow::Cow, collections::HashSet};
+use futures::future::BoxFuture;
+use std::{
+	borrow::Cow,
+	collections::HashSet,
+	time::{SystemTime, UNIX_EPOCH},
+};
 
 /// A core primitive of [`fetcher`](`crate`).
 ///
 /// Contains everything from a [`Source`] that allows to fetch some data, to a [`Sink`] that takes that data and sends it somewhere.
 /// It also contains any transformators
-#[derive(Debug)]
+#[derive(Default, Debug)]
 pub struct Task {
 	/// An optional tag that may be put near a message body to differentiate this task from others that may be similar
 	pub tag: Option<String>,
@@ -39,6 +46,29 @@ pub struct Task {
 
 	/// Map of an entry to a message. Used when an entry is a reply to an older entry to be able to show that as a message, too
 	pub entry_to_msg_map: Option<EntryToMsgMap>,
+
+	/// Send all entries as a single digest message instead of one message per entry
+	pub digest: Option<Digest>,
+
+	/// Edit the already sent message of an entry when the entry changes instead of ignoring it as already read.
+	///
+	/// Entries that have already been sent, i.e. are present in [`Task::entry_to_msg_map`], aren't removed by read filters.
+	/// Instead, their messages are compared to the ones they were sent as, and the sent message is edited via [`Sink::edit()`] if they differ.
+	/// Does nothing without [`Task::entry_to_msg_map`] and when sending a [`Task::digest`]
+	pub edit_on_update: bool,
+
+	/// Delete the already sent message of an entry when the entry is no longer returned by the source, e.g. when a job listing gets taken down.
+	///
+	/// An entry is considered removed if it's present in [`Task::entry_to_msg_map`] but hasn't reached a read filter or a sink this time,
+	/// i.e. its id is compared after the entries have been transformed but before read filters have removed the already read ones.
+	/// Thus the source should return all currently available entries on every fetch.
+	/// The message is deleted via [`Sink::delete()`] of the first sink of the task.
+	/// Does nothing without [`Task::entry_to_msg_map`], when sending a [`Task::digest`], if no entries have reached a read filter or a sink,
+	/// and if the source hasn't [`fetched everything`](`crate::source::Fetch::fetched_everything`), e.g. because a page hasn't changed since the last fetch
+	pub delete_removed: bool,
+
+	/// Labels to add to all [`metrics`](`crate::metrics`) recorded by this task, e.g. the name of the job and the task
+	pub metrics_labels: Vec<(&'static str, String)>,
 }
 
 impl Task {
@@ -49,79 +79,390 @@ impl Task {
 	#[tracing::instrument(skip(self))]
 	pub async fn run(&mut self) -> Result<(), FetcherError> {
 		tracing::trace!("Running task");
+		metrics::counter!(metrics_names::TASK_RUNS, &self.metrics_labels).increment(1);
+
+		match self.run_inner().await {
+			Ok(()) => {
+				let now = SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.unwrap_or_default();
+
+				metrics::gauge!(metrics_names::TASK_LAST_SUCCESS, &self.metrics_labels)
+					.set(now.as_secs_f64());
+
+				Ok(())
+			}
+			Err(e) => {
+				let mut labels = self.metrics_labels.clone();
+				labels.push(("kind", metrics_names::error_kind(&e).to_owned()));
+				metrics::counter!(metrics_names::TASK_ERRORS, &labels).increment(1);
+
+				Err(e)
+			}
+		}
+	}
+
+	/// Wait until the source of the task gets notified that new entries are available.
+	/// See [`Fetch::wait_for_new_entries`](`crate::source::Fetch::wait_for_new_entries`)
+	pub async fn wait_for_new_entries(&mut self) {
+		match &mut self.source {
+			Some(source) => source.wait_for_new_entries().await,
+			None => std::future::pending().await,
+		}
+	}
 
+	async fn run_inner(&mut self) -> Result<(), FetcherError> {
 		let raw = match &mut self.source {
 			Some(source) => source.fetch().await?,
 			None => vec![Entry::default()], // return just an empty entry if there is no source
 		};
 
 		tracing::debug!("Got {} raw entries from the sources", raw.len());
+		metrics::counter!(metrics_names::ENTRIES_FETCHED, &self.metrics_labels)
+			.increment(raw.len() as u64);
 		tracing::trace!("Raw entries: {raw:#?}");
 
-		self.process_entries(raw).await?;
+		let present = self.process_entries(raw).await?;
+
+		if let Some(present) = present {
+			self.delete_removed_entries(&present).await?;
+		}
+
+		if let Some(source) = &mut self.source {
+			source.save_cache().await?;
+		}
+
+		Ok(())
+	}
+
+	/// Delete the messages of all already sent entries that aren't `present` anymore
+	async fn delete_removed_entries(
+		&mut self,
+		present: &HashSet<EntryId>,
+	) -> Result<(), FetcherError> {
+		let (Some(source), Some(map), Some(actions)) =
+			(&self.source, &mut self.entry_to_msg_map, &self.actions)
+		else {
+			return Ok(());
+		};
+
+		let Some(sink) = first_sink(actions) else {
+			return Ok(());
+		};
+
+		// entries that haven't been fetched this time might still be available
+		if !source.fetched_everything() {
+			tracing::debug!("Source hasn't returned all of its entries, not deleting anything");
+			return Ok(());
+		}
+
+		// it's more likely that the source is temporarily broken than that every single entry has been removed at once
+		if present.is_empty() {
+			tracing::debug!("No entries have been fetched, not deleting anything");
+			return Ok(());
+		}
+
+		let removed = map
+			.iter()
+			.filter(|(eid, _)| !present.contains(eid))
+			.map(|(eid, msgid)| (eid.clone(), msgid.clone()))
+			.collect::<Vec<_>>();
+
+		for (eid, msgid) in removed {
+			tracing::info!(
+				"Entry {eid:?} has been removed from the source, deleting message {msgid:?}"
+			);
+			sink.delete(&msgid).await?;
+
+			for part in map.parts(&eid).to_vec() {
+				sink.delete(&part).await?;
+			}
+
+			map.remove(&eid).await?;
+		}
 
 		Ok(())
 	}
 
-	// TODO: figure out a way to split into several functions to avoid 15 level nesting?
-	// It's a bit difficult because this function can't be a method because we are borrowing self.actions
-	// throughout the entire process
-	async fn process_entries(&mut self, mut entries: Vec<Entry>) -> Result<(), FetcherError> {
-		for act in self.actions.iter().flatten() {
+	/// Run `entries` through the actions of the task.
+	///
+	/// Returns the ids of all entries that have reached a read filter or a sink if [`Task::delete_removed`] is enabled
+	async fn process_entries(
+		&mut self,
+		entries: Vec<Entry>,
+	) -> Result<Option<HashSet<EntryId>>, FetcherError> {
+		let Some(actions) = &self.actions else {
+			return Ok(None);
+		};
+
+		// a digest message contains other entries that might still be available
+		let track_present = self.delete_removed
+			&& self.source.is_some()
+			&& self.digest.is_none()
+			&& self.entry_to_msg_map.is_some();
+
+		let mut cx = ProcessCx {
+			tag: self.tag.as_deref(),
+			source: self.source.as_mut(),
+			entry_to_msg_map: self.entry_to_msg_map.as_mut(),
+			digest: self.digest.as_ref(),
+			edit_on_update: self.edit_on_update,
+			present: track_present.then(HashSet::new),
+			metrics_labels: &self.metrics_labels,
+		};
+
+		process_actions(actions, entries.into_iter().enumerate().collect(), &mut cx).await?;
+
+		Ok(cx.present)
+	}
+}
+/// Parts of a [`Task`] that are needed while running entries through its actions
+struct ProcessCx<'a> {
+	tag: Option<&'a str>,
+	source: Option<&'a mut Box<dyn Source>>,
+	entry_to_msg_map: Option<&'a mut EntryToMsgMap>,
+	digest: Option<&'a Digest>,
+	edit_on_update: bool,
+
+	/// Ids of all entries that have reached a read filter or a sink, if they should be tracked
+	present: Option<HashSet<EntryId>>,
+
+	metrics_labels: &'a [(&'static str, String)],
+}
+
+impl ProcessCx<'_> {
+	/// Returns the map of already sent entries if their messages should be edited when they change
+	fn edit_map(&mut self) -> Option<&mut EntryToMsgMap> {
+		if self.edit_on_update && self.digest.is_none() {
+			self.entry_to_msg_map.as_deref_mut()
+		} else {
+			None
+		}
+	}
+
+	/// Remember the ids of `entries` as present in the source
+	fn remember_present(&mut self, entries: &[Entry]) {
+		if let Some(present) = &mut self.present {
+			present.extend(entries.iter().filter_map(|entry| entry.id.clone()));
+		}
+	}
+}
+
+/// Run `entries` through all `actions` one by one and return the entries left after the last one.
+///
+/// Every entry is paired with the position of the fetched entry it comes from,
+/// to put the entries left after a [branch](`Action::Branch`) back in their original order.
+///
+/// Boxed because it calls itself recursively to process [branches](`Action::Branch`)
+fn process_actions<'a>(
+	actions: &'a [Action],
+	entries: Vec<(usize, Entry)>,
+	cx: &'a mut ProcessCx<'_>,
+) -> BoxFuture<'a, Result<Vec<(usize, Entry)>, FetcherError>> {
+	Box::pin(async move {
+		let (mut positions, mut entries): (Vec<usize>, Vec<Entry>) = entries.into_iter().unzip();
+
+		for act in actions {
 			match act {
 				Action::Filter(f) => {
+					if f.is_readfilter() {
+						cx.remember_present(&entries);
+					}
+
+					// already sent entries should get to the sink to check if they have changed
+					let sent = match cx.edit_map() {
+						Some(map) if f.is_readfilter() => positions
+							.iter()
+							.zip(&entries)
+							.filter(|(_, entry)| map.get_if_exists(entry.id.as_ref()).is_some())
+							.map(|(pos, entry)| (*pos, entry.clone()))
+							.collect(),
+						_ => Vec::new(),
+					};
+
+					let unfiltered_ids = entry_ids(&entries);
 					f.filter(&mut entries).await;
+					positions = remaining_positions(&positions, &unfiltered_ids, &entries);
+
+					if f.is_readfilter() {
+						metrics::counter!(metrics_names::ENTRIES_READ_FILTERED, cx.metrics_labels)
+							.increment((unfiltered_ids.len() - entries.len()) as u64);
+					}
+
+					// put them last since they are older than any new entries
+					for (pos, entry) in sent {
+						if !entries.iter().any(|e| e.id == entry.id) {
+							positions.push(pos);
+							entries.push(entry);
+						}
+					}
 				}
 				Action::Transform(tr) => {
 					let mut fully_transformed = Vec::new();
+					let mut transformed_positions = Vec::new();
 
-					for entry in entries {
-						fully_transformed.extend(tr.transform(entry).await?);
+					for (pos, entry) in positions.into_iter().zip(entries) {
+						let transformed = tr.transform(entry).await?;
+						transformed_positions.extend(std::iter::repeat_n(pos, transformed.len()));
+						fully_transformed.extend(transformed);
 					}
 
+					positions = transformed_positions;
 					entries = fully_transformed;
 				}
 				Action::Sink(s) => {
-					let undeduped_len = entries.len();
-					tracing::trace!("Entries to send before dedup: {undeduped_len}");
+					cx.remember_present(&entries);
+
+					let unsent_ids = entry_ids(&entries);
+					entries = sink_entries(&**s, entries, cx).await?;
+					positions = remaining_positions(&positions, &unsent_ids, &entries);
+				}
+				Action::Branch(branch) => {
+					let (matching, rest): (Vec<_>, Vec<_>) = positions
+						.into_iter()
+						.zip(entries)
+						.partition(|(_, entry)| branch.condition.matches(entry));
+
+					tracing::trace!(
+						"{} entries matched the branch condition, {} didn't",
+						matching.len(),
+						rest.len()
+					);
+
+					let mut merged = process_actions(&branch.then, matching, cx).await?;
+					merged.extend(process_actions(&branch.otherwise, rest, cx).await?);
+
+					// keep the entries sorted newest to oldest for the following actions
+					merged.sort_by_key(|(pos, _)| *pos);
+					(positions, entries) = merged.into_iter().unzip();
+				}
+			}
+		}
 
-					entries = remove_duplicates(entries);
+		Ok(positions.into_iter().zip(entries).collect())
+	})
+}
 
-					if undeduped_len - entries.len() > 0 {
-						tracing::info!(
-							"Removed {} duplicate entries before sending",
-							undeduped_len - entries.len()
-						);
-					}
+fn entry_ids(entries: &[Entry]) -> Vec<Option<EntryId>> {
+	entries.iter().map(|entry| entry.id.clone()).collect()
+}
 
-					tracing::trace!("Sending entries: {entries:#?}");
-
-					// entries should be sorted newest to oldest but we should send oldest first
-					for entry in entries.iter().rev() {
-						let msg_id = send_entry(
-							&**s,
-							self.entry_to_msg_map.as_mut(),
-							self.tag.as_deref(),
-							entry,
-						)
-						.await?;
-
-						if let Some(entry_id) = entry.id.as_ref() {
-							mark_entry_as_read(
-								entry_id,
-								msg_id,
-								self.source.as_mut(),
-								self.entry_to_msg_map.as_mut(),
-							)
-							.await?;
-						}
-					}
-				}
+/// Returns the positions of the `remaining` entries, given the `positions` and the `ids` of the entries they were taken from.
+///
+/// Filters and sinks only remove entries without reordering them,
+/// so the remaining entries are matched with the original ones by their ids in order
+fn remaining_positions(
+	positions: &[usize],
+	ids: &[Option<EntryId>],
+	remaining: &[Entry],
+) -> Vec<usize> {
+	let mut originals = positions.iter().zip(ids);
+	let mut last_pos = positions.first().copied().unwrap_or_default();
+
+	remaining
+		.iter()
+		.map(|entry| {
+			if let Some((pos, _)) = originals.find(|(_, id)| **id == entry.id) {
+				last_pos = *pos;
 			}
+
+			last_pos
+		})
+		.collect()
+}
+
+/// Returns the first sink of the `actions`, including the ones inside of branches
+fn first_sink(actions: &[Action]) -> Option<&dyn Sink> {
+	actions.iter().find_map(|act| match act {
+		Action::Sink(sink) => Some(&**sink),
+		Action::Branch(branch) => {
+			first_sink(&branch.then).or_else(|| first_sink(&branch.otherwise))
 		}
+		Action::Filter(_) | Action::Transform(_) => None,
+	})
+}
 
-		Ok(())
+/// Send all `entries` to the `sink` and mark them as read, returning the entries without duplicates
+async fn sink_entries(
+	sink: &dyn Sink,
+	entries: Vec<Entry>,
+	cx: &mut ProcessCx<'_>,
+) -> Result<Vec<Entry>, FetcherError> {
+	let undeduped_len = entries.len();
+	tracing::trace!("Entries to send before dedup: {undeduped_len}");
+
+	let entries = remove_duplicates(entries);
+
+	if undeduped_len - entries.len() > 0 {
+		tracing::info!(
+			"Removed {} duplicate entries before sending",
+			undeduped_len - entries.len()
+		);
 	}
+
+	tracing::trace!("Sending entries: {entries:#?}");
+
+	let mut labels = cx.metrics_labels.to_vec();
+	labels.push(("sink", sink.name().to_owned()));
+	let sent_counter = metrics::counter!(metrics_names::ENTRIES_SENT, &labels);
+
+	if let Some(digest) = cx.digest {
+		let msg_id = send_digest(sink, digest, cx.tag, &entries).await?;
+		sent_counter.increment(entries.len() as u64);
+
+		// mark as read only after the digest has been sent successfully
+		for entry_id in entries.iter().rev().filter_map(|e| e.id.as_ref()) {
+			mark_entry_as_read(
+				entry_id,
+				msg_id.clone(),
+				Vec::new(),
+				None,
+				cx.source.as_deref_mut(),
+				cx.entry_to_msg_map.as_deref_mut(),
+			)
+			.await?;
+		}
+
+		return Ok(entries);
+	}
+
+	// entries should be sorted newest to oldest but we should send oldest first
+	for entry in entries.iter().rev() {
+		let tag = cx.tag;
+		if let Some(map) = cx.edit_map()
+			&& let Some(entry_id) = entry.id.as_ref()
+			&& let Some(msg_id) = map.get(entry_id).cloned()
+		{
+			// don't mark it as read again since that could move the read filter back to an older entry
+			update_entry(sink, map, tag, entry, entry_id, &msg_id).await?;
+			continue;
+		}
+
+		let content_hash = cx
+			.edit_map()
+			.is_some()
+			.then(|| entry_message(entry).map(|msg| msg.content_hash()))
+			.flatten();
+
+		let mut msg_ids = send_entry(sink, cx.entry_to_msg_map.as_deref_mut(), cx.tag, entry)
+			.await?
+			.into_iter();
+		sent_counter.increment(1);
+
+		if let Some(entry_id) = entry.id.as_ref() {
+			mark_entry_as_read(
+				entry_id,
+				msg_ids.next(),
+				msg_ids.collect(),
+				content_hash,
+				cx.source.as_deref_mut(),
+				cx.entry_to_msg_map.as_deref_mut(),
+			)
+			.await?;
+		}
+	}
+
+	Ok(entries)
 }
 
 #[tracing::instrument(level = "trace", skip_all, fields(entry_id = ?entry.id))]
@@ -130,26 +471,11 @@ async fn send_entry(
 	mut entry_to_msg_map: Option<&mut EntryToMsgMap>,
 	tag: Option<&str>,
 	entry: &Entry,
-) -> Result<Option<MessageId>, FetcherError> {
+) -> Result<Vec<MessageId>, FetcherError> {
 	tracing::trace!("Sending entry");
 
-	// send message if it isn't empty or raw_contents of they aren't
-	if entry.msg.is_empty() && entry.raw_contents.is_none() {
-		return Ok(None);
-	}
-
-	let msg = if entry.msg.is_empty() {
-		Cow::Owned(Message {
-			body: Some(
-				entry
-					.raw_contents
-					.clone()
-					.expect("raw_contents should be some because of the early return check"),
-			),
-			..entry.msg.clone()
-		})
-	} else {
-		Cow::Borrowed(&entry.msg)
+	let Some(msg) = entry_message(entry) else {
+		return Ok(Vec::new());
 	};
 
 	let reply_to = entry_to_msg_map
@@ -157,12 +483,83 @@ async fn send_entry(
 		.and_then(|map| map.get_if_exists(entry.reply_to.as_ref()));
 
 	tracing::debug!("Sending {msg:?} to a sink with tag {tag:?}, replying to {reply_to:?}");
-	Ok(sink.send(&msg, reply_to, tag).await?)
+	Ok(sink
+		.send_entry_parts(&msg, entry.id.as_ref(), reply_to, tag)
+		.await?)
+}
+
+/// Edit the message `msg_id` that the already sent `entry` was sent as if the entry has changed since then
+#[tracing::instrument(level = "trace", skip_all, fields(entry_id = ?entry_id))]
+async fn update_entry(
+	sink: &dyn Sink,
+	map: &mut EntryToMsgMap,
+	tag: Option<&str>,
+	entry: &Entry,
+	entry_id: &EntryId,
+	msg_id: &MessageId,
+) -> Result<(), FetcherError> {
+	let Some(msg) = entry_message(entry) else {
+		return Ok(());
+	};
+
+	let content_hash = msg.content_hash();
+
+	match map.content_hash(entry_id) {
+		Some(old_hash) if old_hash == content_hash => {
+			tracing::trace!("Entry hasn't changed since it was sent");
+		}
+		None => {
+			// it was sent before editing was enabled, so there's nothing to compare it with
+			tracing::debug!("Remembering the contents of the already sent entry");
+			map.set_content_hash(entry_id.clone(), content_hash).await?;
+		}
+		Some(_) => {
+			tracing::info!("Entry {entry_id:?} has changed, editing message {msg_id:?}");
+			sink.edit_entry(&msg, Some(entry_id), msg_id, tag).await?;
+			map.set_content_hash(entry_id.clone(), content_hash).await?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns the message to send for the `entry`: either its message, or its raw contents if the message is empty.
+/// Returns [`None`] if both are empty
+fn entry_message(entry: &Entry) -> Option<Cow<'_, Message>> {
+	if !entry.msg.is_empty() {
+		return Some(Cow::Borrowed(&entry.msg));
+	}
+
+	entry.raw_contents.as_ref().map(|raw_contents| {
+		Cow::Owned(Message {
+			body: Some(raw_contents.clone()),
+			..entry.msg.clone()
+		})
+	})
+}
+
+#[tracing::instrument(level = "trace", skip_all, fields(entries = entries.len()))]
+async fn send_digest(
+	sink: &dyn Sink,
+	digest: &Digest,
+	tag: Option<&str>,
+	entries: &[Entry],
+) -> Result<Option<MessageId>, FetcherError> {
+	// entries should be sorted newest to oldest but the digest should list oldest first
+	let Some(msg) = digest.compose(entries.iter().rev()) else {
+		tracing::debug!("Nothing to put in the digest, skipping");
+		return Ok(None);
+	};
+
+	tracing::debug!("Sending digest {msg:?} to a sink with tag {tag:?}");
+	Ok(sink.send(&msg, None, tag).await?)
 }
 
 async fn mark_entry_as_read(
 	entry_id: &EntryId,
 	msg_id: Option<MessageId>,
+	parts: Vec<MessageId>,
+	content_hash: Option<u64>,
 	// source: Option<&mut dyn Source>, // TODO: this doesn't work. Why?
 	source: Option<&mut Box<dyn Source>>,
 	entry_to_msg_map: Option<&mut EntryToMsgMap>,
@@ -174,7 +571,9 @@ async fn mark_entry_as_read(
 
 	if let Some((msgid, map)) = msg_id.zip(entry_to_msg_map) {
 		tracing::debug!("Associating entry {entry_id:?} with message {msgid:?}");
-		map.insert(entry_id.clone(), msgid).await?;
+
+		map.insert_with_parts(entry_id.clone(), msgid, parts, content_hash)
+			.await?;
 	}
 
 	Ok(())
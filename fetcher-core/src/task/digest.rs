@@ -0,0 +1,147 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! This module contains [`Digest`]
+
+use crate::{entry::Entry, sink::message::Message};
+
+use std::fmt::Write as _;
+
+/// Combine all entries a [`Task`](`crate::task::Task`) run would send into a single digest message
+/// listing their titles and links instead of sending each of them separately
+#[derive(Clone, Default, Debug)]
+pub struct Digest {
+	/// The title of the digest message
+	pub header: Option<String>,
+
+	/// The max amount of entries to list in the digest. The rest are only counted
+	pub max_items: Option<usize>,
+}
+
+impl Digest {
+	/// Compose a single digest message out of `entries`.
+	/// `entries` are expected to be sorted oldest to newest, the same order they would've been sent in.
+	///
+	/// Returns [`None`] if none of the entries have anything to list
+	#[must_use]
+	pub fn compose<'a, I>(&self, entries: I) -> Option<Message>
+	where
+		I: IntoIterator<Item = &'a Entry>,
+	{
+		let items = entries
+			.into_iter()
+			.filter_map(digest_item)
+			.collect::<Vec<_>>();
+
+		if items.is_empty() {
+			return None;
+		}
+
+		let max_items = self.max_items.unwrap_or(items.len());
+		let mut body = items
+			.iter()
+			.take(max_items)
+			.map(String::as_str)
+			.collect::<Vec<_>>()
+			.join("\n");
+
+		if let Some(left) = items.len().checked_sub(max_items).filter(|&n| n > 0) {
+			_ = write!(body, "\n...and {left} more");
+		}
+
+		Some(Message {
+			title: self.header.clone(),
+			body: Some(body),
+			..Default::default()
+		})
+	}
+}
+
+/// Make a single line of the digest out of the entry's title (or the first line of the body if there's no title) and its link
+fn digest_item(entry: &Entry) -> Option<String> {
+	let msg = &entry.msg;
+	let title = msg
+		.title
+		.as_deref()
+		.or_else(|| msg.body.as_deref().and_then(|body| body.lines().next()))
+		.map(str::trim)
+		.filter(|title| !title.is_empty());
+
+	match (title, &msg.link) {
+		(Some(title), Some(link)) => Some(format!("• {title}\n{link}")),
+		(Some(title), None) => Some(format!("• {title}")),
+		(None, Some(link)) => Some(format!("• {link}")),
+		(None, None) => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	fn entry(title: Option<&str>, body: Option<&str>, link: Option<&str>) -> Entry {
+		Entry {
+			msg: Message {
+				title: title.map(ToOwned::to_owned),
+				body: body.map(ToOwned::to_owned),
+				link: link.map(|l| l.try_into().unwrap()),
+				..Default::default()
+			},
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn compose() {
+		let digest = Digest {
+			header: Some("Daily digest".to_owned()),
+			max_items: None,
+		};
+
+		let entries = [
+			entry(Some("First"), Some("body"), Some("https://example.com/1")),
+			entry(None, Some("Second\nrest of the body"), None),
+			entry(None, None, Some("https://example.com/3")),
+			entry(None, None, None),
+		];
+
+		let msg = digest.compose(&entries).unwrap();
+		assert_eq!(msg.title.as_deref(), Some("Daily digest"));
+		assert_eq!(
+			msg.body.as_deref(),
+			Some("• First\nhttps://example.com/1\n• Second\n• https://example.com/3")
+		);
+		assert!(msg.link.is_none());
+	}
+
+	#[test]
+	fn compose_max_items() {
+		let digest = Digest {
+			header: None,
+			max_items: Some(2),
+		};
+
+		let entries = [
+			entry(Some("1"), None, None),
+			entry(Some("2"), None, None),
+			entry(Some("3"), None, None),
+			entry(Some("4"), None, None),
+		];
+
+		let msg = digest.compose(&entries).unwrap();
+		assert_eq!(msg.body.as_deref(), Some("• 1\n• 2\n...and 2 more"));
+	}
+
+	#[test]
+	fn compose_empty() {
+		let digest = Digest::default();
+
+		assert!(digest.compose(&[entry(None, None, None)]).is_none());
+		assert!(digest.compose(&[]).is_none());
+	}
+}
@@ -23,6 +23,14 @@ pub struct EntryToMsgMap {
 	pub external_save: Option<Box<dyn ExternalSave>>,
 
 	map: HashMap<EntryId, MessageId>,
+
+	/// [`Message::content_hash()`](`crate::sink::message::Message::content_hash()`) of the message each entry was last sent as.
+	/// Used to check if an entry has changed since then
+	content_hashes: HashMap<EntryId, u64>,
+
+	/// Ids of the other messages each entry was sent as if the sink has split it into several ones, e.g. because it was too long.
+	/// Used to delete all of them if the entry has been removed
+	parts: HashMap<EntryId, Vec<MessageId>>,
 }
 
 impl EntryToMsgMap {
@@ -36,6 +44,8 @@ impl EntryToMsgMap {
 		Self {
 			external_save: Some(Box::new(external_save)),
 			map: HashMap::new(),
+			content_hashes: HashMap::new(),
+			parts: HashMap::new(),
 		}
 	}
 
@@ -48,23 +58,104 @@ impl EntryToMsgMap {
 		Self {
 			external_save: Some(Box::new(external_save)),
 			map,
+			content_hashes: HashMap::new(),
+			parts: HashMap::new(),
 		}
 	}
 
+	/// Set the previously saved content hashes of the messages of the entries
+	#[must_use]
+	pub fn with_content_hashes(mut self, content_hashes: HashMap<EntryId, u64>) -> Self {
+		self.content_hashes = content_hashes;
+		self
+	}
+
+	/// Set the previously saved ids of the other messages the entries were sent as
+	#[must_use]
+	pub fn with_parts(mut self, parts: HashMap<EntryId, Vec<MessageId>>) -> Self {
+		self.parts = parts;
+		self
+	}
+
 	/// Insert a mapping from [`EntryId`] `eid` to [`MessageId`] `msgid` and save that externally
 	///
 	/// # Errors
 	/// if external save has failed
 	pub async fn insert(&mut self, eid: EntryId, msgid: MessageId) -> Result<(), FetcherError> {
+		self.parts.remove(&eid);
 		self.map.insert(eid, msgid);
-		if let Some(ext_save) = &mut self.external_save {
-			ext_save
-				.save_entry_to_msg_map(&self.map)
-				.await
-				.map_err(FetcherError::ExternalSave)?;
+		self.save().await
+	}
+
+	/// Insert a mapping from [`EntryId`] `eid` to [`MessageId`] `msgid` alongside the `content_hash` of the sent message and save that externally
+	///
+	/// # Errors
+	/// if external save has failed
+	pub async fn insert_with_content_hash(
+		&mut self,
+		eid: EntryId,
+		msgid: MessageId,
+		content_hash: u64,
+	) -> Result<(), FetcherError> {
+		self.content_hashes.insert(eid.clone(), content_hash);
+		self.parts.remove(&eid);
+		self.map.insert(eid, msgid);
+		self.save().await
+	}
+
+	/// Insert a mapping from [`EntryId`] `eid` to [`MessageId`] `msgid` alongside the ids of the other messages the entry was sent as (`parts`)
+	/// and the `content_hash` of the sent message if it's known, and save that externally
+	///
+	/// # Errors
+	/// if external save has failed
+	pub async fn insert_with_parts(
+		&mut self,
+		eid: EntryId,
+		msgid: MessageId,
+		parts: Vec<MessageId>,
+		content_hash: Option<u64>,
+	) -> Result<(), FetcherError> {
+		if let Some(content_hash) = content_hash {
+			self.content_hashes.insert(eid.clone(), content_hash);
 		}
 
-		Ok(())
+		if parts.is_empty() {
+			self.parts.remove(&eid);
+		} else {
+			self.parts.insert(eid.clone(), parts);
+		}
+
+		self.map.insert(eid, msgid);
+		self.save().await
+	}
+
+	/// Set the content hash of the message the entry with [`EntryId`] `eid` was sent as and save that externally
+	///
+	/// # Errors
+	/// if external save has failed
+	pub async fn set_content_hash(
+		&mut self,
+		eid: EntryId,
+		content_hash: u64,
+	) -> Result<(), FetcherError> {
+		self.content_hashes.insert(eid, content_hash);
+		self.save().await
+	}
+
+	/// Remove the mapping of the entry with [`EntryId`] `eid` alongside its content hash and other messages and save that externally
+	///
+	/// # Errors
+	/// if external save has failed
+	pub async fn remove(&mut self, eid: &EntryId) -> Result<(), FetcherError> {
+		self.map.remove(eid);
+		self.content_hashes.remove(eid);
+		self.parts.remove(eid);
+		self.save().await
+	}
+
+	/// Iterate over all mappings from [`EntryId`] to [`MessageId`]
+	pub fn iter(&self) -> impl Iterator<Item = (&EntryId, &MessageId)> {
+		self.map.iter()
 	}
 
 	/// Get the [`MessageId`] corresponding to the provided [`EntryId`]
@@ -78,4 +169,27 @@ impl EntryToMsgMap {
 	pub fn get_if_exists(&self, eid: Option<&EntryId>) -> Option<&MessageId> {
 		eid.and_then(|eid| self.map.get(eid))
 	}
+
+	/// Get the ids of the other messages the entry with [`EntryId`] `eid` was sent as, apart from the one returned by [`Self::get()`]
+	#[must_use]
+	pub fn parts(&self, eid: &EntryId) -> &[MessageId] {
+		self.parts.get(eid).map_or(&[], Vec::as_slice)
+	}
+
+	/// Get the content hash of the message the entry with [`EntryId`] `eid` was last sent as, if it's known
+	#[must_use]
+	pub fn content_hash(&self, eid: &EntryId) -> Option<u64> {
+		self.content_hashes.get(eid).copied()
+	}
+
+	async fn save(&mut self) -> Result<(), FetcherError> {
+		if let Some(ext_save) = &mut self.external_save {
+			ext_save
+				.save_entry_to_msg_map(&self.map, &self.content_hashes, &self.parts)
+				.await
+				.map_err(FetcherError::ExternalSave)?;
+		}
+
+		Ok(())
+	}
 }
@@ -0,0 +1,124 @@
+//! This test asserts that branches send matching and non-matching entries to different sinks,
+//! can be nested, and pass the entries left after them down to the next action in their original order
+
+#![allow(clippy::missing_assert_message)]
+#![allow(clippy::tests_outside_test_module)]
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use fetcher_core::{
+	action::{
+		Action,
+		branch::Branch,
+		filter::{Condition, Contains},
+		transform::{Caps, field::Field, field::TransformFieldWrapper},
+	},
+	read_filter::Newer,
+	source::SourceWithSharedRF,
+	task::Task,
+};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use self::common::{DummySink, DummySource, Log, entry};
+
+fn sink(name: &'static str, log: &Log) -> Action {
+	Action::Sink(Box::new(DummySink::new(name, log)))
+}
+
+fn contains(re: &str) -> Box<dyn Condition> {
+	Box::new(Contains::new(re, Field::Title).unwrap())
+}
+
+#[tokio::test]
+async fn branch() {
+	let log = Log::default();
+
+	let mut task = Task {
+		source: Some(Box::new(DummySource::new(
+			["rust news", "rust release", "go news"]
+				.into_iter()
+				.map(|title| entry(title, title))
+				.collect(),
+		))),
+		actions: Some(vec![
+			Action::Branch(Branch {
+				condition: contains("^rust"),
+				then: vec![Action::Branch(Branch {
+					condition: contains("release"),
+					then: vec![
+						Action::Transform(Box::new(TransformFieldWrapper {
+							field: Field::Title,
+							transformator: Caps,
+						})),
+						sink("releases", &log),
+					],
+					otherwise: vec![sink("rust", &log)],
+				})],
+				otherwise: vec![sink("other", &log)],
+			}),
+			sink("all", &log),
+		]),
+		..Default::default()
+	};
+
+	task.run().await.unwrap();
+
+	assert_eq!(
+		*log.lock().unwrap(),
+		[
+			"releases: sent RUST RELEASE",
+			"rust: sent rust news",
+			"other: sent go news",
+			"all: sent go news",
+			"all: sent RUST RELEASE",
+			"all: sent rust news",
+		]
+	);
+}
+
+#[tokio::test]
+async fn branch_marks_newest_as_read_last() {
+	let log = Log::default();
+	let rf = Arc::new(RwLock::new(Newer::new()));
+
+	let mut task = Task {
+		source: Some(Box::new(SourceWithSharedRF {
+			source: DummySource::new(
+				["go news", "rust news", "rust release"]
+					.into_iter()
+					.map(|title| entry(title, title))
+					.collect(),
+			),
+			rf: Some(Arc::clone(&rf)),
+		})),
+		actions: Some(vec![
+			Action::Filter(Box::new(Arc::clone(&rf))),
+			Action::Branch(Branch {
+				condition: contains("^rust"),
+				then: vec![sink("rust", &log)],
+				otherwise: vec![sink("other", &log)],
+			}),
+			sink("all", &log),
+		]),
+		..Default::default()
+	};
+
+	task.run().await.unwrap();
+	assert_eq!(rf.read().await.last_read().unwrap().0, "go news");
+
+	// nothing is sent again
+	task.run().await.unwrap();
+	assert_eq!(
+		*log.lock().unwrap(),
+		[
+			"rust: sent rust release",
+			"rust: sent rust news",
+			"other: sent go news",
+			"all: sent rust release",
+			"all: sent rust news",
+			"all: sent go news",
+		]
+	);
+}
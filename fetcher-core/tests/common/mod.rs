@@ -0,0 +1,150 @@
+//! Sources and sinks shared by the integration tests that log what the task does with them
+
+#![allow(dead_code, reason = "not every test uses every fixture")]
+
+use async_trait::async_trait;
+use fetcher_core::{
+	entry::{Entry, EntryId},
+	error::FetcherError,
+	read_filter::MarkAsRead,
+	sink::{
+		Sink,
+		error::SinkError,
+		message::{Message, MessageId},
+	},
+	source::{Fetch, Source, error::SourceError},
+};
+use std::sync::{
+	Arc, Mutex,
+	atomic::{AtomicBool, AtomicI64, Ordering},
+};
+
+/// A log of everything [`DummySource`] and [`DummySink`] have done
+pub type Log = Arc<Mutex<Vec<String>>>;
+
+/// A source that returns a copy of its `entries` on every fetch and logs every entry it has marked as read
+#[derive(Debug)]
+pub struct DummySource {
+	pub entries: Arc<Mutex<Vec<Entry>>>,
+	pub log: Log,
+
+	/// Pretend that some entries haven't been fetched if `false`
+	pub fetched_everything: Arc<AtomicBool>,
+}
+
+/// A sink that logs every message it has sent, edited, or deleted, prefixed with its `name`.
+/// Sent messages get consecutive ids starting from 0
+#[derive(Default, Debug)]
+pub struct DummySink {
+	pub name: &'static str,
+	pub log: Log,
+	next_message_id: AtomicI64,
+}
+
+/// Creates an entry with `id` and a message with `title`
+pub fn entry(id: &str, title: &str) -> Entry {
+	Entry {
+		id: Some(EntryId(id.to_owned())),
+		msg: Message {
+			title: Some(title.to_owned()),
+			..Default::default()
+		},
+		..Default::default()
+	}
+}
+
+impl DummySource {
+	pub fn new(entries: Vec<Entry>) -> Self {
+		Self {
+			entries: Arc::new(Mutex::new(entries)),
+			log: Log::default(),
+			fetched_everything: Arc::new(AtomicBool::new(true)),
+		}
+	}
+}
+
+#[async_trait]
+impl Fetch for DummySource {
+	async fn fetch(&mut self) -> Result<Vec<Entry>, SourceError> {
+		Ok(self.entries.lock().unwrap().clone())
+	}
+
+	fn fetched_everything(&self) -> bool {
+		self.fetched_everything.load(Ordering::Relaxed)
+	}
+}
+
+#[async_trait]
+impl MarkAsRead for DummySource {
+	async fn mark_as_read(&mut self, id: &EntryId) -> Result<(), FetcherError> {
+		self.log.lock().unwrap().push(format!("read {}", id.0));
+		Ok(())
+	}
+
+	async fn set_read_only(&mut self) {}
+}
+
+impl Source for DummySource {}
+
+impl DummySink {
+	pub fn new(name: &'static str, log: &Log) -> Self {
+		Self {
+			name,
+			log: Arc::clone(log),
+			next_message_id: AtomicI64::new(0),
+		}
+	}
+}
+
+#[async_trait]
+impl Sink for DummySink {
+	async fn send(
+		&self,
+		message: &Message,
+		reply_to: Option<&MessageId>,
+		_tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		let reply_to = reply_to
+			.map(|id| format!(" in reply to {id:?}"))
+			.unwrap_or_default();
+
+		self.log
+			.lock()
+			.unwrap()
+			.push(format!("{}: sent {}{reply_to}", self.name, text(message)));
+
+		let id = self.next_message_id.fetch_add(1, Ordering::Relaxed);
+		Ok(Some(MessageId::Int(id)))
+	}
+
+	async fn edit(
+		&self,
+		message: &Message,
+		message_id: &MessageId,
+		_tag: Option<&str>,
+	) -> Result<(), SinkError> {
+		self.log.lock().unwrap().push(format!(
+			"{}: edited {message_id:?} to {}",
+			self.name,
+			text(message)
+		));
+		Ok(())
+	}
+
+	async fn delete(&self, message_id: &MessageId) -> Result<(), SinkError> {
+		self.log
+			.lock()
+			.unwrap()
+			.push(format!("{}: deleted {message_id:?}", self.name));
+		Ok(())
+	}
+}
+
+/// Returns the title of the message, or its body if it doesn't have one
+fn text(message: &Message) -> &str {
+	message
+		.title
+		.as_deref()
+		.or(message.body.as_deref())
+		.unwrap_or_default()
+}
@@ -0,0 +1,181 @@
+//! This test asserts that the message of an already sent entry, alongside all the other messages it has been split into,
+//! is deleted once the source stops returning that entry
+
+#![allow(clippy::missing_assert_message)]
+#![allow(clippy::tests_outside_test_module)]
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use async_trait::async_trait;
+use fetcher_core::{
+	action::{
+		Action,
+		transform::{Transform, error::TransformError},
+	},
+	entry::{Entry, EntryId},
+	read_filter::NotPresent,
+	sink::{
+		Sink,
+		error::SinkError,
+		message::{Message, MessageId},
+	},
+	source::SourceWithSharedRF,
+	task::{Task, entry_to_msg_map::EntryToMsgMap},
+};
+use std::sync::{Arc, atomic::Ordering};
+use tokio::sync::RwLock;
+
+use self::common::{DummySink, DummySource, Log};
+
+/// Splits a page into an entry per line, like a feed or an HTML transform would
+#[derive(Debug)]
+struct SplitLines;
+
+#[async_trait]
+impl Transform for SplitLines {
+	async fn transform(&self, entry: Entry) -> Result<Vec<Entry>, TransformError> {
+		Ok(entry
+			.raw_contents
+			.unwrap_or_default()
+			.lines()
+			.map(|line| common::entry(line, line))
+			.collect())
+	}
+}
+
+/// A sink that splits every message into two, the second one being the main one
+#[derive(Debug)]
+struct SplittingSink(DummySink);
+
+#[async_trait]
+impl Sink for SplittingSink {
+	async fn send(
+		&self,
+		message: &Message,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Option<MessageId>, SinkError> {
+		self.0.send(message, reply_to, tag).await
+	}
+
+	async fn send_entry_parts(
+		&self,
+		message: &Message,
+		_entry_id: Option<&EntryId>,
+		reply_to: Option<&MessageId>,
+		tag: Option<&str>,
+	) -> Result<Vec<MessageId>, SinkError> {
+		let first = self.0.send(message, reply_to, tag).await?;
+		let second = self.0.send(message, first.as_ref(), tag).await?;
+
+		Ok(second.into_iter().chain(first).collect())
+	}
+
+	async fn delete(&self, message_id: &MessageId) -> Result<(), SinkError> {
+		self.0.delete(message_id).await
+	}
+}
+
+/// A page without an id that contains an entry per each of `ids`
+fn page(ids: &[&str]) -> Vec<Entry> {
+	vec![Entry {
+		raw_contents: Some(ids.join("\n")),
+		..Default::default()
+	}]
+}
+
+#[tokio::test]
+async fn delete_removed() {
+	let log = Log::default();
+	let source = DummySource::new(page(&["1", "0"]));
+	let entries = Arc::clone(&source.entries);
+	let fetched_everything = Arc::clone(&source.fetched_everything);
+	let rf = Arc::new(RwLock::new(NotPresent::new()));
+
+	let mut task = Task {
+		source: Some(Box::new(SourceWithSharedRF {
+			source,
+			rf: Some(Arc::clone(&rf)),
+		})),
+		actions: Some(vec![
+			Action::Transform(Box::new(SplitLines)),
+			Action::Filter(Box::new(rf)),
+			Action::Sink(Box::new(DummySink::new("sink", &log))),
+		]),
+		entry_to_msg_map: Some(EntryToMsgMap::default()),
+		delete_removed: true,
+		..Default::default()
+	};
+
+	task.run().await.unwrap();
+	assert_eq!(*log.lock().unwrap(), ["sink: sent 0", "sink: sent 1"]);
+	log.lock().unwrap().clear();
+
+	// entries filtered out as already read are still present in the source
+	task.run().await.unwrap();
+	assert!(log.lock().unwrap().is_empty());
+
+	// nothing is deleted if the source hasn't returned all of its entries
+	*entries.lock().unwrap() = page(&["1"]);
+	fetched_everything.store(false, Ordering::Relaxed);
+	task.run().await.unwrap();
+	assert!(log.lock().unwrap().is_empty());
+
+	fetched_everything.store(true, Ordering::Relaxed);
+	task.run().await.unwrap();
+	assert_eq!(*log.lock().unwrap(), ["sink: deleted Int(0)"]);
+	log.lock().unwrap().clear();
+
+	// an empty page is ignored
+	*entries.lock().unwrap() = page(&[]);
+	task.run().await.unwrap();
+	assert!(log.lock().unwrap().is_empty());
+
+	// already deleted messages aren't deleted again
+	*entries.lock().unwrap() = page(&["1"]);
+	task.run().await.unwrap();
+	assert!(log.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn delete_all_parts() {
+	let log = Log::default();
+	let source = DummySource::new(page(&["1", "0"]));
+	let entries = Arc::clone(&source.entries);
+	let rf = Arc::new(RwLock::new(NotPresent::new()));
+
+	let mut task = Task {
+		source: Some(Box::new(SourceWithSharedRF {
+			source,
+			rf: Some(Arc::clone(&rf)),
+		})),
+		actions: Some(vec![
+			Action::Transform(Box::new(SplitLines)),
+			Action::Filter(Box::new(rf)),
+			Action::Sink(Box::new(SplittingSink(DummySink::new("sink", &log)))),
+		]),
+		entry_to_msg_map: Some(EntryToMsgMap::default()),
+		delete_removed: true,
+		..Default::default()
+	};
+
+	task.run().await.unwrap();
+	assert_eq!(
+		*log.lock().unwrap(),
+		[
+			"sink: sent 0",
+			"sink: sent 0 in reply to Int(0)",
+			"sink: sent 1",
+			"sink: sent 1 in reply to Int(2)"
+		]
+	);
+	log.lock().unwrap().clear();
+
+	*entries.lock().unwrap() = page(&["1"]);
+	task.run().await.unwrap();
+	assert_eq!(
+		*log.lock().unwrap(),
+		["sink: deleted Int(1)", "sink: deleted Int(0)"]
+	);
+}
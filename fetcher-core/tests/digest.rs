@@ -0,0 +1,52 @@
+//! This test asserts that a task with a digest sends all entries as a single message
+//! and marks them as read only after the digest has been sent
+
+#![allow(clippy::missing_assert_message)]
+#![allow(clippy::tests_outside_test_module)]
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use fetcher_core::{
+	action::Action,
+	task::{Task, digest::Digest},
+};
+use std::sync::Arc;
+
+use self::common::{DummySink, DummySource, Log, entry};
+
+#[tokio::test]
+async fn digest() {
+	let log = Log::default();
+
+	// newest to oldest
+	let entries = (0..3)
+		.rev()
+		.map(|i| entry(&i.to_string(), &format!("Entry {i}")))
+		.collect();
+
+	let mut task = Task {
+		source: Some(Box::new(DummySource {
+			log: Arc::clone(&log),
+			..DummySource::new(entries)
+		})),
+		actions: Some(vec![Action::Sink(Box::new(DummySink::new("sink", &log)))]),
+		digest: Some(Digest {
+			header: None,
+			max_items: None,
+		}),
+		..Default::default()
+	};
+
+	task.run().await.unwrap();
+
+	assert_eq!(
+		*log.lock().unwrap(),
+		[
+			"sink: sent • Entry 0\n• Entry 1\n• Entry 2",
+			"read 0",
+			"read 1",
+			"read 2",
+		]
+	);
+}
@@ -0,0 +1,63 @@
+//! This test asserts that an already sent message is edited when its entry changes
+//! and is left alone when it doesn't, even though the read filter has already filtered it out
+
+#![allow(clippy::missing_assert_message)]
+#![allow(clippy::tests_outside_test_module)]
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use fetcher_core::{
+	action::Action,
+	read_filter::NotPresent,
+	source::SourceWithSharedRF,
+	task::{Task, entry_to_msg_map::EntryToMsgMap},
+};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use self::common::{DummySink, DummySource, Log, entry};
+
+#[tokio::test]
+async fn edit_on_update() {
+	let log = Log::default();
+	let source = DummySource::new(vec![entry("0", "original")]);
+	let entries = Arc::clone(&source.entries);
+	let rf = Arc::new(RwLock::new(NotPresent::new()));
+
+	let mut task = Task {
+		source: Some(Box::new(SourceWithSharedRF {
+			source,
+			rf: Some(Arc::clone(&rf)),
+		})),
+		actions: Some(vec![
+			Action::Filter(Box::new(rf)),
+			Action::Sink(Box::new(DummySink::new("sink", &log))),
+		]),
+		entry_to_msg_map: Some(EntryToMsgMap::default()),
+		edit_on_update: true,
+		..Default::default()
+	};
+
+	task.run().await.unwrap();
+	assert_eq!(*log.lock().unwrap(), ["sink: sent original"]);
+
+	// unchanged entries aren't sent or edited again
+	task.run().await.unwrap();
+	assert_eq!(*log.lock().unwrap(), ["sink: sent original"]);
+
+	*entries.lock().unwrap() = vec![entry("1", "new"), entry("0", "updated")];
+	task.run().await.unwrap();
+	assert_eq!(
+		*log.lock().unwrap(),
+		[
+			"sink: sent original",
+			"sink: edited Int(0) to updated",
+			"sink: sent new"
+		]
+	);
+
+	// the updated entry is remembered
+	task.run().await.unwrap();
+	assert_eq!(log.lock().unwrap().len(), 3);
+}
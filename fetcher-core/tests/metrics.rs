@@ -0,0 +1,109 @@
+//! This test asserts that running a task records the metrics of fetched, read filtered, and sent entries,
+//! labeled with the task's labels
+
+#![allow(clippy::missing_assert_message)]
+#![allow(clippy::tests_outside_test_module)]
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use async_trait::async_trait;
+use fetcher_core::{
+	action::{Action, filter::Filter},
+	entry::Entry,
+	metrics,
+	task::Task,
+};
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+use self::common::{DummySink, DummySource, Log};
+
+#[derive(Debug)]
+struct DummyReadFilter;
+
+#[async_trait]
+impl Filter for DummyReadFilter {
+	async fn filter(&self, entries: &mut Vec<Entry>) {
+		// pretend the first 2 entries have already been read
+		entries.truncate(3);
+	}
+
+	fn is_readfilter(&self) -> bool {
+		true
+	}
+}
+
+#[tokio::test]
+async fn metrics() {
+	let recorder = DebuggingRecorder::new();
+	let snapshotter = recorder.snapshotter();
+	recorder.install().unwrap();
+
+	let mut task = Task {
+		source: Some(Box::new(DummySource::new(
+			(0..5)
+				.map(|i| Entry {
+					id: Some(i.to_string().into()),
+					raw_contents: Some(i.to_string()),
+					..Default::default()
+				})
+				.collect(),
+		))),
+		actions: Some(vec![
+			Action::Filter(Box::new(DummyReadFilter)),
+			Action::Sink(Box::new(DummySink::new("sink", &Log::default()))),
+		]),
+		metrics_labels: vec![("job", "test".to_owned())],
+		..Default::default()
+	};
+
+	task.run().await.unwrap();
+
+	let metrics = snapshotter
+		.snapshot()
+		.into_vec()
+		.into_iter()
+		.map(|(key, _, _, value)| {
+			let key = key.key();
+			let labels = key
+				.labels()
+				.map(|l| format!("{}={}", l.key(), l.value()))
+				.collect::<Vec<_>>()
+				.join(",");
+
+			(format!("{}{{{labels}}}", key.name()), value)
+		})
+		.collect::<Vec<_>>();
+
+	let counter = |name: &str| {
+		metrics
+			.iter()
+			.find_map(|(key, value)| match value {
+				DebugValue::Counter(c) if key == name => Some(*c),
+				_ => None,
+			})
+			.unwrap()
+	};
+
+	assert_eq!(counter(&format!("{}{{job=test}}", metrics::TASK_RUNS)), 1);
+	assert_eq!(
+		counter(&format!("{}{{job=test}}", metrics::ENTRIES_FETCHED)),
+		5
+	);
+	assert_eq!(
+		counter(&format!("{}{{job=test}}", metrics::ENTRIES_READ_FILTERED)),
+		2
+	);
+	assert_eq!(
+		counter(&format!(
+			"{}{{job=test,sink=DummySink}}",
+			metrics::ENTRIES_SENT
+		)),
+		3
+	);
+	assert!(
+		metrics
+			.iter()
+			.any(|(key, _)| key == &format!("{}{{job=test}}", metrics::TASK_LAST_SUCCESS))
+	);
+}
@@ -0,0 +1,45 @@
+//! This test asserts that a job that's asked to stop finishes its current run first
+//! instead of leaving its entries half-processed
+
+#![allow(clippy::missing_assert_message)]
+#![allow(clippy::tests_outside_test_module)]
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use fetcher_core::{
+	action::Action,
+	job::{Job, timepoint::TimePoint},
+	task::Task,
+};
+use std::{sync::Arc, time::Duration};
+
+use self::common::{DummySink, DummySource, Log, entry};
+
+#[tokio::test]
+async fn stop_after_current_run() {
+	let log = Log::default();
+	let source = DummySource::new(vec![entry("0", "entry")]);
+	let source_log = Arc::clone(&source.log);
+
+	let mut job = Job {
+		tasks: vec![Task {
+			source: Some(Box::new(source)),
+			actions: Some(vec![Action::Sink(Box::new(DummySink::new("sink", &log)))]),
+			..Default::default()
+		}],
+		refresh_time: Some(TimePoint::Duration(Duration::from_secs(10))),
+	};
+
+	// asked to stop even before the run has started
+	tokio::time::timeout(
+		Duration::from_secs(5),
+		job.run_until(std::future::ready(())),
+	)
+	.await
+	.unwrap()
+	.unwrap();
+
+	assert_eq!(*log.lock().unwrap(), ["sink: sent entry"]);
+	assert_eq!(*source_log.lock().unwrap(), ["read 0"]);
+}
@@ -12,7 +12,7 @@ use fetcher_config::jobs::{
 
 use argh::FromArgs;
 use color_eyre::{Report, Result};
-use std::{path::PathBuf, str::FromStr};
+use std::{net::SocketAddr, path::PathBuf, str::FromStr};
 
 /// Automation and scalping tool
 #[derive(FromArgs, Debug)]
@@ -45,6 +45,7 @@ pub enum TopLvlSubcommand {
 	MarkOldAsRead(MarkOldAsRead),
 	Verify(Verify),
 	Save(Save),
+	MigrateToSqlite(MigrateToSqlite),
 }
 
 /// Run all jobs. Default if started with no command
@@ -63,6 +64,10 @@ pub struct Run {
 	#[argh(switch)]
 	pub dry_run: bool,
 
+	/// serve Prometheus metrics on this address, e.g. 127.0.0.1:9100
+	#[argh(option)]
+	pub metrics: Option<SocketAddr>,
+
 	/// run only these jobs and tasks formatted as "job\[:task\]..."
 	#[argh(positional)]
 	pub run_filter: Vec<String>,
@@ -95,6 +100,11 @@ pub struct Verify {
 	pub job_run_filter: Vec<String>,
 }
 
+/// Import all read filter, entry to message map, and HTTP cache save files into a single sqlite database and use it from now on
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "migrate-to-sqlite")]
+pub struct MigrateToSqlite {}
+
 /// Save a setting
 #[derive(FromArgs, Debug)]
 #[argh(subcommand, name = "save")]
@@ -102,6 +112,10 @@ pub struct Save {
 	/// which setting to save
 	#[argh(positional)]
 	pub setting: Setting,
+
+	/// the name of the secret to save, only used with `http_secret`
+	#[argh(positional)]
+	pub name: Option<String>,
 }
 
 #[derive(Debug)]
@@ -110,6 +124,8 @@ pub enum Setting {
 	EmailPassword,
 	Telegram,
 	Discord,
+	Matrix,
+	HttpSecret,
 }
 
 impl FromStr for Setting {
@@ -121,9 +137,11 @@ impl FromStr for Setting {
 			"email_password" => Self::EmailPassword,
 			"telegram" => Self::Telegram,
 			"discord" => Self::Discord,
+			"matrix" => Self::Matrix,
+			"http_secret" => Self::HttpSecret,
 			s => {
 				return Err(format!(
-					"{s:?} is not a valid setting. Available settings: google_oauth, email_password, telegram"
+					"{s:?} is not a valid setting. Available settings: google_oauth, email_password, telegram, discord, matrix, http_secret"
 				));
 			}
 		})
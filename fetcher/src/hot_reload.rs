@@ -0,0 +1,243 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Reload job configs when they change on disk or when SIGHUP is received
+
+use crate::{
+	Jobs, RunFlags, apply_run_flags,
+	settings::{self, config::jobs::filter::JobFilter, context::StaticContext as Context},
+};
+use fetcher_config::jobs::named::{JobName, JobWithTaskNames};
+
+use color_eyre::{Result, eyre::WrapErr};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::{collections::HashMap, time::Duration};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// How long to wait for more changes after the first one before reloading.
+/// Editors usually write a file in several steps, and we don't want to reload the config after each one of them
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
+/// What to do with a job after the configs have been reloaded
+pub enum Change {
+	/// The job has been removed or disabled, or its config has changed
+	Stop(JobName),
+
+	/// The job has been added, or its config has changed.
+	///
+	/// If the old version of the job is still running, it should be started with [`Reloader::load()`] once it has stopped instead
+	Start(JobName, JobWithTaskNames),
+}
+
+/// Watches the config directories and reloads the jobs from them.
+///
+/// Changes are detected and debounced in a background task,
+/// so that waiting for them can be cancelled at any point without losing any
+pub struct Reloader {
+	run_filter: Option<Vec<JobFilter>>,
+	run_flags: RunFlags,
+	cx: Context,
+
+	changes_rx: mpsc::UnboundedReceiver<Vec<Change>>,
+	task: JoinHandle<()>,
+}
+
+/// The part of the [`Reloader`] that runs in the background
+struct Watch {
+	run_filter: Option<Vec<JobFilter>>,
+	run_flags: RunFlags,
+	cx: Context,
+
+	/// Fingerprints of the configs of all currently loaded jobs
+	fingerprints: HashMap<JobName, u64>,
+
+	// the watcher stops watching once dropped
+	_watcher: RecommendedWatcher,
+	events_rx: mpsc::UnboundedReceiver<()>,
+}
+
+impl Reloader {
+	/// Start watching the config directories for changes to the jobs in `jobs`
+	pub fn new(
+		jobs: &Jobs,
+		run_filter: Option<Vec<JobFilter>>,
+		run_flags: RunFlags,
+		cx: Context,
+	) -> Result<Self> {
+		let (events_tx, events_rx) = mpsc::unbounded_channel();
+
+		#[cfg(unix)]
+		{
+			use tokio::signal::unix::{SignalKind, signal};
+
+			let events_tx = events_tx.clone();
+			let mut sighup =
+				signal(SignalKind::hangup()).wrap_err("Unable to set up a SIGHUP handler")?;
+
+			tokio::spawn(async move {
+				while sighup.recv().await.is_some() {
+					tracing::info!("Received SIGHUP");
+
+					if events_tx.send(()).is_err() {
+						break;
+					}
+				}
+			});
+		}
+
+		let mut watcher =
+			notify::recommended_watcher(move |event: notify::Result<_>| match event {
+				Ok(notify::Event { kind, .. }) if kind.is_access() => (),
+				Ok(_) => _ = events_tx.send(()),
+				Err(e) => tracing::warn!("Error watching the config directories: {e}"),
+			})
+			.wrap_err("Unable to set up a config file watcher")?;
+
+		for cfg_dir in &cx.conf_paths {
+			match watcher.watch(cfg_dir, RecursiveMode::Recursive) {
+				Ok(()) => tracing::debug!("Watching {} for config changes", cfg_dir.display()),
+				Err(e) => tracing::debug!("Not watching {} for changes: {e}", cfg_dir.display()),
+			}
+		}
+
+		let mut fingerprints = settings::config::jobs::get_all_fingerprints(cx)?;
+		fingerprints.retain(|name, _| jobs.contains_key(name));
+
+		let watch = Watch {
+			run_filter: run_filter.clone(),
+			run_flags,
+			cx,
+			fingerprints,
+			_watcher: watcher,
+			events_rx,
+		};
+
+		let (changes_tx, changes_rx) = mpsc::unbounded_channel();
+		let task = tokio::spawn(watch.run(changes_tx));
+
+		Ok(Self {
+			run_filter,
+			run_flags,
+			cx,
+			changes_rx,
+			task,
+		})
+	}
+
+	/// Wait for the configs to change and return what jobs should be stopped and started as a result.
+	///
+	/// If any of the configs is invalid, the error is logged and the jobs are left as is.
+	/// Cancel safe: no changes are lost if the returned future is dropped before completion
+	pub async fn changes(&mut self) -> Vec<Change> {
+		match self.changes_rx.recv().await {
+			Some(changes) => changes,
+			// the watcher has stopped, nothing will ever change again
+			None => std::future::pending().await,
+		}
+	}
+
+	/// Parse the config of the job `name` again.
+	///
+	/// A changed job should be started only after the old version of it has stopped
+	/// to not miss any entries the old version has marked as read in the meantime.
+	/// Returns [`None`] if the job has been removed or disabled since then
+	pub async fn load(&self, name: &JobName) -> Result<Option<JobWithTaskNames>> {
+		let Some(job) =
+			settings::config::jobs::get_by_name(name, self.run_filter.as_deref(), self.cx)?
+		else {
+			return Ok(None);
+		};
+
+		let mut jobs = Jobs::from([(name.clone(), job)]);
+		apply_run_flags(&mut jobs, self.run_flags).await;
+
+		Ok(jobs.remove(name))
+	}
+}
+
+impl Drop for Reloader {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+impl Watch {
+	/// Reload the configs after every change and send the resulting [`Change`]s to `changes_tx`
+	async fn run(mut self, changes_tx: mpsc::UnboundedSender<Vec<Change>>) {
+		while self.events_rx.recv().await.is_some() {
+			// debounce
+			tokio::time::sleep(DEBOUNCE_DELAY).await;
+			while self.events_rx.try_recv().is_ok() {}
+
+			tracing::info!("Reloading job configs");
+
+			match self.reload().await {
+				Ok(changes) if changes.is_empty() => {
+					tracing::info!("No jobs have changed");
+				}
+				Ok(changes) => {
+					if changes_tx.send(changes).is_err() {
+						// the reloader has been dropped
+						return;
+					}
+				}
+				Err(e) => {
+					tracing::error!(
+						"Not reloading jobs because some of the configs are invalid: {e:?}"
+					);
+				}
+			}
+		}
+	}
+
+	async fn reload(&mut self) -> Result<Vec<Change>> {
+		let new_fingerprints = settings::config::jobs::get_all_fingerprints(self.cx)?;
+		let mut new_jobs = settings::config::jobs::get_all(self.run_filter.as_deref(), self.cx)?;
+
+		let mut changes = Vec::new();
+
+		// stop all jobs that have been removed or disabled
+		self.fingerprints.retain(|name, _| {
+			if new_jobs.contains_key(name) {
+				true
+			} else {
+				tracing::info!("Job {name} has been removed");
+				changes.push(Change::Stop(name.clone()));
+				false
+			}
+		});
+
+		// leave only the new or changed jobs
+		new_jobs.retain(|name, _| {
+			let new_fingerprint = new_fingerprints.get(name).copied();
+
+			match self.fingerprints.get(name) {
+				Some(&old) if Some(old) == new_fingerprint => false,
+				Some(_) => {
+					tracing::info!("Job {name} has changed");
+					changes.push(Change::Stop(name.clone()));
+					true
+				}
+				None => {
+					tracing::info!("Job {name} has been added");
+					true
+				}
+			}
+		});
+
+		apply_run_flags(&mut new_jobs, self.run_flags).await;
+
+		for (name, job) in new_jobs {
+			if let Some(&fingerprint) = new_fingerprints.get(&name) {
+				self.fingerprints.insert(name.clone(), fingerprint);
+			}
+
+			changes.push(Change::Start(name, job));
+		}
+
+		Ok(changes)
+	}
+}
@@ -15,12 +15,15 @@
 pub mod args;
 pub mod error_handling;
 pub mod extentions;
+pub mod hot_reload;
+pub mod prometheus;
 pub mod settings;
 
 use crate::{
 	args::{Args, Setting},
 	error_handling::{DEFAULT_MAX_ERROR_LIMIT, ErrorHandling, PrevErrors},
 	extentions::{ErrorChainExt, SliceDisplayExt, slice_display::job_display::JobDisplay},
+	hot_reload::{Change, Reloader},
 	settings::{
 		config::jobs::filter::JobFilter, context::Context as OwnedContext,
 		context::StaticContext as Context,
@@ -38,8 +41,14 @@ use color_eyre::{
 	Report, Result, Section,
 	eyre::{WrapErr, eyre},
 };
-use futures::{StreamExt, stream::FuturesUnordered};
-use std::{collections::HashMap, fmt::Write, ops::ControlFlow, path::PathBuf, time::Duration};
+use futures::{FutureExt, StreamExt, future::LocalBoxFuture, stream::FuturesUnordered};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Write,
+	ops::ControlFlow,
+	path::PathBuf,
+	time::Duration,
+};
 use tap::TapOptional;
 use tokio::{
 	select,
@@ -118,7 +127,7 @@ async fn async_main() -> Result<()> {
 		Some(args::TopLvlSubcommand::Run(run_args)) => run_command(run_args, cx).await,
 		None => run_command(args::Run::default(), cx).await,
 		Some(args::TopLvlSubcommand::RunManual(args::RunManual { job_config })) => {
-			run_jobs(job_config.decode(cx)?, ErrorHandling::Forward, cx).await?;
+			run_jobs(job_config.decode(cx)?, ErrorHandling::Forward, None, cx).await?;
 
 			Ok(())
 		}
@@ -142,20 +151,17 @@ async fn async_main() -> Result<()> {
 				job.inner.refresh_time = None;
 
 				for task in &mut job.inner.tasks {
-					if let Some(actions) = task.actions.take() {
-						let no_sink_acts = actions
-							.into_iter()
-							.filter(|a| !matches!(a, Action::Sink(_)))
-							.collect::<Vec<_>>();
-
-						if !no_sink_acts.is_empty() {
-							task.actions = Some(no_sink_acts);
+					if let Some(mut actions) = task.actions.take() {
+						retain_actions(&mut actions, &|a| !matches!(a, Action::Sink(_)));
+
+						if !actions.is_empty() {
+							task.actions = Some(actions);
 						}
 					}
 				}
 			}
 
-			run_jobs(jobs, ErrorHandling::LogAndIgnore, cx).await?;
+			run_jobs(jobs, ErrorHandling::LogAndIgnore, None, cx).await?;
 			tracing::info!("Marked jobs as read, exiting...");
 
 			Ok(())
@@ -188,10 +194,23 @@ async fn async_main() -> Result<()> {
 				Setting::EmailPassword => settings::data::email_password::prompt(cx)?,
 				Setting::Telegram => settings::data::telegram::prompt(cx)?,
 				Setting::Discord => settings::data::discord::prompt(cx)?,
+				Setting::Matrix => settings::data::matrix::prompt(cx)?,
+				Setting::HttpSecret => {
+					let name = save.name.ok_or_else(|| {
+						eyre!(
+							"Missing the name of the secret, e.g. fetcher save http_secret <name>"
+						)
+					})?;
+
+					settings::data::http_secrets::prompt(&name, cx)?;
+				}
 			}
 
 			Ok(())
 		}
+		Some(args::TopLvlSubcommand::MigrateToSqlite(args::MigrateToSqlite {})) => {
+			settings::data::runtime_external_save::sqlite::migrate(cx).await
+		}
 	}
 }
 
@@ -246,9 +265,14 @@ async fn run_command(run_args: args::Run, cx: Context) -> Result<()> {
 		once,
 		no_skip_read: ignore_read,
 		dry_run,
+		metrics,
 		run_filter,
 	} = run_args;
 
+	if let Some(addr) = metrics {
+		prometheus::serve(addr)?;
+	}
+
 	let run_filter = {
 		let run_filter = run_filter
 			.into_iter()
@@ -262,7 +286,7 @@ async fn run_command(run_args: args::Run, cx: Context) -> Result<()> {
 		}
 	};
 
-	let Some(mut jobs) = get_jobs(run_filter, cx)? else {
+	let Some(mut jobs) = get_jobs(run_filter.clone(), cx)? else {
 		return Ok(());
 	};
 
@@ -274,30 +298,67 @@ async fn run_command(run_args: args::Run, cx: Context) -> Result<()> {
 		}
 	}
 
+	let run_flags = RunFlags {
+		ignore_read,
+		dry_run,
+	};
+	apply_run_flags(&mut jobs, run_flags).await;
+
+	let (error_handling, reloader) = if once {
+		(ErrorHandling::Forward, None)
+	} else {
+		let reloader = Reloader::new(&jobs, run_filter, run_flags, cx)
+			.inspect_err(|e| tracing::error!("Job configs won't be reloaded on change: {e:?}"))
+			.ok();
+
+		let error_handling = ErrorHandling::Sleep {
+			prev_errors: PrevErrors::new(DEFAULT_MAX_ERROR_LIMIT),
+		};
+
+		(error_handling, reloader)
+	};
+
+	run_jobs(jobs, error_handling, reloader, cx).await?;
+	Ok(())
+}
+
+/// Flags of the run command that modify the jobs before they are run
+#[derive(Clone, Copy, Debug)]
+pub struct RunFlags {
+	/// Don't filter out already read entries
+	pub ignore_read: bool,
+
+	/// Make no permanent changes to the system
+	pub dry_run: bool,
+}
+
+pub async fn apply_run_flags(
+	jobs: &mut Jobs,
+	RunFlags {
+		ignore_read,
+		dry_run,
+	}: RunFlags,
+) {
 	if ignore_read {
 		tracing::trace!("Disabling read filters");
 		for job in jobs.values_mut() {
 			for task in &mut job.inner.tasks {
-				let Some(actions) = task.actions.take() else {
+				// fetch everything even if it hasn't changed since the last time
+				if let Some(source) = &mut task.source {
+					source.disable_cache();
+				}
+
+				let Some(mut actions) = task.actions.take() else {
 					continue;
 				};
 
-				// TODO: use .retain mb
-				let new_actions = actions
-					.into_iter()
-					.filter(|act| {
-						if let Action::Filter(filter) = &act
-							&& filter.is_readfilter()
-						{
-							return false;
-						}
-
-						true
-					})
-					.collect::<Vec<_>>();
+				retain_actions(
+					&mut actions,
+					&|act| !matches!(act, Action::Filter(filter) if filter.is_readfilter()),
+				);
 
-				if !new_actions.is_empty() {
-					task.actions = Some(new_actions);
+				if !actions.is_empty() {
+					task.actions = Some(actions);
 				}
 			}
 		}
@@ -308,17 +369,16 @@ async fn run_command(run_args: args::Run, cx: Context) -> Result<()> {
 
 		for job in jobs.values_mut() {
 			for task in &mut job.inner.tasks {
-				// don't save read filtered items to the fs
+				// don't save read filtered items and the HTTP cache to the fs
 				if let Some(source) = &mut task.source {
 					source.set_read_only().await;
+					source.disable_cache();
 				}
 
 				// don't send anything anywhere, just print
-				for act in task.actions.iter_mut().flatten() {
-					if let Action::Sink(sink) = act {
-						*sink = Box::new(Stdout);
-					}
-				}
+				for_each_sink(task.actions.iter_mut().flatten(), &mut |sink| {
+					*sink = Box::new(Stdout);
+				});
 
 				// don't save entry to msg map to the fs
 				if let Some(entry_to_msg_map) = &mut task.entry_to_msg_map {
@@ -327,17 +387,34 @@ async fn run_command(run_args: args::Run, cx: Context) -> Result<()> {
 			}
 		}
 	}
+}
 
-	let error_handling = if once {
-		ErrorHandling::Forward
-	} else {
-		ErrorHandling::Sleep {
-			prev_errors: PrevErrors::new(DEFAULT_MAX_ERROR_LIMIT),
+/// Retain only the actions for which `f` returns true, including actions inside of branches
+fn retain_actions(actions: &mut Vec<Action>, f: &impl Fn(&Action) -> bool) {
+	actions.retain(f);
+
+	for act in actions {
+		if let Action::Branch(branch) = act {
+			retain_actions(&mut branch.then, f);
+			retain_actions(&mut branch.otherwise, f);
 		}
-	};
+	}
+}
 
-	run_jobs(jobs, error_handling, cx).await?;
-	Ok(())
+/// Call `f` on every sink in `actions`, including sinks inside of branches
+fn for_each_sink<'a>(
+	actions: impl IntoIterator<Item = &'a mut Action>,
+	f: &mut impl FnMut(&mut Box<dyn Sink>),
+) {
+	for act in actions {
+		match act {
+			Action::Sink(sink) => f(sink),
+			Action::Branch(branch) => {
+				for_each_sink(branch.then.iter_mut().chain(&mut branch.otherwise), f);
+			}
+			Action::Filter(_) | Action::Transform(_) => (),
+		}
+	}
 }
 
 #[tracing::instrument(level = "debug", skip(cx))]
@@ -391,43 +468,69 @@ fn get_jobs(run_filter: Option<Vec<JobFilter>>, cx: Context) -> Result<Option<Jo
 async fn run_jobs(
 	jobs: impl IntoIterator<Item = (JobName, JobWithTaskNames)>,
 	error_handling: ErrorHandling,
+	mut reloader: Option<Reloader>,
 	cx: Context,
 ) -> Result<()> {
-	let shutdown_rx = set_up_signal_handler();
-
-	let jobs = jobs
-		.into_iter()
-		.map(|(name, job)| {
-			run_job(
-				name,
-				job.inner,
-				error_handling.clone(),
-				shutdown_rx.clone(),
-				cx,
-			)
-		})
-		.collect::<FuturesUnordered<_>>();
-
-	let mut errors: Vec<(JobName, Report)> = jobs
-		.filter_map(|(job_name, async_task_res)| async move {
-			if let Ok(job_res) = async_task_res {
-				match job_res {
-					Ok(()) => {
-						tracing::info!("Job {job_name} has finished");
-						None
+	let mut shutdown_rx = set_up_signal_handler();
+	let mut shutting_down = false;
+
+	let mut running = RunningJobs::default();
+	for (name, job) in jobs {
+		running.start(name, job, &error_handling, cx);
+	}
+
+	let mut errors: Vec<(JobName, Report)> = Vec::new();
+
+	#[allow(clippy::redundant_pub_crate)] // false positive
+	while !running.futures.is_empty() {
+		select! {
+			Some((job_name, generation, async_task_res)) = running.futures.next() => {
+				// the job has been stopped and might have to be started again with its new config
+				if !running.finished(&job_name, generation) {
+					if running.restart_pending(&job_name)
+						&& let Some(reloader) = &reloader
+					{
+						match reloader.load(&job_name).await {
+							Ok(Some(job)) => running.start(job_name, job, &error_handling, cx),
+							Ok(None) => tracing::info!("Job {job_name} has been removed in the meantime"),
+							Err(e) => tracing::error!("Job {job_name} can't be restarted: {e:?}"),
+						}
 					}
-					Err(e) => {
+
+					continue;
+				}
+
+				match async_task_res {
+					Ok(Ok(())) => tracing::info!("Job {job_name} has finished"),
+					Ok(Err(e)) => {
 						tracing::error!("Job {job_name} has exited with an error: {e:?}");
-						Some((job_name, e))
+						errors.push((job_name, e));
 					}
+					Err(_) => tracing::error!("Job {job_name} has crashed"),
 				}
-			} else {
-				tracing::error!("Job {job_name} has crashed");
-				None
 			}
-		})
-		.collect()
-		.await;
+			changes = async { reloader.as_mut().expect("checked by the if guard").changes().await }, if reloader.is_some() => {
+				for change in changes {
+					match change {
+						Change::Stop(name) => running.stop(&name),
+						Change::Start(name, _) if running.is_stopping(&name) => {
+							// its state isn't final until the old version has finished its current run
+							tracing::info!("Job {name} will be restarted once its old version has stopped");
+							running.restart_later(name);
+						}
+						Change::Start(name, job) => running.start(name, job, &error_handling, cx),
+					}
+				}
+			}
+			_ = shutdown_rx.changed(), if !shutting_down => {
+				shutting_down = true;
+
+				// don't start any new jobs while shutting down
+				reloader = None;
+				running.stop_all();
+			}
+		}
+	}
 
 	match errors.len() {
 		0 => Ok(()),
@@ -447,6 +550,105 @@ async fn run_jobs(
 	}
 }
 
+type JobFuture = LocalBoxFuture<'static, (JobName, u64, Result<Result<()>, JoinError>)>;
+
+/// All currently running jobs that can be stopped and restarted separately from each other
+#[derive(Default)]
+struct RunningJobs {
+	futures: FuturesUnordered<JobFuture>,
+
+	/// The generation of the currently running version of each job and the sender to signal it to shutdown
+	shutdown_txs: HashMap<JobName, (u64, watch::Sender<()>)>,
+	next_generation: u64,
+
+	/// The number of versions of each job that have been signaled to stop but haven't finished yet
+	stopping: HashMap<JobName, usize>,
+
+	/// Jobs to start again once their old versions have stopped
+	restarts: HashSet<JobName>,
+}
+
+impl RunningJobs {
+	fn start(
+		&mut self,
+		name: JobName,
+		job: JobWithTaskNames,
+		error_handling: &ErrorHandling,
+		cx: Context,
+	) {
+		let generation = self.next_generation;
+		self.next_generation += 1;
+
+		let (shutdown_tx, shutdown_rx) = watch::channel(());
+		if let Some((_, old_shutdown_tx)) = self
+			.shutdown_txs
+			.insert(name.clone(), (generation, shutdown_tx))
+		{
+			_ = old_shutdown_tx.send(());
+			*self.stopping.entry(name.clone()).or_default() += 1;
+		}
+
+		let fut = run_job(name, job.inner, error_handling.clone(), shutdown_rx, cx)
+			.map(move |(name, res)| (name, generation, res));
+		self.futures.push(fut.boxed_local());
+	}
+
+	fn stop(&mut self, name: &JobName) {
+		self.restarts.remove(name);
+
+		if let Some((_, shutdown_tx)) = self.shutdown_txs.remove(name) {
+			_ = shutdown_tx.send(());
+			*self.stopping.entry(name.clone()).or_default() += 1;
+		}
+	}
+
+	fn stop_all(&mut self) {
+		self.restarts.clear();
+
+		for (_, shutdown_tx) in self.shutdown_txs.values() {
+			_ = shutdown_tx.send(());
+		}
+	}
+
+	/// Returns true if an old version of the job is still finishing its current run
+	fn is_stopping(&self, name: &JobName) -> bool {
+		self.stopping.contains_key(name)
+	}
+
+	/// Start the job again once all of its old versions have stopped
+	fn restart_later(&mut self, name: JobName) {
+		self.restarts.insert(name);
+	}
+
+	/// Returns true, only once, if the job should be started again now that all of its old versions have stopped
+	fn restart_pending(&mut self, name: &JobName) -> bool {
+		!self.is_stopping(name) && self.restarts.remove(name)
+	}
+
+	/// Forget about a job that has finished.
+	///
+	/// Returns false if it was an old version of the job that has been stopped
+	fn finished(&mut self, name: &JobName, generation: u64) -> bool {
+		match self.shutdown_txs.get(name) {
+			Some(&(current, _)) if current == generation => {
+				self.shutdown_txs.remove(name);
+				true
+			}
+			_ => {
+				if let Some(stopping) = self.stopping.get_mut(name) {
+					*stopping -= 1;
+
+					if *stopping == 0 {
+						self.stopping.remove(name);
+					}
+				}
+
+				false
+			}
+		}
+	}
+}
+
 fn set_up_signal_handler() -> Receiver<()> {
 	let (shutdown_tx, shutdown_rx) = watch::channel(());
 	let (force_close_tx, mut force_close_rx) = watch::channel(());
@@ -509,38 +711,37 @@ async fn run_job(
 		}
 	}
 
-	let async_job = {
+	// tokio task
+	let async_task = {
 		let name = name.clone();
 
 		async move {
 			loop {
-				let job_result = job.run().await;
+				// let the current run finish to not leave anything sent but not marked as read
+				let mut stop_rx = shutdown_rx.clone();
+				let job_result = job
+					.run_until(async move {
+						_ = stop_rx.changed().await;
+					})
+					.await;
 
-				match handle_errors(job_result, &mut error_handling, (&name, &job), cx).await {
-					ControlFlow::Continue(()) => (),
-					ControlFlow::Break(res) => {
-						return res.map_err(fold_task_errors);
-					}
+				// signaled to shutdown, or the sender is already gone after the job has been stopped
+				if shutdown_rx.has_changed().unwrap_or(true) {
+					tracing::info!("Job {name} has been stopped");
+					return job_result.map_err(fold_task_errors);
 				}
-			}
-		}
-	};
 
-	// tokio task
-	let async_task = {
-		let name = name.clone();
-
-		async move {
-			#[allow(clippy::redundant_pub_crate)] // false positive
-			{
-				select! {
-					res = async_job => {
-						res
-					}
+				#[allow(clippy::redundant_pub_crate)] // false positive
+				let flow = select! {
+					flow = handle_errors(job_result, &mut error_handling, (&name, &job), cx) => flow,
 					_ = shutdown_rx.changed() => {
 						tracing::info!("Job {name} signaled to shutdown...");
-						Ok(())
+						return Ok(());
 					}
+				};
+
+				if let ControlFlow::Break(res) = flow {
+					return res.map_err(fold_task_errors);
 				}
 			}
 		}
@@ -606,12 +807,10 @@ async fn handle_errors_sleep(
 	// TODO: maybe figure out a more optimal time interval than just 2 times longer than the refresh timer
 	if let Some((last_error, refresh_time)) = prev_errors.last_error().zip(job_refresh_time) {
 		let last_error_sleep_dur = exponential_backoff_duration(prev_errors.count());
-		match refresh_time {
+		let reset = match refresh_time {
 			TimePoint::Duration(dur) => {
 				let twice_refresh_dur = *dur * 2; // two times the refresh duration to make sure the job ran at least twice with no errors
-				if last_error.elapsed() > last_error_sleep_dur + twice_refresh_dur {
-					prev_errors.reset();
-				}
+				last_error.elapsed() > last_error_sleep_dur + twice_refresh_dur
 			}
 			// once a day
 			TimePoint::Time(_) => {
@@ -619,10 +818,28 @@ async fn handle_errors_sleep(
 					2 /* days */ * 24 /* hours a day */ * 60 /* mins an hour */ * 60, /* secs a min */
 				);
 
-				if last_error.elapsed() > last_error_sleep_dur + TWO_DAYS {
-					prev_errors.reset();
+				last_error.elapsed() > last_error_sleep_dur + TWO_DAYS
+			}
+			TimePoint::Cron(cron) => {
+				// the job has been resumed after sleeping for last_error_sleep_dur.
+				// Make sure the schedule has fired at least twice since then with no errors
+				if let Some(since_resumed) = last_error.elapsed().checked_sub(last_error_sleep_dur)
+					&& let Ok(since_resumed) = chrono::Duration::from_std(since_resumed)
+				{
+					let now = chrono::Local::now().naive_local();
+
+					cron.fire_times_between(now - since_resumed, now)
+						.nth(1)
+						.is_some()
+				} else {
+					false
 				}
 			}
+		};
+
+		if reset {
+			prev_errors.reset();
+			prometheus::record_backoff(job_name, 0, Duration::ZERO);
 		}
 	}
 
@@ -681,7 +898,9 @@ async fn handle_errors_sleep(
 
 	let sleep_dur = exponential_backoff_duration(prev_errors.count());
 	tracing::info!("Pausing job {job_name} for {}m", sleep_dur.as_secs() / 60);
+	prometheus::record_backoff(job_name, prev_errors.count(), sleep_dur);
 	sleep(sleep_dur).await;
+	prometheus::record_backoff(job_name, prev_errors.count(), Duration::ZERO);
 
 	ControlFlow::Continue(())
 }
@@ -696,7 +915,11 @@ const fn exponential_backoff_duration(consecutive_err_count: u32) -> Duration {
 
 // TODO: move that to a tracing layer that sends all WARN and higher logs automatically
 async fn report_error(job_name: &str, err: &str, context: Context) -> Result<()> {
-	use fetcher_core::sink::{Telegram, message::Message, telegram::LinkLocation};
+	use fetcher_core::sink::{
+		Telegram,
+		message::Message,
+		telegram::{Chat, LinkLocation, Options},
+	};
 
 	let admin_chat_id = std::env::var("FETCHER_TELEGRAM_ADMIN_CHAT_ID")
 		.wrap_err("FETCHER_TELEGRAM_ADMIN_CHAT_ID not set")?
@@ -711,10 +934,15 @@ async fn report_error(job_name: &str, err: &str, context: Context) -> Result<()>
 		body: Some(err.to_owned()),
 		..Default::default()
 	};
-	Telegram::new(bot, admin_chat_id, LinkLocation::default())
-		.send(&msg, None, Some(job_name))
-		.await
-		.map_err(fetcher_core::error::FetcherError::Sink)?;
+	Telegram::new(
+		bot,
+		Chat::Id(admin_chat_id),
+		LinkLocation::default(),
+		Options::default(),
+	)
+	.send(&msg, None, Some(job_name))
+	.await
+	.map_err(fetcher_core::error::FetcherError::Sink)?;
 
 	Ok(())
 }
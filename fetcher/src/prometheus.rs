@@ -0,0 +1,46 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Prometheus metrics exporter. Exposes the metrics recorded by [`fetcher_core::metrics`] and the error backoff state of the jobs
+
+use color_eyre::{Result, eyre::WrapErr};
+use fetcher_config::jobs::named::JobName;
+use metrics::{Unit, describe_gauge, gauge};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use std::{net::SocketAddr, time::Duration};
+
+const JOB_CONSECUTIVE_ERRORS: &str = "fetcher_job_consecutive_errors";
+const JOB_BACKOFF: &str = "fetcher_job_backoff_seconds";
+
+/// Start serving metrics over HTTP on `addr` in the background
+pub fn serve(addr: SocketAddr) -> Result<()> {
+	PrometheusBuilder::new()
+		.with_http_listener(addr)
+		.install()
+		.wrap_err("Unable to start the Prometheus metrics exporter")?;
+
+	fetcher_core::metrics::describe();
+	describe_gauge!(
+		JOB_CONSECUTIVE_ERRORS,
+		"Number of times in a row a job has finished with an error, not counting network errors"
+	);
+	describe_gauge!(
+		JOB_BACKOFF,
+		Unit::Seconds,
+		"How long a job is paused for after an error. 0 if it is currently running"
+	);
+
+	tracing::info!("Serving Prometheus metrics on http://{addr}/metrics");
+	Ok(())
+}
+
+/// Record the current error backoff state of the job
+pub fn record_backoff(job: &JobName, consecutive_errors: u32, backoff: Duration) {
+	let labels = [("job", job.as_str().to_owned())];
+
+	gauge!(JOB_CONSECUTIVE_ERRORS, &labels).set(consecutive_errors);
+	gauge!(JOB_BACKOFF, &labels).set(backoff.as_secs_f64());
+}
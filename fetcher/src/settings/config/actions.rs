@@ -13,7 +13,7 @@ use figment::{
 	Figment,
 	providers::{Format, Yaml},
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 const ACTIONS_DIR: &str = "actions";
 
@@ -32,6 +32,16 @@ pub fn find(name: &str, context: Context) -> Result<Option<Vec<ActionConfig>>> {
 	Ok(None)
 }
 
+/// Find the path of the file containing the actions with `name` in the default actions paths
+#[must_use]
+pub fn find_path(name: &str, context: Context) -> Option<PathBuf> {
+	context
+		.conf_paths
+		.iter()
+		.map(|p| path_in(&p.join(ACTIONS_DIR), name))
+		.find(|path| path.exists())
+}
+
 /// Find all actions with `name` in `actions_path`.
 /// Returns Some(ActionConfig) if the action was found in the directory, None otherwise
 ///
@@ -40,7 +50,7 @@ pub fn find(name: &str, context: Context) -> Result<Option<Vec<ActionConfig>>> {
 /// * if the config exists at `action_path` but is invalid
 pub fn find_in(action_path: &Path, name: &str) -> Result<Option<Vec<ActionConfig>>> {
 	tracing::trace!("Searching for an action {name:?} in {action_path:?}");
-	let path = action_path.join(name).with_extension(CONFIG_FILE_EXT);
+	let path = path_in(action_path, name);
 
 	if !path.exists() {
 		tracing::trace!("{path:?} doesn't exist");
@@ -59,3 +69,7 @@ pub fn find_in(action_path: &Path, name: &str) -> Result<Option<Vec<ActionConfig
 
 	Ok(Some(action_config))
 }
+
+fn path_in(action_path: &Path, name: &str) -> PathBuf {
+	action_path.join(name).with_extension(CONFIG_FILE_EXT)
+}
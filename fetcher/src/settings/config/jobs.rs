@@ -23,9 +23,17 @@ use color_eyre::{Result, eyre::eyre};
 use figment::{
 	Figment,
 	providers::{Format, Yaml},
+	value::Value,
 };
 use serde::Deserialize;
-use std::{fmt::Write, io, path::Path};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Write,
+	fs,
+	hash::{DefaultHasher, Hash, Hasher},
+	io,
+	path::Path,
+};
 use walkdir::{DirEntry, WalkDir};
 
 const JOBS_DIR_NAME: &str = "jobs";
@@ -62,78 +70,214 @@ pub fn get_all_from(
 			let job_config_path = dir_entry_is_job_config_file(&dir_entry)?;
 			let job_name = JobName::from_job_config_path(job_config_path, &jobs_dir);
 
-			// filter out all jobs that don't match the filter
-			if let Some(filter) = filter {
-				if !filter.iter().any(|filter| filter.job_matches(&job_name)) {
-					tracing::trace!("Filtering out job {job_name:?}");
-					return None;
-				}
+			get_filtered(job_config_path, job_name, filter, cx)
+		})
+}
+
+/// Get the job `name`, if it exists, is enabled, and matches the `filter`.
+///
+/// If the job exists in several config locations, the last one is used, just like in [`get_all`]
+pub fn get_by_name(
+	name: &JobName,
+	filter: Option<&[JobFilter]>,
+	cx: Context,
+) -> Result<Option<JobWithTaskNames>> {
+	let mut job_config_path = None;
+
+	for cfg_dir in &cx.conf_paths {
+		let jobs_dir = cfg_dir.join(JOBS_DIR_NAME);
+
+		for dir_entry in WalkDir::new(&jobs_dir).follow_links(true) {
+			if let Some(path) = dir_entry_is_job_config_file(&dir_entry)
+				&& JobName::from_job_config_path(path, &jobs_dir) == *name
+			{
+				job_config_path = Some(path.to_owned());
 			}
+		}
+	}
 
-			// parse the job from the config located at the config path
-			let (job_name, mut job) = match get(job_config_path, job_name, cx).map_err(|e| {
-				e.wrap_err(format!("invalid config at: {}", job_config_path.display()))
-			}) {
-				Ok(Some(job)) => job,
-				Ok(None) => return None,
-				Err(e) => return Some(Err(e)),
-			};
+	let Some(job_config_path) = job_config_path else {
+		return Ok(None);
+	};
 
-			// when the job config doesn't contain any tasks, the global job settings are used to create a dummy task with no name
-			assert!(
-				!job.inner.tasks.is_empty(),
-				"Jobs should always contain at least one task"
+	get_filtered(&job_config_path, name.clone(), filter, cx)
+		.transpose()
+		.map(|job| job.map(|(_, job)| job))
+}
+
+/// Parse the job at `job_config_path`, skipping it if it doesn't match the `filter` or is disabled,
+/// and remove all tasks that don't match the `filter`
+fn get_filtered(
+	job_config_path: &Path,
+	job_name: JobName,
+	filter: Option<&[JobFilter]>,
+	cx: Context,
+) -> Option<Result<(JobName, JobWithTaskNames)>> {
+	// filter out all jobs that don't match the filter
+	if let Some(filter) = filter {
+		if !filter.iter().any(|filter| filter.job_matches(&job_name)) {
+			tracing::trace!("Filtering out job {job_name:?}");
+			return None;
+		}
+	}
+
+	// parse the job from the config located at the config path
+	let (job_name, mut job) = match get(job_config_path, job_name, cx)
+		.map_err(|e| e.wrap_err(format!("invalid config at: {}", job_config_path.display())))
+	{
+		Ok(Some(job)) => job,
+		Ok(None) => return None,
+		Err(e) => return Some(Err(e)),
+	};
+
+	// when the job config doesn't contain any tasks, the global job settings are used to create a dummy task with no name
+	assert!(
+		!job.inner.tasks.is_empty(),
+		"Jobs should always contain at least one task"
+	);
+
+	// filter out all tasks that don't match the filter
+	if let Some(filter) = filter
+		&& let Some(task_names) = &job.task_names
+	{
+		job.inner.tasks = job
+			.inner
+			.tasks
+			.into_iter()
+			.enumerate()
+			.filter_map(|(idx, task)| {
+				let task_name = task_names
+					.get(&idx)
+					.expect("task name map should always contain all task indecies and names");
+
+				if filter
+					.iter()
+					.any(|filter| filter.task_matches(&job_name, task_name))
+				{
+					Some(task)
+				} else {
+					tracing::trace!("Filtering out task {job_name:?}:{task_name:?}",);
+					None
+				}
+			})
+			.collect();
+
+		if job.inner.tasks.is_empty() {
+			let task_names_str = task_names.values().enumerate().fold(
+				String::new(),
+				|mut names_str, (idx, name)| {
+					if idx == 0 {
+						names_str.push_str(name);
+					} else {
+						_ = write!(names_str, ", {name}");
+					}
+
+					names_str
+				},
 			);
 
-			// filter out all tasks that don't match the filter
-			if let Some(filter) = filter
-				&& let Some(task_names) = &job.task_names
-			{
-				job.inner.tasks = job
-					.inner
-					.tasks
-					.into_iter()
-					.enumerate()
-					.filter_map(|(idx, task)| {
-						let task_name = task_names.get(&idx).expect(
-							"task name map should always contain all task indecies and names",
-						);
-
-						if filter
-							.iter()
-							.any(|filter| filter.task_matches(&job_name, task_name))
-						{
-							Some(task)
-						} else {
-							tracing::trace!("Filtering out task {job_name:?}:{task_name:?}",);
-							None
-						}
-					})
-					.collect();
-
-				if job.inner.tasks.is_empty() {
-					let task_names_str = task_names.values().enumerate().fold(
-						String::new(),
-						|mut names_str, (idx, name)| {
-							if idx == 0 {
-								names_str.push_str(name);
-							} else {
-								_ = write!(names_str, ", {name}");
-							}
-
-							names_str
-						},
-					);
-
-					tracing::warn!(
-						"Asked to run job {job_name} but no tasks matched the task filter. Available tasks: {task_names_str}"
-					);
-					return None;
+			tracing::warn!(
+				"Asked to run job {job_name} but no tasks matched the task filter. Available tasks: {task_names_str}"
+			);
+			return None;
+		}
+	}
+
+	Some(Ok((job_name, job)))
+}
+
+/// Get the fingerprints of all jobs in all config locations.
+///
+/// A fingerprint is a hash of the contents of the job config and of all templates and actions it uses,
+/// meaning it changes whenever any of them do
+pub fn get_all_fingerprints(cx: Context) -> Result<HashMap<JobName, u64>> {
+	let mut fingerprints = HashMap::new();
+
+	for cfg_dir in &cx.conf_paths {
+		let jobs_dir = cfg_dir.join(JOBS_DIR_NAME);
+
+		for dir_entry in WalkDir::new(&jobs_dir).follow_links(true) {
+			let Some(job_config_path) = dir_entry_is_job_config_file(&dir_entry) else {
+				continue;
+			};
+
+			let job_name = JobName::from_job_config_path(job_config_path, &jobs_dir);
+			let fingerprint = fingerprint(job_config_path, cx).map_err(|e| {
+				e.wrap_err(format!("invalid config at: {}", job_config_path.display()))
+			})?;
+
+			fingerprints.insert(job_name, fingerprint);
+		}
+	}
+
+	Ok(fingerprints)
+}
+
+fn fingerprint(path: &Path, cx: Context) -> Result<u64> {
+	let mut hasher = DefaultHasher::new();
+	fs::read_to_string(path)?.hash(&mut hasher);
+
+	let mut full_conf = Figment::new();
+
+	let TemplatesField { templates } = Figment::new().merge(Yaml::file(path)).extract()?;
+	for tmpl_name in templates.into_iter().flatten() {
+		// missing templates are reported when parsing the job itself
+		if let Some(tmpl) = settings::config::templates::find(&tmpl_name, cx)? {
+			tmpl.contents.hash(&mut hasher);
+			full_conf = full_conf.merge(Yaml::string(&tmpl.contents));
+		}
+	}
+
+	let full_conf: Value = full_conf.merge(Yaml::file(path)).extract()?;
+	hash_imports(&full_conf, &mut HashSet::new(), &mut hasher, cx)?;
+
+	Ok(hasher.finish())
+}
+
+/// Hash the contents of all action files imported in `conf`, including the ones imported by these files themselves.
+///
+/// Every file is only hashed once, its name is added to `imported`
+fn hash_imports(
+	conf: &Value,
+	imported: &mut HashSet<String>,
+	hasher: &mut DefaultHasher,
+	cx: Context,
+) -> Result<()> {
+	match conf {
+		Value::Dict(_, dict) => {
+			for (key, value) in dict {
+				if key == "import"
+					&& let Some(name) = value.as_str()
+				{
+					if !imported.insert(name.to_owned()) {
+						continue;
+					}
+
+					// missing actions are reported when parsing the job itself
+					let Some(path) = settings::config::actions::find_path(name, cx) else {
+						continue;
+					};
+
+					let contents = fs::read_to_string(&path)?;
+					contents.hash(hasher);
+
+					// a list of actions, not a map, so it can't be a Figment provider
+					let actions: Value = Yaml::from_str(&contents)?;
+					hash_imports(&actions, imported, hasher, cx)?;
+				} else {
+					hash_imports(value, imported, hasher, cx)?;
 				}
 			}
+		}
+		Value::Array(_, values) => {
+			for value in values {
+				hash_imports(value, imported, hasher, cx)?;
+			}
+		}
+		_ => (),
+	}
 
-			Some(Ok((job_name, job)))
-		})
+	Ok(())
 }
 
 #[tracing::instrument(skip(cx))]
@@ -203,3 +347,41 @@ fn dir_entry_is_job_config_file(dir_entry: &Result<DirEntry, walkdir::Error>) ->
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+	use crate::settings::context::Context as FullContext;
+
+	#[test]
+	fn fingerprint_changes_with_imports() {
+		let conf_path = std::env::temp_dir()
+			.join(format!("fetcher-test-{}", std::process::id()))
+			.join("fingerprint_imports");
+		_ = fs::remove_dir_all(&conf_path);
+		fs::create_dir_all(conf_path.join(JOBS_DIR_NAME)).unwrap();
+		fs::create_dir_all(conf_path.join("actions")).unwrap();
+
+		let job_path = conf_path.join(JOBS_DIR_NAME).join("job.yml");
+		fs::write(&job_path, "process:\n  - import: outer\n").unwrap();
+		fs::write(conf_path.join("actions/outer.yml"), "- import: inner\n").unwrap();
+
+		let inner_path = conf_path.join("actions/inner.yml");
+		fs::write(&inner_path, "- caps\n").unwrap();
+
+		let cx: Context = Box::leak(Box::new(FullContext {
+			data_path: conf_path.join("data"),
+			conf_paths: vec![conf_path.clone()],
+			log_path: conf_path.join("log"),
+		}));
+
+		let before = fingerprint(&job_path, cx).unwrap();
+		assert_eq!(before, fingerprint(&job_path, cx).unwrap());
+
+		// an action file imported by another imported one
+		fs::write(&inner_path, "- trim: title\n").unwrap();
+		assert_ne!(before, fingerprint(&job_path, cx).unwrap());
+	}
+}
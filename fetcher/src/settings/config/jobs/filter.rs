@@ -8,7 +8,7 @@ use color_eyre::{Report, eyre::eyre};
 use fetcher_config::jobs::named::{JobName, TaskName};
 use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct JobFilter {
 	pub job: JobName,
 	pub task: Option<TaskName>,
@@ -7,6 +7,8 @@
 pub mod discord;
 pub mod email_password;
 pub mod google_oauth2;
+pub mod http_secrets;
+pub mod matrix;
 pub mod runtime_external_save;
 pub mod telegram;
 
@@ -0,0 +1,54 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use super::prompt_user_for;
+use crate::settings::context::StaticContext as Context;
+use fetcher_config::{jobs::external_data::ExternalDataError, settings::HttpSecrets as Config};
+
+use color_eyre::{Result, eyre::WrapErr};
+use std::{collections::HashMap, fs, io};
+
+const FILE_NAME: &str = "http_secrets.json";
+
+/// Returns the secret called `name`, or [`None`] if no such secret has been saved
+pub fn get(name: &str, cx: Context) -> Result<Option<String>, ExternalDataError> {
+	Ok(get_all(cx)?.remove(name))
+}
+
+pub fn prompt(name: &str, cx: Context) -> Result<()> {
+	let value = prompt_user_for(&format!("Value of HTTP secret {name:?}: "))?;
+	let path = cx.data_path.join(FILE_NAME);
+
+	let mut secrets = get_all(cx)?;
+	secrets.insert(name.to_owned(), value);
+
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	fs::write(
+		&path,
+		serde_json::to_string(&Config::encode_into_conf(secrets))
+			.expect("Config should always serialize to JSON without issues"),
+	)
+	.wrap_err_with(|| path.to_string_lossy().into_owned())?;
+
+	Ok(())
+}
+
+fn get_all(cx: Context) -> Result<HashMap<String, String>, ExternalDataError> {
+	let path = cx.data_path.join(FILE_NAME);
+
+	let raw = match fs::read_to_string(&path) {
+		Ok(raw) => raw,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+		Err(e) => return Err((e, &path).into()),
+	};
+
+	let conf: Config = serde_json::from_str(&raw).map_err(|e| (e, &path))?;
+
+	Ok(conf.decode_from_conf())
+}
@@ -5,13 +5,16 @@
  */
 
 pub mod entry_to_msg_map;
+pub mod http_cache;
 pub mod read_filter;
+pub mod sqlite;
 
 use fetcher_core::{
 	entry::EntryId,
 	external_save::{ExternalSave, ExternalSaveError},
 	read_filter::ReadFilter,
 	sink::message::MessageId,
+	source::http::cache::Validators,
 };
 
 use async_trait::async_trait;
@@ -23,6 +26,10 @@ use tokio::{
 	io::{AsyncSeekExt, AsyncWriteExt},
 };
 
+const READ_DATA_DIR: &str = "read";
+const ENTRY_TO_MSG_MAP_DATA_DIR: &str = "entry_to_msg_map";
+const HTTP_CACHE_DATA_DIR: &str = "http_cache";
+
 #[derive(Debug)]
 pub struct TruncatingFileWriter {
 	path: PathBuf,
@@ -68,10 +75,14 @@ impl ExternalSave for TruncatingFileWriter {
 	async fn save_entry_to_msg_map(
 		&mut self,
 		map: &HashMap<EntryId, MessageId>,
+		content_hashes: &HashMap<EntryId, u64>,
+		parts: &HashMap<EntryId, Vec<MessageId>>,
 	) -> Result<(), ExternalSaveError> {
 		let map_conf =
 			fetcher_config::jobs::task::entry_to_msg_map::EntryToMsgMap::encode_into_conf(
 				map.clone(),
+				content_hashes.clone(),
+				parts.clone(),
 			);
 		let s = serde_json::to_string(&map_conf)
 			.expect("An EntryToMsgMap should always be serializable");
@@ -83,6 +94,23 @@ impl ExternalSave for TruncatingFileWriter {
 				path: Some(Box::new(DisplayPath(self.path.clone()))),
 			})
 	}
+
+	async fn save_http_cache(
+		&mut self,
+		map: &HashMap<String, Validators>,
+	) -> Result<(), ExternalSaveError> {
+		let cache_conf =
+			fetcher_config::jobs::source::http::cache::HttpCache::encode_into_conf(map.clone());
+		let s =
+			serde_json::to_string(&cache_conf).expect("An HttpCache should always be serializable");
+
+		self.write(s.as_bytes())
+			.await
+			.map_err(|source| ExternalSaveError {
+				source,
+				path: Some(Box::new(DisplayPath(self.path.clone()))),
+			})
+	}
 }
 
 impl TruncatingFileWriter {
@@ -6,7 +6,7 @@
 
 use std::fs;
 
-use super::TruncatingFileWriter;
+use super::{ENTRY_TO_MSG_MAP_DATA_DIR, TruncatingFileWriter, sqlite};
 use crate::settings::context::StaticContext;
 use fetcher_config::jobs::{
 	external_data::ExternalDataError,
@@ -15,13 +15,15 @@ use fetcher_config::jobs::{
 };
 use fetcher_core::task::entry_to_msg_map::EntryToMsgMap;
 
-const ENTRY_TO_MSG_MAP_DATA_DIR: &str = "entry_to_msg_map";
-
 pub fn get(
 	job: &JobName,
 	task: Option<&TaskName>,
 	cx: StaticContext,
 ) -> Result<EntryToMsgMap, ExternalDataError> {
+	if let Some(map) = sqlite::entry_to_msg_map(job, task, cx)? {
+		return Ok(map);
+	}
+
 	let path = {
 		let mut path = cx.data_path.join(ENTRY_TO_MSG_MAP_DATA_DIR).join(&**job);
 
@@ -46,10 +48,13 @@ pub fn get(
 		Ok(map_raw) => {
 			let conf: EntryToMsgMapConf = serde_json::from_str(&map_raw).map_err(|e| (e, &path))?;
 
-			Ok(EntryToMsgMap::new_with_map(
-				conf.decode_from_conf(),
-				TruncatingFileWriter::new(path),
-			))
+			let (map, content_hashes, parts) = conf.decode_from_conf();
+
+			Ok(
+				EntryToMsgMap::new_with_map(map, TruncatingFileWriter::new(path))
+					.with_content_hashes(content_hashes)
+					.with_parts(parts),
+			)
 		}
 	}
 }
@@ -0,0 +1,57 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::fs;
+
+use super::{HTTP_CACHE_DATA_DIR, TruncatingFileWriter, sqlite};
+use crate::settings::context::StaticContext;
+use fetcher_config::jobs::{
+	external_data::ExternalDataError,
+	named::{JobName, TaskName},
+	source::http::cache::HttpCache as HttpCacheConf,
+};
+use fetcher_core::source::http::cache::HttpCache;
+
+pub fn get(
+	job: &JobName,
+	task: Option<&TaskName>,
+	cx: StaticContext,
+) -> Result<HttpCache, ExternalDataError> {
+	if let Some(cache) = sqlite::http_cache(job, task, cx)? {
+		return Ok(cache);
+	}
+
+	let path = {
+		let mut path = cx.data_path.join(HTTP_CACHE_DATA_DIR).join(&**job);
+
+		if let Some(task) = task {
+			path.push(&**task);
+		}
+
+		path
+	};
+
+	match fs::read_to_string(&path) {
+		Ok(cache_raw) if cache_raw.trim().is_empty() => {
+			tracing::trace!("HTTP cache save file is empty");
+
+			Ok(HttpCache::new(TruncatingFileWriter::new(path)))
+		}
+		Err(e) => {
+			tracing::debug!("HTTP cache save file doesn't exist or is inaccessible: {e}");
+
+			Ok(HttpCache::new(TruncatingFileWriter::new(path)))
+		}
+		Ok(cache_raw) => {
+			let conf: HttpCacheConf = serde_json::from_str(&cache_raw).map_err(|e| (e, &path))?;
+
+			Ok(HttpCache::new_with_map(
+				conf.decode_from_conf(),
+				TruncatingFileWriter::new(path),
+			))
+		}
+	}
+}
@@ -4,7 +4,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use super::TruncatingFileWriter;
+use super::{READ_DATA_DIR, TruncatingFileWriter, sqlite};
 use crate::settings::context::StaticContext as Context;
 use fetcher_config::jobs::{
 	external_data::ExternalDataError,
@@ -15,8 +15,6 @@ use fetcher_core::read_filter::ReadFilter;
 
 use std::fs;
 
-const READ_DATA_DIR: &str = "read";
-
 #[tracing::instrument(level = "debug", skip(cx))]
 pub fn get(
 	job: &JobName,
@@ -24,6 +22,10 @@ pub fn get(
 	expected_rf_kind: ReadFilterKind,
 	cx: Context,
 ) -> Result<Box<dyn ReadFilter>, ExternalDataError> {
+	if let Some(rf) = sqlite::read_filter(job, task, expected_rf_kind, cx)? {
+		return Ok(rf);
+	}
+
 	let path = {
 		let mut path = cx.data_path.join(READ_DATA_DIR).join(&**job);
 
@@ -0,0 +1,1031 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! An [`ExternalSave`] that keeps read filters, entry to message maps, and HTTP caches of all tasks in a single `SQLite` database.
+//!
+//! Rows are keyed by the same "job\[/task\]" key that is used as the relative path of the per-task JSON files,
+//! which makes importing them from the JSON files trivial.
+//! Only the rows that have actually changed are written, and every save happens inside of a single transaction
+
+use super::{ENTRY_TO_MSG_MAP_DATA_DIR, HTTP_CACHE_DATA_DIR, READ_DATA_DIR};
+use crate::settings::context::StaticContext as Context;
+use fetcher_config::jobs::{
+	external_data::ExternalDataError,
+	named::{JobName, TaskName},
+	read_filter::{Kind as ReadFilterKind, ReadFilter as ReadFilterConf},
+	source::http::cache::HttpCache as HttpCacheConf,
+	task::entry_to_msg_map::EntryToMsgMap as EntryToMsgMapConf,
+};
+use fetcher_core::{
+	entry::EntryId,
+	external_save::{ExternalSave, ExternalSaveError},
+	read_filter::{ExternalSaveRFWrapper, Newer, NotPresent, ReadFilter},
+	sink::message::MessageId,
+	source::http::cache::{HttpCache, Validators},
+	task::entry_to_msg_map::EntryToMsgMap,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use color_eyre::{
+	Result,
+	eyre::{WrapErr, eyre},
+};
+use once_cell::sync::OnceCell;
+use rusqlite::{
+	Connection, OptionalExtension, params,
+	types::{FromSqlError, Value},
+};
+use std::{
+	collections::{HashMap, HashSet},
+	fs, io,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+const DB_FILE_NAME: &str = "runtime.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS read_filter (
+	key TEXT PRIMARY KEY NOT NULL,
+	kind TEXT NOT NULL,
+	last_read_id TEXT
+);
+
+CREATE TABLE IF NOT EXISTS read_list (
+	key TEXT NOT NULL,
+	entry_id TEXT NOT NULL,
+	read_at TEXT NOT NULL,
+	PRIMARY KEY (key, entry_id)
+);
+
+CREATE TABLE IF NOT EXISTS entry_to_msg_map (
+	key TEXT NOT NULL,
+	entry_id TEXT NOT NULL,
+	-- no type affinity since it's either an integer or a text id, depending on the sink
+	message_id NOT NULL,
+	PRIMARY KEY (key, entry_id)
+);
+
+CREATE TABLE IF NOT EXISTS entry_content_hash (
+	key TEXT NOT NULL,
+	entry_id TEXT NOT NULL,
+	-- the u64 hash reinterpreted as an i64 since SQLite integers are signed
+	content_hash INTEGER NOT NULL,
+	PRIMARY KEY (key, entry_id)
+);
+
+-- the other messages an entry was sent as if the sink has split it into several ones
+CREATE TABLE IF NOT EXISTS entry_msg_part (
+	key TEXT NOT NULL,
+	entry_id TEXT NOT NULL,
+	position INTEGER NOT NULL,
+	message_id NOT NULL,
+	PRIMARY KEY (key, entry_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS http_cache (
+	key TEXT NOT NULL,
+	url TEXT NOT NULL,
+	etag TEXT,
+	last_modified TEXT,
+	PRIMARY KEY (key, url)
+);
+";
+
+/// A shared connection to the runtime `SQLite` database
+#[derive(Clone, Debug)]
+pub struct Database {
+	conn: Arc<Mutex<Connection>>,
+	path: PathBuf,
+}
+
+/// Saves the read filter, entry to message map, and HTTP cache of a single task into the [`Database`]
+#[derive(Debug)]
+pub struct SqliteWriter {
+	db: Database,
+	key: String,
+}
+
+impl Database {
+	/// Returns the database located in the data directory if it exists,
+	/// i.e. if the JSON save files have already been migrated to it
+	pub fn get(cx: Context) -> Result<Option<&'static Database>, ExternalDataError> {
+		static DB: OnceCell<Option<Database>> = OnceCell::new();
+
+		DB.get_or_try_init(|| {
+			let path = cx.data_path.join(DB_FILE_NAME);
+
+			if !path.exists() {
+				return Ok(None);
+			}
+
+			Self::open(path).map(Some)
+		})
+		.map(Option::as_ref)
+	}
+
+	/// Opens the database at `path`, creating it if it doesn't exist yet
+	fn open(path: PathBuf) -> Result<Self, ExternalDataError> {
+		let conn = Connection::open(&path).map_err(|e| (io::Error::other(e), &path))?;
+
+		// WAL makes sure a crash while writing doesn't corrupt the database and speeds up writes
+		conn.pragma_update(None, "journal_mode", "WAL")
+			.and_then(|()| conn.execute_batch(SCHEMA))
+			.map_err(|e| (io::Error::other(e), &path))?;
+
+		Ok(Self {
+			conn: Arc::new(Mutex::new(conn)),
+			path,
+		})
+	}
+
+	/// Closes the database, making sure all changes have been written to the database file.
+	/// Fails if the database is still in use
+	fn close(self) -> Result<(), ExternalDataError> {
+		let conn = Arc::try_unwrap(self.conn)
+			.map_err(|_| {
+				ExternalDataError::new_io_with_path(
+					io::Error::other("the database is still in use"),
+					&self.path,
+				)
+			})?
+			.into_inner()
+			.unwrap_or_else(std::sync::PoisonError::into_inner);
+
+		conn.close()
+			.map_err(|(_, e)| ExternalDataError::new_io_with_path(io::Error::other(e), &self.path))
+	}
+
+	fn read_filter(
+		&self,
+		key: &str,
+		expected_rf_kind: ReadFilterKind,
+	) -> Result<Box<dyn ReadFilter>, ExternalDataError> {
+		let saved = load_read_filter(&self.lock(), key).map_err(|e| self.error(e))?;
+
+		let Some((kind, last_read_id)) = saved else {
+			tracing::debug!("No saved read filter found in the database for {key:?}");
+			return Ok(expected_rf_kind.new_from_kind(self.writer(key)));
+		};
+
+		let kind = kind_from_str(&kind).ok_or_else(|| {
+			self.error(io::Error::other(format!(
+				"unknown read filter kind {kind:?}"
+			)))
+		})?;
+
+		// the old read filter saved in the database is of the same type as the one set in config
+		if kind != expected_rf_kind {
+			return Err(ExternalDataError::new_rf_incompat_with_path(
+				expected_rf_kind,
+				kind,
+				&self.path,
+			));
+		}
+
+		let rf: Box<dyn ReadFilter> = match kind {
+			ReadFilterKind::NewerThanRead => Box::new(ExternalSaveRFWrapper {
+				rf: Newer {
+					last_read_id: last_read_id.map(EntryId),
+				},
+				external_save: Some(self.writer(key)),
+			}),
+			ReadFilterKind::NotPresentInReadList => Box::new(ExternalSaveRFWrapper {
+				rf: load_read_list(&self.lock(), key).map_err(|e| self.error(e))?,
+				external_save: Some(self.writer(key)),
+			}),
+		};
+
+		Ok(rf)
+	}
+
+	fn entry_to_msg_map(&self, key: &str) -> Result<EntryToMsgMap, ExternalDataError> {
+		let (map, content_hashes, parts) = {
+			let conn = self.lock();
+
+			(
+				load_entry_to_msg_map(&conn, key).map_err(|e| self.error(e))?,
+				load_content_hashes(&conn, key).map_err(|e| self.error(e))?,
+				load_msg_parts(&conn, key).map_err(|e| self.error(e))?,
+			)
+		};
+
+		Ok(EntryToMsgMap::new_with_map(map, self.writer(key))
+			.with_content_hashes(content_hashes)
+			.with_parts(parts))
+	}
+
+	fn http_cache(&self, key: &str) -> Result<HttpCache, ExternalDataError> {
+		let map = load_http_cache(&self.lock(), key).map_err(|e| self.error(e))?;
+
+		Ok(HttpCache::new_with_map(map, self.writer(key)))
+	}
+
+	fn writer(&self, key: &str) -> SqliteWriter {
+		SqliteWriter {
+			db: self.clone(),
+			key: key.to_owned(),
+		}
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+		// the connection can't be left in an invalid state since every write happens in a transaction
+		self.conn
+			.lock()
+			.unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+
+	fn error(&self, e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ExternalDataError {
+		ExternalDataError::new_io_with_path(io::Error::other(e), &self.path)
+	}
+}
+
+#[async_trait]
+impl ExternalSave for SqliteWriter {
+	async fn save_read_filter(
+		&mut self,
+		read_filter: &dyn ReadFilter,
+	) -> Result<(), ExternalSaveError> {
+		// Box<dyn Any> isn't Send and thus can't be held across an await point
+		let save = {
+			let any_rf = read_filter.as_any().await;
+
+			if let Some(newer) = any_rf.downcast_ref::<Newer>() {
+				let Some(last_read_id) = &newer.last_read_id else {
+					return Ok(());
+				};
+
+				ReadFilterSave::Newer(last_read_id.0.clone())
+			} else if let Some(not_present) = any_rf.downcast_ref::<NotPresent>() {
+				ReadFilterSave::NotPresent(
+					not_present
+						.iter()
+						.map(|(id, read_at)| (id.0.clone(), *read_at))
+						.collect(),
+				)
+			} else {
+				tracing::error!("Unknown read filter type, can't save it to the database");
+				return Ok(());
+			}
+		};
+
+		let db = self.db.clone();
+		let key = self.key.clone();
+
+		spawn_blocking_save(&self.db, move || {
+			save_read_filter(&mut db.lock(), &key, &save)
+		})
+		.await
+	}
+
+	async fn save_entry_to_msg_map(
+		&mut self,
+		map: &HashMap<EntryId, MessageId>,
+		content_hashes: &HashMap<EntryId, u64>,
+		parts: &HashMap<EntryId, Vec<MessageId>>,
+	) -> Result<(), ExternalSaveError> {
+		// the other parts are saved alongside the main message to remove the ones that are no longer there
+		let map = map
+			.iter()
+			.map(|(eid, msgid)| {
+				let parts = parts
+					.get(eid)
+					.map(|parts| parts.iter().map(message_id_to_sql).collect())
+					.unwrap_or_default();
+
+				(eid.0.clone(), message_id_to_sql(msgid), parts)
+			})
+			.collect::<Vec<_>>();
+
+		let content_hashes = content_hashes
+			.iter()
+			.map(|(eid, hash)| (eid.0.clone(), content_hash_to_sql(*hash)))
+			.collect::<Vec<_>>();
+
+		let db = self.db.clone();
+		let key = self.key.clone();
+
+		spawn_blocking_save(&self.db, move || {
+			save_entry_to_msg_map(&mut db.lock(), &key, &map, &content_hashes)
+		})
+		.await
+	}
+
+	async fn save_http_cache(
+		&mut self,
+		map: &HashMap<String, Validators>,
+	) -> Result<(), ExternalSaveError> {
+		let map = map
+			.iter()
+			.map(|(url, validators)| (url.clone(), validators.clone()))
+			.collect::<Vec<_>>();
+
+		let db = self.db.clone();
+		let key = self.key.clone();
+
+		spawn_blocking_save(&self.db, move || {
+			save_http_cache(&mut db.lock(), &key, &map)
+		})
+		.await
+	}
+}
+
+/// The state of a read filter to save, extracted out of the read filter to be sent to a blocking thread
+enum ReadFilterSave {
+	Newer(String),
+	NotPresent(Vec<(String, DateTime<Utc>)>),
+}
+
+fn load_read_filter(
+	conn: &Connection,
+	key: &str,
+) -> rusqlite::Result<Option<(String, Option<String>)>> {
+	conn.query_row(
+		"SELECT kind, last_read_id FROM read_filter WHERE key = ?1",
+		[key],
+		|row| Ok((row.get(0)?, row.get(1)?)),
+	)
+	.optional()
+}
+
+fn load_read_list(conn: &Connection, key: &str) -> rusqlite::Result<NotPresent> {
+	conn.prepare_cached("SELECT entry_id, read_at FROM read_list WHERE key = ?1 ORDER BY rowid")?
+		.query_map([key], |row| {
+			Ok((EntryId(row.get(0)?), row.get::<_, DateTime<Utc>>(1)?))
+		})?
+		.collect()
+}
+
+fn load_entry_to_msg_map(
+	conn: &Connection,
+	key: &str,
+) -> rusqlite::Result<HashMap<EntryId, MessageId>> {
+	conn.prepare_cached("SELECT entry_id, message_id FROM entry_to_msg_map WHERE key = ?1")?
+		.query_map([key], |row| {
+			Ok((EntryId(row.get(0)?), message_id_from_sql(row.get(1)?)?))
+		})?
+		.collect()
+}
+
+fn load_content_hashes(conn: &Connection, key: &str) -> rusqlite::Result<HashMap<EntryId, u64>> {
+	conn.prepare_cached("SELECT entry_id, content_hash FROM entry_content_hash WHERE key = ?1")?
+		.query_map([key], |row| {
+			Ok((EntryId(row.get(0)?), content_hash_from_sql(row.get(1)?)))
+		})?
+		.collect()
+}
+
+fn load_msg_parts(
+	conn: &Connection,
+	key: &str,
+) -> rusqlite::Result<HashMap<EntryId, Vec<MessageId>>> {
+	let mut parts = HashMap::<_, Vec<_>>::new();
+
+	let rows = conn
+		.prepare_cached(
+			"SELECT entry_id, message_id FROM entry_msg_part WHERE key = ?1 ORDER BY entry_id, position",
+		)?
+		.query_map([key], |row| {
+			Ok((EntryId(row.get(0)?), message_id_from_sql(row.get(1)?)?))
+		})?
+		.collect::<rusqlite::Result<Vec<_>>>()?;
+
+	for (eid, msgid) in rows {
+		parts.entry(eid).or_default().push(msgid);
+	}
+
+	Ok(parts)
+}
+
+fn load_http_cache(conn: &Connection, key: &str) -> rusqlite::Result<HashMap<String, Validators>> {
+	conn.prepare_cached("SELECT url, etag, last_modified FROM http_cache WHERE key = ?1")?
+		.query_map([key], |row| {
+			Ok((
+				row.get(0)?,
+				Validators {
+					etag: row.get(1)?,
+					last_modified: row.get(2)?,
+				},
+			))
+		})?
+		.collect()
+}
+
+fn save_read_filter(
+	conn: &mut Connection,
+	key: &str,
+	save: &ReadFilterSave,
+) -> rusqlite::Result<()> {
+	let tx = conn.transaction()?;
+
+	match save {
+		ReadFilterSave::Newer(last_read_id) => {
+			tx.execute(
+				"INSERT INTO read_filter (key, kind, last_read_id) VALUES (?1, ?2, ?3)
+				ON CONFLICT (key) DO UPDATE SET kind = excluded.kind, last_read_id = excluded.last_read_id",
+				params![
+					key,
+					kind_to_str(ReadFilterKind::NewerThanRead),
+					last_read_id
+				],
+			)?;
+		}
+		ReadFilterSave::NotPresent(read_list) => {
+			tx.execute(
+				"INSERT INTO read_filter (key, kind) VALUES (?1, ?2)
+				ON CONFLICT (key) DO UPDATE SET kind = excluded.kind, last_read_id = NULL",
+				params![key, kind_to_str(ReadFilterKind::NotPresentInReadList)],
+			)?;
+
+			{
+				// already saved entries are left untouched
+				let mut stmt = tx.prepare_cached(
+					"INSERT OR IGNORE INTO read_list (key, entry_id, read_at) VALUES (?1, ?2, ?3)",
+				)?;
+
+				for (id, read_at) in read_list {
+					stmt.execute(params![key, id, read_at])?;
+				}
+			}
+
+			// remove entries that have been pushed out of the read list
+			tx.execute(
+				"DELETE FROM read_list WHERE key = ?1 AND rowid NOT IN
+				(SELECT rowid FROM read_list WHERE key = ?1 ORDER BY rowid DESC LIMIT ?2)",
+				params![key, read_list.len()],
+			)?;
+		}
+	}
+
+	tx.commit()
+}
+
+fn save_entry_to_msg_map(
+	conn: &mut Connection,
+	key: &str,
+	map: &[(String, Value, Vec<Value>)],
+	content_hashes: &[(String, i64)],
+) -> rusqlite::Result<()> {
+	let tx = conn.transaction()?;
+
+	{
+		// only rows that are new or have changed are actually written
+		let mut stmt = tx.prepare_cached(
+			"INSERT INTO entry_to_msg_map (key, entry_id, message_id) VALUES (?1, ?2, ?3)
+			ON CONFLICT (key, entry_id) DO UPDATE SET message_id = excluded.message_id
+			WHERE message_id != excluded.message_id",
+		)?;
+
+		for (eid, msgid, _) in map {
+			stmt.execute(params![key, eid, msgid])?;
+		}
+	}
+
+	{
+		let mut stmt = tx.prepare_cached(
+			"INSERT INTO entry_msg_part (key, entry_id, position, message_id) VALUES (?1, ?2, ?3, ?4)
+			ON CONFLICT (key, entry_id, position) DO UPDATE SET message_id = excluded.message_id
+			WHERE message_id != excluded.message_id",
+		)?;
+
+		// the entry might've been sent again as fewer messages
+		let mut delete_extra = tx.prepare_cached(
+			"DELETE FROM entry_msg_part WHERE key = ?1 AND entry_id = ?2 AND position >= ?3",
+		)?;
+
+		for (eid, _, parts) in map {
+			for (position, msgid) in parts.iter().enumerate() {
+				stmt.execute(params![key, eid, position, msgid])?;
+			}
+
+			delete_extra.execute(params![key, eid, parts.len()])?;
+		}
+	}
+
+	{
+		let mut stmt = tx.prepare_cached(
+			"INSERT INTO entry_content_hash (key, entry_id, content_hash) VALUES (?1, ?2, ?3)
+			ON CONFLICT (key, entry_id) DO UPDATE SET content_hash = excluded.content_hash
+			WHERE content_hash != excluded.content_hash",
+		)?;
+
+		for (eid, hash) in content_hashes {
+			stmt.execute(params![key, eid, hash])?;
+		}
+	}
+
+	// remove entries that have been removed from the map, e.g. because their messages have been deleted
+	{
+		let saved = tx
+			.prepare_cached("SELECT entry_id FROM entry_to_msg_map WHERE key = ?1")?
+			.query_map([key], |row| row.get::<_, String>(0))?
+			.collect::<rusqlite::Result<Vec<_>>>()?;
+
+		let present = map.iter().map(|(eid, _, _)| eid).collect::<HashSet<_>>();
+
+		let mut delete_msgid =
+			tx.prepare_cached("DELETE FROM entry_to_msg_map WHERE key = ?1 AND entry_id = ?2")?;
+		let mut delete_hash =
+			tx.prepare_cached("DELETE FROM entry_content_hash WHERE key = ?1 AND entry_id = ?2")?;
+		let mut delete_parts =
+			tx.prepare_cached("DELETE FROM entry_msg_part WHERE key = ?1 AND entry_id = ?2")?;
+
+		for eid in saved.iter().filter(|eid| !present.contains(eid)) {
+			delete_msgid.execute(params![key, eid])?;
+			delete_hash.execute(params![key, eid])?;
+			delete_parts.execute(params![key, eid])?;
+		}
+	}
+
+	tx.commit()
+}
+
+fn save_http_cache(
+	conn: &mut Connection,
+	key: &str,
+	map: &[(String, Validators)],
+) -> rusqlite::Result<()> {
+	let tx = conn.transaction()?;
+
+	{
+		let mut stmt = tx.prepare_cached(
+			"INSERT INTO http_cache (key, url, etag, last_modified) VALUES (?1, ?2, ?3, ?4)
+			ON CONFLICT (key, url) DO UPDATE SET etag = excluded.etag, last_modified = excluded.last_modified",
+		)?;
+
+		for (url, validators) in map {
+			stmt.execute(params![key, url, validators.etag, validators.last_modified])?;
+		}
+	}
+
+	tx.commit()
+}
+
+async fn spawn_blocking_save<F>(db: &Database, f: F) -> Result<(), ExternalSaveError>
+where
+	F: FnOnce() -> rusqlite::Result<()> + Send + 'static,
+{
+	let res = match tokio::task::spawn_blocking(f).await {
+		Ok(res) => res.map_err(io::Error::other),
+		Err(e) => Err(io::Error::other(e)),
+	};
+
+	res.map_err(|source| ExternalSaveError {
+		source,
+		path: Some(Box::new(super::DisplayPath(db.path.clone()))),
+	})
+}
+
+/// Returns the read filter of the task from the database if it has been migrated to one
+pub fn read_filter(
+	job: &JobName,
+	task: Option<&TaskName>,
+	expected_rf_kind: ReadFilterKind,
+	cx: Context,
+) -> Result<Option<Box<dyn ReadFilter>>, ExternalDataError> {
+	Database::get(cx)?
+		.map(|db| db.read_filter(&key(job, task), expected_rf_kind))
+		.transpose()
+}
+
+/// Returns the entry to message map of the task from the database if it has been migrated to one
+pub fn entry_to_msg_map(
+	job: &JobName,
+	task: Option<&TaskName>,
+	cx: Context,
+) -> Result<Option<EntryToMsgMap>, ExternalDataError> {
+	Database::get(cx)?
+		.map(|db| db.entry_to_msg_map(&key(job, task)))
+		.transpose()
+}
+
+/// Returns the HTTP cache of the task from the database if it has been migrated to one
+pub fn http_cache(
+	job: &JobName,
+	task: Option<&TaskName>,
+	cx: Context,
+) -> Result<Option<HttpCache>, ExternalDataError> {
+	Database::get(cx)?
+		.map(|db| db.http_cache(&key(job, task)))
+		.transpose()
+}
+
+/// Imports all read filter, entry to message map, and HTTP cache JSON save files into a newly created database.
+/// From then on, the database is used instead of the JSON files
+pub async fn migrate(cx: Context) -> Result<()> {
+	migrate_dir(&cx.data_path).await
+}
+
+/// Imports all JSON save files in `data_path` into a database in `data_path`.
+///
+/// The database is first created under a temporary name and renamed only after all files have been imported,
+/// so that a failed migration doesn't leave a partially filled database behind that would've been used from then on
+async fn migrate_dir(data_path: &Path) -> Result<()> {
+	let path = data_path.join(DB_FILE_NAME);
+
+	if path.exists() {
+		return Err(eyre!(
+			"Database {} already exists, refusing to overwrite it",
+			path.display()
+		));
+	}
+
+	fs::create_dir_all(data_path)?;
+
+	// left over from a previous migration that has failed midway
+	let tmp_path = data_path.join(format!("{DB_FILE_NAME}.tmp"));
+	remove_db_files(&tmp_path)?;
+
+	let (rf_count, map_count, cache_count) = match import_save_files(data_path, &tmp_path).await {
+		Ok(counts) => counts,
+		Err(e) => {
+			if let Err(remove_err) = remove_db_files(&tmp_path) {
+				tracing::warn!(
+					"Unable to remove the partially migrated database {}: {remove_err}",
+					tmp_path.display()
+				);
+			}
+
+			return Err(e);
+		}
+	};
+
+	fs::rename(&tmp_path, &path).wrap_err_with(|| {
+		format!(
+			"Unable to move the migrated database {} to {}",
+			tmp_path.display(),
+			path.display()
+		)
+	})?;
+
+	tracing::info!(
+		"Imported {rf_count} read filters, {map_count} entry to message maps, and {cache_count} HTTP caches into {}. The old JSON save files are no longer used and can be removed",
+		path.display()
+	);
+
+	Ok(())
+}
+
+/// Imports all JSON save files in `data_path` into a new database at `db_path`.
+///
+/// Returns the number of imported read filters, entry to message maps, and HTTP caches
+async fn import_save_files(data_path: &Path, db_path: &Path) -> Result<(usize, usize, usize)> {
+	let db = Database::open(db_path.to_owned())?;
+
+	let mut rf_count = 0;
+	for (key, save_file) in save_files(&data_path.join(READ_DATA_DIR)) {
+		let raw = fs::read_to_string(&save_file)?;
+		if raw.trim().is_empty() {
+			continue;
+		}
+
+		let conf: ReadFilterConf = serde_json::from_str(&raw)
+			.wrap_err_with(|| format!("invalid read filter save file {}", save_file.display()))?;
+
+		let mut writer = db.writer(&key);
+		let rf = conf.decode_from_conf(db.writer(&key));
+		writer.save_read_filter(&*rf).await?;
+
+		rf_count += 1;
+	}
+
+	let mut map_count = 0;
+	for (key, save_file) in save_files(&data_path.join(ENTRY_TO_MSG_MAP_DATA_DIR)) {
+		let raw = fs::read_to_string(&save_file)?;
+		if raw.trim().is_empty() {
+			continue;
+		}
+
+		let conf: EntryToMsgMapConf = serde_json::from_str(&raw).wrap_err_with(|| {
+			format!(
+				"invalid entry to message map save file {}",
+				save_file.display()
+			)
+		})?;
+
+		let (map, content_hashes, parts) = conf.decode_from_conf();
+		db.writer(&key)
+			.save_entry_to_msg_map(&map, &content_hashes, &parts)
+			.await?;
+
+		map_count += 1;
+	}
+
+	let mut cache_count = 0;
+	for (key, save_file) in save_files(&data_path.join(HTTP_CACHE_DATA_DIR)) {
+		let raw = fs::read_to_string(&save_file)?;
+		if raw.trim().is_empty() {
+			continue;
+		}
+
+		let conf: HttpCacheConf = serde_json::from_str(&raw)
+			.wrap_err_with(|| format!("invalid HTTP cache save file {}", save_file.display()))?;
+
+		db.writer(&key)
+			.save_http_cache(&conf.decode_from_conf())
+			.await?;
+
+		cache_count += 1;
+	}
+
+	// write everything from the WAL into the database file itself before it's moved
+	db.close()?;
+
+	Ok((rf_count, map_count, cache_count))
+}
+
+/// Removes the database at `path` alongside its WAL files, if it exists
+fn remove_db_files(path: &Path) -> io::Result<()> {
+	for suffix in ["", "-wal", "-shm"] {
+		let mut file = path.as_os_str().to_owned();
+		file.push(suffix);
+
+		match fs::remove_file(&file) {
+			Ok(()) => (),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+			Err(e) => return Err(e),
+		}
+	}
+
+	Ok(())
+}
+
+/// Returns all save files in `dir` alongside their "job\[/task\]" keys
+fn save_files(dir: &Path) -> impl Iterator<Item = (String, PathBuf)> + '_ {
+	WalkDir::new(dir)
+		.into_iter()
+		.filter_map(Result::ok)
+		.filter(|dir_entry| !dir_entry.file_type().is_dir())
+		.filter_map(move |dir_entry| {
+			let key = dir_entry
+				.path()
+				.strip_prefix(dir)
+				.ok()?
+				.components()
+				.map(|c| c.as_os_str().to_string_lossy())
+				.collect::<Vec<_>>()
+				.join("/");
+
+			Some((key, dir_entry.into_path()))
+		})
+}
+
+fn key(job: &JobName, task: Option<&TaskName>) -> String {
+	match task {
+		Some(task) => format!("{}/{}", job.as_str(), task.as_str()),
+		None => job.as_str().to_owned(),
+	}
+}
+
+fn kind_to_str(kind: ReadFilterKind) -> &'static str {
+	match kind {
+		ReadFilterKind::NewerThanRead => "newer_than_read",
+		ReadFilterKind::NotPresentInReadList => "not_present_in_read_list",
+	}
+}
+
+fn kind_from_str(kind: &str) -> Option<ReadFilterKind> {
+	Some(match kind {
+		"newer_than_read" => ReadFilterKind::NewerThanRead,
+		"not_present_in_read_list" => ReadFilterKind::NotPresentInReadList,
+		_ => return None,
+	})
+}
+
+fn message_id_to_sql(msgid: &MessageId) -> Value {
+	match msgid {
+		MessageId::Int(id) => Value::Integer(*id),
+		MessageId::Str(id) => Value::Text(id.clone()),
+	}
+}
+
+fn message_id_from_sql(value: Value) -> rusqlite::Result<MessageId> {
+	match value {
+		Value::Integer(id) => Ok(MessageId::Int(id)),
+		Value::Text(id) => Ok(MessageId::Str(id)),
+		other => Err(rusqlite::Error::FromSqlConversionFailure(
+			1,
+			other.data_type(),
+			Box::new(FromSqlError::InvalidType),
+		)),
+	}
+}
+
+fn content_hash_to_sql(hash: u64) -> i64 {
+	i64::from_ne_bytes(hash.to_ne_bytes())
+}
+
+fn content_hash_from_sql(hash: i64) -> u64 {
+	u64::from_ne_bytes(hash.to_ne_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::unwrap_used)]
+
+	use super::*;
+
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir()
+			.join(format!("fetcher-test-{}", std::process::id()))
+			.join(name);
+		_ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+
+		dir
+	}
+
+	async fn read_list(db: &Database, key: &str) -> Vec<String> {
+		let rf = db
+			.read_filter(key, ReadFilterKind::NotPresentInReadList)
+			.unwrap();
+		let any_rf = rf.as_any().await;
+
+		any_rf
+			.downcast_ref::<NotPresent>()
+			.unwrap()
+			.iter()
+			.map(|(id, _)| id.0.clone())
+			.collect()
+	}
+
+	fn write_save_file(data_path: &Path, dir: &str, key: &str, contents: &str) {
+		let path = data_path.join(dir).join(key);
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(path, contents).unwrap();
+	}
+
+	#[tokio::test]
+	async fn round_trip() {
+		let db = Database::open(test_dir("round_trip").join(DB_FILE_NAME)).unwrap();
+
+		let mut rf = db
+			.read_filter("job/task", ReadFilterKind::NotPresentInReadList)
+			.unwrap();
+		rf.mark_as_read(&"1".to_owned().into()).await.unwrap();
+		rf.mark_as_read(&"2".to_owned().into()).await.unwrap();
+
+		assert_eq!(read_list(&db, "job/task").await, ["1", "2"]);
+		assert!(
+			db.read_filter("job/task", ReadFilterKind::NewerThanRead)
+				.is_err(),
+			"the saved read filter is of a different kind"
+		);
+
+		let mut map = db.entry_to_msg_map("job").unwrap();
+		map.insert_with_content_hash("1".to_owned().into(), MessageId::Int(10), u64::MAX)
+			.await
+			.unwrap();
+		map.insert("2".to_owned().into(), MessageId::Str("$event".to_owned()))
+			.await
+			.unwrap();
+		map.insert_with_parts(
+			"3".to_owned().into(),
+			MessageId::Int(30),
+			vec![MessageId::Int(31), MessageId::Int(32)],
+			None,
+		)
+		.await
+		.unwrap();
+
+		let mut map = db.entry_to_msg_map("job").unwrap();
+		assert_eq!(map.get(&"1".to_owned().into()), Some(&MessageId::Int(10)));
+		assert_eq!(map.content_hash(&"1".to_owned().into()), Some(u64::MAX));
+		assert_eq!(
+			map.get(&"2".to_owned().into()),
+			Some(&MessageId::Str("$event".to_owned()))
+		);
+		assert_eq!(
+			map.parts(&"3".to_owned().into()),
+			[MessageId::Int(31), MessageId::Int(32)]
+		);
+
+		map.remove(&"1".to_owned().into()).await.unwrap();
+
+		// sent again as fewer messages
+		map.insert_with_parts(
+			"3".to_owned().into(),
+			MessageId::Int(40),
+			vec![MessageId::Int(41)],
+			None,
+		)
+		.await
+		.unwrap();
+
+		let mut map = db.entry_to_msg_map("job").unwrap();
+		assert_eq!(map.get(&"1".to_owned().into()), None);
+		assert_eq!(map.content_hash(&"1".to_owned().into()), None);
+		assert!(map.get(&"2".to_owned().into()).is_some());
+		assert_eq!(map.parts(&"3".to_owned().into()), [MessageId::Int(41)]);
+
+		map.remove(&"3".to_owned().into()).await.unwrap();
+
+		let map = db.entry_to_msg_map("job").unwrap();
+		assert!(map.parts(&"3".to_owned().into()).is_empty());
+
+		let validators = Validators {
+			etag: Some("\"v1\"".to_owned()),
+			last_modified: None,
+		};
+
+		let mut cache = db.http_cache("job").unwrap();
+		cache
+			.insert("https://example.com/".to_owned(), validators.clone())
+			.await
+			.unwrap();
+
+		let cache = db.http_cache("job").unwrap();
+		assert_eq!(cache.get("https://example.com/"), Some(&validators));
+
+		// keys are independent of each other
+		assert!(read_list(&db, "other").await.is_empty());
+		assert!(
+			db.http_cache("other")
+				.unwrap()
+				.get("https://example.com/")
+				.is_none()
+		);
+	}
+
+	#[tokio::test]
+	async fn migrate() {
+		let data_path = test_dir("migrate");
+
+		write_save_file(
+			&data_path,
+			READ_DATA_DIR,
+			"job/task",
+			r#"{ "type": "not_present_in_read_list", "read_list": [["1", "2024-01-01T00:00:00Z"]] }"#,
+		);
+		write_save_file(
+			&data_path,
+			ENTRY_TO_MSG_MAP_DATA_DIR,
+			"job",
+			r#"{ "messages": { "1": 10 }, "content_hashes": { "1": 5 }, "parts": { "1": [11] } }"#,
+		);
+		write_save_file(
+			&data_path,
+			HTTP_CACHE_DATA_DIR,
+			"job",
+			r#"{ "https://example.com/": { "etag": "\"v1\"" } }"#,
+		);
+
+		migrate_dir(&data_path).await.unwrap();
+
+		let db = Database::open(data_path.join(DB_FILE_NAME)).unwrap();
+		assert_eq!(read_list(&db, "job/task").await, ["1"]);
+
+		let map = db.entry_to_msg_map("job").unwrap();
+		assert_eq!(map.get(&"1".to_owned().into()), Some(&MessageId::Int(10)));
+		assert_eq!(map.content_hash(&"1".to_owned().into()), Some(5));
+		assert_eq!(map.parts(&"1".to_owned().into()), [MessageId::Int(11)]);
+
+		assert_eq!(
+			db.http_cache("job")
+				.unwrap()
+				.get("https://example.com/")
+				.and_then(|v| v.etag.as_deref()),
+			Some("\"v1\"")
+		);
+
+		assert!(
+			migrate_dir(&data_path).await.is_err(),
+			"an existing database shouldn't be overwritten"
+		);
+	}
+
+	#[tokio::test]
+	async fn failed_migration_leaves_no_database() {
+		let data_path = test_dir("failed_migration");
+
+		write_save_file(
+			&data_path,
+			READ_DATA_DIR,
+			"job",
+			r#"{ "type": "newer_than_read", "last_read_id": "1" }"#,
+		);
+		write_save_file(&data_path, ENTRY_TO_MSG_MAP_DATA_DIR, "job", "not json");
+
+		assert!(migrate_dir(&data_path).await.is_err());
+		assert!(!data_path.join(DB_FILE_NAME).exists());
+		assert!(!data_path.join(format!("{DB_FILE_NAME}.tmp")).exists());
+
+		// the migration can be retried once the invalid file has been fixed
+		write_save_file(
+			&data_path,
+			ENTRY_TO_MSG_MAP_DATA_DIR,
+			"job",
+			r#"{ "1": 10 }"#,
+		);
+		migrate_dir(&data_path).await.unwrap();
+
+		let db = Database::open(data_path.join(DB_FILE_NAME)).unwrap();
+		let map = db.entry_to_msg_map("job").unwrap();
+		assert_eq!(map.get(&"1".to_owned().into()), Some(&MessageId::Int(10)));
+	}
+}
@@ -13,7 +13,10 @@ use fetcher_config::jobs::{
 	named::{JobName, TaskName},
 	read_filter::Kind as ReadFilterKind,
 };
-use fetcher_core::{auth, read_filter::ReadFilter, task::entry_to_msg_map::EntryToMsgMap};
+use fetcher_core::{
+	auth, read_filter::ReadFilter, source::http::cache::HttpCache,
+	task::entry_to_msg_map::EntryToMsgMap,
+};
 
 pub struct ExternalDataFromDataDir {
 	pub cx: StaticContext,
@@ -38,6 +41,18 @@ impl ProvideExternalData for ExternalDataFromDataDir {
 		data::discord::get(self.cx).into()
 	}
 
+	fn matrix_access_token(&self) -> ExternalDataResult<String> {
+		data::matrix::get(self.cx).into()
+	}
+
+	fn http_secret(&self, name: &str) -> ExternalDataResult<String> {
+		match data::http_secrets::get(name, self.cx) {
+			Ok(Some(secret)) => ExternalDataResult::Ok(secret),
+			Ok(None) => ExternalDataResult::Unavailable,
+			Err(e) => ExternalDataResult::Err(e),
+		}
+	}
+
 	fn read_filter(
 		&self,
 		job: &JobName,
@@ -55,6 +70,10 @@ impl ProvideExternalData for ExternalDataFromDataDir {
 		data::runtime_external_save::entry_to_msg_map::get(job, task, self.cx).into()
 	}
 
+	fn http_cache(&self, job: &JobName, task: Option<&TaskName>) -> ExternalDataResult<HttpCache> {
+		data::runtime_external_save::http_cache::get(job, task, self.cx).into()
+	}
+
 	fn import(&self, name: &str) -> ExternalDataResult<Vec<ActionConfig>> {
 		match config::actions::find(name, self.cx) {
 			Ok(Some(x)) => ExternalDataResult::Ok(x),